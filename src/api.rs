@@ -0,0 +1,167 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::diff::FileDiff;
+use crate::heuristics::is_test;
+
+/// Item keywords that can follow `pub`, with qualifiers skipped before them
+const ITEM_KINDS: &[&str] = &["fn", "struct", "enum", "trait", "type", "const", "static", "mod", "union"];
+const QUALIFIERS: &[&str] = &["async", "unsafe", "const", "extern", "\"C\""];
+
+/// How a public item changed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ApiChange {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A `pub` item whose declaration the diff adds, removes or edits
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiItem {
+    pub change: ApiChange,
+    pub path: String,
+    pub kind: &'static str,
+    pub name: String,
+    /// Declaration line before the change, for removed and changed items
+    pub before: Option<String>,
+    /// Declaration line after the change, for added and changed items
+    pub after: Option<String>,
+}
+
+impl ApiItem {
+    /// Whether existing callers may stop compiling
+    pub fn is_breaking(&self) -> bool {
+        self.change != ApiChange::Added
+    }
+}
+
+impl fmt::Display for ApiItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.change, &self.before, &self.after) {
+            (ApiChange::Changed, Some(before), Some(after)) => {
+                write!(f, "changed `{}` to `{}` ({})", before, after, self.path)
+            }
+            (ApiChange::Removed, Some(before), _) => write!(f, "removed `{}` ({})", before, self.path),
+            (_, _, Some(after)) => write!(f, "added `{}` ({})", after, self.path),
+            _ => write!(f, "{} {} ({})", self.kind, self.name, self.path),
+        }
+    }
+}
+
+/// Public items of Rust library crates that `files` add, remove or change
+///
+/// Declarations are compared line by line, so a signature spread over several
+/// lines is judged by its first one. A `pub` item made private counts as
+/// removed. Binaries, tests, examples and benches have no public API.
+pub fn api_changes(root: &Path, files: &[FileDiff]) -> Vec<ApiItem> {
+    files
+        .iter()
+        .filter(|file| is_library_source(root, &file.path))
+        .flat_map(file_changes)
+        .collect()
+}
+
+fn is_library_source(root: &Path, path: &str) -> bool {
+    if !path.ends_with(".rs") || is_test(path) {
+        return false;
+    }
+    let Some((crate_dir, inside)) = path.rsplit_once("src/").filter(|(dir, _)| dir.is_empty() || dir.ends_with('/')) else {
+        return false;
+    };
+    inside != "main.rs" && !inside.starts_with("bin/") && root.join(crate_dir).join("src/lib.rs").exists()
+}
+
+fn file_changes(file: &FileDiff) -> Vec<ApiItem> {
+    let mut removed: Vec<(&'static str, String, String)> = Vec::new();
+    let mut added: Vec<(&'static str, String, String)> = Vec::new();
+    for hunk in &file.hunks {
+        for line in hunk.lines().skip(1) {
+            if let Some(old) = line.strip_prefix('-') {
+                removed.extend(declaration(old));
+            } else if let Some(new) = line.strip_prefix('+') {
+                added.extend(declaration(new));
+            }
+        }
+    }
+
+    let mut items = Vec::new();
+    for (kind, name, before) in removed {
+        let matching = added.iter().position(|(k, n, _)| *k == kind && *n == name);
+        match matching.map(|at| added.remove(at)) {
+            Some((_, _, after)) if after == before => {}
+            Some((_, _, after)) => items.push(item(file, ApiChange::Changed, kind, name, Some(before), Some(after))),
+            None => items.push(item(file, ApiChange::Removed, kind, name, Some(before), None)),
+        }
+    }
+    for (kind, name, after) in added {
+        items.push(item(file, ApiChange::Added, kind, name, None, Some(after)));
+    }
+    items
+}
+
+fn item(
+    file: &FileDiff,
+    change: ApiChange,
+    kind: &'static str,
+    name: String,
+    before: Option<String>,
+    after: Option<String>,
+) -> ApiItem {
+    ApiItem {
+        change,
+        path: file.path.clone(),
+        kind,
+        name,
+        before,
+        after,
+    }
+}
+
+/// Kind, name and signature of a `pub` declaration on `line`
+fn declaration(line: &str) -> Option<(&'static str, String, String)> {
+    let line = line.trim();
+    let rest = line.strip_prefix("pub ")?;
+    let words: Vec<&str> = rest.split_whitespace().collect();
+    // `const` is a qualifier in `pub const fn` but the item in `pub const MAX`
+    let at = words.iter().position(|word| !QUALIFIERS.contains(word) || *word == "const" && !words.contains(&"fn"))?;
+    let kind = ITEM_KINDS.iter().find(|kind| **kind == words[at])?;
+    let name: String = words
+        .get(at + 1)?
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+    if name.is_empty() {
+        return None;
+    }
+    let signature = line.trim_end_matches(['{', ';']).trim_end().to_string();
+    Some((kind, name, signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::parse_diff;
+
+    #[test]
+    fn test_file_changes() {
+        let diff = "diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,5 +1,5 @@\n\
+                    -pub fn parse(input: &str) -> Tree {\n+pub fn parse(input: &str, strict: bool) -> Tree {\n\
+                    -pub struct Legacy;\n+struct Legacy;\n+pub async fn fetch() {\n-pub(crate) fn helper() {}\n+    let x = 1;\n";
+        let items = file_changes(&parse_diff(diff)[0]);
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].change, ApiChange::Changed);
+        assert_eq!(
+            items[0].to_string(),
+            "changed `pub fn parse(input: &str) -> Tree` to `pub fn parse(input: &str, strict: bool) -> Tree` (src/lib.rs)"
+        );
+        assert_eq!((items[1].change, items[1].kind, items[1].name.as_str()), (ApiChange::Removed, "struct", "Legacy"));
+        assert_eq!((items[2].change, items[2].name.as_str()), (ApiChange::Added, "fetch"));
+        assert!(items[1].is_breaking() && !items[2].is_breaking());
+
+        assert_eq!(declaration("pub unsafe extern \"C\" fn init()").map(|d| d.1), Some("init".into()));
+        assert_eq!(declaration("pub const MAX: usize = 3;").map(|d| d.0), Some("const"));
+        assert_eq!(declaration("pub const fn new() -> Self {").map(|d| d.1), Some("new".into()));
+        assert_eq!(declaration("pub use crate::x;"), None);
+    }
+}
@@ -0,0 +1,158 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::config::Config;
+use crate::exit::{Exit, Failure};
+use crate::provider::ProviderError;
+use crate::template;
+use crate::ui;
+use crate::vcs::git;
+
+/// Appends of one process, so concurrent candidates chain one after another
+static APPEND: Mutex<()> = Mutex::new(());
+
+/// `prev` of the first entry
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Hex SHA-256 of `text`
+pub fn sha256(text: &str) -> String {
+    Sha256::digest(text.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Hash of an entry: its fields without `hash`, chained to the entry before
+fn entry_hash(entry: &Map<String, Value>) -> String {
+    let mut fields = entry.clone();
+    fields.remove("hash");
+    let prev = fields.get("prev").and_then(Value::as_str).unwrap_or(GENESIS).to_string();
+    sha256(&format!("{}\n{}", prev, Value::Object(fields)))
+}
+
+/// Append `fields` to the log at `path`, numbered and chained to the last entry
+///
+/// Only hashes of prompts, responses and messages are written, never the
+/// text itself, so the log can be handed to auditors without the code.
+fn append(path: &Path, kind: &str, fields: Value) {
+    let _guard = APPEND.lock().unwrap_or_else(|e| e.into_inner());
+    let last = fs::read_to_string(path)
+        .ok()
+        .and_then(|log| log.lines().rev().find(|l| !l.trim().is_empty()).map(str::to_string))
+        .and_then(|line| serde_json::from_str::<Value>(&line).ok());
+    let seq = last.as_ref().and_then(|e| e["seq"].as_u64()).map_or(1, |seq| seq + 1);
+    let prev = last.as_ref().and_then(|e| e["hash"].as_str()).unwrap_or(GENESIS).to_string();
+
+    let mut entry = Map::new();
+    entry.insert("seq".into(), json!(seq));
+    entry.insert("time".into(), json!(template::timestamp()));
+    entry.insert("repo".into(), json!(git::root()));
+    entry.insert("kind".into(), json!(kind));
+    if let Value::Object(fields) = fields {
+        entry.extend(fields);
+    }
+    entry.insert("prev".into(), json!(prev));
+    let hash = entry_hash(&entry);
+    entry.insert("hash".into(), json!(hash));
+
+    let written = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| OpenOptions::new().create(true).append(true).open(path))
+        .and_then(|mut file| writeln!(file, "{}", Value::Object(entry)));
+    if let Err(e) = written {
+        ui::warn("⚠️  ", format!("Could not write the audit log {}: {}", path.display(), e));
+    }
+}
+
+/// Record a request to `provider`, with the response or why it failed
+///
+/// Error messages can quote the prompt or the provider's answer, so only
+/// their hash and kind are kept.
+pub fn generation(path: &Path, provider: &str, model: &str, prompt: &str, result: Result<&str, &ProviderError>) {
+    let outcome = match result {
+        Ok(response) => json!({ "response": sha256(response) }),
+        Err(error) => json!({ "error": sha256(&error.to_string()), "error_kind": error.kind() }),
+    };
+    let mut fields = json!({ "provider": provider, "model": model, "prompt": sha256(prompt) });
+    if let (Value::Object(fields), Value::Object(outcome)) = (&mut fields, outcome) {
+        fields.extend(outcome);
+    }
+    append(path, "generation", fields);
+}
+
+/// Record what the user did with a message: `accept`, `edit`, `regenerate`,
+/// `exclude`, `quit`, `print` or `commit`
+pub fn action(config: &Config, action: &str, message: Option<&str>) {
+    if let Some(path) = &config.audit_log {
+        append(path, "action", json!({ "action": action, "message": message.map(sha256) }));
+    }
+}
+
+/// Check the chain of `log`, returning its entries or the first that was changed
+pub fn verify(log: &str) -> Result<Vec<Value>, String> {
+    let mut prev = GENESIS.to_string();
+    let mut entries = Vec::new();
+    for (number, line) in log.lines().filter(|l| !l.trim().is_empty()).enumerate() {
+        let entry = serde_json::from_str::<Value>(line).map_err(|_| format!("entry {} is not valid JSON", number + 1))?;
+        let fields = entry.as_object().ok_or(format!("entry {} is not an object", number + 1))?;
+        let intact = fields.get("prev").and_then(Value::as_str) == Some(prev.as_str())
+            && fields.get("seq").and_then(Value::as_u64) == Some(number as u64 + 1)
+            && fields.get("hash").and_then(Value::as_str) == Some(entry_hash(fields).as_str());
+        if !intact {
+            return Err(format!("entry {} was changed, removed or reordered", number + 1));
+        }
+        prev = fields["hash"].as_str().unwrap_or_default().to_string();
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Verify the audit log and print it as JSON lines (`cm audit export`)
+pub fn run_export() -> Result<Exit, Box<dyn std::error::Error>> {
+    let config = Config::from_env()?;
+    let path = config
+        .audit_log
+        .ok_or_else(|| Failure::new(Exit::InvalidConfig, "Audit logging is off. Set CM_AUDIT=1 to start a log"))?;
+    let log = fs::read_to_string(&path).map_err(|e| format!("Cannot read the audit log {}: {}", path.display(), e))?;
+    let entries = verify(&log)
+        .map_err(|e| Failure::new(Exit::Error, format!("The audit log {} was tampered with: {}", path.display(), e)))?;
+    for entry in &entries {
+        println!("{}", entry);
+    }
+    ui::status("✓ ", format!("{} entries, chain intact ({})", entries.len(), path.display()));
+    Ok(Exit::Success)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::TempRepo;
+
+    #[test]
+    fn test_chain() {
+        let repo = TempRepo::new();
+        let path = repo.path.join("audit.jsonl");
+        generation(&path, "ollama", "llama3.2", "system\nuser", Ok("feat: add x"));
+        let error = ProviderError::NetworkError("timed out near feat: add x".to_string());
+        generation(&path, "ollama", "llama3.2", "system\nuser", Err(&error));
+        append(&path, "action", json!({ "action": "accept", "message": sha256("feat: add x") }));
+
+        let log = fs::read_to_string(&path).unwrap();
+        let entries = verify(&log).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0]["response"], json!(sha256("feat: add x")));
+        assert_eq!(entries[1]["error"], json!(sha256(&error.to_string())));
+        assert_eq!(entries[1]["error_kind"], json!("network"));
+        assert_eq!(entries[2]["prev"], entries[1]["hash"]);
+        assert!(!log.contains("feat: add x"));
+
+        let edited = log.replacen("\"action\":\"accept\"", "\"action\":\"quit\"", 1);
+        assert_eq!(verify(&edited).unwrap_err(), "entry 3 was changed, removed or reordered");
+        let removed: String = log.lines().skip(1).map(|l| format!("{}\n", l)).collect();
+        assert_eq!(verify(&removed).unwrap_err(), "entry 1 was changed, removed or reordered");
+        assert_eq!(sha256("abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+}
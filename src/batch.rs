@@ -0,0 +1,196 @@
+use std::env;
+use std::path::Path;
+
+use crate::cli::Args;
+use crate::config::{commit_groups, workspace_repos};
+use crate::exit::{self, Exit};
+use crate::i18n::{tf, Msg};
+use crate::ui;
+use crate::vcs::{self, git::submodules, GitError};
+
+/// How committing in one repository went
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Committed,
+    NothingStaged,
+    Aborted,
+    Failed(Exit, String),
+}
+
+impl Outcome {
+    fn label(&self) -> String {
+        match self {
+            Self::Committed => "committed".to_string(),
+            Self::NothingStaged => "nothing staged".to_string(),
+            Self::Aborted => "skipped".to_string(),
+            Self::Failed(_, message) => format!("failed: {}", message.lines().next().unwrap_or_default()),
+        }
+    }
+}
+
+/// Run `cm` in every submodule and configured workspace repository, then the current one
+///
+/// Submodules come first so their new commits can be staged in the superproject
+/// before it is committed last.
+pub async fn run_batch(args: &Args) -> Result<Exit, Box<dyn std::error::Error>> {
+    let home = env::current_dir()?;
+    let mut repos = submodules()?;
+    repos.extend(workspace_repos().iter().map(|path| home.join(path)));
+    repos.push(home.clone());
+
+    let mut results = Vec::with_capacity(repos.len());
+    for repo in &repos {
+        ui::status("\n📂 ", ui::paint(&display_path(repo, &home), ui::theme().reference));
+        env::set_current_dir(repo)?;
+        let outcome = match crate::run(args).await {
+            Ok(Exit::Success) => Outcome::Committed,
+            Ok(Exit::Aborted) => Outcome::Aborted,
+            Ok(exit) => Outcome::Failed(exit, format!("exit code {}", exit.code())),
+            Err(e) => match exit::classify(e.as_ref()) {
+                Exit::NoStagedChanges => Outcome::NothingStaged,
+                exit => {
+                    ui::error("❌ ", tf(Msg::Error, &[&e]));
+                    Outcome::Failed(exit, e.to_string())
+                }
+            },
+        };
+        results.push((display_path(repo, &home), outcome));
+    }
+    env::set_current_dir(&home)?;
+
+    println!("\n{}", summary(&results));
+    Ok(results
+        .iter()
+        .find_map(|(_, outcome)| match outcome {
+            Outcome::Failed(exit, _) => Some(*exit),
+            _ => None,
+        })
+        .unwrap_or(Exit::Success))
+}
+
+/// Commit the staged files one at a time, or one configured group at a time
+///
+/// Stops at the first file that is quit or fails, leaving the rest staged.
+pub async fn run_per_file(args: &Args) -> Result<Exit, Box<dyn std::error::Error>> {
+    let repo = vcs::detect().ok_or(GitError::NotARepository)?;
+    let files = repo.changed_files(&args.only);
+    if files.is_empty() {
+        return Err(GitError::NoStagedChanges.into());
+    }
+
+    let groups = group_files(&files, &commit_groups());
+    for (i, group) in groups.iter().enumerate() {
+        ui::status(
+            "\n📄 ",
+            format!("[{}/{}] {}", i + 1, groups.len(), ui::paint(&group.join(", "), ui::theme().reference)),
+        );
+        let exit = crate::run(&Args { only: group.clone(), ..args.clone() }).await?;
+        if exit != Exit::Success {
+            return Ok(exit);
+        }
+    }
+    Ok(Exit::Success)
+}
+
+/// Split `files` into commits: files matching the same group pattern together, the rest alone
+fn group_files(files: &[String], patterns: &[String]) -> Vec<Vec<String>> {
+    let mut groups: Vec<(Option<&str>, Vec<String>)> = Vec::new();
+    for file in files {
+        let pattern = patterns.iter().map(String::as_str).find(|pattern| matches_pattern(pattern, file));
+        match groups.iter_mut().find(|(key, _)| pattern.is_some() && *key == pattern) {
+            Some((_, group)) => group.push(file.clone()),
+            None => groups.push((pattern, vec![file.clone()])),
+        }
+    }
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+/// Whether `path` matches `pattern`: a directory (`docs/`), or a path where `*` matches anything
+fn matches_pattern(pattern: &str, path: &str) -> bool {
+    if let Some(dir) = pattern.strip_suffix('/') {
+        return path.starts_with(dir) && path[dir.len()..].starts_with('/');
+    }
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard: the path itself, or a file inside that directory
+        return rest.is_empty() || rest.starts_with('/');
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// `repo` relative to where `cm` was started, `.` for that directory itself
+fn display_path(repo: &Path, home: &Path) -> String {
+    match repo.strip_prefix(home) {
+        Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+        Ok(relative) => relative.display().to_string(),
+        Err(_) => repo.display().to_string(),
+    }
+}
+
+/// Two-column table of repositories and what happened in each
+fn summary(results: &[(String, Outcome)]) -> String {
+    let width = results.iter().map(|(path, _)| path.chars().count()).max().unwrap_or(0);
+    results
+        .iter()
+        .map(|(path, outcome)| format!("  {:<width$}  {}", path, outcome.label(), width = width))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_summary() {
+        let home = PathBuf::from("/work/meta");
+        assert_eq!(display_path(&home, &home), ".");
+        assert_eq!(display_path(&home.join("libs/core"), &home), "libs/core");
+
+        let results = vec![
+            ("libs/core".to_string(), Outcome::Committed),
+            ("docs".to_string(), Outcome::NothingStaged),
+            (".".to_string(), Outcome::Failed(Exit::Error, "Push failed\nmore".to_string())),
+        ];
+        assert_eq!(
+            summary(&results),
+            "  libs/core  committed\n  docs       nothing staged\n  .          failed: Push failed"
+        );
+    }
+
+    #[test]
+    fn test_group_files() {
+        assert!(matches_pattern("docs/", "docs/guide.md"));
+        assert!(!matches_pattern("docs/", "docsite/index.md"));
+        assert!(matches_pattern("*.md", "src/README.md"));
+        assert!(matches_pattern("src/*/mod.rs", "src/vcs/mod.rs"));
+        assert!(matches_pattern("tests", "tests/cli.rs"));
+        assert!(!matches_pattern("*.md", "src/main.rs"));
+
+        let files: Vec<String> = ["README.md", "src/main.rs", "docs/a.md", "src/cli.rs"]
+            .iter()
+            .map(|f| f.to_string())
+            .collect();
+        assert_eq!(
+            group_files(&files, &["*.md".to_string()]),
+            vec![
+                vec!["README.md".to_string(), "docs/a.md".to_string()],
+                vec!["src/main.rs".to_string()],
+                vec!["src/cli.rs".to_string()],
+            ]
+        );
+        assert_eq!(group_files(&files, &[]).len(), 4);
+    }
+}
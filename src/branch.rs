@@ -0,0 +1,68 @@
+use crate::codeowners::glob;
+
+/// Longest branch name [`suggest_name`] produces, before the type prefix
+const MAX_SLUG: usize = 40;
+
+/// Whether `branch` matches one of the protected patterns, e.g. `main` or `release/*`
+pub fn is_protected(branch: &str, patterns: &[String]) -> bool {
+    patterns.iter().any(|pattern| glob(pattern.as_bytes(), branch.as_bytes()))
+}
+
+/// A branch name for the change a commit subject describes
+///
+/// `feat(auth): add JWT validation` becomes `feat/add-jwt-validation`; a
+/// subject without a conventional header gives just the slug.
+pub fn suggest_name(subject: &str) -> String {
+    let subject = subject.trim_start_matches(|c: char| !c.is_ascii_alphanumeric());
+    let (kind, description) = match subject.split_once(':') {
+        Some((header, description)) if !header.contains(' ') => {
+            (header.split(['(', '!']).next().filter(|kind| !kind.is_empty()), description)
+        }
+        _ => (None, subject),
+    };
+
+    let description: String = description.chars().filter(|c| !matches!(c, '\'' | '’')).collect();
+    let mut slug = String::new();
+    for word in description
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        if !slug.is_empty() && slug.len() + 1 + word.len() > MAX_SLUG {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(&word.to_lowercase());
+    }
+    slug.truncate(MAX_SLUG);
+
+    match kind {
+        Some(kind) if !slug.is_empty() => format!("{}/{}", kind.to_lowercase(), slug),
+        Some(kind) => kind.to_lowercase(),
+        None if slug.is_empty() => "wip".to_string(),
+        None => slug,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_branch() {
+        let patterns = vec!["main".to_string(), "release/*".to_string()];
+        assert!(is_protected("main", &patterns));
+        assert!(is_protected("release/1.2", &patterns));
+        assert!(!is_protected("feature/main", &patterns));
+        assert!(!is_protected("release/1.2/hotfix", &patterns));
+
+        assert_eq!(suggest_name("feat(auth): add JWT validation"), "feat/add-jwt-validation");
+        assert_eq!(suggest_name("✨ fix!: don't crash on empty input"), "fix/dont-crash-on-empty-input");
+        assert_eq!(suggest_name("Update README"), "update-readme");
+        assert_eq!(
+            suggest_name("refactor: split the configuration loader into smaller functions per section"),
+            "refactor/split-the-configuration-loader-into"
+        );
+    }
+}
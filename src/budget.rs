@@ -0,0 +1,165 @@
+/// Known context windows (in tokens), matched against whole segments of the lowercased model id.
+/// More specific patterns must come before the general ones they contain.
+const CONTEXT_WINDOWS: &[(&str, usize)] = &[
+    ("gemini", 1_048_576),
+    ("claude", 200_000),
+    ("kat-coder", 256_000),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("gpt-5", 400_000),
+    ("gpt-3.5", 16_385),
+    ("o3", 200_000),
+    ("o4-mini", 200_000),
+    ("deepseek", 128_000),
+    ("llama-3.1", 131_072),
+    ("llama3.1", 131_072),
+    ("llama-3.2", 131_072),
+    ("llama3.2", 131_072),
+    ("llama-3", 8_192),
+    ("llama3", 8_192),
+    ("qwen", 32_768),
+    ("mistral", 32_768),
+    ("phi", 4_096),
+];
+
+/// Context window assumed for models not in the table
+pub const DEFAULT_CONTEXT_TOKENS: usize = 16_384;
+
+/// Most diff tokens ever sent, however large the model's window
+///
+/// Keeps million-token models from receiving (and billing for) a whole
+/// vendored directory when a smaller slice is plenty to describe the change.
+pub const MAX_DIFF_TOKENS: usize = 32_000;
+
+/// Tokens kept free for the model's answer
+const OUTPUT_RESERVE_TOKENS: usize = 1_000;
+
+/// Look up the context window for a model
+pub fn context_window(model: &str) -> usize {
+    let model = model.to_lowercase();
+    let model = segments(&model);
+    CONTEXT_WINDOWS
+        .iter()
+        .find(|(pattern, _)| {
+            let pattern = segments(pattern);
+            model.windows(pattern.len()).any(|window| matches_segments(window, &pattern))
+        })
+        .map(|(_, tokens)| *tokens)
+        .unwrap_or(DEFAULT_CONTEXT_TOKENS)
+}
+
+/// Split a model id on `/`, `:`, `-`, `.` and any other separator
+fn segments(model: &str) -> Vec<&str> {
+    model.split(|c: char| !c.is_ascii_alphanumeric()).filter(|s| !s.is_empty()).collect()
+}
+
+/// Whether a run of model segments matches a pattern's segments
+///
+/// The last pattern segment may be followed by a version number when it ends
+/// in a letter, so `phi` matches `phi3` and `qwen` matches `qwen2`.
+fn matches_segments(window: &[&str], pattern: &[&str]) -> bool {
+    let last = pattern.len() - 1;
+    window.iter().zip(pattern).enumerate().all(|(i, (segment, expected))| {
+        if segment == expected {
+            return true;
+        }
+        i == last
+            && expected.ends_with(|c: char| c.is_ascii_alphabetic())
+            && segment
+                .strip_prefix(expected)
+                .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+    })
+}
+
+/// Rough token estimate (~4 characters per token for code and English)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+/// Tokens available for the diff once the rest of the prompt and the answer are accounted for
+///
+/// Only three quarters of the window is used so the estimate's error never
+/// pushes a request over the limit.
+pub fn diff_token_budget(context_tokens: usize, overhead: &str) -> usize {
+    (context_tokens * 3 / 4)
+        .saturating_sub(estimate_tokens(overhead))
+        .saturating_sub(OUTPUT_RESERVE_TOKENS)
+}
+
+/// Trim a diff to fit both an optional line cap and a token budget (never above `MAX_DIFF_TOKENS`)
+pub fn truncate_diff(diff: &str, max_lines: Option<usize>, max_tokens: usize) -> String {
+    let lines: Vec<&str> = diff.lines().collect();
+    let max_chars = max_tokens.min(MAX_DIFF_TOKENS).saturating_mul(4);
+
+    let mut kept = 0;
+    let mut chars = 0;
+    for line in &lines {
+        if max_lines.is_some_and(|max| kept >= max) || chars + line.len() + 1 > max_chars {
+            break;
+        }
+        chars += line.len() + 1;
+        kept += 1;
+    }
+
+    if kept == lines.len() {
+        diff.to_string()
+    } else {
+        format!(
+            "{}\n\n... [TRUNCATED: {} more lines not shown] ...",
+            lines[..kept].join("\n"),
+            lines.len() - kept
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_window() {
+        assert_eq!(context_window("gemini-flash-lite-latest"), 1_048_576);
+        assert_eq!(context_window("anthropic/claude-sonnet-4"), 200_000);
+        assert_eq!(context_window("meta-llama/llama-3.1-8b-instruct"), 131_072);
+        assert_eq!(context_window("llama3:8b"), 8_192);
+        assert_eq!(context_window("some/unknown-model"), DEFAULT_CONTEXT_TOKENS);
+        assert_eq!(context_window("phi3:mini"), 4_096);
+        assert_eq!(context_window("qwen2.5-coder:7b"), 32_768);
+        assert_eq!(context_window("openai/o3-mini"), 200_000);
+    }
+
+    #[test]
+    fn test_context_window_ignores_substrings() {
+        // "phi" inside a longer word is not the phi family
+        assert_eq!(context_window("dolphin-mixtral:8x7b"), DEFAULT_CONTEXT_TOKENS);
+        assert_eq!(context_window("sophia-7b"), DEFAULT_CONTEXT_TOKENS);
+        assert_eq!(context_window("cognitivecomputations/dolphin-mistral-24b"), 32_768);
+        // "o3" and "mistral" only as whole segments
+        assert_eq!(context_window("nemo3-chat"), DEFAULT_CONTEXT_TOKENS);
+        assert_eq!(context_window("mistralai/codestral-2501"), DEFAULT_CONTEXT_TOKENS);
+        // a version suffix is only allowed after a name, not after a number
+        assert_eq!(context_window("gpt-50"), DEFAULT_CONTEXT_TOKENS);
+    }
+
+    #[test]
+    fn test_truncate_diff_token_ceiling() {
+        let diff = "x\n".repeat(MAX_DIFF_TOKENS * 4);
+        let truncated = truncate_diff(&diff, None, 1_000_000);
+        assert!(truncated.len() <= MAX_DIFF_TOKENS * 4 + 100);
+        assert!(truncated.contains("[TRUNCATED"));
+    }
+
+    #[test]
+    fn test_truncate_diff_by_lines() {
+        let diff = "a\nb\nc\nd";
+        assert_eq!(truncate_diff(diff, Some(2), 1000), "a\nb\n\n... [TRUNCATED: 2 more lines not shown] ...");
+        assert_eq!(truncate_diff(diff, None, 1000), diff);
+    }
+
+    #[test]
+    fn test_truncate_diff_by_tokens() {
+        let diff = "0123456789\n0123456789\n0123456789";
+        // 6 tokens = 24 chars: room for two 11-char lines
+        assert_eq!(truncate_diff(diff, None, 6), "0123456789\n0123456789\n\n... [TRUNCATED: 1 more lines not shown] ...");
+    }
+}
@@ -0,0 +1,109 @@
+use serde_json::{json, Value};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::config::cache_dir;
+
+/// Provider exchanges recorded during this run, when capture is enabled
+static CAPTURE: Mutex<Option<Capture>> = Mutex::new(None);
+
+struct Capture {
+    secrets: Vec<String>,
+    exchanges: Vec<Value>,
+}
+
+/// Path of the capture file written at the end of a run
+pub fn capture_path() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("last_run.json"))
+}
+
+/// Start recording provider traffic; `secrets` are scrubbed from everything recorded
+pub fn enable(secrets: Vec<String>) {
+    let secrets = secrets.into_iter().filter(|s| !s.is_empty()).collect();
+    *CAPTURE.lock().unwrap() = Some(Capture {
+        secrets,
+        exchanges: Vec::new(),
+    });
+}
+
+/// Record a single request/response pair (no-op unless capture is enabled)
+pub fn record(provider: &str, url: &str, request: &Value, status: u16, response: &str) {
+    let mut guard = CAPTURE.lock().unwrap();
+    let Some(capture) = guard.as_mut() else {
+        return;
+    };
+
+    let response = serde_json::from_str(response).unwrap_or_else(|_| Value::String(response.to_string()));
+    let exchange = json!({
+        "provider": provider,
+        "url": url,
+        "request": request,
+        "status": status,
+        "response": response,
+    });
+
+    let exchange = redact(exchange, &capture.secrets);
+    capture.exchanges.push(exchange);
+}
+
+/// Write the recorded exchanges to the capture file, returning its path
+pub fn flush(meta: Value) -> std::io::Result<Option<PathBuf>> {
+    let Some(capture) = CAPTURE.lock().unwrap().take() else {
+        return Ok(None);
+    };
+    let Some(path) = capture_path() else {
+        return Ok(None);
+    };
+
+    let doc = redact(
+        json!({
+            "meta": meta,
+            "exchanges": capture.exchanges,
+        }),
+        &capture.secrets,
+    );
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(&doc).unwrap_or_default())?;
+    Ok(Some(path))
+}
+
+/// Replace every occurrence of a secret in string values with a placeholder
+fn redact(value: Value, secrets: &[String]) -> Value {
+    match value {
+        Value::String(mut s) => {
+            for secret in secrets {
+                s = s.replace(secret.as_str(), "[REDACTED]");
+            }
+            Value::String(s)
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(|v| redact(v, secrets)).collect()),
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| (k, redact(v, secrets)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_nested_values() {
+        let value = json!({
+            "url": "https://example.com/?key=sk-secret",
+            "nested": [{ "auth": "Bearer sk-secret" }],
+            "count": 3,
+        });
+        let redacted = redact(value, &["sk-secret".to_string()]);
+        assert_eq!(redacted["url"], "https://example.com/?key=[REDACTED]");
+        assert_eq!(redacted["nested"][0]["auth"], "Bearer [REDACTED]");
+        assert_eq!(redacted["count"], 3);
+    }
+}
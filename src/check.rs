@@ -0,0 +1,90 @@
+use std::time::{Duration, Instant};
+
+use crate::exit::{Exit, Failure};
+use crate::i18n::{tf, Msg};
+use crate::scripts::shell;
+use crate::ui;
+
+/// Output lines kept from a failing check, for the prompt and the error
+const TAIL_LINES: usize = 20;
+
+/// How a `CM_CHECK_COMMAND` run went
+#[derive(Debug, PartialEq)]
+pub struct CheckResult {
+    pub command: String,
+    pub passed: bool,
+    /// Last lines of combined stdout and stderr
+    pub tail: String,
+    pub duration: Duration,
+}
+
+impl CheckResult {
+    /// Prompt section summarizing the run
+    pub fn prompt_section(&self) -> String {
+        if self.passed {
+            format!("## Checks\n`{}` passed on the working tree.", self.command)
+        } else {
+            format!(
+                "## Checks\n`{}` fails on the working tree. Don't claim the change fixes or completes anything; this output may explain why:\n```\n{}\n```",
+                self.command, self.tail
+            )
+        }
+    }
+}
+
+/// Run `command` through the shell, reporting progress
+pub fn run(command: &str) -> Result<CheckResult, Failure> {
+    ui::status("🧪 ", tf(Msg::RunningCheck, &[&command]));
+    let start = Instant::now();
+    let output = shell(command)
+        .output()
+        .map_err(|e| Failure::new(Exit::CheckFailed, tf(Msg::CheckNotRun, &[&command, &e])))?;
+    let combined = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    let result = CheckResult {
+        command: command.to_string(),
+        passed: output.status.success(),
+        tail: tail(&combined, TAIL_LINES),
+        duration: start.elapsed(),
+    };
+    log::debug!("$ {} ({} in {:?})", command, output.status, result.duration);
+    Ok(result)
+}
+
+/// Stop unless the check passed or `allowed` (`--allow-failing-check`) says to go on
+pub fn enforce(result: &CheckResult, allowed: bool) -> Result<(), Failure> {
+    let seconds = format!("{:.1}", result.duration.as_secs_f64());
+    if result.passed {
+        ui::status("✓ ", tf(Msg::CheckPassed, &[&result.command, &seconds]));
+        return Ok(());
+    }
+    let message = tf(Msg::CheckFailed, &[&result.command, &seconds, &result.tail]);
+    if !allowed {
+        return Err(Failure::new(Exit::CheckFailed, message));
+    }
+    ui::warn("⚠️  ", message);
+    Ok(())
+}
+
+fn tail(text: &str, count: usize) -> String {
+    let lines: Vec<&str> = text.trim_end().lines().collect();
+    lines[lines.len().saturating_sub(count)..].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        assert_eq!(tail("a\nb\nc\n", 2), "b\nc");
+        assert_eq!(tail("", 2), "");
+
+        let result = run("echo building; echo 'error: expected `;`' >&2; exit 1").unwrap();
+        assert!(!result.passed);
+        assert_eq!(result.tail, "building\nerror: expected `;`");
+        assert!(result.prompt_section().contains("fails"));
+        assert_eq!(enforce(&result, false).unwrap_err().exit, Exit::CheckFailed);
+        assert!(enforce(&result, true).is_ok());
+        assert!(run("true").unwrap().passed);
+    }
+}
@@ -0,0 +1,565 @@
+use std::env;
+
+use crate::lint::Format;
+use crate::telemetry;
+use crate::ui::Verbosity;
+use crate::why::Target;
+
+/// Top-level command selected on the command line
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Generate a message for the staged changes and commit (default)
+    Generate,
+    /// Print environment info and the last captured run for bug reports
+    Report,
+    /// Print usage, keys and settings (`cm help`, `-h`, `--help`)
+    Help,
+    /// Translate existing commit messages (`cm translate <rev-range> --to <lang>`)
+    Translate {
+        range: String,
+        language: String,
+        /// Rewrite the commits via rebase instead of only printing translations
+        rewrite: bool,
+    },
+    /// Check existing commit messages against the rules (`cm lint <rev-range> [--format github]`)
+    Lint { range: String, format: Format },
+    /// Serve the generation pipeline over HTTP (`cm serve --http <addr>`)
+    Serve { addr: String },
+    /// Stage everything and commit a `wip:` snapshot, or undo one with `--pop`
+    Wip { pop: bool },
+    /// Auto-commit the working tree when it settles (`cm watch`)
+    Watch,
+    /// Check each configured provider's key, model and latency (`cm ping`)
+    Ping,
+    /// Summarize how commits in a range follow the conventions (`cm repo-stats [<rev-range>]`)
+    RepoStats { range: String },
+    /// Rewrite non-conventional messages into Conventional Commits (`cm migrate-history --range <a>..<b> [--dry-run]`)
+    MigrateHistory { range: String, dry_run: bool },
+    /// Explain a file or line range from the commits that changed it (`cm why <file>[:line]`)
+    Why { target: Target },
+    /// Draft review notes for the staged changes, a commit or a range (`cm review [<rev>|<range>]`)
+    Review { target: Option<String> },
+    /// Verify the audit log and print it (`cm audit export`)
+    AuditExport,
+    /// Report unknown and invalid `CM_*` settings (`cm config check`)
+    ConfigCheck,
+    /// Show, switch or preview opt-in usage counts (`cm telemetry status|on|off|preview`)
+    Telemetry { action: telemetry::Action },
+}
+
+/// Where the changes to describe come from
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    /// The staged changes, which are then committed (default)
+    Index,
+    /// A diff piped in (`--stdin`)
+    Stdin,
+    /// An existing commit (`--for <rev>`)
+    Revision(String),
+    /// A patch file (`--patch-file <path>`)
+    PatchFile(String),
+}
+
+/// Parsed command-line arguments
+#[derive(Debug, Clone)]
+pub struct Args {
+    pub command: Command,
+    /// Pathspecs limiting both the prompt context and the commit (`--only`)
+    pub only: Vec<String>,
+    /// Ignore saved provider responses (`--no-cache`)
+    pub no_cache: bool,
+    /// Plain, screen-reader friendly output (`--a11y` or `CM_A11Y=1`)
+    pub a11y: bool,
+    /// `-q`, `-v` or `-vv`
+    pub verbosity: Verbosity,
+    /// Changes to describe; anything but the index only prints the message
+    pub source: Source,
+    /// Write the message to this file instead of committing (`--commit-msg-filename`)
+    pub message_file: Option<String>,
+    /// Why git is preparing the message (`--commit-msg-source` or `PRE_COMMIT_COMMIT_MSG_SOURCE`)
+    pub message_source: Option<String>,
+    /// Print only the plain message for scripts and TUI custom commands (`--porcelain`)
+    pub porcelain: bool,
+    /// Commit in each dirty submodule and workspace repository too (`--recurse-submodules`)
+    pub recurse: bool,
+    /// One commit per staged file or `CM_COMMIT_GROUPS` group (`--per-file`)
+    pub per_file: bool,
+    /// Commit even if the staged changes seem to contain credentials (`--allow-secrets`)
+    pub allow_secrets: bool,
+    /// Commit even if `CM_CHECK_COMMAND` fails (`--allow-failing-check`)
+    pub allow_failing_check: bool,
+    /// Temperature 0 and a fixed seed, for reproducible messages (`--deterministic`)
+    pub deterministic: bool,
+    /// Model or alias such as `smart` to use instead of `CM_MODEL` (`--model`)
+    pub model: Option<String>,
+    /// Generate a subject line only, from a smaller prompt (`-s`/`--subject-only`)
+    pub subject_only: bool,
+    /// Pick files and hunks to withhold before the first generation (`-x`/`--exclude`)
+    pub exclude: bool,
+}
+
+impl Source {
+    fn flag(&self) -> &'static str {
+        match self {
+            Self::Index => "",
+            Self::Stdin => "--stdin",
+            Self::Revision(_) => "--for",
+            Self::PatchFile(_) => "--patch-file",
+        }
+    }
+}
+
+impl Args {
+    /// Parse arguments from the process command line
+    pub fn from_env() -> Result<Self, String> {
+        let mut args = Self::parse(env::args().skip(1))?;
+        args.a11y |= env::var("CM_A11Y").is_ok_and(|v| v == "1" || v.to_lowercase() == "true");
+        if args.message_source.is_none() {
+            args.message_source = env::var("PRE_COMMIT_COMMIT_MSG_SOURCE").ok().filter(|s| !s.is_empty());
+        }
+        Ok(args)
+    }
+
+    /// Whether the message is printed or written out rather than committed
+    pub fn describes_only(&self) -> bool {
+        self.source != Source::Index || self.message_file.is_some() || self.porcelain
+    }
+
+    pub fn parse<I>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut command = Command::Generate;
+        let mut only = Vec::new();
+        let mut positional = Vec::new();
+        let mut language = None;
+        let mut rewrite = false;
+        let mut history_range = None;
+        let mut dry_run = false;
+        let mut format = None;
+        let mut http = None;
+        let mut no_cache = false;
+        let mut a11y = false;
+        let mut quiet = false;
+        let mut verbose = 0;
+        let mut sources = Vec::new();
+        let mut message_file = None;
+        let mut message_source = None;
+        let mut porcelain = false;
+        let mut recurse = false;
+        let mut per_file = false;
+        let mut allow_secrets = false;
+        let mut allow_failing_check = false;
+        let mut deterministic = false;
+        let mut model = None;
+        let mut subject_only = false;
+        let mut exclude = false;
+        let mut args = args.into_iter().peekable();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "report" if command == Command::Generate => command = Command::Report,
+                "help" if command == Command::Generate => command = Command::Help,
+                "-h" | "--help" => command = Command::Help,
+                "translate" if command == Command::Generate => {
+                    command = Command::Translate {
+                        range: String::new(),
+                        language: String::new(),
+                        rewrite: false,
+                    }
+                }
+                "lint" if command == Command::Generate => {
+                    command = Command::Lint {
+                        range: String::new(),
+                        format: Format::Text,
+                    }
+                }
+                "serve" if command == Command::Generate => command = Command::Serve { addr: String::new() },
+                "wip" if command == Command::Generate => command = Command::Wip { pop: false },
+                "watch" if command == Command::Generate => command = Command::Watch,
+                "ping" if command == Command::Generate => command = Command::Ping,
+                "repo-stats" if command == Command::Generate => command = Command::RepoStats { range: String::new() },
+                "migrate-history" if command == Command::Generate => {
+                    command = Command::MigrateHistory {
+                        range: String::new(),
+                        dry_run: false,
+                    }
+                }
+                "review" if command == Command::Generate => command = Command::Review { target: None },
+                "audit" if command == Command::Generate => command = Command::AuditExport,
+                "config" if command == Command::Generate => command = Command::ConfigCheck,
+                "telemetry" if command == Command::Generate => {
+                    command = Command::Telemetry {
+                        action: telemetry::Action::Status,
+                    }
+                }
+                "why" if command == Command::Generate => {
+                    command = Command::Why {
+                        target: Target {
+                            path: String::new(),
+                            lines: None,
+                        },
+                    }
+                }
+                "--range" => {
+                    history_range = Some(args.next().ok_or("--range requires a revision range such as 'main..HEAD'")?);
+                }
+                "--dry-run" => dry_run = true,
+                "--pop" if matches!(command, Command::Wip { .. }) => command = Command::Wip { pop: true },
+                "--http" => {
+                    http = Some(args.next().ok_or("--http requires an address such as 127.0.0.1:8080")?);
+                }
+                "--format" => {
+                    let name = args.next().ok_or("--format requires 'text' or 'github'")?;
+                    format = Some(Format::parse(&name).ok_or(format!("Unknown format '{}'. Use 'text' or 'github'", name))?);
+                }
+                "--to" => {
+                    language = Some(args.next().ok_or("--to requires a language")?);
+                }
+                "--rewrite" => rewrite = true,
+                "--no-cache" => no_cache = true,
+                "--a11y" => a11y = true,
+                "-q" | "--quiet" => quiet = true,
+                "-v" | "--verbose" => verbose += 1,
+                "-vv" => verbose += 2,
+                "--stdin" => sources.push(Source::Stdin),
+                "--porcelain" => porcelain = true,
+                "--recurse-submodules" => recurse = true,
+                "--per-file" => per_file = true,
+                "--allow-secrets" => allow_secrets = true,
+                "--allow-failing-check" => allow_failing_check = true,
+                "--deterministic" => deterministic = true,
+                "-s" | "--subject-only" => subject_only = true,
+                "-x" | "--exclude" => exclude = true,
+                "--model" => {
+                    model = Some(args.next().ok_or("--model requires a model name or an alias such as 'smart'")?);
+                }
+                "--commit-msg-filename" => {
+                    message_file = Some(args.next().ok_or("--commit-msg-filename requires a path")?);
+                }
+                "--commit-msg-source" => {
+                    message_source = Some(args.next().ok_or("--commit-msg-source requires a source")?);
+                }
+                "--for" => sources.push(Source::Revision(args.next().ok_or("--for requires a revision")?)),
+                "--patch-file" => {
+                    sources.push(Source::PatchFile(args.next().ok_or("--patch-file requires a path")?));
+                }
+                "--only" => {
+                    while let Some(path) = args.next_if(|a| !a.starts_with("--")) {
+                        only.push(path);
+                    }
+                    if only.is_empty() {
+                        return Err("--only requires at least one pathspec".to_string());
+                    }
+                }
+                other if !other.starts_with('-') && matches!(command, Command::Translate { .. } | Command::Lint { .. } | Command::RepoStats { .. } | Command::Why { .. } | Command::Review { .. } | Command::AuditExport | Command::ConfigCheck | Command::Telemetry { .. }) => {
+                    positional.push(arg);
+                }
+                other => return Err(format!("Unknown argument '{}'", other)),
+            }
+        }
+
+        // Both subcommands take a single revision range
+        let range = || {
+            <[String; 1]>::try_from(positional.clone())
+                .map(|[range]| range)
+                .map_err(|_| "takes exactly one revision range")
+        };
+
+        if let Command::Translate { .. } = command {
+            let range = range().map_err(|e| format!("translate {}", e))?;
+            let language = language.ok_or("translate requires --to <language>")?;
+            command = Command::Translate { range, language, rewrite };
+        } else if language.is_some() || rewrite {
+            return Err("--to and --rewrite are only valid with 'translate'".to_string());
+        }
+
+        if let Command::Lint { .. } = command {
+            let range = range().map_err(|e| format!("lint {}", e))?;
+            command = Command::Lint { range, format: format.unwrap_or_default() };
+        } else if format.is_some() {
+            return Err("--format is only valid with 'lint'".to_string());
+        }
+
+        if let Command::RepoStats { .. } = command {
+            let range = match positional.as_slice() {
+                [] => "HEAD".to_string(),
+                _ => range().map_err(|e| format!("repo-stats {}", e))?,
+            };
+            command = Command::RepoStats { range };
+        }
+
+        if let Command::Review { .. } = command {
+            let target = match positional.as_slice() {
+                [] => None,
+                _ => Some(range().map_err(|_| "review takes at most one revision or range")?),
+            };
+            command = Command::Review { target };
+        }
+
+        if command == Command::AuditExport && positional != ["export"] {
+            return Err("audit takes one subcommand: 'cm audit export'".to_string());
+        }
+
+        if command == Command::ConfigCheck && positional != ["check"] {
+            return Err("config takes one subcommand: 'cm config check'".to_string());
+        }
+
+        if let Command::Telemetry { .. } = command {
+            let action = match positional.as_slice() {
+                [] => telemetry::Action::Status,
+                [name] => telemetry::Action::parse(name).ok_or(format!("Unknown telemetry action '{}'. Use status, on, off or preview", name))?,
+                _ => return Err("telemetry takes one of status, on, off or preview".to_string()),
+            };
+            command = Command::Telemetry { action };
+        }
+
+        if let Command::Why { .. } = command {
+            let [spec] = <[String; 1]>::try_from(positional.clone()).map_err(|_| "why takes exactly one <file>[:line]")?;
+            command = Command::Why { target: Target::parse(&spec)? };
+        }
+
+        if let Command::MigrateHistory { .. } = command {
+            let range = history_range.ok_or("migrate-history requires --range <a>..<b>")?;
+            if !range.contains("..") {
+                return Err("migrate-history needs a range such as 'main..HEAD'".to_string());
+            }
+            command = Command::MigrateHistory { range, dry_run };
+        } else if history_range.is_some() || dry_run {
+            return Err("--range and --dry-run are only valid with 'migrate-history'".to_string());
+        }
+
+        if let Command::Serve { .. } = command {
+            let addr = http.ok_or("serve requires --http <addr>")?;
+            command = Command::Serve { addr };
+        } else if http.is_some() {
+            return Err("--http is only valid with 'serve'".to_string());
+        }
+
+        let source = match <[Source; 1]>::try_from(sources) {
+            Ok([source]) if command != Command::Generate || !only.is_empty() => {
+                return Err(format!("{} cannot be combined with commands or --only", source.flag()));
+            }
+            Ok([source]) => source,
+            Err(sources) if sources.is_empty() => Source::Index,
+            Err(_) => return Err("Use only one of --stdin, --for and --patch-file".to_string()),
+        };
+        if message_file.is_some() && (source != Source::Index || command != Command::Generate) {
+            return Err("--commit-msg-filename describes the staged changes and can't be combined with other sources".to_string());
+        }
+
+        if recurse && (command != Command::Generate || !only.is_empty() || source != Source::Index || message_file.is_some() || porcelain) {
+            return Err("--recurse-submodules commits the staged changes of each repository and takes no other mode".to_string());
+        }
+
+        if per_file && (command != Command::Generate || source != Source::Index || message_file.is_some() || porcelain || recurse) {
+            return Err("--per-file commits the staged changes and can only be combined with --only".to_string());
+        }
+
+        if subject_only && command != Command::Generate {
+            return Err("--subject-only is only valid when generating a message".to_string());
+        }
+
+        if exclude && (command != Command::Generate || source != Source::Index || message_file.is_some() || porcelain) {
+            return Err("--exclude asks at the terminal before committing the staged changes and takes no other source or output mode".to_string());
+        }
+
+        let verbosity = match (quiet, verbose) {
+            (true, 0) => Verbosity::Quiet,
+            (true, _) => return Err("-q cannot be combined with -v".to_string()),
+            // Porcelain output is only the message, unless diagnostics were asked for
+            (false, 0) if porcelain => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Debug,
+        };
+
+        Ok(Self {
+            command,
+            only,
+            no_cache,
+            a11y,
+            verbosity,
+            source,
+            message_file,
+            message_source,
+            porcelain,
+            recurse,
+            per_file,
+            allow_secrets,
+            allow_failing_check,
+            deterministic,
+            model,
+            subject_only,
+            exclude,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_parse_only() {
+        let args = parse(&["--only", "src/", "README.md"]).unwrap();
+        assert_eq!(args.command, Command::Generate);
+        assert_eq!(args.only, vec!["src/", "README.md"]);
+        assert!(parse(&["--only"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_translate() {
+        let args = parse(&["translate", "main..HEAD", "--to", "German", "--rewrite"]).unwrap();
+        assert_eq!(
+            args.command,
+            Command::Translate {
+                range: "main..HEAD".to_string(),
+                language: "German".to_string(),
+                rewrite: true,
+            }
+        );
+        assert!(parse(&["translate", "HEAD~3.."]).is_err());
+        assert!(parse(&["--to", "French"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_lint() {
+        let args = parse(&["lint", "origin/main..HEAD", "--format", "github"]).unwrap();
+        assert_eq!(
+            args.command,
+            Command::Lint {
+                range: "origin/main..HEAD".to_string(),
+                format: Format::Github,
+            }
+        );
+        assert_eq!(
+            parse(&["lint", "HEAD"]).unwrap().command,
+            Command::Lint { range: "HEAD".to_string(), format: Format::Text }
+        );
+        assert!(parse(&["lint"]).is_err());
+        assert!(parse(&["lint", "HEAD", "--format", "json"]).is_err());
+        assert!(parse(&["--format", "github"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_serve() {
+        assert_eq!(
+            parse(&["serve", "--http", "127.0.0.1:8080"]).unwrap().command,
+            Command::Serve { addr: "127.0.0.1:8080".to_string() }
+        );
+        assert!(parse(&["serve"]).is_err());
+        assert!(parse(&["--http", "0.0.0.0:80"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_wip() {
+        assert_eq!(parse(&["wip"]).unwrap().command, Command::Wip { pop: false });
+        assert_eq!(parse(&["wip", "--pop"]).unwrap().command, Command::Wip { pop: true });
+        assert!(parse(&["--pop"]).is_err());
+        assert!(parse(&["wip", "--stdin"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_unknown() {
+        assert!(parse(&["--bogus"]).is_err());
+        assert_eq!(parse(&["report"]).unwrap().command, Command::Report);
+        assert_eq!(parse(&["--help"]).unwrap().command, Command::Help);
+        assert_eq!(parse(&["watch"]).unwrap().command, Command::Watch);
+        assert_eq!(parse(&["ping"]).unwrap().command, Command::Ping);
+        assert_eq!(parse(&["repo-stats"]).unwrap().command, Command::RepoStats { range: "HEAD".to_string() });
+        assert_eq!(
+            parse(&["repo-stats", "v1.0..HEAD"]).unwrap().command,
+            Command::RepoStats { range: "v1.0..HEAD".to_string() }
+        );
+        assert!(parse(&["repo-stats", "a", "b"]).is_err());
+        assert_eq!(
+            parse(&["migrate-history", "--range", "v0.1..HEAD", "--dry-run"]).unwrap().command,
+            Command::MigrateHistory { range: "v0.1..HEAD".to_string(), dry_run: true }
+        );
+        assert!(parse(&["migrate-history"]).is_err());
+        assert!(parse(&["migrate-history", "--range", "HEAD"]).is_err());
+        assert!(parse(&["--dry-run"]).is_err());
+        assert_eq!(
+            parse(&["why", "src/main.rs:10-12"]).unwrap().command,
+            Command::Why { target: Target { path: "src/main.rs".to_string(), lines: Some((10, 12)) } }
+        );
+        assert!(parse(&["why"]).is_err());
+        assert_eq!(parse(&["review"]).unwrap().command, Command::Review { target: None });
+        assert_eq!(
+            parse(&["review", "main..HEAD"]).unwrap().command,
+            Command::Review { target: Some("main..HEAD".to_string()) }
+        );
+        assert_eq!(parse(&["audit", "export"]).unwrap().command, Command::AuditExport);
+        assert_eq!(parse(&["config", "check"]).unwrap().command, Command::ConfigCheck);
+        assert!(parse(&["config"]).is_err());
+        assert_eq!(
+            parse(&["telemetry"]).unwrap().command,
+            Command::Telemetry { action: telemetry::Action::Status }
+        );
+        assert_eq!(
+            parse(&["telemetry", "preview"]).unwrap().command,
+            Command::Telemetry { action: telemetry::Action::Preview }
+        );
+        assert!(parse(&["telemetry", "upload"]).is_err());
+        assert!(parse(&["audit"]).is_err());
+        assert!(parse(&["--no-cache"]).unwrap().no_cache);
+        assert!(parse(&["wip", "--allow-secrets"]).unwrap().allow_secrets);
+        assert!(parse(&["--allow-failing-check"]).unwrap().allow_failing_check);
+        assert!(parse(&["--deterministic"]).unwrap().deterministic);
+        assert!(parse(&["-s"]).unwrap().subject_only);
+        assert!(parse(&["--stdin", "--subject-only"]).unwrap().subject_only);
+        assert!(parse(&["wip", "-s"]).is_err());
+        assert!(parse(&["-x", "--only", "src/"]).unwrap().exclude);
+        assert!(parse(&["--exclude", "--per-file"]).unwrap().exclude);
+        assert!(parse(&["--exclude", "--porcelain"]).is_err());
+        assert!(parse(&["--stdin", "-x"]).is_err());
+        assert_eq!(parse(&["--model", "smart"]).unwrap().model.as_deref(), Some("smart"));
+        assert!(parse(&["--model"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_source() {
+        assert_eq!(parse(&[]).unwrap().source, Source::Index);
+        assert_eq!(parse(&["--stdin"]).unwrap().source, Source::Stdin);
+        assert_eq!(parse(&["--for", "HEAD~2"]).unwrap().source, Source::Revision("HEAD~2".to_string()));
+        assert_eq!(
+            parse(&["--patch-file", "fix.patch"]).unwrap().source,
+            Source::PatchFile("fix.patch".to_string())
+        );
+        assert!(parse(&["--stdin", "--only", "src/"]).is_err());
+        assert!(parse(&["--stdin", "--for", "HEAD"]).is_err());
+        assert!(parse(&["--for"]).is_err());
+
+        let hook = parse(&["--commit-msg-filename", ".git/COMMIT_EDITMSG", "--commit-msg-source", "template"]).unwrap();
+        assert_eq!(hook.message_file.as_deref(), Some(".git/COMMIT_EDITMSG"));
+        assert_eq!(hook.message_source.as_deref(), Some("template"));
+        assert!(hook.describes_only());
+        assert!(parse(&["--commit-msg-filename", "f", "--stdin"]).is_err());
+
+        let porcelain = parse(&["--porcelain"]).unwrap();
+        assert!(porcelain.describes_only());
+        assert_eq!(porcelain.verbosity, Verbosity::Quiet);
+        assert_eq!(parse(&["--porcelain", "-v"]).unwrap().verbosity, Verbosity::Verbose);
+
+        assert!(parse(&["--recurse-submodules"]).unwrap().recurse);
+        assert!(parse(&["--recurse-submodules", "--only", "src/"]).is_err());
+        assert!(parse(&["--recurse-submodules", "--porcelain"]).is_err());
+        assert!(parse(&["--per-file", "--only", "src/"]).unwrap().per_file);
+        assert!(parse(&["--per-file", "--stdin"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_verbosity() {
+        assert_eq!(parse(&[]).unwrap().verbosity, Verbosity::Normal);
+        assert_eq!(parse(&["-q"]).unwrap().verbosity, Verbosity::Quiet);
+        assert_eq!(parse(&["-v"]).unwrap().verbosity, Verbosity::Verbose);
+        assert_eq!(parse(&["-v", "-v"]).unwrap().verbosity, Verbosity::Debug);
+        assert_eq!(parse(&["-vv"]).unwrap().verbosity, Verbosity::Debug);
+        assert!(parse(&["-q", "-v"]).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use std::fs;
+use std::path::Path;
+
+use crate::diff::normalize_newlines;
+
+/// Where GitHub and GitLab look for the owners file, in order
+const LOCATIONS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// One `pattern @owner...` line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub pattern: String,
+    pub owners: Vec<String>,
+}
+
+/// Rules from the repository's CODEOWNERS file, if it has one
+pub fn load(root: &Path) -> Vec<Rule> {
+    LOCATIONS
+        .iter()
+        .find_map(|location| fs::read_to_string(root.join(location)).ok())
+        .map(|text| parse(&normalize_newlines(&text)))
+        .unwrap_or_default()
+}
+
+fn parse(text: &str) -> Vec<Rule> {
+    text.lines()
+        .map(|line| line.split(" #").next().unwrap_or_default().trim())
+        // GitLab `[Section]` headers and comments
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('['))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pattern = fields.next()?.to_string();
+            Some(Rule {
+                pattern,
+                owners: fields.map(String::from).collect(),
+            })
+        })
+        .collect()
+}
+
+/// The rule that applies to `path`: the last matching one, as on GitHub
+fn owning_rule<'a>(rules: &'a [Rule], path: &str) -> Option<&'a Rule> {
+    rules.iter().rev().find(|rule| matches(&rule.pattern, path))
+}
+
+/// Scope named by the rules owning every one of `paths`, when they all agree
+///
+/// The scope is the last literal directory of the pattern: `/src/auth/` and
+/// `/packages/auth/**` both give `auth`. Extension rules such as `*.js` name
+/// no scope.
+pub fn scope(rules: &[Rule], paths: &[String]) -> Option<String> {
+    let mut scopes = paths.iter().map(|path| owning_rule(rules, path).and_then(|rule| pattern_scope(&rule.pattern)));
+    let first = scopes.next()??;
+    scopes.all(|scope| scope.as_deref() == Some(first.as_str())).then_some(first)
+}
+
+/// Owners of the rules covering `paths`, in order of first appearance
+pub fn reviewers(rules: &[Rule], paths: &[String]) -> Vec<String> {
+    let mut owners: Vec<String> = Vec::new();
+    for rule in paths.iter().filter_map(|path| owning_rule(rules, path)) {
+        for owner in &rule.owners {
+            if !owners.contains(owner) {
+                owners.push(owner.clone());
+            }
+        }
+    }
+    owners
+}
+
+fn pattern_scope(pattern: &str) -> Option<String> {
+    let directory = if pattern.ends_with('/') || pattern.ends_with("/**") || pattern.ends_with("/*") {
+        pattern
+    } else {
+        // A file pattern: its directory names the scope
+        pattern.rsplit_once('/').map_or("", |(dir, _)| dir)
+    };
+    directory
+        .split('/')
+        .rev()
+        .find(|segment| !segment.is_empty() && !segment.contains('*'))
+        .map(str::to_lowercase)
+}
+
+/// CODEOWNERS (gitignore-style) matching
+///
+/// A leading or inner `/` anchors the pattern at the root; otherwise it may
+/// match at any depth. A pattern matching a directory covers everything in it.
+fn matches(pattern: &str, path: &str) -> bool {
+    let trimmed = pattern.trim_end_matches('/');
+    let directory_only = pattern.ends_with('/');
+    let anchored = trimmed.contains('/');
+    let trimmed = trimmed.trim_start_matches('/');
+
+    let starts = std::iter::once(0).chain(path.match_indices('/').map(|(at, _)| at + 1));
+    for start in starts {
+        if anchored && start > 0 {
+            break;
+        }
+        let candidate = &path[start..];
+        let ends = candidate.match_indices('/').map(|(at, _)| at).chain((!directory_only).then_some(candidate.len()));
+        for end in ends {
+            if glob(trimmed.as_bytes(), &candidate.as_bytes()[..end]) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// `*` matches within a path segment, `**` across segments, `?` one character
+pub fn glob(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+            (0..=text.len()).any(|i| glob(rest, &text[i..]))
+        }
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != b'/')
+            .any(|i| glob(rest, &text[i..])),
+        [b'?', rest @ ..] => text.first().is_some_and(|&c| c != b'/') && glob(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && glob(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codeowners() {
+        let rules = parse(
+            "# Owners\n*       @acme/everyone\n*.md    @acme/docs\n/src/auth/  @alice @acme/security\n\
+             /packages/web/** @bob # frontend\n[Backend]\napi/handlers/*.go @carol\n",
+        );
+        assert_eq!(rules.len(), 5);
+
+        assert!(matches("/src/auth/", "src/auth/token.rs"));
+        assert!(!matches("/src/auth/", "lib/src/auth/token.rs"));
+        assert!(matches("*.md", "docs/guide/intro.md"));
+        assert!(matches("api/handlers/*.go", "api/handlers/user.go"));
+        assert!(!matches("api/handlers/*.go", "api/handlers/v2/user.go"));
+        assert!(matches("/packages/web/**", "packages/web/src/app.tsx"));
+
+        let paths = |list: &[&str]| list.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        let auth = paths(&["src/auth/token.rs", "src/auth/session.rs"]);
+        assert_eq!(scope(&rules, &auth).as_deref(), Some("auth"));
+        assert_eq!(reviewers(&rules, &auth), vec!["@alice", "@acme/security"]);
+        assert_eq!(scope(&rules, &paths(&["api/handlers/user.go"])).as_deref(), Some("handlers"));
+        assert_eq!(scope(&rules, &paths(&["src/auth/token.rs", "packages/web/app.tsx"])), None);
+        assert_eq!(scope(&rules, &paths(&["README.md"])), None);
+    }
+}
@@ -1,42 +1,635 @@
+use crate::budget::context_window;
+use crate::diff::normalize_newlines;
+use crate::http;
+use crate::plugin::{self, Plugin};
+use crate::prompt::PromptOptions;
 use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Diff lines sent with `--subject-only`; a one-line summary needs no more
+const SUBJECT_ONLY_DIFF_LINES: usize = 300;
 
 /// Supported AI providers
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub enum Provider {
+    #[default]
     OpenRouter,
     Gemini,
+    /// A local Ollama server
+    Ollama,
+    /// A provider plugin, by name (`CM_PROVIDER=plugin:<name>`)
+    Plugin(String),
+}
+
+impl Provider {
+    /// Lowercase name as used in `CM_PROVIDER`
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::OpenRouter => "openrouter",
+            Self::Gemini => "gemini",
+            Self::Ollama => "ollama",
+            Self::Plugin(_) => "plugin",
+        }
+    }
+
+    /// Model used when `CM_MODEL` isn't set
+    pub fn default_model(&self) -> &'static str {
+        match self {
+            Self::OpenRouter => "kwaipilot/kat-coder-pro:free",
+            Self::Gemini => "gemini-flash-lite-latest",
+            Self::Ollama => "llama3.2",
+            Self::Plugin(_) => "default",
+        }
+    }
+
+    /// Built-in model for the `fast`, `smart` and `cheap` aliases
+    fn alias(&self, name: &str) -> Option<&'static str> {
+        Some(match (self, name) {
+            (Self::OpenRouter, "fast") => "google/gemini-2.5-flash-lite",
+            (Self::OpenRouter, "smart") => "anthropic/claude-sonnet-4.5",
+            (Self::OpenRouter, "cheap") => self.default_model(),
+            (Self::Gemini, "fast" | "cheap") => "gemini-flash-lite-latest",
+            (Self::Gemini, "smart") => "gemini-2.5-pro",
+            (Self::Ollama, "fast" | "cheap") => "llama3.2",
+            (Self::Ollama, "smart") => "qwen2.5-coder:14b",
+            _ => return None,
+        })
+    }
+
+    /// Variable overriding or adding aliases for this provider
+    fn aliases_var(&self) -> &'static str {
+        match self {
+            Self::OpenRouter => "CM_OPENROUTER_MODELS",
+            Self::Gemini => "CM_GEMINI_MODELS",
+            Self::Ollama => "CM_OLLAMA_MODELS",
+            Self::Plugin(_) => "CM_PLUGIN_MODELS",
+        }
+    }
+}
+
+/// Preset controlling how long and what kind of message is requested
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum PromptStyle {
+    /// Body only when the change needs it
+    #[default]
+    Balanced,
+    /// Subject line only, as terse as possible
+    Concise,
+    /// Always a bulleted body listing the changes
+    Detailed,
+    /// Body focused on motivation and context
+    Explanatory,
+}
+
+/// How the body is laid out (`CM_BODY_STYLE`), whatever the style preset asks for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BodyStyle {
+    /// One `- ` item per change
+    Bullets,
+    /// Prose paragraphs
+    Paragraphs,
+    /// No body at all
+    Omit,
+}
+
+/// Where the type emoji goes in the subject (`CM_EMOJI_POSITION`)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum EmojiPlacement {
+    /// `✨ feat: add x`
+    #[default]
+    Prefix,
+    /// `feat: ✨ add x`
+    AfterType,
+    /// `feat: add x ✨`
+    End,
+}
+
+/// A step of the chain every message goes through (`CM_POSTPROCESS`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transform {
+    /// Drop `<think>` blocks reasoning models leave in the response
+    StripReasoning,
+    /// `feature:` → `feat:`
+    NormalizeType,
+    /// `CM_SCOPE_MODE` and `CM_SCOPE_DEPTH`
+    Scope,
+    /// `--subject-only` and `CM_BODY_STYLE`
+    BodyStyle,
+    /// `transform-*` plugins
+    Plugins,
+    /// Body lines longer than 72 columns
+    WrapBody,
+    /// `BREAKING CHANGE`, `Refs` and `CM_FOOTER`
+    Trailers,
+    /// The type emoji, when `CM_EMOJI` is on
+    Emoji,
+    /// Warnings about what `cm lint` would reject
+    Lint,
+}
+
+impl Transform {
+    /// Every step with its name, in the default order
+    pub const ALL: &'static [(Transform, &'static str)] = &[
+        (Transform::StripReasoning, "strip-reasoning"),
+        (Transform::NormalizeType, "normalize-type"),
+        (Transform::Scope, "scope"),
+        (Transform::BodyStyle, "body-style"),
+        (Transform::Plugins, "plugins"),
+        (Transform::WrapBody, "wrap-body"),
+        (Transform::Trailers, "trailers"),
+        (Transform::Emoji, "emoji"),
+        (Transform::Lint, "lint"),
+    ];
+
+    /// The steps named in `names`, in that order
+    fn from_names(names: &[String]) -> Result<Vec<Self>, ConfigError> {
+        names
+            .iter()
+            .map(|name| {
+                Self::ALL
+                    .iter()
+                    .find(|(_, n)| n == name)
+                    .map(|(transform, _)| *transform)
+                    .ok_or_else(|| ConfigError::InvalidPostprocess(name.clone()))
+            })
+            .collect()
+    }
+}
+
+/// How to scope commits that touch several areas
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ScopeMode {
+    /// Let the model decide
+    #[default]
+    Auto,
+    /// A single scope: the area with most of the changes
+    Dominant,
+    /// Every affected scope, comma-separated: `feat(api,ui): ...`
+    Stack,
+    /// Never use a scope
+    Omit,
+}
+
+/// How much of each past commit is shown as style reference
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum HistoryFormat {
+    /// `hash subject`
+    #[default]
+    Oneline,
+    /// Subject and body
+    Full,
+}
+
+/// What to do when a generated message contains a banned phrase
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum BannedAction {
+    /// Discard the message and generate another
+    #[default]
+    Regenerate,
+    /// Show the message with a red warning and let the user decide
+    Flag,
+}
+
+/// What to do when the staged changes seem to contain credentials (`CM_SECRET_SCAN`)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SecretAction {
+    /// Don't scan
+    Off,
+    /// List the suspicious lines and carry on
+    #[default]
+    Warn,
+    /// Stop before generating unless `--allow-secrets` is given
+    Refuse,
+}
+
+/// What to do when committing directly on a protected branch (`CM_PROTECTED_BRANCH_ACTION`)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ProtectedBranchAction {
+    Off,
+    /// Warn and offer to commit on a new branch instead
+    #[default]
+    Warn,
+    /// Refuse unless a new branch is created
+    Block,
+}
+
+/// What to do when a request would go over budget
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum BudgetAction {
+    /// Stop before sending the request
+    #[default]
+    Refuse,
+    /// Print a warning and send it anyway
+    Warn,
+}
+
+/// A spending cap, in tokens or in dollars
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpendLimit {
+    Tokens(u64),
+    Dollars(f64),
+}
+
+/// Sampling settings sent with every request
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sampling {
+    pub temperature: f32,
+    /// Seed for providers and models that support one (`CM_SEED`)
+    pub seed: Option<u64>,
+}
+
+impl Sampling {
+    /// Seed used by `--deterministic` when `CM_SEED` isn't set
+    const DEFAULT_SEED: u64 = 42;
+
+    /// Temperature 0 and a fixed seed, so the same prompt gives the same message
+    pub fn deterministic(self) -> Self {
+        Self {
+            temperature: 0.0,
+            seed: Some(self.seed.unwrap_or(Self::DEFAULT_SEED)),
+        }
+    }
+}
+
+/// Daily and monthly spending caps for the selected provider
+#[derive(Debug, Clone, Default)]
+pub struct BudgetOptions {
+    pub daily: Option<SpendLimit>,
+    pub monthly: Option<SpendLimit>,
+    /// Dollars per million tokens, needed for dollar limits
+    pub price_per_mtok: Option<f64>,
+    pub action: BudgetAction,
+}
+
+impl BudgetOptions {
+    pub fn is_set(&self) -> bool {
+        self.daily.is_some() || self.monthly.is_some()
+    }
+}
+
+/// A remote pushed to after committing (`CM_PUSH_REMOTES`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PushRemote {
+    pub name: String,
+    /// `false` for `name=off`, kept in the list but skipped
+    pub enabled: bool,
+}
+
+/// A path segment naming the scope (`CM_SCOPE_DEPTH`): `2`, or `packages=2` for paths under `packages/`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScopeDepth {
+    /// Directory the rule is limited to; any path when `None`
+    pub prefix: Option<String>,
+    /// 1-based segment of the path that is the scope
+    pub depth: usize,
+}
+
+/// Repository rules checked before generating (`CM_LICENSE_HEADER`, `CM_MAX_FILE_SIZE`, `CM_DISALLOWED_EXTENSIONS`)
+#[derive(Debug, Clone, Default)]
+pub struct PolicyOptions {
+    /// Text new source files must contain near the top, e.g. `SPDX-License-Identifier`
+    pub license_header: Option<String>,
+    /// Largest file size allowed, in bytes
+    pub max_file_size: Option<u64>,
+    pub disallowed_extensions: Vec<String>,
+}
+
+/// Built-in terminal color palette
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ThemeName {
+    #[default]
+    Default,
+    /// Blue/orange palette that avoids red-green distinctions
+    Deuteranopia,
+    /// Bright colors only
+    HighContrast,
+}
+
+/// Which pieces of repository context are sent with the prompt
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContextSections {
+    /// Recent commit messages used as style reference
+    pub history: bool,
+    /// `git diff --stat` summary
+    pub stat: bool,
+    /// The staged diff itself
+    pub diff: bool,
+    /// Branch name, and the ticket derived from it, in prompt templates
+    pub branch: bool,
+    /// Issues referenced by recent commits to the staged files
+    pub issues: bool,
+    /// Names and descriptions of the packages the staged files belong to
+    pub packages: bool,
+    /// Full message of the last commit to the staged files
+    pub previous: bool,
 }
 
-impl Default for Provider {
+impl Default for ContextSections {
     fn default() -> Self {
-        Provider::OpenRouter
+        Self {
+            history: true,
+            stat: true,
+            diff: true,
+            branch: true,
+            issues: true,
+            packages: true,
+            previous: true,
+        }
     }
 }
 
+impl ContextSections {
+    pub const NAMES: &'static [&'static str] = &["history", "stat", "diff", "branch", "issues", "packages", "previous"];
+
+    /// Only the sections named in `names`
+    fn from_names(names: &[String]) -> Result<Self, ConfigError> {
+        if let Some(unknown) = names.iter().find(|n| !Self::NAMES.contains(&n.as_str())) {
+            return Err(ConfigError::InvalidContextSection(unknown.clone()));
+        }
+        let has = |name: &str| names.iter().any(|n| n == name);
+        Ok(Self {
+            history: has("history"),
+            stat: has("stat"),
+            diff: has("diff"),
+            branch: has("branch"),
+            issues: has("issues"),
+            packages: has("packages"),
+            previous: has("previous"),
+        })
+    }
+}
+
+/// Shell commands run at extension points (`CM_HOOK_*`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HookScripts {
+    /// Before each request, with the prompt (`CM_HOOK_PRE_GENERATE`)
+    pub pre_generate: Option<String>,
+    /// For each generated message (`CM_HOOK_POST_GENERATE`)
+    pub post_generate: Option<String>,
+    /// Right before committing (`CM_HOOK_PRE_COMMIT_MESSAGE`)
+    pub pre_commit_message: Option<String>,
+}
+
+/// OpenRouter-specific routing options
+#[derive(Debug, Clone, Default)]
+pub struct OpenRouterOptions {
+    /// Upstream providers to try, in order (`provider.order`)
+    pub provider_order: Vec<String>,
+    /// Whether OpenRouter may fall back to providers outside `provider_order`
+    pub allow_fallbacks: Option<bool>,
+    /// Routing strategy, e.g. `fallback`
+    pub route: Option<String>,
+    /// Alternate models used when `route` is `fallback`
+    pub fallback_models: Vec<String>,
+    /// Prompt transforms, e.g. `middle-out`
+    pub transforms: Vec<String>,
+    /// Reasoning effort (`low`, `medium`, `high`) or `off` to disable reasoning
+    pub reasoning: Option<String>,
+    /// Mark the system prompt cacheable for models that support it (Anthropic)
+    pub prompt_cache: bool,
+    /// Extra headers sent with every request (`CM_OPENROUTER_HEADERS`)
+    pub headers: Vec<(String, String)>,
+}
+
+/// Gemini-specific options
+#[derive(Debug, Clone, Default)]
+pub struct GeminiOptions {
+    /// Raw `CM_GEMINI_SAFETY` value: a threshold for every category, or
+    /// comma-separated `category=threshold` pairs
+    pub safety: Option<String>,
+    /// Store the system prompt with the context caching API and reuse it across runs
+    pub prompt_cache: bool,
+    /// Extra headers sent with every request (`CM_GEMINI_HEADERS`)
+    pub headers: Vec<(String, String)>,
+}
+
+/// Ollama-specific options
+#[derive(Debug, Clone, Default)]
+pub struct OllamaOptions {
+    /// Server address, `http://host:port` or `unix:/path/to/socket`
+    pub host: String,
+    /// How long the server keeps the model loaded after a request, e.g. `30m`
+    /// or `-1` for good; the server's own default (5 minutes) when unset
+    pub keep_alive: Option<String>,
+    /// Extra headers sent with every request (`CM_OLLAMA_HEADERS`)
+    pub headers: Vec<(String, String)>,
+}
+
+/// Settings of the HTTP client all providers share
+#[derive(Debug, Clone, Default)]
+pub struct HttpOptions {
+    /// Limit on a whole request (`CM_HTTP_TIMEOUT`, seconds; 0 for none)
+    pub timeout: Option<Duration>,
+    /// Proxy for every request (`CM_HTTP_PROXY`); `HTTPS_PROXY` and `NO_PROXY` apply otherwise
+    pub proxy: Option<String>,
+    /// Extra trusted root certificate in PEM format (`CM_CA_CERT`), for TLS-intercepting gateways
+    pub ca_cert: Option<PathBuf>,
+}
+
 /// Application configuration
 #[derive(Debug, Clone)]
 pub struct Config {
     pub provider: Provider,
     pub model: String,
+    /// Model aliases from `CM_OPENROUTER_MODELS` or `CM_GEMINI_MODELS`, ahead of the built-in ones
+    pub model_aliases: Vec<(String, String)>,
     pub emoji_enabled: bool,
-    pub max_diff_lines: usize,
+    pub emoji_placement: EmojiPlacement,
+    /// Steps applied to every message, in order (`CM_POSTPROCESS`)
+    pub postprocess: Vec<Transform>,
+    /// Hard cap on diff lines; when unset only the token budget (and `MAX_DIFF_TOKENS`) applies
+    pub max_diff_lines: Option<usize>,
+    /// Context window of the selected model, in tokens
+    pub context_tokens: usize,
     pub min_message_length: usize,
     pub max_retries: u32,
+    pub style: PromptStyle,
+    /// Body layout enforced on generated messages; the style preset decides when unset
+    pub body_style: Option<BodyStyle>,
+    /// Ask for a subject line only and drop any body (`--subject-only`)
+    pub subject_only: bool,
+    /// Quality score (0-100) a message needs to be shown; lower ones are regenerated
+    pub min_quality: Option<u8>,
+    /// Number of past commits shown as style reference
+    pub history_count: usize,
+    pub history_format: HistoryFormat,
+    /// Only show past commits by this author (`me` for `user.email`)
+    pub history_author: Option<String>,
+    /// Drop merge, bot and revert commits from the style history
+    pub history_filter: bool,
+    pub scope_mode: ScopeMode,
+    /// Derive the scope from a directory level (`CM_SCOPE_DEPTH`), first matching rule wins
+    pub scope_depth: Vec<ScopeDepth>,
+    /// Suggest the scope CODEOWNERS assigns to the staged files
+    pub codeowners: bool,
+    /// List the CODEOWNERS of the committed files as reviewers
+    pub suggest_reviewers: bool,
+    /// Custom system prompt template (`CM_PROMPT_FILE`), with `{{variables}}` unresolved
+    pub prompt_template: Option<String>,
+    pub theme: ThemeName,
+    /// Context sections included in the prompt (`CM_CONTEXT`)
+    pub context: ContextSections,
+    /// Add a `Refs:` footer for the issues related commits referenced
+    pub refs_footer: bool,
+    /// Footer added when a ticket is known (`CM_FOOTER`), such as `Refs: {{ticket}}`
+    pub footer_template: Option<String>,
+    /// Link to a ticket (`CM_TICKET_URL`), such as `https://jira.example.com/browse/{{ticket}}`
+    pub ticket_url: Option<String>,
+    /// Mark messages breaking when public Rust items are removed or changed
+    pub breaking_footer: bool,
+    /// Strip author names and emails from the prompt context
+    pub privacy: bool,
+    /// Replace directory names in the prompt context with stable hashes
+    pub privacy_hash_paths: bool,
+    /// Learn from accepted and edited messages and feed that back into the prompt
+    pub learn: bool,
+    /// Ask the author one question about changes too small to explain themselves
+    pub interview: bool,
+    /// Hash-chained log of every request and what was done with its message (`CM_AUDIT`)
+    pub audit_log: Option<PathBuf>,
+    /// Phrases that must not appear in messages (`CM_BANNED_WORDS`)
+    pub banned_words: Vec<String>,
+    pub banned_action: BannedAction,
+    /// Build dependency-bump messages locally instead of asking the AI
+    pub local_deps: bool,
+    /// Number of messages generated per attempt to choose from
+    pub candidates: usize,
+    pub capture: bool,
+    /// How long provider responses are reused for identical prompts, in seconds (0 disables)
+    pub cache_ttl: u64,
+    /// Encrypt cached responses and learned preferences with a key kept in the OS keychain
+    pub encrypt_store: bool,
+    /// Spending caps (`CM_BUDGET_*`)
+    pub budget: BudgetOptions,
+    /// Pre-generation repository checks
+    pub policy: PolicyOptions,
+    /// Quick check run on the staged tree before generating, e.g. `cargo check`
+    pub check_command: Option<String>,
+    /// Scripts that can rewrite or veto prompts and messages
+    pub hooks: HookScripts,
+    /// Ask before sending more changed lines than this (`CM_CONFIRM_LINES`)
+    pub confirm_lines: Option<usize>,
+    /// Ask before sending a prompt of more tokens than this (`CM_CONFIRM_TOKENS`)
+    pub confirm_tokens: Option<u64>,
+    /// Remotes to push to after committing; empty pushes to the default remote
+    pub push_remotes: Vec<PushRemote>,
+    /// Branch patterns not to commit on directly, e.g. `main` or `release/*`
+    pub protected_branches: Vec<String>,
+    pub protected_branch_action: ProtectedBranchAction,
+    pub sampling: Sampling,
+    pub openrouter: OpenRouterOptions,
+    pub gemini: GeminiOptions,
+    pub ollama: OllamaOptions,
     pub openrouter_api_key: Option<String>,
     pub google_api_key: Option<String>,
+    /// Only needed for Ollama servers behind an authenticating proxy
+    pub ollama_api_key: Option<String>,
+    /// Passed on to provider plugins that need one
+    pub plugin_api_key: Option<String>,
+    /// Executables found in the plugin directory
+    pub plugins: Vec<Plugin>,
 }
 
 #[derive(Debug)]
 pub enum ConfigError {
     MissingApiKey(&'static str),
     InvalidProvider(String),
+    InvalidStyle(String),
+    InvalidBodyStyle(String),
+    InvalidEmojiPlacement(String),
+    InvalidQuality(String),
+    InvalidScopeMode(String),
+    InvalidHistoryFormat(String),
+    InvalidBannedAction(String),
+    InvalidContextSection(String),
+    InvalidPostprocess(String),
+    /// `CM_PROVIDER=plugin:<name>` without a `provider-<name>` plugin
+    UnknownPlugin(String),
+    InvalidTheme(String),
+    InvalidBudget(String),
+    InvalidSecretAction(String),
+    InvalidFileSize(String),
+    InvalidPushRemote(String),
+    InvalidScopeDepth(String),
+    InvalidProtectedBranchAction(String),
+    /// Variable and the entry in it that isn't a `Name: value` header
+    InvalidHeader(&'static str, String),
+    /// The HTTP client couldn't be built from `CM_HTTP_PROXY` or `CM_CA_CERT`
+    InvalidHttp(String),
+    UnreadablePromptFile(String, std::io::Error),
 }
 
 impl std::fmt::Display for ConfigError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::MissingApiKey(key) => write!(f, "Missing required environment variable: {}", key),
-            Self::InvalidProvider(p) => write!(f, "Invalid provider '{}'. Use 'openrouter' or 'gemini'", p),
+            Self::MissingApiKey(key) => write!(
+                f,
+                "Missing required environment variable: {}. Run `cm help` for provider setup",
+                key
+            ),
+            Self::InvalidProvider(p) => {
+                write!(f, "Invalid provider '{}'. Use 'openrouter', 'gemini', 'ollama' or 'plugin:<name>'", p)
+            }
+            Self::InvalidStyle(s) => write!(
+                f,
+                "Invalid style '{}'. Use 'balanced', 'concise', 'detailed' or 'explanatory'",
+                s
+            ),
+            Self::InvalidBodyStyle(s) => {
+                write!(f, "Invalid body style '{}'. Use 'bullets', 'paragraphs' or 'none'", s)
+            }
+            Self::InvalidEmojiPlacement(p) => {
+                write!(f, "Invalid emoji position '{}'. Use 'prefix', 'after-type' or 'end'", p)
+            }
+            Self::InvalidQuality(q) => write!(f, "Invalid CM_MIN_QUALITY '{}'. Use a score from 0 to 100", q),
+            Self::InvalidScopeMode(m) => {
+                write!(f, "Invalid scope mode '{}'. Use 'auto', 'dominant', 'stack' or 'omit'", m)
+            }
+            Self::InvalidHistoryFormat(h) => {
+                write!(f, "Invalid history format '{}'. Use 'oneline' or 'full'", h)
+            }
+            Self::InvalidBannedAction(a) => {
+                write!(f, "Invalid banned-words action '{}'. Use 'regenerate' or 'flag'", a)
+            }
+            Self::InvalidContextSection(c) => write!(
+                f,
+                "Invalid context section '{}'. Use any of: {}",
+                c,
+                ContextSections::NAMES.join(", ")
+            ),
+            Self::InvalidPostprocess(p) => {
+                let names: Vec<&str> = Transform::ALL.iter().map(|(_, name)| *name).collect();
+                write!(f, "Invalid CM_POSTPROCESS step '{}'. Use any of: {}", p, names.join(", "))
+            }
+            Self::UnknownPlugin(name) => match plugin_dir() {
+                Some(dir) => write!(f, "No provider plugin '{}': add an executable 'provider-{}' to {}", name, name, dir.display()),
+                None => write!(f, "No provider plugin '{}': set CM_PLUGIN_DIR", name),
+            },
+            Self::InvalidTheme(t) => {
+                write!(f, "Invalid theme '{}'. Use 'default', 'deuteranopia' or 'high-contrast'", t)
+            }
+            Self::InvalidBudget(reason) => write!(f, "Invalid budget: {}", reason),
+            Self::InvalidPushRemote(v) => {
+                write!(f, "Invalid CM_PUSH_REMOTES entry '{}'. Use a remote name, optionally with '=on' or '=off'", v)
+            }
+            Self::InvalidScopeDepth(v) => {
+                write!(f, "Invalid CM_SCOPE_DEPTH entry '{}'. Use a segment number such as '2', or 'packages=2'", v)
+            }
+            Self::InvalidProtectedBranchAction(a) => {
+                write!(f, "Invalid CM_PROTECTED_BRANCH_ACTION '{}'. Use 'off', 'warn' or 'block'", a)
+            }
+            Self::InvalidHeader(var, entry) => {
+                write!(f, "Invalid {} entry '{}'. Use comma-separated 'Name: value' headers", var, entry)
+            }
+            Self::InvalidHttp(reason) => write!(f, "Invalid HTTP settings: {}", reason),
+            Self::InvalidFileSize(v) => {
+                write!(f, "Invalid CM_MAX_FILE_SIZE '{}'. Use bytes or a size such as '500k' or '5M'", v)
+            }
+            Self::InvalidSecretAction(a) => {
+                write!(f, "Invalid secret scan setting '{}'. Use 'off', 'warn' or 'refuse'", a)
+            }
+            Self::UnreadablePromptFile(path, e) => write!(f, "Cannot read prompt file '{}': {}", path, e),
         }
     }
 }
@@ -46,63 +639,709 @@ impl std::error::Error for ConfigError {}
 impl Config {
     /// Load configuration from environment variables
     pub fn from_env() -> Result<Self, ConfigError> {
-        let provider = match env::var("CM_PROVIDER").ok().as_deref() {
-            Some("gemini") => Provider::Gemini,
-            Some("openrouter") | None => Provider::OpenRouter,
-            Some(other) => return Err(ConfigError::InvalidProvider(other.to_string())),
-        };
+        Self::for_provider(selected_provider()?)
+    }
 
-        let model = env::var("CM_MODEL").unwrap_or_else(|_| {
-            match provider {
-                Provider::OpenRouter => "kwaipilot/kat-coder-pro:free".to_string(),
-                Provider::Gemini => "gemini-flash-lite-latest".to_string(),
-            }
-        });
+    /// Load configuration as if `provider` were selected, e.g. to check every
+    /// provider with a key (`cm ping`)
+    ///
+    /// `CM_MODEL` only applies to the provider `CM_PROVIDER` selects.
+    pub fn for_provider(provider: Provider) -> Result<Self, ConfigError> {
+        let model_aliases: Vec<(String, String)> = env_list(provider.aliases_var())
+            .iter()
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(alias, model)| (alias.trim().to_string(), model.trim().to_string()))
+            .collect();
+        let model = match env::var("CM_MODEL") {
+            Ok(name) if selected_provider().ok().as_ref() == Some(&provider) => resolve_model(&provider, &model_aliases, &name),
+            _ => provider.default_model().to_string(),
+        };
 
         let emoji_enabled = env::var("CM_EMOJI")
             .map(|v| v != "0" && v.to_lowercase() != "false")
             .unwrap_or(true);
 
+        let emoji_placement = match env::var("CM_EMOJI_POSITION").ok().as_deref() {
+            Some("prefix") | Some("") | None => EmojiPlacement::Prefix,
+            Some("after-type") => EmojiPlacement::AfterType,
+            Some("end") => EmojiPlacement::End,
+            Some(other) => return Err(ConfigError::InvalidEmojiPlacement(other.to_string())),
+        };
+        let postprocess = match env::var("CM_POSTPROCESS") {
+            Ok(_) => Transform::from_names(&env_list("CM_POSTPROCESS"))?,
+            Err(_) => Transform::ALL.iter().map(|(transform, _)| *transform).collect(),
+        };
+
         let max_diff_lines = env::var("CM_MAX_DIFF_LINES")
+            .ok()
+            .and_then(|v| v.parse().ok());
+
+        let context_tokens = env::var("CM_CONTEXT_TOKENS")
             .ok()
             .and_then(|v| v.parse().ok())
-            .unwrap_or(200);
+            .unwrap_or_else(|| context_window(&model));
 
-        let min_message_length = env::var("CM_MIN_LENGTH")
+        let min_message_length = min_message_length();
+
+        let style = match env::var("CM_STYLE").ok().as_deref() {
+            Some("balanced") | None => PromptStyle::Balanced,
+            Some("concise") => PromptStyle::Concise,
+            Some("detailed") => PromptStyle::Detailed,
+            Some("explanatory") => PromptStyle::Explanatory,
+            Some(other) => return Err(ConfigError::InvalidStyle(other.to_string())),
+        };
+
+        let body_style = match env::var("CM_BODY_STYLE").ok().as_deref() {
+            None | Some("") => None,
+            Some("bullets") => Some(BodyStyle::Bullets),
+            Some("paragraphs") => Some(BodyStyle::Paragraphs),
+            Some("none") => Some(BodyStyle::Omit),
+            Some(other) => return Err(ConfigError::InvalidBodyStyle(other.to_string())),
+        };
+
+        let min_quality = match env::var("CM_MIN_QUALITY").ok().filter(|v| !v.is_empty()) {
+            None => None,
+            Some(v) => Some(v.parse().ok().filter(|q| *q <= 100).ok_or(ConfigError::InvalidQuality(v))?),
+        };
+
+        let scope_mode = match env::var("CM_SCOPE_MODE").ok().as_deref() {
+            Some("auto") | None => ScopeMode::Auto,
+            Some("dominant") => ScopeMode::Dominant,
+            Some("stack") => ScopeMode::Stack,
+            Some("omit") => ScopeMode::Omit,
+            Some(other) => return Err(ConfigError::InvalidScopeMode(other.to_string())),
+        };
+
+        let history_count = env::var("CM_HISTORY_COUNT")
             .ok()
             .and_then(|v| v.parse().ok())
-            .unwrap_or(20);
+            .unwrap_or(10);
+
+        let history_format = match env::var("CM_HISTORY_FORMAT").ok().as_deref() {
+            Some("oneline") | None => HistoryFormat::Oneline,
+            Some("full") => HistoryFormat::Full,
+            Some(other) => return Err(ConfigError::InvalidHistoryFormat(other.to_string())),
+        };
+
+        let history_author = env::var("CM_HISTORY_AUTHOR").ok().filter(|v| !v.is_empty());
+
+        let history_filter = env::var("CM_HISTORY_FILTER")
+            .map(|v| v != "0" && v.to_lowercase() != "false")
+            .unwrap_or(true);
+
+        let prompt_template = match env::var("CM_PROMPT_FILE") {
+            Ok(path) => Some(
+                std::fs::read_to_string(&path)
+                    .map(|text| normalize_newlines(&text))
+                    .map_err(|e| ConfigError::UnreadablePromptFile(path, e))?,
+            ),
+            Err(_) => None,
+        };
+
+        let theme = match env::var("CM_THEME").ok().as_deref() {
+            Some("default") | None => ThemeName::Default,
+            Some("deuteranopia") => ThemeName::Deuteranopia,
+            Some("high-contrast") => ThemeName::HighContrast,
+            Some(other) => return Err(ConfigError::InvalidTheme(other.to_string())),
+        };
+
+        let context = match env::var("CM_CONTEXT") {
+            Ok(_) => ContextSections::from_names(&env_list("CM_CONTEXT"))?,
+            Err(_) => ContextSections::default(),
+        };
+
+        let refs_footer = env::var("CM_REFS_FOOTER")
+            .map(|v| v == "1" || v.to_lowercase() == "true")
+            .unwrap_or(false);
+
+        let footer_template = env::var("CM_FOOTER").ok().filter(|v| !v.trim().is_empty());
+        let ticket_url = env::var("CM_TICKET_URL").ok().filter(|v| !v.trim().is_empty());
+
+        let breaking_footer = env::var("CM_BREAKING_FOOTER")
+            .map(|v| v != "0" && v.to_lowercase() != "false")
+            .unwrap_or(true);
+
+        let privacy = env::var("CM_PRIVACY")
+            .map(|v| v == "1" || v.to_lowercase() == "true")
+            .unwrap_or(false);
+
+        let privacy_hash_paths = env::var("CM_PRIVACY_HASH_PATHS")
+            .map(|v| v == "1" || v.to_lowercase() == "true")
+            .unwrap_or(false);
+
+        let codeowners = env::var("CM_CODEOWNERS")
+            .map(|v| v != "0" && v.to_lowercase() != "false")
+            .unwrap_or(true);
+
+        let suggest_reviewers = env::var("CM_SUGGEST_REVIEWERS")
+            .map(|v| v == "1" || v.to_lowercase() == "true")
+            .unwrap_or(false);
+
+        let learn = env::var("CM_LEARN")
+            .map(|v| v != "0" && v.to_lowercase() != "false")
+            .unwrap_or(true);
+
+        let interview = env::var("CM_INTERVIEW")
+            .map(|v| v == "1" || v.to_lowercase() == "true")
+            .unwrap_or(false);
+
+        // CM_AUDIT_LOG alone turns logging on too
+        let audit_log = match env::var_os("CM_AUDIT_LOG").filter(|path| !path.is_empty()) {
+            Some(path) => Some(PathBuf::from(path)),
+            None if env::var("CM_AUDIT").is_ok_and(|v| v == "1" || v.to_lowercase() == "true") => {
+                cache_dir().map(|dir| dir.join("audit.jsonl"))
+            }
+            None => None,
+        };
+
+        let banned_words = env_list("CM_BANNED_WORDS");
+        let banned_action = match env::var("CM_BANNED_ACTION").ok().as_deref() {
+            Some("regenerate") | None => BannedAction::Regenerate,
+            Some("flag") => BannedAction::Flag,
+            Some(other) => return Err(ConfigError::InvalidBannedAction(other.to_string())),
+        };
+
+        let local_deps = env::var("CM_LOCAL_DEPS")
+            .map(|v| v != "0" && v.to_lowercase() != "false")
+            .unwrap_or(true);
+
+        let candidates = env::var("CM_CANDIDATES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(1);
+
+        let capture = env::var("CM_CAPTURE")
+            .map(|v| v == "1" || v.to_lowercase() == "true")
+            .unwrap_or(false);
+
+        let cache_ttl = env::var("CM_CACHE_TTL")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(86_400);
+
+        let encrypt_store = env::var("CM_ENCRYPT_STORE")
+            .map(|v| v != "0" && v.to_lowercase() != "false")
+            .unwrap_or(true);
+
+        let budget = budget_options(&provider)?;
+
+        let scope_depth = env_list("CM_SCOPE_DEPTH")
+            .iter()
+            .map(|entry| parse_scope_depth(entry))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let push_remotes = env_list("CM_PUSH_REMOTES")
+            .iter()
+            .map(|entry| parse_push_remote(entry))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let protected_branches = match env::var("CM_PROTECTED_BRANCHES") {
+            Ok(_) => env_list("CM_PROTECTED_BRANCHES"),
+            Err(_) => ["main", "master", "release/*"].map(String::from).to_vec(),
+        };
+        let protected_branch_action = match env::var("CM_PROTECTED_BRANCH_ACTION").ok().as_deref() {
+            Some("warn") | None => ProtectedBranchAction::Warn,
+            Some("off") => ProtectedBranchAction::Off,
+            Some("block") => ProtectedBranchAction::Block,
+            Some(other) => return Err(ConfigError::InvalidProtectedBranchAction(other.to_string())),
+        };
+
+        let policy = PolicyOptions {
+            license_header: env::var("CM_LICENSE_HEADER").ok().filter(|v| !v.trim().is_empty()),
+            max_file_size: env::var("CM_MAX_FILE_SIZE").ok().map(|v| parse_size(&v)).transpose()?,
+            disallowed_extensions: env_list("CM_DISALLOWED_EXTENSIONS"),
+        };
+
+        let sampling = Sampling {
+            temperature: env::var("CM_TEMPERATURE").ok().and_then(|v| v.parse().ok()).unwrap_or(0.7),
+            seed: env::var("CM_SEED").ok().and_then(|v| v.parse().ok()),
+        };
+        let sampling = if env::var("CM_DETERMINISTIC").is_ok_and(|v| v == "1" || v.to_lowercase() == "true") {
+            sampling.deterministic()
+        } else {
+            sampling
+        };
+
+        let prompt_cache = env::var("CM_PROMPT_CACHE")
+            .map(|v| v != "0" && v.to_lowercase() != "false")
+            .unwrap_or(true);
+
+        let openrouter = OpenRouterOptions {
+            provider_order: env_list("CM_OPENROUTER_PROVIDERS"),
+            allow_fallbacks: env::var("CM_OPENROUTER_ALLOW_FALLBACKS")
+                .ok()
+                .map(|v| v != "0" && v.to_lowercase() != "false"),
+            route: env::var("CM_OPENROUTER_ROUTE").ok(),
+            fallback_models: env_list("CM_OPENROUTER_FALLBACK_MODELS"),
+            transforms: env_list("CM_OPENROUTER_TRANSFORMS"),
+            reasoning: env::var("CM_OPENROUTER_REASONING").ok(),
+            prompt_cache,
+            headers: env_headers("CM_OPENROUTER_HEADERS")?,
+        };
+
+        let gemini = GeminiOptions {
+            safety: env::var("CM_GEMINI_SAFETY").ok(),
+            prompt_cache,
+            headers: env_headers("CM_GEMINI_HEADERS")?,
+        };
+
+        let ollama = OllamaOptions {
+            host: ollama_host(),
+            keep_alive: env::var("CM_OLLAMA_KEEP_ALIVE").ok().filter(|v| !v.trim().is_empty()),
+            headers: env_headers("CM_OLLAMA_HEADERS")?,
+        };
+
+        http::init(&HttpOptions {
+            timeout: Some(env::var("CM_HTTP_TIMEOUT").ok().and_then(|v| v.parse().ok()).unwrap_or(120))
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+            proxy: env::var("CM_HTTP_PROXY").ok().filter(|v| !v.trim().is_empty()),
+            ca_cert: env::var_os("CM_CA_CERT").map(PathBuf::from),
+        })?;
 
         let openrouter_api_key = env::var("OPENROUTER_API_KEY").ok();
         let google_api_key = env::var("GOOGLE_API_KEY").ok();
+        let ollama_api_key = env::var("OLLAMA_API_KEY").ok();
+        let plugin_api_key = env::var("CM_PLUGIN_API_KEY").ok();
+        let plugins = plugin_dir().map(|dir| plugin::discover(&dir)).unwrap_or_default();
 
-        match provider {
+        match &provider {
             Provider::OpenRouter if openrouter_api_key.is_none() => {
                 return Err(ConfigError::MissingApiKey("OPENROUTER_API_KEY"));
             }
             Provider::Gemini if google_api_key.is_none() => {
                 return Err(ConfigError::MissingApiKey("GOOGLE_API_KEY"));
             }
+            Provider::Plugin(name) if plugin::provider(&plugins, name).is_none() => {
+                return Err(ConfigError::UnknownPlugin(name.clone()));
+            }
             _ => {}
         }
 
         Ok(Self {
             provider,
             model,
+            model_aliases,
             emoji_enabled,
+            emoji_placement,
+            postprocess,
             max_diff_lines,
+            context_tokens,
             min_message_length,
             max_retries: 3,
+            style,
+            body_style,
+            subject_only: false,
+            min_quality,
+            history_count,
+            history_format,
+            history_author,
+            history_filter,
+            scope_mode,
+            scope_depth,
+            codeowners,
+            suggest_reviewers,
+            prompt_template,
+            theme,
+            context,
+            refs_footer,
+            footer_template,
+            ticket_url,
+            breaking_footer,
+            privacy,
+            privacy_hash_paths,
+            learn,
+            audit_log,
+            interview,
+            banned_words,
+            banned_action,
+            local_deps,
+            candidates,
+            capture,
+            cache_ttl,
+            encrypt_store,
+            budget,
+            policy,
+            check_command: env::var("CM_CHECK_COMMAND").ok().filter(|c| !c.trim().is_empty()),
+            hooks: HookScripts {
+                pre_generate: env::var("CM_HOOK_PRE_GENERATE").ok().filter(|c| !c.trim().is_empty()),
+                post_generate: env::var("CM_HOOK_POST_GENERATE").ok().filter(|c| !c.trim().is_empty()),
+                pre_commit_message: env::var("CM_HOOK_PRE_COMMIT_MESSAGE").ok().filter(|c| !c.trim().is_empty()),
+            },
+            confirm_lines: env::var("CM_CONFIRM_LINES").ok().and_then(|v| v.parse().ok()),
+            confirm_tokens: env::var("CM_CONFIRM_TOKENS").ok().and_then(|v| v.parse().ok()),
+            push_remotes,
+            protected_branches,
+            protected_branch_action,
+            sampling,
+            openrouter,
+            gemini,
+            ollama,
             openrouter_api_key,
             google_api_key,
+            ollama_api_key,
+            plugin_api_key,
+            plugins,
         })
     }
 
+    /// Switch to the model `name`, which may be an alias such as `smart` (`--model`)
+    pub fn select_model(&mut self, name: &str) {
+        self.model = resolve_model(&self.provider, &self.model_aliases, name);
+        if env::var("CM_CONTEXT_TOKENS").is_err() {
+            self.context_tokens = context_window(&self.model);
+        }
+    }
+
+    /// Generate one-line messages from a smaller prompt (`--subject-only`)
+    ///
+    /// The body instructions, the Conventional Commits summary, the change
+    /// statistics, related issues, packages and the previous commit are left out, the diff is capped
+    /// and no footers are added, since they would need a body.
+    pub fn subject_only(&mut self) {
+        self.subject_only = true;
+        self.style = PromptStyle::Concise;
+        self.context.stat = false;
+        self.context.issues = false;
+        self.context.packages = false;
+        self.context.previous = false;
+        self.max_diff_lines = Some(self.max_diff_lines.unwrap_or(SUBJECT_ONLY_DIFF_LINES).min(SUBJECT_ONLY_DIFF_LINES));
+        self.refs_footer = false;
+        self.footer_template = None;
+        self.breaking_footer = false;
+    }
+
+    /// Name of the environment variable holding the current provider's API key
+    pub fn api_key_var(&self) -> &'static str {
+        match self.provider {
+            Provider::OpenRouter => "OPENROUTER_API_KEY",
+            Provider::Gemini => "GOOGLE_API_KEY",
+            Provider::Ollama => "OLLAMA_API_KEY",
+            Provider::Plugin(_) => "CM_PLUGIN_API_KEY",
+        }
+    }
+
+    /// Options that shape the built-in instructions
+    pub fn prompt_options(&self) -> PromptOptions {
+        PromptOptions {
+            style: self.style,
+            scope_mode: self.scope_mode,
+            context: self.context,
+            body_style: self.body_style,
+            subject_only: self.subject_only,
+        }
+    }
+
     /// Get the API key for the current provider
     pub fn api_key(&self) -> &str {
         match self.provider {
             Provider::OpenRouter => self.openrouter_api_key.as_ref().unwrap(),
             Provider::Gemini => self.google_api_key.as_ref().unwrap(),
+            Provider::Ollama => self.ollama_api_key.as_deref().unwrap_or_default(),
+            Provider::Plugin(_) => self.plugin_api_key.as_deref().unwrap_or_default(),
+        }
+    }
+}
+
+/// When `cm watch` commits
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WatchOptions {
+    /// Commit once the tree has been unchanged this long (`CM_WATCH_QUIET`, seconds)
+    pub quiet: Duration,
+    /// Commit at least this often while changes keep coming (`CM_WATCH_INTERVAL`, seconds)
+    pub interval: Duration,
+}
+
+impl WatchOptions {
+    pub fn from_env() -> Self {
+        let seconds = |key: &str, default: u64| {
+            Duration::from_secs(env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default))
+        };
+        Self {
+            quiet: seconds("CM_WATCH_QUIET", 60),
+            interval: seconds("CM_WATCH_INTERVAL", 900),
         }
     }
 }
+
+/// Rules `cm lint` checks commit messages against
+///
+/// Read separately from [`Config`] so CI can lint without an API key.
+#[derive(Debug, Clone)]
+pub struct LintOptions {
+    pub min_length: usize,
+    pub banned_words: Vec<String>,
+}
+
+impl LintOptions {
+    pub fn from_env() -> Self {
+        Self {
+            min_length: min_message_length(),
+            banned_words: env_list("CM_BANNED_WORDS"),
+        }
+    }
+}
+
+/// Bearer token clients of `cm serve` must send (`CM_SERVE_TOKEN`)
+pub fn serve_token() -> Option<String> {
+    env::var("CM_SERVE_TOKEN").ok().filter(|v| !v.trim().is_empty())
+}
+
+/// Extra repositories `--recurse-submodules` visits (`CM_WORKSPACE_REPOS`), relative to the current directory
+pub fn workspace_repos() -> Vec<String> {
+    env_list("CM_WORKSPACE_REPOS")
+}
+
+/// Patterns whose matching files `--per-file` commits together (`CM_COMMIT_GROUPS`)
+pub fn commit_groups() -> Vec<String> {
+    env_list("CM_COMMIT_GROUPS")
+}
+
+/// Where opted-in usage counts are sent (`CM_TELEMETRY_URL`); nothing is sent without it
+pub fn telemetry_url() -> Option<String> {
+    env::var("CM_TELEMETRY_URL").ok().filter(|v| !v.trim().is_empty())
+}
+
+/// Whether `CM_TELEMETRY=0` or `DO_NOT_TRACK=1` rule out telemetry, whatever `cm telemetry on` said
+pub fn telemetry_blocked() -> bool {
+    env::var("CM_TELEMETRY").is_ok_and(|v| v == "0" || v.to_lowercase() == "false")
+        || env::var("DO_NOT_TRACK").is_ok_and(|v| v == "1" || v.to_lowercase() == "true")
+}
+
+/// How staged credentials are handled (`CM_SECRET_SCAN`)
+///
+/// Read outside [`Config`] so `cm wip` and `cm watch` check even without an API key.
+pub fn secret_action() -> Result<SecretAction, ConfigError> {
+    secret_action_or(SecretAction::Warn)
+}
+
+/// `CM_SECRET_SCAN`, or `default` when it isn't set
+pub fn secret_action_or(default: SecretAction) -> Result<SecretAction, ConfigError> {
+    match env::var("CM_SECRET_SCAN").ok().as_deref() {
+        None => Ok(default),
+        Some("warn") => Ok(SecretAction::Warn),
+        Some("off") => Ok(SecretAction::Off),
+        Some("refuse") => Ok(SecretAction::Refuse),
+        Some(other) => Err(ConfigError::InvalidSecretAction(other.to_string())),
+    }
+}
+
+/// Shortest acceptable subject line (`CM_MIN_LENGTH`)
+fn min_message_length() -> usize {
+    env::var("CM_MIN_LENGTH")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(20)
+}
+
+/// The provider `CM_PROVIDER` selects
+pub fn selected_provider() -> Result<Provider, ConfigError> {
+    match env::var("CM_PROVIDER").ok().as_deref() {
+        Some("gemini") => Ok(Provider::Gemini),
+        Some("ollama") => Ok(Provider::Ollama),
+        Some("openrouter") | None => Ok(Provider::OpenRouter),
+        Some(other) if other.starts_with("plugin:") && other.len() > "plugin:".len() => {
+            Ok(Provider::Plugin(other["plugin:".len()..].to_string()))
+        }
+        Some(other) => Err(ConfigError::InvalidProvider(other.to_string())),
+    }
+}
+
+/// Ollama server address: `CM_OLLAMA_HOST`, then Ollama's own `OLLAMA_HOST`
+///
+/// `OLLAMA_HOST` is often a bare `host:port`; plain HTTP is assumed then.
+fn ollama_host() -> String {
+    let host = env::var("CM_OLLAMA_HOST")
+        .or_else(|_| env::var("OLLAMA_HOST"))
+        .ok()
+        .map(|v| v.trim().trim_end_matches('/').to_string())
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "http://127.0.0.1:11434".to_string());
+    if host.contains("://") || host.starts_with("unix:") {
+        host
+    } else {
+        format!("http://{}", host)
+    }
+}
+
+/// The model an alias stands for with `provider`, or `name` itself if it isn't one
+fn resolve_model(provider: &Provider, aliases: &[(String, String)], name: &str) -> String {
+    aliases
+        .iter()
+        .find(|(alias, _)| alias == name)
+        .map(|(_, model)| model.as_str())
+        .or_else(|| provider.alias(name))
+        .unwrap_or(name)
+        .to_string()
+}
+
+/// The value of `key` for `provider`: either a plain value, or comma-separated
+/// `provider=value` pairs
+fn per_provider(key: &str, provider: &Provider) -> Option<String> {
+    let raw = env::var(key).ok()?;
+    if !raw.contains('=') {
+        return Some(raw.trim().to_string()).filter(|v| !v.is_empty());
+    }
+    raw.split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| name.trim() == provider.id())
+        .map(|(_, value)| value.trim().to_string())
+}
+
+/// Extra request headers from `key`, as comma-separated `Name: value` pairs
+fn env_headers(key: &'static str) -> Result<Vec<(String, String)>, ConfigError> {
+    env_list(key)
+        .iter()
+        .map(|entry| {
+            let (name, value) = entry
+                .split_once(':')
+                .filter(|(name, _)| !name.trim().is_empty() && !name.trim().contains(char::is_whitespace))
+                .ok_or_else(|| ConfigError::InvalidHeader(key, entry.to_string()))?;
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Parse `origin`, `mirror=off` or `backup=on` from `CM_PUSH_REMOTES`
+fn parse_push_remote(entry: &str) -> Result<PushRemote, ConfigError> {
+    let (name, enabled) = match entry.split_once('=') {
+        None => (entry, true),
+        Some((name, "on" | "1" | "true")) => (name, true),
+        Some((name, "off" | "0" | "false")) => (name, false),
+        Some(_) => return Err(ConfigError::InvalidPushRemote(entry.to_string())),
+    };
+    let name = name.trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(ConfigError::InvalidPushRemote(entry.to_string()));
+    }
+    Ok(PushRemote {
+        name: name.to_string(),
+        enabled,
+    })
+}
+
+/// Parse `2` or `packages=2` from `CM_SCOPE_DEPTH`
+fn parse_scope_depth(entry: &str) -> Result<ScopeDepth, ConfigError> {
+    let invalid = || ConfigError::InvalidScopeDepth(entry.to_string());
+    let (prefix, depth) = match entry.split_once('=') {
+        Some((prefix, depth)) => (Some(prefix.trim().trim_matches('/').to_string()).filter(|p| !p.is_empty()), depth),
+        None => (None, entry),
+    };
+    match depth.trim().parse() {
+        Ok(depth) if depth > 0 => Ok(ScopeDepth { prefix, depth }),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parse `1048576`, `500k` or `5M` as a number of bytes
+fn parse_size(value: &str) -> Result<u64, ConfigError> {
+    let value = value.trim();
+    let (number, multiplier) = match value.char_indices().last() {
+        Some((at, 'k' | 'K')) => (&value[..at], 1024),
+        Some((at, 'm' | 'M')) => (&value[..at], 1024 * 1024),
+        Some((at, 'g' | 'G')) => (&value[..at], 1024 * 1024 * 1024),
+        _ => (value, 1),
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .map(|n| n * multiplier)
+        .map_err(|_| ConfigError::InvalidFileSize(value.to_string()))
+}
+
+/// Parse `50000` as a token limit and `$2.50` as a dollar limit
+fn parse_limit(key: &str, value: &str) -> Result<SpendLimit, ConfigError> {
+    let invalid = || ConfigError::InvalidBudget(format!("{}='{}' is not a token count or $amount", key, value));
+    match value.strip_prefix('$') {
+        Some(dollars) => dollars.parse().map(SpendLimit::Dollars).map_err(|_| invalid()),
+        None => value.parse().map(SpendLimit::Tokens).map_err(|_| invalid()),
+    }
+}
+
+fn budget_options(provider: &Provider) -> Result<BudgetOptions, ConfigError> {
+    let limit = |key: &str| per_provider(key, provider).map(|v| parse_limit(key, &v)).transpose();
+    let daily = limit("CM_BUDGET_DAILY")?;
+    let monthly = limit("CM_BUDGET_MONTHLY")?;
+
+    let price_per_mtok = match per_provider("CM_PRICE_PER_MTOK", provider) {
+        Some(v) => Some(v.parse().map_err(|_| {
+            ConfigError::InvalidBudget(format!("CM_PRICE_PER_MTOK='{}' is not a number", v))
+        })?),
+        None => None,
+    };
+    let uses_dollars = [daily, monthly].iter().any(|l| matches!(l, Some(SpendLimit::Dollars(_))));
+    if uses_dollars && price_per_mtok.is_none() {
+        return Err(ConfigError::InvalidBudget(
+            "dollar limits need CM_PRICE_PER_MTOK (dollars per million tokens)".to_string(),
+        ));
+    }
+
+    let action = match env::var("CM_BUDGET_ACTION").ok().as_deref() {
+        Some("refuse") | None => BudgetAction::Refuse,
+        Some("warn") => BudgetAction::Warn,
+        Some(other) => {
+            return Err(ConfigError::InvalidBudget(format!(
+                "unknown action '{}'. Use 'refuse' or 'warn'",
+                other
+            )))
+        }
+    };
+
+    Ok(BudgetOptions {
+        daily,
+        monthly,
+        price_per_mtok,
+        action,
+    })
+}
+
+/// Read a comma-separated environment variable into a list, skipping empty entries
+fn env_list(key: &str) -> Vec<String> {
+    env::var(key)
+        .map(|v| {
+            v.split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Where plugins are looked for: `CM_PLUGIN_DIR`, or `plugins` in the per-user
+/// config directory (`$XDG_CONFIG_HOME/commit-message` or `~/.config/commit-message`;
+/// `%APPDATA%\commit-message` on Windows)
+pub fn plugin_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("CM_PLUGIN_DIR").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir));
+    }
+    #[cfg(windows)]
+    if let Some(roaming) = env::var_os("APPDATA").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(roaming).join("commit-message").join("plugins"));
+    }
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME")
+                .or_else(|| env::var_os("USERPROFILE"))
+                .map(|home| PathBuf::from(home).join(".config"))
+        })?;
+    Some(base.join("commit-message").join("plugins"))
+}
+
+/// Per-user cache directory (`$XDG_CACHE_HOME/commit-message` or `~/.cache/commit-message`;
+/// `%LOCALAPPDATA%\commit-message` on Windows)
+pub fn cache_dir() -> Option<PathBuf> {
+    #[cfg(windows)]
+    if let Some(local) = env::var_os("LOCALAPPDATA").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(local).join("commit-message"));
+    }
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME")
+                .or_else(|| env::var_os("USERPROFILE"))
+                .map(|home| PathBuf::from(home).join(".cache"))
+        })?;
+    Some(base.join("commit-message"))
+}
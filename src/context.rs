@@ -0,0 +1,79 @@
+use crate::diff::{diff_stat, parse_diff, FileDiff};
+use crate::privacy::Privacy;
+use crate::vcs::Vcs;
+
+/// The changes being described and what is known about the repository they
+/// belong to, passed through the pipeline as one value
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GitContext {
+    /// Unified diff of the changes
+    pub diff: String,
+    /// `diff` split into files and hunks
+    pub files: Vec<FileDiff>,
+    /// `git diff --stat` style summary
+    pub stat: String,
+    /// Recent commits formatted for the prompt, newest first
+    pub history: String,
+    /// Checked-out branch or bookmark
+    pub branch: Option<String>,
+    /// Top-level directory of the repository
+    pub root: Option<String>,
+    /// Repository name, from its directory
+    pub repo_name: Option<String>,
+    /// `user.name` of whoever is committing
+    pub author: Option<String>,
+}
+
+impl GitContext {
+    /// Context for `diff` alone, with the summary computed from the diff itself
+    pub fn from_diff(diff: String, history: String) -> Self {
+        let files = parse_diff(&diff);
+        Self {
+            stat: diff_stat(&files),
+            diff,
+            files,
+            history,
+            ..Self::default()
+        }
+    }
+
+    /// Add the branch and repository details of `repo`
+    pub fn with_repo(self, repo: &dyn Vcs) -> Self {
+        Self {
+            branch: repo.branch(),
+            root: repo.root(),
+            repo_name: repo.repo_name(),
+            author: repo.config_value("user.name"),
+            ..self
+        }
+    }
+
+    /// Paths of the changed files, in diff order
+    pub fn paths(&self) -> Vec<String> {
+        self.files.iter().map(|file| file.path.clone()).collect()
+    }
+
+    /// A copy for the prompt, with history and summary scrubbed in privacy mode
+    pub fn scrubbed(&self, privacy: &Privacy) -> Self {
+        Self {
+            history: privacy.apply(&self.history),
+            stat: privacy.apply(&self.stat),
+            ..self.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_diff() {
+        let diff = "diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,2 +1,2 @@\n-old\n+new\n+more\n";
+        let context = GitContext::from_diff(diff.to_string(), "abc1234 feat: x\n".to_string());
+        assert_eq!(context.paths(), vec!["src/lib.rs"]);
+        assert_eq!(context.files[0].line_counts(), (2, 1));
+        assert!(context.stat.contains("1 file(s) changed, 2 insertion(s)(+), 1 deletion(s)(-)"));
+        assert_eq!(context.branch, None);
+    }
+}
@@ -0,0 +1,319 @@
+use std::path::Path;
+
+use crate::diff::FileDiff;
+use crate::message::CommitMessage;
+
+/// A dependency whose version changed in a manifest or lockfile
+#[derive(Debug, Clone, PartialEq)]
+pub struct DependencyChange {
+    pub name: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl DependencyChange {
+    fn describe(&self) -> String {
+        match (&self.from, &self.to) {
+            (Some(from), Some(to)) => format!("bump {} from {} to {}", self.name, from, to),
+            (None, Some(to)) => format!("add {} {}", self.name, to),
+            (Some(from), None) => format!("remove {} {}", self.name, from),
+            (None, None) => format!("update {}", self.name),
+        }
+    }
+}
+
+/// Manifest keys that describe the package itself rather than a dependency
+const PACKAGE_KEYS: &[&str] = &[
+    "name",
+    "version",
+    "edition",
+    "description",
+    "license",
+    "authors",
+    "rust-version",
+    "private",
+    "main",
+    "type",
+];
+
+/// How a file lists dependency versions
+enum Format {
+    /// A name line followed by a version line (`Cargo.lock`, `package-lock.json`, ...)
+    Lockfile {
+        name: fn(&str) -> Option<String>,
+        version: fn(&str) -> Option<String>,
+    },
+    /// `name` and version on the same line (`Cargo.toml`, `go.mod`, ...)
+    Manifest(fn(&str) -> Option<(String, String)>),
+}
+
+fn format_for(path: &str) -> Option<Format> {
+    let name = Path::new(path).file_name()?.to_str()?;
+    Some(match name {
+        "Cargo.lock" | "poetry.lock" => Format::Lockfile {
+            name: |l| toml_string(l, "name"),
+            version: |l| toml_string(l, "version"),
+        },
+        "package-lock.json" => Format::Lockfile {
+            name: npm_lock_name,
+            version: |l| json_string(l, "version"),
+        },
+        "composer.lock" => Format::Lockfile {
+            name: |l| json_string(l, "name"),
+            version: |l| json_string(l, "version"),
+        },
+        "yarn.lock" => Format::Lockfile {
+            name: yarn_lock_name,
+            version: |l| {
+                let rest = l.trim().strip_prefix("version ")?;
+                Some(rest.trim_matches('"').to_string())
+            },
+        },
+        "Cargo.toml" => Format::Manifest(cargo_toml_dependency),
+        "package.json" => Format::Manifest(package_json_dependency),
+        "go.mod" => Format::Manifest(go_mod_dependency),
+        "requirements.txt" => Format::Manifest(requirements_dependency),
+        "Gemfile.lock" => Format::Manifest(gemfile_lock_dependency),
+        _ => return None,
+    })
+}
+
+/// `key = "value"` on a TOML line
+fn toml_string(line: &str, key: &str) -> Option<String> {
+    let (k, v) = line.split_once('=')?;
+    if k.trim() != key {
+        return None;
+    }
+    Some(v.trim().trim_matches('"').to_string())
+}
+
+/// `"key": "value",` on a JSON line
+fn json_string(line: &str, key: &str) -> Option<String> {
+    let (k, v) = line.split_once(':')?;
+    if k.trim().trim_matches('"') != key {
+        return None;
+    }
+    let v = v.trim().trim_end_matches(',').trim();
+    v.starts_with('"').then(|| v.trim_matches('"').to_string())
+}
+
+fn npm_lock_name(line: &str) -> Option<String> {
+    let key = line.trim().strip_suffix('{')?.trim().strip_suffix(':')?.trim_matches('"');
+    let (_, name) = key.rsplit_once("node_modules/")?;
+    Some(name.to_string())
+}
+
+fn yarn_lock_name(line: &str) -> Option<String> {
+    if line.starts_with(' ') || !line.ends_with(':') {
+        return None;
+    }
+    let spec = line.trim_end_matches(':').split(',').next()?.trim().trim_matches('"');
+    let at = spec.rfind('@').filter(|i| *i > 0)?;
+    Some(spec[..at].to_string())
+}
+
+fn cargo_toml_dependency(line: &str) -> Option<(String, String)> {
+    let (name, value) = line.split_once('=')?;
+    let name = name.trim().to_string();
+    if PACKAGE_KEYS.contains(&name.as_str()) {
+        return None;
+    }
+    let value = value.trim();
+    let version = if value.starts_with('{') {
+        let (_, rest) = value.split_once("version")?;
+        rest.trim_start().strip_prefix('=')?.trim().split('"').nth(1)?.to_string()
+    } else {
+        value.strip_prefix('"')?.split('"').next()?.to_string()
+    };
+    Some((name, version))
+}
+
+fn package_json_dependency(line: &str) -> Option<(String, String)> {
+    let (name, value) = line.split_once(':')?;
+    let name = name.trim().trim_matches('"').to_string();
+    if PACKAGE_KEYS.contains(&name.as_str()) {
+        return None;
+    }
+    let value = value.trim().trim_end_matches(',').trim();
+    value.starts_with('"').then(|| (name, value.trim_matches('"').to_string()))
+}
+
+fn go_mod_dependency(line: &str) -> Option<(String, String)> {
+    let line = line.trim();
+    let line = line.strip_prefix("require ").unwrap_or(line);
+    let mut parts = line.split_whitespace();
+    let (name, version) = (parts.next()?, parts.next()?);
+    (name.contains('/') && version.starts_with('v')).then(|| (name.to_string(), version.to_string()))
+}
+
+fn requirements_dependency(line: &str) -> Option<(String, String)> {
+    let (name, version) = line.trim().split_once("==")?;
+    Some((name.trim().to_string(), version.trim().to_string()))
+}
+
+fn gemfile_lock_dependency(line: &str) -> Option<(String, String)> {
+    let (name, rest) = line.trim().split_once(" (")?;
+    let version = rest.strip_suffix(')')?;
+    // Constraint lines such as `rails (~> 7.0)` list requirements, not resolved versions
+    (!version.contains(' ')).then(|| (name.to_string(), version.to_string()))
+}
+
+fn record(changes: &mut Vec<DependencyChange>, name: &str, version: String, removed: bool) {
+    let idx = match changes.iter().position(|c| c.name == name) {
+        Some(idx) => idx,
+        None => {
+            changes.push(DependencyChange { name: name.to_string(), from: None, to: None });
+            changes.len() - 1
+        }
+    };
+    let change = &mut changes[idx];
+    if removed {
+        change.from = Some(version);
+    } else {
+        change.to = Some(version);
+    }
+}
+
+/// Changes found in one file, or `None` if a manifest line could not be explained
+fn file_changes(file: &FileDiff) -> Option<Vec<DependencyChange>> {
+    let format = format_for(&file.path)?;
+    let mut changes = Vec::new();
+    let mut current: Option<String> = None;
+
+    for line in file.hunks.iter().flat_map(|h| h.lines().skip(1)) {
+        let (marker, content) = line.split_at(line.len().min(1));
+        match &format {
+            Format::Lockfile { name, version } => {
+                if let Some(n) = name(content) {
+                    current = Some(n);
+                } else if let (Some(v), Some(n), "+" | "-") = (version(content), &current, marker) {
+                    record(&mut changes, n, v, marker == "-");
+                }
+            }
+            Format::Manifest(parse) => {
+                if marker != "+" && marker != "-" || content.trim().is_empty() {
+                    continue;
+                }
+                let (n, v) = parse(content)?;
+                record(&mut changes, &n, v, marker == "-");
+            }
+        }
+    }
+
+    Some(changes)
+}
+
+/// Collect dependency changes when every file is a supported manifest or lockfile
+///
+/// Returns an empty list if any file is something else, or a manifest change
+/// isn't a plain version change (e.g. the crate's own version or a feature flag).
+pub fn dependency_changes(files: &[FileDiff]) -> Vec<DependencyChange> {
+    let mut all: Vec<DependencyChange> = Vec::new();
+
+    // Lockfiles carry exact versions, so let them win over manifest ranges
+    let mut ordered: Vec<&FileDiff> = files.iter().collect();
+    ordered.sort_by_key(|f| matches!(format_for(&f.path), Some(Format::Manifest(_))));
+
+    for file in ordered {
+        let Some(changes) = file_changes(file) else {
+            return Vec::new();
+        };
+        for change in changes {
+            if change.from != change.to && !all.iter().any(|c| c.name == change.name) {
+                all.push(change);
+            }
+        }
+    }
+
+    all
+}
+
+/// A `chore(deps)` message describing the changes, without asking the AI
+pub fn bump_message(changes: &[DependencyChange]) -> Option<CommitMessage> {
+    match changes {
+        [] => None,
+        [single] => Some(CommitMessage {
+            subject: format!("chore(deps): {}", single.describe()),
+            body: None,
+        }),
+        many => Some(CommitMessage {
+            subject: format!("chore(deps): update {} dependencies", many.len()),
+            body: Some(
+                many.iter()
+                    .map(|c| format!("- {}", c.describe()))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::parse_diff;
+
+    #[test]
+    fn test_cargo_bump() {
+        let diff = "\
+diff --git a/Cargo.toml b/Cargo.toml
+--- a/Cargo.toml
++++ b/Cargo.toml
+@@ -10,3 +10,3 @@ edition = \"2021\"
+ [dependencies]
+-serde = { version = \"1.0.1\", features = [\"derive\"] }
++serde = { version = \"1.0.2\", features = [\"derive\"] }
+diff --git a/Cargo.lock b/Cargo.lock
+--- a/Cargo.lock
++++ b/Cargo.lock
+@@ -1,6 +1,6 @@
+ [[package]]
+ name = \"serde\"
+-version = \"1.0.1\"
++version = \"1.0.2\"
+ source = \"registry+https://github.com/rust-lang/crates.io-index\"
+-checksum = \"aaa\"
++checksum = \"bbb\"
+";
+        let changes = dependency_changes(&parse_diff(diff));
+        assert_eq!(changes.len(), 1);
+        let msg = bump_message(&changes).unwrap();
+        assert_eq!(msg.subject, "chore(deps): bump serde from 1.0.1 to 1.0.2");
+    }
+
+    #[test]
+    fn test_own_version_bump_is_not_a_dependency_change() {
+        let diff = "\
+diff --git a/Cargo.toml b/Cargo.toml
+--- a/Cargo.toml
++++ b/Cargo.toml
+@@ -1,3 +1,3 @@
+ name = \"cm\"
+-version = \"0.2.0\"
++version = \"0.3.0\"
+";
+        assert!(dependency_changes(&parse_diff(diff)).is_empty());
+    }
+
+    #[test]
+    fn test_package_json_multiple() {
+        let diff = "\
+diff --git a/package.json b/package.json
+--- a/package.json
++++ b/package.json
+@@ -5,4 +5,4 @@
+   \"dependencies\": {
+-    \"left-pad\": \"^1.0.0\",
+-    \"react\": \"^18.2.0\"
++    \"left-pad\": \"^1.1.0\",
++    \"react\": \"^18.3.0\"
+";
+        let changes = dependency_changes(&parse_diff(diff));
+        let msg = bump_message(&changes).unwrap();
+        assert_eq!(msg.subject, "chore(deps): update 2 dependencies");
+        assert_eq!(
+            msg.body.unwrap(),
+            "- bump left-pad from ^1.0.0 to ^1.1.0\n- bump react from ^18.2.0 to ^18.3.0"
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashSet;
+
+/// One file's section of a unified diff
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDiff {
+    pub path: String,
+    /// `diff --git` line through the `+++` line (or the whole section for binary files)
+    pub header: String,
+    /// Hunks, each starting with its `@@` line
+    pub hunks: Vec<String>,
+}
+
+impl FileDiff {
+    /// Whether the file doesn't exist before the change
+    pub fn is_new(&self) -> bool {
+        self.header.lines().any(|line| line.starts_with("new file mode") || line.starts_with("--- /dev/null"))
+    }
+
+    /// Whether the change deletes the file
+    pub fn is_deleted(&self) -> bool {
+        self.header.lines().any(|line| line.starts_with("deleted file mode") || line.starts_with("+++ /dev/null"))
+    }
+
+    /// Lines the change adds, without their `+`
+    pub fn added_lines(&self) -> impl Iterator<Item = &str> {
+        self.hunks.iter().flat_map(|hunk| hunk.lines().skip(1)).filter_map(|line| line.strip_prefix('+'))
+    }
+
+    /// Number of lines added and removed
+    pub fn line_counts(&self) -> (usize, usize) {
+        let lines = self.hunks.iter().flat_map(|hunk| hunk.lines().skip(1));
+        lines.fold((0, 0), |(added, removed), line| match line.as_bytes().first() {
+            Some(b'+') => (added + 1, removed),
+            Some(b'-') => (added, removed + 1),
+            _ => (added, removed),
+        })
+    }
+
+    /// Lines the change adds or removes, without their `+`/`-`
+    pub fn changed_lines(&self) -> impl Iterator<Item = &str> {
+        self.hunks
+            .iter()
+            .flat_map(|hunk| hunk.lines().skip(1))
+            .filter_map(|line| line.strip_prefix('+').or_else(|| line.strip_prefix('-')))
+    }
+}
+
+/// A file or a single hunk withheld from the prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Exclusion {
+    File(usize),
+    Hunk(usize, usize),
+}
+
+/// `text` with Windows line endings (`\r\n`) turned into `\n`
+///
+/// Diffs of CRLF files, messages saved by Windows editors and config files
+/// written on Windows would otherwise carry a `\r` into every line.
+pub fn normalize_newlines(text: &str) -> String {
+    text.replace("\r\n", "\n")
+}
+
+/// Split `git diff` output into files and hunks
+///
+/// Plain unified diffs (`diff -u`, no `diff --git` lines) are split at each
+/// `---`/`+++` header pair instead.
+pub fn parse_diff(diff: &str) -> Vec<FileDiff> {
+    let git_format = diff.lines().any(|line| line.starts_with("diff --git "));
+    let mut files: Vec<FileDiff> = Vec::new();
+    let mut lines = diff.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            let path = rest
+                .rsplit_once(" b/")
+                .map(|(_, b)| b.to_string())
+                .unwrap_or_else(|| rest.to_string());
+            files.push(FileDiff {
+                path,
+                header: String::new(),
+                hunks: Vec::new(),
+            });
+        } else if let Some(old) = line.strip_prefix("--- ").filter(|_| !git_format) {
+            if let Some(new) = lines.peek().and_then(|next| next.strip_prefix("+++ ")) {
+                let path = if unified_path(new) == "/dev/null" { old } else { new };
+                files.push(FileDiff {
+                    path: unified_path(path),
+                    header: String::new(),
+                    hunks: Vec::new(),
+                });
+            }
+        }
+
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+
+        if line.starts_with("@@") {
+            file.hunks.push(String::new());
+        }
+
+        let target = file.hunks.last_mut().unwrap_or(&mut file.header);
+        target.push_str(line);
+        target.push('\n');
+    }
+
+    files
+}
+
+/// Path from a `---`/`+++` line, without the timestamp or `a/`/`b/` prefix
+fn unified_path(spec: &str) -> String {
+    let path = spec.split('\t').next().unwrap_or(spec).trim_end();
+    path.strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path)
+        .to_string()
+}
+
+/// A `git diff --stat` style summary of `files`
+pub fn diff_stat(files: &[FileDiff]) -> String {
+    let counts: Vec<(usize, usize)> = files.iter().map(FileDiff::line_counts).collect();
+    let width = files.iter().map(|f| f.path.len()).max().unwrap_or(0);
+
+    let mut out = String::new();
+    for (file, (added, removed)) in files.iter().zip(&counts) {
+        out.push_str(&format!(
+            " {:<width$} | {} {}{}\n",
+            file.path,
+            added + removed,
+            "+".repeat(*added),
+            "-".repeat(*removed),
+            width = width
+        ));
+    }
+    let added: usize = counts.iter().map(|c| c.0).sum();
+    let removed: usize = counts.iter().map(|c| c.1).sum();
+    out.push_str(&format!(
+        " {} file(s) changed, {} insertion(s)(+), {} deletion(s)(-)\n",
+        files.len(),
+        added,
+        removed
+    ));
+    out
+}
+
+/// Reassemble a diff, replacing excluded files and hunks with a placeholder
+pub fn render_diff(files: &[FileDiff], excluded: &HashSet<Exclusion>) -> String {
+    let mut out = String::new();
+
+    for (i, file) in files.iter().enumerate() {
+        if excluded.contains(&Exclusion::File(i)) {
+            out.push_str(&format!("[changes to {} withheld by user]\n", file.path));
+            continue;
+        }
+
+        out.push_str(&file.header);
+        for (j, hunk) in file.hunks.iter().enumerate() {
+            if excluded.contains(&Exclusion::Hunk(i, j)) {
+                let header = hunk.lines().next().unwrap_or("@@");
+                out.push_str(&format!("{}\n[hunk withheld by user]\n", header));
+            } else {
+                out.push_str(hunk);
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/src/a.rs b/src/a.rs
+index 1..2 100644
+--- a/src/a.rs
++++ b/src/a.rs
+@@ -1,2 +1,2 @@ fn a
+-old
++new
+@@ -10,1 +10,1 @@ fn b
+-secret = 1
++secret = 2
+diff --git a/b.txt b/b.txt
+--- a/b.txt
++++ b/b.txt
+@@ -1 +1 @@
+-x
++y
+";
+
+    #[test]
+    fn test_parse_diff() {
+        let files = parse_diff(DIFF);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "src/a.rs");
+        assert_eq!(files[0].hunks.len(), 2);
+        assert!(files[0].hunks[1].starts_with("@@ -10,1"));
+        assert_eq!(files[1].path, "b.txt");
+
+        // A diff of CRLF files parses the same once its line endings are normalized
+        let crlf = normalize_newlines(&DIFF.replace('\n', "\r\n"));
+        assert_eq!(crlf, DIFF);
+        assert_eq!(parse_diff(&crlf), files);
+    }
+
+    #[test]
+    fn test_render_roundtrip_and_exclusions() {
+        let files = parse_diff(DIFF);
+        assert_eq!(render_diff(&files, &HashSet::new()), DIFF);
+
+        let excluded = HashSet::from([Exclusion::Hunk(0, 1), Exclusion::File(1)]);
+        let rendered = render_diff(&files, &excluded);
+        assert!(!rendered.contains("secret"));
+        assert!(rendered.contains("@@ -10,1 +10,1 @@ fn b\n[hunk withheld by user]"));
+        assert!(rendered.contains("[changes to b.txt withheld by user]"));
+        assert!(rendered.contains("+new"));
+    }
+
+    #[test]
+    fn test_plain_unified_diff() {
+        let plain = "--- old/notes.txt\t2024-01-01 10:00:00\n+++ new/notes.txt\t2024-01-02 10:00:00\n@@ -1 +1,2 @@\n-a\n+b\n+c\n";
+        let files = parse_diff(plain);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "new/notes.txt");
+        assert_eq!(render_diff(&files, &HashSet::new()), plain);
+        assert_eq!(
+            diff_stat(&files),
+            " new/notes.txt | 3 ++-\n 1 file(s) changed, 2 insertion(s)(+), 1 deletion(s)(-)\n"
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+use crate::i18n::{tf, Msg};
+use crate::ui;
+use crate::vcs::git;
+
+/// Editors tried when none is configured, in order
+#[cfg(windows)]
+const DEFAULTS: &[&str] = &["notepad"];
+#[cfg(not(windows))]
+const DEFAULTS: &[&str] = &["editor", "nano", "vim", "vi"];
+
+/// An editor command line and where it was configured
+#[derive(Debug, Clone, PartialEq)]
+pub struct Editor {
+    pub program: String,
+    pub args: Vec<String>,
+    /// `EDITOR`, `core.editor`, `default`, ...
+    pub source: &'static str,
+}
+
+/// The editor to use: `CM_EDITOR`, `GIT_EDITOR`, git's `core.editor`, `VISUAL`,
+/// `EDITOR`, then a platform default
+///
+/// A configured editor that isn't installed is reported and skipped, so a
+/// stale `EDITOR` in a container doesn't leave the user with nothing.
+pub fn resolve() -> Result<Editor, String> {
+    let configured = [
+        ("CM_EDITOR", env::var("CM_EDITOR").ok()),
+        ("GIT_EDITOR", env::var("GIT_EDITOR").ok()),
+        ("core.editor", git::config_value("core.editor")),
+        ("VISUAL", env::var("VISUAL").ok()),
+        ("EDITOR", env::var("EDITOR").ok()),
+    ];
+    let mut missing = Vec::new();
+    for (source, value) in configured {
+        let Some(editor) = value.as_deref().and_then(|v| parse(v, source)) else {
+            continue;
+        };
+        if exists(&editor.program) {
+            return Ok(editor);
+        }
+        ui::warn("⚠️  ", tf(Msg::EditorMissing, &[&editor.program, &source]));
+        missing.push(format!("{} ({})", editor.program, source));
+    }
+    if let Some(program) = DEFAULTS.iter().find(|program| exists(program)) {
+        return Ok(Editor {
+            program: program.to_string(),
+            args: Vec::new(),
+            source: "default",
+        });
+    }
+    missing.extend(DEFAULTS.iter().map(|program| program.to_string()));
+    Err(missing.join(", "))
+}
+
+/// Temporary file an edited message is written to
+///
+/// It is named per process so two runs don't edit each other's message, and
+/// gets a `.txt` extension so Windows editors open it as text.
+pub fn message_path() -> PathBuf {
+    env::temp_dir().join(format!("cm-commit-msg-{}.txt", std::process::id()))
+}
+
+/// The character starting comment lines in an edited message (`core.commentChar`)
+///
+/// `auto` asks git to pick one the message doesn't use; `#` stands in for it.
+pub fn comment_char() -> char {
+    git::config_value("core.commentChar")
+        .filter(|value| value != "auto")
+        .and_then(|value| value.chars().next())
+        .unwrap_or('#')
+}
+
+/// `text` without its comment lines, the way git cleans up an edited message
+pub fn strip_comments(text: &str, comment: char) -> String {
+    text.lines()
+        .filter(|line| !line.starts_with(comment))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Split `code --wait` into program and arguments
+fn parse(command: &str, source: &'static str) -> Option<Editor> {
+    let mut words = command.split_whitespace().map(String::from);
+    Some(Editor {
+        program: words.next()?,
+        args: words.collect(),
+        source,
+    })
+}
+
+/// Whether `program` is a path to a file or can be found on `PATH`
+fn exists(program: &str) -> bool {
+    if program.contains(['/', '\\']) {
+        return Path::new(program).is_file();
+    }
+    let extensions: Vec<String> = if cfg!(windows) {
+        env::var("PATHEXT")
+            .unwrap_or_else(|_| ".EXE;.CMD;.BAT".to_string())
+            .split(';')
+            .map(str::to_lowercase)
+            .chain([String::new()])
+            .collect()
+    } else {
+        vec![String::new()]
+    };
+    env::var_os("PATH").is_some_and(|path| {
+        env::split_paths(&path).any(|dir| {
+            extensions
+                .iter()
+                .any(|ext| PathBuf::from(&dir).join(format!("{}{}", program, ext)).is_file())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editor() {
+        let editor = parse("code --wait --new-window", "VISUAL").unwrap();
+        assert_eq!(editor.program, "code");
+        assert_eq!(editor.args, vec!["--wait", "--new-window"]);
+        assert_eq!(parse("   ", "EDITOR"), None);
+
+        assert_eq!(
+            strip_comments("fix: handle #42 properly\n\n# note to self\nBody text\n#\n# Lines starting with '#'...", '#'),
+            "fix: handle #42 properly\n\nBody text"
+        );
+        assert_eq!(strip_comments("; only comments\n;\n", ';'), "");
+        // As saved by Notepad
+        let saved = crate::diff::normalize_newlines("fix: trim input\r\n\r\nBody text\r\n# hint\r\n");
+        assert_eq!(strip_comments(&saved, '#'), "fix: trim input\n\nBody text");
+
+        assert!(exists("sh") || cfg!(windows));
+        assert!(!exists("definitely-not-an-editor-cm"));
+        assert!(!exists("/nonexistent/bin/vi"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_windows_paths() {
+        let path = message_path();
+        assert!(path.starts_with(env::temp_dir()));
+        assert_eq!(path.extension().unwrap(), "txt");
+        assert!(!path.to_string_lossy().contains('/'));
+        if let Some(local) = env::var_os("LOCALAPPDATA") {
+            assert_eq!(crate::config::cache_dir(), Some(PathBuf::from(local).join("commit-message")));
+        }
+    }
+}
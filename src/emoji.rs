@@ -1,3 +1,7 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::config::EmojiPlacement;
+
 /// Mapping of conventional commit types to their emoji representations
 pub const EMOJI_MAP: &[(&str, &str, &str)] = &[
     ("feat", "✨", "New feature"),
@@ -17,6 +21,36 @@ pub const EMOJI_MAP: &[(&str, &str, &str)] = &[
     ("release", "🚀", "Release"),
 ];
 
+/// Type names models use instead of the conventional ones
+pub const TYPE_ALIASES: &[(&str, &str)] = &[
+    ("feature", "feat"),
+    ("features", "feat"),
+    ("bugfix", "fix"),
+    ("bug", "fix"),
+    ("hotfix", "fix"),
+    ("fixes", "fix"),
+    ("documentation", "docs"),
+    ("doc", "docs"),
+    ("tests", "test"),
+    ("testing", "test"),
+    ("refactoring", "refactor"),
+    ("performance", "perf"),
+    ("chores", "chore"),
+    ("dependencies", "deps"),
+    ("dep", "deps"),
+];
+
+/// The conventional type `commit_type` stands for, whatever its case, if it is one or an alias of one
+pub fn canonical_type(commit_type: &str) -> Option<&'static str> {
+    let type_lower = commit_type.to_lowercase();
+    EMOJI_MAP
+        .iter()
+        .map(|(t, _, _)| (*t, *t))
+        .chain(TYPE_ALIASES.iter().copied())
+        .find(|(alias, _)| *alias == type_lower)
+        .map(|(_, canonical)| canonical)
+}
+
 /// Get the emoji for a commit type
 pub fn get_emoji(commit_type: &str) -> Option<&'static str> {
     let type_lower = commit_type.to_lowercase();
@@ -32,7 +66,7 @@ pub fn extract_type(message: &str) -> Option<&str> {
 
     // Find the position of the first special character (!, (, or :)
     let type_end = first_line
-        .find(|c| c == '!' || c == '(' || c == ':')
+        .find(['!', '(', ':'])
         .unwrap_or(first_line.len());
 
     if type_end > 0 {
@@ -42,32 +76,75 @@ pub fn extract_type(message: &str) -> Option<&str> {
     }
 }
 
-/// Add emoji prefix to a commit message
-pub fn add_emoji_prefix(message: &str) -> String {
-    if let Some(commit_type) = extract_type(message) {
-        if let Some(emoji) = get_emoji(commit_type) {
-            // Check if already has an emoji (avoid double-adding)
-            let first_char = message.chars().next();
-            if first_char.map(|c| c.is_ascii_alphabetic()).unwrap_or(false) {
-                return format!("{} {}", emoji, message);
-            }
-        }
+/// Add the type emoji to a commit subject at `placement`
+pub fn add_emoji(message: &str, placement: EmojiPlacement) -> String {
+    // Check if already has an emoji (avoid double-adding)
+    if !message.chars().next().is_some_and(|c| c.is_ascii_alphabetic()) || remove_emoji_prefix(message) != message {
+        return message.to_string();
+    }
+    let Some(emoji) = extract_type(message).and_then(get_emoji) else {
+        return message.to_string();
+    };
+    match placement {
+        EmojiPlacement::Prefix => format!("{} {}", emoji, message),
+        EmojiPlacement::AfterType => match message.split_once(':') {
+            Some((header, description)) => format!("{}: {} {}", header, emoji, description.trim_start()),
+            None => format!("{} {}", emoji, message),
+        },
+        EmojiPlacement::End => format!("{} {}", message.trim_end(), emoji),
     }
-    message.to_string()
 }
 
-/// Remove emoji prefix from a commit message
+/// Remove emoji from a commit subject, wherever [`add_emoji`] would have put them
+///
+/// Any emoji counts, not only those of [`EMOJI_MAP`]: models pick their own,
+/// with skin tones, ZWJ sequences and variation selectors.
 pub fn remove_emoji_prefix(message: &str) -> String {
     let trimmed = message.trim_start();
+    let rest = strip_leading_emoji(trimmed);
+    if rest.len() != trimmed.len() {
+        return rest.to_string();
+    }
 
-    // Check if starts with a known emoji
-    for (_, emoji, _) in EMOJI_MAP {
-        if trimmed.starts_with(emoji) {
-            return trimmed[emoji.len()..].trim_start().to_string();
+    // Right after the type, or at the end
+    if let Some((header, description)) = message.split_once(": ") {
+        let rest = strip_leading_emoji(description);
+        if rest.len() != description.len() {
+            return format!("{}: {}", header, rest);
         }
     }
+    strip_trailing_emoji(message).to_string()
+}
 
-    message.to_string()
+/// `text` without the emoji (and spaces between them) it starts with
+fn strip_leading_emoji(text: &str) -> &str {
+    let mut rest = text;
+    while let Some(first) = rest.graphemes(true).next().filter(|g| is_emoji(g)) {
+        rest = rest[first.len()..].trim_start();
+    }
+    rest
+}
+
+/// `text` without the emoji (and spaces between them) it ends with
+fn strip_trailing_emoji(text: &str) -> &str {
+    let mut rest = text.trim_end();
+    while let Some(last) = rest.graphemes(true).next_back().filter(|g| is_emoji(g)) {
+        rest = rest[..rest.len() - last.len()].trim_end();
+    }
+    rest
+}
+
+/// Whether a grapheme cluster is an emoji: a pictograph, flag or keycap, or
+/// any character forced to emoji presentation
+fn is_emoji(grapheme: &str) -> bool {
+    let Some(first) = grapheme.chars().next() else {
+        return false;
+    };
+    grapheme.contains(['\u{FE0F}', '\u{20E3}'])
+        || matches!(
+            first as u32,
+            0x1F000..=0x1FAFF | 0x2600..=0x27BF | 0x2B00..=0x2BFF | 0x2300..=0x23FF | 0x2190..=0x21FF | 0x3030 | 0x303D | 0x3297 | 0x3299
+        )
 }
 
 #[cfg(test)]
@@ -82,6 +159,16 @@ mod tests {
         assert_eq!(get_emoji("unknown"), None);
     }
 
+    #[test]
+    fn test_canonical_type() {
+        assert_eq!(canonical_type("feature"), Some("feat"));
+        assert_eq!(canonical_type("BugFix"), Some("fix"));
+        assert_eq!(canonical_type("documentation"), Some("docs"));
+        assert_eq!(canonical_type("tests"), Some("test"));
+        assert_eq!(canonical_type("Feat"), Some("feat"));
+        assert_eq!(canonical_type("update"), None);
+    }
+
     #[test]
     fn test_extract_type() {
         assert_eq!(extract_type("feat: add feature"), Some("feat"));
@@ -90,14 +177,38 @@ mod tests {
     }
 
     #[test]
-    fn test_add_emoji_prefix() {
-        assert_eq!(add_emoji_prefix("feat: add feature"), "✨ feat: add feature");
-        assert_eq!(add_emoji_prefix("fix(auth): fix bug"), "🐛 fix(auth): fix bug");
+    fn test_add_emoji() {
+        assert_eq!(add_emoji("feat: add feature", EmojiPlacement::Prefix), "✨ feat: add feature");
+        assert_eq!(add_emoji("fix(auth): fix bug", EmojiPlacement::Prefix), "🐛 fix(auth): fix bug");
+        assert_eq!(add_emoji("fix(auth)!: fix bug", EmojiPlacement::AfterType), "fix(auth)!: 🐛 fix bug");
+        assert_eq!(add_emoji("refactor: split parser", EmojiPlacement::End), "refactor: split parser ♻️");
+        // Never twice
+        assert_eq!(add_emoji("feat: ✨ add feature", EmojiPlacement::Prefix), "feat: ✨ add feature");
+        assert_eq!(add_emoji("✨ feat: add feature", EmojiPlacement::End), "✨ feat: add feature");
     }
 
     #[test]
     fn test_remove_emoji_prefix() {
         assert_eq!(remove_emoji_prefix("✨ feat: add feature"), "feat: add feature");
         assert_eq!(remove_emoji_prefix("feat: no emoji"), "feat: no emoji");
+        assert_eq!(remove_emoji_prefix("fix(auth): 🐛 fix bug"), "fix(auth): fix bug");
+        assert_eq!(remove_emoji_prefix("refactor: split parser ♻️"), "refactor: split parser");
+    }
+
+    #[test]
+    fn test_remove_any_emoji() {
+        // Not in EMOJI_MAP
+        assert_eq!(remove_emoji_prefix("🎉 feat: launch"), "feat: launch");
+        // Skin tone, ZWJ sequence, VS16 and several in a row
+        assert_eq!(remove_emoji_prefix("👍🏽 fix: typo"), "fix: typo");
+        assert_eq!(remove_emoji_prefix("👩‍💻 chore: tooling"), "chore: tooling");
+        assert_eq!(remove_emoji_prefix("❤️ docs: thanks"), "docs: thanks");
+        assert_eq!(remove_emoji_prefix("✨🚀 feat: ship"), "feat: ship");
+        assert_eq!(remove_emoji_prefix("feat: 🇩🇪 add German"), "feat: add German");
+        assert_eq!(remove_emoji_prefix("fix: handle 100% of cases"), "fix: handle 100% of cases");
+        assert_eq!(remove_emoji_prefix("fix: Ünïcödé names"), "fix: Ünïcödé names");
+        // Whatever the model chose is kept, not doubled
+        assert_eq!(add_emoji("feat: 🎉 launch", EmojiPlacement::Prefix), "feat: 🎉 launch");
+        assert_eq!(add_emoji("feat: launch 👍🏽", EmojiPlacement::AfterType), "feat: launch 👍🏽");
     }
 }
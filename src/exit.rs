@@ -0,0 +1,125 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::config::ConfigError;
+use crate::vcs::GitError;
+use crate::provider::ProviderError;
+
+/// Process exit codes; part of the scripting interface, so never renumber
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exit {
+    /// Committed, or the requested command finished
+    Success = 0,
+    /// Any error not covered below
+    Error = 1,
+    /// The user quit or left the message empty
+    Aborted = 2,
+    NoStagedChanges = 3,
+    /// Missing or rejected API key
+    AuthFailed = 4,
+    /// Provider quota or local spending limit reached
+    QuotaExceeded = 5,
+    NotARepository = 6,
+    InvalidConfig = 7,
+    /// Unknown or malformed command-line arguments
+    Usage = 8,
+    /// The provider kept failing or returned no usable message
+    GenerationFailed = 9,
+    /// `cm lint` found commit messages that break the rules
+    LintFailed = 10,
+    /// The staged changes look like they contain credentials (`CM_SECRET_SCAN=refuse`)
+    SecretsDetected = 11,
+    /// `CM_CHECK_COMMAND` failed and `--allow-failing-check` wasn't given
+    CheckFailed = 12,
+    /// Committing on a protected branch with `CM_PROTECTED_BRANCH_ACTION=block`
+    ProtectedBranch = 13,
+    /// A `CM_HOOK_PRE_GENERATE` or `CM_HOOK_PRE_COMMIT_MESSAGE` script vetoed the run
+    Vetoed = 14,
+}
+
+impl Exit {
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+/// An error message tagged with the exit code it should produce
+#[derive(Debug)]
+pub struct Failure {
+    pub exit: Exit,
+    message: String,
+}
+
+impl Failure {
+    pub fn new(exit: Exit, message: impl Into<String>) -> Self {
+        Self {
+            exit,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for Failure {}
+
+/// Exit code for an error returned from a command
+pub fn classify(error: &(dyn Error + 'static)) -> Exit {
+    if let Some(failure) = error.downcast_ref::<Failure>() {
+        return failure.exit;
+    }
+    if let Some(e) = error.downcast_ref::<GitError>() {
+        return match e {
+            GitError::NotARepository => Exit::NotARepository,
+            GitError::NoStagedChanges => Exit::NoStagedChanges,
+            GitError::CommandFailed(_) | GitError::ToolFailed(..) | GitError::PushFailed(..) => Exit::Error,
+        };
+    }
+    if let Some(e) = error.downcast_ref::<ConfigError>() {
+        return match e {
+            ConfigError::MissingApiKey(_) => Exit::AuthFailed,
+            _ => Exit::InvalidConfig,
+        };
+    }
+    if let Some(e) = error.downcast_ref::<ProviderError>() {
+        return provider_exit(e);
+    }
+    Exit::Error
+}
+
+fn provider_exit(error: &ProviderError) -> Exit {
+    match error {
+        ProviderError::AuthFailed(_) => Exit::AuthFailed,
+        ProviderError::QuotaExceeded(_) => Exit::QuotaExceeded,
+        _ => Exit::GenerationFailed,
+    }
+}
+
+/// A provider error that ended the run, with `message` explaining how to fix it
+pub fn provider_failure(error: &ProviderError, message: String) -> Failure {
+    Failure::new(provider_exit(error), message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let boxed: Box<dyn Error> = GitError::NoStagedChanges.into();
+        assert_eq!(classify(boxed.as_ref()), Exit::NoStagedChanges);
+
+        let boxed: Box<dyn Error> = ConfigError::MissingApiKey("GOOGLE_API_KEY").into();
+        assert_eq!(classify(boxed.as_ref()).code(), 4);
+
+        let boxed: Box<dyn Error> = Failure::new(Exit::QuotaExceeded, "over budget").into();
+        assert_eq!(classify(boxed.as_ref()), Exit::QuotaExceeded);
+
+        let boxed: Box<dyn Error> = "something else".into();
+        assert_eq!(classify(boxed.as_ref()), Exit::Error);
+    }
+}
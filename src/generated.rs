@@ -0,0 +1,138 @@
+use crate::diff::FileDiff;
+use crate::heuristics::TypeHint;
+
+/// File name endings of generated code, with what generates them
+const SUFFIXES: &[(&str, &str)] = &[
+    (".pb.rs", "protobuf"),
+    (".pb.go", "protobuf"),
+    ("_grpc.pb.go", "gRPC"),
+    (".pb.cc", "protobuf"),
+    (".pb.h", "protobuf"),
+    ("_pb2.py", "protobuf"),
+    ("_pb2.pyi", "protobuf"),
+    ("_pb2_grpc.py", "gRPC"),
+    ("_pb.js", "protobuf"),
+    ("_pb.d.ts", "protobuf"),
+    ("_generated.go", "generated"),
+    (".g.dart", "build_runner"),
+    (".freezed.dart", "build_runner"),
+    (".designer.cs", "designer"),
+    (".g.cs", "generated"),
+];
+
+/// Markers generators put at the top of their output
+const MARKERS: &[&str] = &["@generated", "DO NOT EDIT", "auto-generated", "autogenerated", "Autogenerated"];
+
+/// Lines at the top of a file searched for a marker
+const MARKER_LINES: usize = 5;
+
+/// A staged file that a tool generated
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratedFile {
+    pub path: String,
+    /// What generated it, e.g. `protobuf`
+    pub kind: &'static str,
+}
+
+/// Files among `files` that were generated, by name or by a marker near their top
+pub fn detect(files: &[FileDiff]) -> Vec<GeneratedFile> {
+    files
+        .iter()
+        .filter_map(|file| {
+            let name = file.path.rsplit('/').next().unwrap_or_default();
+            let by_name = SUFFIXES
+                .iter()
+                .filter(|(suffix, _)| name.ends_with(suffix))
+                .max_by_key(|(suffix, _)| suffix.len())
+                .map(|(_, kind)| *kind)
+                .or_else(|| name.starts_with("zz_generated").then_some("generated"));
+            let kind = by_name.or_else(|| has_marker(file).then_some("generated"))?;
+            Some(GeneratedFile {
+                path: file.path.clone(),
+                kind,
+            })
+        })
+        .collect()
+}
+
+/// Whether the first lines of the new file carry a generator marker
+fn has_marker(file: &FileDiff) -> bool {
+    let Some(first) = file.hunks.first() else {
+        return false;
+    };
+    let mut lines = first.lines();
+    let starts_at_top = lines.next().is_some_and(|header| header.contains(" +1,") || header.contains(" +1 "));
+    starts_at_top
+        && lines
+            .filter(|line| !line.starts_with('-'))
+            .take(MARKER_LINES)
+            .any(|line| MARKERS.iter().any(|marker| line.contains(marker)))
+}
+
+/// `regenerated protobuf code (2 files)` and similar, one entry per generator
+fn summary(generated: &[GeneratedFile]) -> String {
+    let mut kinds: Vec<(&str, usize)> = Vec::new();
+    for file in generated {
+        match kinds.iter_mut().find(|(kind, _)| *kind == file.kind) {
+            Some((_, count)) => *count += 1,
+            None => kinds.push((file.kind, 1)),
+        }
+    }
+    kinds
+        .iter()
+        .map(|(kind, count)| {
+            let what = if *kind == "generated" { String::new() } else { format!("{} ", kind) };
+            format!("regenerated {}code ({} file{})", what, count, if *count == 1 { "" } else { "s" })
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Prompt section standing in for the generated files left out of the diff
+pub fn prompt_section(generated: &[GeneratedFile]) -> Option<String> {
+    if generated.is_empty() {
+        return None;
+    }
+    let paths = generated.iter().map(|file| file.path.as_str()).collect::<Vec<_>>().join(", ");
+    Some(format!(
+        "## Generated Files\nLeft out of the diff: {} ({}). Describe the source change that caused the regeneration; mention the regeneration itself in a few words at most.",
+        summary(generated),
+        paths
+    ))
+}
+
+/// `chore` or `build` when every staged file is generated
+pub fn type_hint(generated: &[GeneratedFile], file_count: usize) -> Option<TypeHint> {
+    (!generated.is_empty() && generated.len() == file_count).then_some(TypeHint {
+        kind: "chore",
+        accepted: &["chore", "build"],
+        reason: "only generated code changed",
+        advisory: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::parse_diff;
+
+    #[test]
+    fn test_detect() {
+        let diff = "diff --git a/api/v1/user.pb.go b/api/v1/user.pb.go\n--- a/api/v1/user.pb.go\n+++ b/api/v1/user.pb.go\n@@ -40 +40 @@\n-a\n+b\n\
+                    diff --git a/api/v1/user_grpc.pb.go b/api/v1/user_grpc.pb.go\n--- a/api/v1/user_grpc.pb.go\n+++ b/api/v1/user_grpc.pb.go\n@@ -9 +9 @@\n-a\n+b\n\
+                    diff --git a/src/schema.rs b/src/schema.rs\n--- a/src/schema.rs\n+++ b/src/schema.rs\n@@ -1,3 +1,3 @@\n // @generated automatically by Diesel CLI.\n-a\n+b\n\
+                    diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -10 +10 @@\n-// DO NOT EDIT below\n+x\n";
+        let generated = detect(&parse_diff(diff));
+        let kinds: Vec<_> = generated.iter().map(|g| (g.path.as_str(), g.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![("api/v1/user.pb.go", "protobuf"), ("api/v1/user_grpc.pb.go", "gRPC"), ("src/schema.rs", "generated")]
+        );
+        assert_eq!(
+            summary(&generated),
+            "regenerated protobuf code (1 file), regenerated gRPC code (1 file), regenerated code (1 file)"
+        );
+        assert!(type_hint(&generated, 4).is_none());
+        assert_eq!(type_hint(&generated, 3).map(|h| h.kind), Some("chore"));
+    }
+}
@@ -0,0 +1,419 @@
+//! End-to-end test support: throwaway git repositories, a mock provider and
+//! scripted answers for driving a [`Session`] the way a user would
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use async_trait::async_trait;
+
+use crate::config::{Config, ProtectedBranchAction, Provider};
+use crate::context::GitContext;
+use crate::diff::{Exclusion, FileDiff};
+use crate::exit::Exit;
+use crate::message::CommitMessage;
+use crate::postprocess::Pipeline;
+use crate::privacy::Privacy;
+use crate::prompt::Prompt;
+use crate::provider::{AiProvider, ProviderResult};
+use crate::session::{Interaction, PromptParts, Session};
+use crate::ui::UserAction;
+use crate::vcs::Vcs;
+use crate::{fit_prompt, template_vars, with_rejected};
+
+/// git runs in the current directory, which every test thread shares
+static CWD: Mutex<()> = Mutex::new(());
+
+/// Numbers the repositories of one test run so parallel tests don't collide
+static REPOS: AtomicUsize = AtomicUsize::new(0);
+
+/// A git repository in a temporary directory, removed when dropped
+pub struct TempRepo {
+    pub path: PathBuf,
+}
+
+impl TempRepo {
+    /// An empty repository with a committer configured and hooks and signing off
+    pub fn new() -> Self {
+        let n = REPOS.fetch_add(1, Ordering::SeqCst);
+        let path = env::temp_dir().join(format!("cm-e2e-{}-{}", std::process::id(), n));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        let repo = Self { path };
+        repo.git(&["init", "-q", "-b", "feature"]);
+        repo.git(&["config", "user.name", "Test User"]);
+        repo.git(&["config", "user.email", "test@example.com"]);
+        repo.git(&["config", "commit.gpgsign", "false"]);
+        repo.git(&["config", "core.hooksPath", ".git/no-hooks"]);
+        repo
+    }
+
+    /// Run git in the repository, panicking if it fails
+    pub fn git(&self, args: &[&str]) -> String {
+        let output = Command::new("git").arg("-C").arg(&self.path).args(args).output().unwrap();
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    /// Write `content` to `file`, creating directories as needed
+    pub fn write(&self, file: &str, content: &str) -> &Self {
+        let path = self.path.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+        self
+    }
+
+    /// Write and stage `file`
+    pub fn stage(&self, file: &str, content: &str) -> &Self {
+        self.write(file, content);
+        self.git(&["add", "--", file]);
+        self
+    }
+
+    /// Commit whatever is staged with `message`, bypassing cm
+    pub fn commit(&self, message: &str) -> &Self {
+        self.git(&["commit", "-q", "-m", message]);
+        self
+    }
+
+    /// Full messages of the commits on the current branch, newest first
+    pub fn messages(&self) -> Vec<String> {
+        let log = self.git(&["log", "--format=%B%x00"]);
+        log.split('\0').map(str::trim).filter(|m| !m.is_empty()).map(String::from).collect()
+    }
+
+    /// Make this the current directory until the guard is dropped
+    ///
+    /// Only one repository can be entered at a time; others wait.
+    pub fn enter(&self) -> Entered<'_> {
+        let lock = CWD.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let previous = env::current_dir().unwrap();
+        env::set_current_dir(&self.path).unwrap();
+        Entered { _lock: lock, previous }
+    }
+}
+
+impl Drop for TempRepo {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// The current directory is a [`TempRepo`] while this lives
+pub struct Entered<'a> {
+    _lock: MutexGuard<'a, ()>,
+    previous: PathBuf,
+}
+
+impl Drop for Entered<'_> {
+    fn drop(&mut self) {
+        let _ = env::set_current_dir(&self.previous);
+    }
+}
+
+/// A provider answering with a numbered message on every call, keeping the prompts it got
+#[derive(Default)]
+pub struct MockProvider {
+    calls: AtomicUsize,
+    pub prompts: Mutex<Vec<Prompt>>,
+}
+
+impl MockProvider {
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl AiProvider for MockProvider {
+    async fn generate(&self, prompt: &Prompt) -> ProviderResult<String> {
+        self.prompts.lock().unwrap().push(prompt.clone());
+        let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+        Ok(format!("SUBJECT: feat: add token refresh handling {}\nBODY: none", call))
+    }
+
+    fn name(&self) -> &'static str {
+        "Test"
+    }
+
+    fn model(&self) -> &str {
+        "test"
+    }
+}
+
+/// Answers given in order, as a user at the terminal would
+#[derive(Default)]
+pub struct Script {
+    pub actions: VecDeque<UserAction>,
+    /// Replaces the message in the editor; the message is kept as is when `None`
+    pub edit: Option<String>,
+    pub push: bool,
+    /// Answer when asked to commit a breaking change
+    pub breaking: bool,
+    /// Files and hunks toggled when asked what to withhold
+    pub exclude: Vec<Exclusion>,
+    /// Subjects shown, in order
+    pub shown: Vec<String>,
+}
+
+impl Script {
+    pub fn new(actions: impl IntoIterator<Item = UserAction>) -> Self {
+        Self {
+            actions: actions.into_iter().collect(),
+            ..Self::default()
+        }
+    }
+}
+
+impl Interaction for Script {
+    fn show(&mut self, message: &CommitMessage, _provider: &str, _model: &str) {
+        self.shown.push(message.subject.clone());
+    }
+
+    fn choose(&mut self, _count: usize) -> io::Result<usize> {
+        Ok(0)
+    }
+
+    fn action(&mut self) -> io::Result<UserAction> {
+        self.actions.pop_front().ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+    }
+
+    fn edit(&mut self, message: &str) -> Result<String, Box<dyn Error>> {
+        Ok(self.edit.clone().unwrap_or_else(|| message.to_string()))
+    }
+
+    fn exclude(&mut self, _files: &[FileDiff], excluded: &mut HashSet<Exclusion>) -> io::Result<()> {
+        for item in &self.exclude {
+            if !excluded.remove(item) {
+                excluded.insert(*item);
+            }
+        }
+        Ok(())
+    }
+
+    fn confirm_push(&mut self) -> io::Result<bool> {
+        Ok(self.push)
+    }
+
+    fn confirm_breaking(&mut self) -> io::Result<bool> {
+        Ok(self.breaking)
+    }
+}
+
+/// A configuration that needs no API key and never stops to ask
+pub fn config() -> Config {
+    let mut config = Config::for_provider(Provider::Ollama).unwrap();
+    config.emoji_enabled = false;
+    config.candidates = 1;
+    config.suggest_reviewers = false;
+    config.learn = false;
+    config.confirm_lines = None;
+    config.confirm_tokens = None;
+    config.protected_branch_action = ProtectedBranchAction::Off;
+    config.plugins = Vec::new();
+    config.audit_log = None;
+    config
+}
+
+/// Run a session over `context` against `repo` until it ends
+pub async fn drive(
+    config: &Config,
+    repo: &dyn Vcs,
+    provider: &dyn AiProvider,
+    script: &mut Script,
+    context: &GitContext,
+) -> Result<Exit, Box<dyn Error>> {
+    let privacy = Privacy::new(false, false, &context.paths());
+    let vars: HashMap<&'static str, String> = template_vars(context, config, &privacy);
+    let prompt = fit_prompt(config, &vars, with_rejected("", &[]).as_deref(), None, &context.diff, context);
+    // A script with exclusions answers them before the first generation, like `--exclude`
+    let exclude_first = !script.exclude.is_empty();
+    let session = Session {
+        config,
+        provider,
+        repo: Some(repo),
+        ui: script,
+        parts: PromptParts {
+            vars: &vars,
+            extra: "",
+            type_hint: None,
+            privacy: &privacy,
+            context,
+            files: &context.files,
+        },
+        prompt,
+        local_message: None,
+        pipeline: Pipeline::new(config),
+        print_only: false,
+        message_file: None,
+        commit_paths: &[],
+        preferences: None,
+        exclude_first,
+        reviewers: Vec::new(),
+    };
+    session.run().await
+}
+
+/// Compare `actual` with `tests/golden/<name>`, rewriting the file instead when
+/// `UPDATE_GOLDEN` is set
+pub fn assert_golden(name: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("{}: {} (run with UPDATE_GOLDEN=1 to create it)", path.display(), e));
+    assert!(
+        expected == actual,
+        "{} differs from the output (run with UPDATE_GOLDEN=1 to accept it)\n--- expected\n{}\n--- actual\n{}",
+        name,
+        expected,
+        actual
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt::{build_commit_prompt, PromptOptions};
+    use crate::vcs::Git;
+
+    const AUTH: &str = "pub fn token() -> String {\n    String::new()\n}\n";
+    const AUTH_REFRESH: &str =
+        "pub fn token() -> String {\n    refresh();\n    String::new()\n}\n\nfn refresh() {}\n";
+
+    /// A repository with one commit and a staged change to it
+    fn fixture() -> TempRepo {
+        let repo = TempRepo::new();
+        repo.stage("src/auth.rs", AUTH).commit("feat: add tokens");
+        repo.stage("src/auth.rs", AUTH_REFRESH);
+        repo
+    }
+
+    fn staged_context() -> GitContext {
+        let diff = Git.diff(&[]).unwrap();
+        GitContext::from_diff(diff, String::new()).with_repo(&Git)
+    }
+
+    #[tokio::test]
+    async fn test_accept_commits_staged_change() {
+        let repo = fixture();
+        let provider = MockProvider::default();
+        let mut script = Script::new([UserAction::Regenerate, UserAction::Accept]);
+        let _entered = repo.enter();
+        let context = staged_context();
+        assert_eq!(context.paths(), vec!["src/auth.rs"]);
+        assert_eq!(context.branch.as_deref(), Some("feature"));
+
+        let exit = drive(&config(), &Git, &provider, &mut script, &context).await.unwrap();
+        assert_eq!(exit, Exit::Success);
+        assert_eq!(provider.calls(), 2);
+        // The second prompt tells the model which subject was turned down
+        let prompts = provider.prompts.lock().unwrap();
+        assert!(prompts[1].user.contains("feat: add token refresh handling 1"));
+        assert_eq!(prompts[1].system, prompts[0].system);
+        assert!(prompts[0].user.contains("+    refresh();"));
+        assert_eq!(repo.messages(), vec!["feat: add token refresh handling 2", "feat: add tokens"]);
+        assert_eq!(repo.git(&["diff", "--cached", "--name-only"]), "");
+    }
+
+    #[tokio::test]
+    async fn test_excluded_hunk_is_never_sent() {
+        let repo = TempRepo::new();
+        let limits: String = (1..=12).map(|n| format!("const LIMIT_{}: u32 = {};\n", n, n)).collect();
+        repo.stage("src/limits.rs", &limits).commit("feat: add limits");
+        let changed = limits.replace("LIMIT_1: u32 = 1;", "LIMIT_1: u32 = 10;").replace("LIMIT_12: u32 = 12;", "LIMIT_12: u32 = 4242;");
+        repo.stage("src/limits.rs", &changed);
+        let _entered = repo.enter();
+        let context = staged_context();
+        assert_eq!(context.files[0].hunks.len(), 2);
+
+        let provider = MockProvider::default();
+        let mut script = Script {
+            exclude: vec![Exclusion::Hunk(0, 1)],
+            ..Script::new([UserAction::Accept])
+        };
+        let exit = drive(&config(), &Git, &provider, &mut script, &context).await.unwrap();
+        assert_eq!(exit, Exit::Success);
+        let prompts = provider.prompts.lock().unwrap();
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].user.contains("LIMIT_1: u32 = 10;"));
+        assert!(prompts[0].user.contains("[hunk withheld by user]"));
+        assert!(!prompts.iter().any(|p| p.user.contains("4242") || p.system.contains("4242")));
+        // Withheld from the model, still committed
+        assert!(repo.git(&["show", "HEAD"]).contains("4242"));
+    }
+
+    #[tokio::test]
+    async fn test_edit_and_quit() {
+        let repo = fixture();
+        let _entered = repo.enter();
+        let context = staged_context();
+
+        let mut script = Script {
+            edit: Some("fix: refresh tokens before use\n\nExpired tokens failed every request.".into()),
+            ..Script::new([UserAction::Edit])
+        };
+        let exit = drive(&config(), &Git, &MockProvider::default(), &mut script, &context).await.unwrap();
+        assert_eq!(exit, Exit::Success);
+        assert_eq!(repo.messages()[0], "fix: refresh tokens before use\n\nExpired tokens failed every request.");
+
+        repo.stage("src/auth.rs", AUTH);
+        let context = staged_context();
+        let mut script = Script::new([UserAction::Quit]);
+        let exit = drive(&config(), &Git, &MockProvider::default(), &mut script, &context).await.unwrap();
+        assert_eq!(exit, Exit::Aborted);
+        assert_eq!(repo.messages().len(), 2);
+        assert_eq!(repo.git(&["diff", "--cached", "--name-only"]), "src/auth.rs\n");
+    }
+
+    #[test]
+    fn test_prompt_golden() {
+        let repo = fixture();
+        let context = {
+            let _entered = repo.enter();
+            GitContext {
+                history: "abc1234 feat: add tokens".to_string(),
+                ..staged_context()
+            }
+        };
+        let prompt = build_commit_prompt(&context.diff, &context, PromptOptions::default(), None);
+        assert_golden("prompt_system.txt", &prompt.system);
+        assert_golden("prompt_user.txt", &prompt.user);
+    }
+
+    #[test]
+    fn test_system_prompt_is_static() {
+        let diff = "diff --git a/src/auth.rs b/src/auth.rs\n--- a/src/auth.rs\n+++ b/src/auth.rs\n@@ -1 +1 @@\n-old\n+new\n";
+        let context = GitContext::from_diff(diff.to_string(), String::new());
+        let config = config();
+        let privacy = Privacy::new(false, false, &context.paths());
+        let vars = template_vars(&context, &config, &privacy);
+        let plain = fit_prompt(&config, &vars, None, None, &context.diff, &context);
+        let extra = with_rejected("## Merge Commit\nThis commit concludes a merge.", &["feat: add x".to_string()]);
+        let prompt = fit_prompt(&config, &vars, extra.as_deref(), None, &context.diff, &context);
+
+        // Per-commit sections and rejected subjects go to the user message only
+        assert_eq!(prompt.system, plain.system);
+        assert!(prompt.user.contains("## Merge Commit") && prompt.user.contains("## Rejected Messages\n"));
+        assert!(prompt.user.ends_with("- feat: add x\n\nGenerate the commit message now:"));
+
+        // A custom template keeps the response format, and the run's values stay out of it
+        let mut config = config;
+        config.prompt_template = Some("Write a message for {{branch}} on {{date}}.".to_string());
+        let mut vars = vars;
+        vars.insert("branch", "feature/ABC-1".to_string());
+        let prompt = fit_prompt(&config, &vars, None, None, &context.diff, &context);
+        assert!(prompt.system.starts_with("Write a message for [branch] on [date].\n\n## Response Format\n"));
+        assert!(prompt.system.ends_with("BODY: <your body here, or just \"none\" if not needed>"));
+        assert!(prompt.user.contains("## Template Values\n- [branch]: feature/ABC-1\n- [date]: "));
+    }
+}
@@ -0,0 +1,188 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::config::{cache_dir, plugin_dir};
+
+/// Settings shown in `cm help`, grouped as in the README
+const SETTINGS: &[(&str, &[(&str, &str)])] = &[
+    (
+        "Provider",
+        &[
+            ("CM_PROVIDER", "openrouter (default), gemini, ollama or plugin:<name>"),
+            ("CM_MODEL", "model name; defaults to the provider's free/lite model"),
+            ("OPENROUTER_API_KEY", "key for OpenRouter"),
+            ("GOOGLE_API_KEY", "key for Gemini"),
+            ("CM_OLLAMA_HOST", "Ollama server, http://host:port or unix:/path"),
+            ("CM_PLUGIN_DIR", "directory of provider, transform and validate plugins"),
+        ],
+    ),
+    (
+        "Message",
+        &[
+            ("CM_STYLE", "balanced, concise, detailed or explanatory"),
+            ("CM_BODY_STYLE", "bullets, paragraphs or none"),
+            ("CM_SCOPE_MODE", "auto, dominant, stack or omit"),
+            ("CM_SCOPE_DEPTH", "path segment naming the scope, e.g. packages=2"),
+            ("CM_EMOJI", "0 to drop the type emoji"),
+            ("CM_EMOJI_POSITION", "prefix, after-type or end"),
+            ("CM_POSTPROCESS", "steps applied to every message, in order"),
+            ("CM_CANDIDATES", "number of messages to choose from"),
+            ("CM_BANNED_WORDS", "comma-separated phrases to avoid"),
+            ("CM_MIN_QUALITY", "regenerate messages scoring under this (0-100)"),
+            ("CM_INTERVIEW", "1 to be asked why about tiny changes"),
+            ("CM_PROMPT_FILE", "custom system prompt template"),
+        ],
+    ),
+    (
+        "Context and privacy",
+        &[
+            ("CM_CONTEXT", "sections to send: history,stat,diff,branch,issues,packages"),
+            ("CM_HISTORY_COUNT", "past commits used as style reference"),
+            ("CM_PRIVACY", "1 to strip names and emails"),
+            ("CM_PRIVACY_HASH_PATHS", "1 to hash directory names"),
+            ("CM_AUDIT", "1 to log hashes of every request and action"),
+            ("CM_TELEMETRY_URL", "where opted-in usage counts are sent"),
+        ],
+    ),
+    (
+        "Hooks",
+        &[
+            ("CM_HOOK_PRE_GENERATE", "script that can rewrite or veto the prompt"),
+            ("CM_HOOK_POST_GENERATE", "script that can rewrite or veto each message"),
+            ("CM_HOOK_PRE_COMMIT_MESSAGE", "script that can rewrite or veto the commit"),
+        ],
+    ),
+    (
+        "Interface",
+        &[
+            ("CM_UI_LANG", "en, de, es or fr"),
+            ("CM_THEME", "default, deuteranopia or high-contrast"),
+            ("CM_A11Y", "1 for screen-reader friendly output"),
+        ],
+    ),
+    (
+        "Cost",
+        &[
+            ("CM_CACHE_TTL", "seconds to reuse responses for identical prompts"),
+            ("CM_BUDGET_DAILY", "daily token or $ limit"),
+            ("CM_BUDGET_MONTHLY", "monthly token or $ limit"),
+        ],
+    ),
+];
+
+/// Print usage, keys, settings and file locations
+pub fn print_help() {
+    println!("cm {} - {}", env!("CARGO_PKG_VERSION"), env!("CARGO_PKG_DESCRIPTION"));
+    println!(
+        r#"
+USAGE
+  cm [--only <pathspec>...] [--no-cache] [--allow-secrets] [--allow-failing-check] [--model <name|alias>] [--deterministic] [-s | --subject-only] [-x | --exclude] [--a11y] [-q | -v | -vv]
+  cm --stdin < changes.diff | --for <rev> | --patch-file <path>
+  cm --porcelain | --commit-msg-filename <file>
+  cm --recurse-submodules | --per-file
+  cm translate <rev-range> --to <language> [--rewrite]
+  cm lint <rev-range> [--format text | github]
+  cm serve --http <addr>
+  cm wip [--pop]
+  cm watch
+  cm ping
+  cm repo-stats [<rev-range>]
+  cm migrate-history --range <a>..<b> [--dry-run]
+  cm review [<rev> | <range>]
+  cm why <file>[:<line>[-<end>]]
+  cm audit export
+  cm config check
+  cm telemetry [status | on | off | preview]
+  cm report
+  cm help
+
+Stage your changes with `git add`, then run `cm` to generate a message.
+
+KEYS
+  a  accept and commit        e  edit in your editor, then commit
+  r  regenerate               x  withhold files/hunks from the AI
+  q  quit without committing
+  With CM_CANDIDATES > 1, pick a message by number first.
+  When withholding, type file numbers (`2`) or hunks (`2.1`) to toggle them;
+  `cm -x` asks before the first message is generated."#
+    );
+
+    println!("\nSETTINGS (environment variables; the README lists all of them)");
+    for (group, vars) in SETTINGS {
+        println!("  {}", group);
+        for (name, description) in *vars {
+            println!("    {:<26} {}", name, description);
+        }
+    }
+
+    println!("\nFILES");
+    match cache_dir() {
+        Some(dir) => println!("  {}  (preferences, response cache, usage, captures)", dir.display()),
+        None => println!("  no cache directory: set HOME or XDG_CACHE_HOME"),
+    }
+    if let Some(dir) = plugin_dir() {
+        println!("  {}  (plugins)", dir.display());
+    }
+    println!("  There is no config file; put the variables above in your shell profile.");
+
+    println!("\nPROVIDER SETUP");
+    print_provider_setup();
+}
+
+fn print_provider_setup() {
+    println!("  OpenRouter: create a key at https://openrouter.ai/keys");
+    println!("    export OPENROUTER_API_KEY=\"sk-or-...\"");
+    println!("  Gemini: create a key at https://aistudio.google.com/app/apikey");
+    println!("    export CM_PROVIDER=gemini");
+    println!("    export GOOGLE_API_KEY=\"...\"");
+    println!("  Ollama: install from https://ollama.com, no key needed");
+    println!("    export CM_PROVIDER=ollama");
+}
+
+/// Marker written once onboarding has been shown
+fn onboarded_path() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("onboarded"))
+}
+
+/// Whether this looks like the first run on this machine
+pub fn is_first_run() -> bool {
+    onboarded_path().is_some_and(|path| !path.exists())
+}
+
+/// Walk a new user through choosing a provider and setting its key
+pub fn onboard() -> io::Result<()> {
+    println!("👋 Welcome to cm! It writes Conventional Commits messages for your staged changes.\n");
+    println!("cm needs an API key for one of these providers:");
+    println!("  1) OpenRouter - many models, including free ones");
+    println!("  2) Gemini     - Google's models, with a free tier");
+    print!("\nWhich would you like to use? [1/2]: ");
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    println!();
+    match input.trim() {
+        "2" => {
+            println!("1. Create a key at https://aistudio.google.com/app/apikey");
+            println!("2. Add these lines to your shell profile (~/.bashrc, ~/.zshrc, ...):");
+            println!("     export CM_PROVIDER=gemini");
+            println!("     export GOOGLE_API_KEY=\"<your key>\"");
+        }
+        _ => {
+            println!("1. Create a key at https://openrouter.ai/keys");
+            println!("2. Add this line to your shell profile (~/.bashrc, ~/.zshrc, ...):");
+            println!("     export OPENROUTER_API_KEY=\"<your key>\"");
+        }
+    }
+    println!("3. Open a new shell, stage some changes and run `cm` again.");
+    println!("\nRun `cm help` at any time to see the keys and all settings.");
+
+    if let Some(path) = onboarded_path() {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, "")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,222 @@
+use std::path::Path;
+
+use crate::config::ScopeDepth;
+use crate::deps::dependency_changes;
+use crate::diff::FileDiff;
+
+/// A commit type implied by the changed paths alone
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TypeHint {
+    /// Type the model is told to use
+    pub kind: &'static str,
+    /// Types accepted from the model without regenerating
+    pub accepted: &'static [&'static str],
+    /// Why the hint applies, for the prompt
+    pub reason: &'static str,
+    /// Only suggested to the model; other types are kept rather than regenerated
+    pub advisory: bool,
+}
+
+/// Manifests and lockfiles that can carry dependency bumps
+const DEPENDENCY_FILES: &[&str] = &[
+    "Cargo.toml",
+    "Cargo.lock",
+    "package.json",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "go.mod",
+    "go.sum",
+    "requirements.txt",
+    "poetry.lock",
+    "Pipfile.lock",
+    "Gemfile.lock",
+    "composer.lock",
+];
+
+/// Whether `path` looks like a test file or lives in a test directory
+pub fn is_test(path: &str) -> bool {
+    let p = Path::new(path);
+    let stem = p.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    path.split('/').any(|dir| matches!(dir, "tests" | "test" | "__tests__" | "spec"))
+        || stem.ends_with("_test")
+        || stem.ends_with(".test")
+        || stem.ends_with("_spec")
+        || stem.ends_with(".spec")
+        || stem.starts_with("test_")
+}
+
+fn is_docs(path: &str) -> bool {
+    let ext = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or_default();
+    path.starts_with("docs/")
+        || path.starts_with("doc/")
+        || matches!(ext, "md" | "rst" | "adoc")
+}
+
+fn is_ci(path: &str) -> bool {
+    path.starts_with(".github/workflows/")
+        || path.starts_with(".circleci/")
+        || path.starts_with(".buildkite/")
+        || matches!(
+            path,
+            ".gitlab-ci.yml" | ".travis.yml" | "Jenkinsfile" | "azure-pipelines.yml" | ".drone.yml"
+        )
+}
+
+fn is_dependency_file(path: &str) -> bool {
+    let name = Path::new(path).file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    DEPENDENCY_FILES.contains(&name)
+}
+
+/// Infer the commit type when every changed path points the same obvious way
+///
+/// Manifest edits only suggest `chore(deps)` when a dependency version actually
+/// changed, since the same files also hold features, scripts and metadata.
+pub fn infer_type(files: &[FileDiff]) -> Option<TypeHint> {
+    if files.is_empty() {
+        return None;
+    }
+    let all = |f: fn(&str) -> bool| files.iter().all(|file| f(&file.path));
+
+    if all(is_test) {
+        Some(TypeHint { kind: "test", accepted: &["test"], reason: "only test files changed", advisory: false })
+    } else if all(is_ci) {
+        Some(TypeHint { kind: "ci", accepted: &["ci"], reason: "only CI configuration changed", advisory: false })
+    } else if all(is_docs) {
+        Some(TypeHint { kind: "docs", accepted: &["docs"], reason: "only documentation changed", advisory: false })
+    } else if all(is_dependency_file) && !dependency_changes(files).is_empty() {
+        Some(TypeHint {
+            kind: "chore(deps)",
+            accepted: &["chore", "build", "deps"],
+            reason: "dependency versions changed",
+            advisory: true,
+        })
+    } else {
+        None
+    }
+}
+
+impl TypeHint {
+    /// Whether a generated commit type is consistent with this hint
+    pub fn accepts(&self, commit_type: &str) -> bool {
+        self.advisory || self.accepted.contains(&commit_type.to_lowercase().as_str())
+    }
+}
+
+/// Scope named by the directory level `rules` pick, when every matching path agrees
+///
+/// Paths no rule covers, such as a root lockfile, don't count against it.
+/// The segment has to be a directory, not the file itself.
+pub fn depth_scope(rules: &[ScopeDepth], paths: &[String]) -> Option<String> {
+    let mut scopes = paths.iter().filter_map(|path| {
+        let rule = rules.iter().find(|rule| match &rule.prefix {
+            Some(prefix) => path.starts_with(&format!("{}/", prefix)),
+            None => true,
+        })?;
+        let segments: Vec<&str> = path.split('/').collect();
+        Some(segments.get(rule.depth - 1).filter(|_| rule.depth < segments.len()).map(|s| s.to_string()))
+    });
+    let first = scopes.next()??;
+    scopes.all(|scope| scope.as_deref() == Some(first.as_str())).then_some(first)
+}
+
+/// Most changed lines a diff can have and still be too small to explain itself
+const AMBIGUOUS_LINES: usize = 4;
+
+/// Whether the diff shows what changed but hardly why: a handful of changed
+/// lines in code, such as a raised timeout or a flipped flag
+///
+/// New files are explained by what they contain, and changes to docs, tests,
+/// CI or dependency files by which files they are.
+pub fn is_ambiguous(files: &[FileDiff]) -> bool {
+    let changed = files
+        .iter()
+        .flat_map(|file| file.hunks.iter().flat_map(|hunk| hunk.lines().skip(1)))
+        .filter(|line| line.starts_with(['+', '-']))
+        .count();
+    changed > 0
+        && changed <= AMBIGUOUS_LINES
+        && files.iter().all(|file| !file.is_new())
+        && !files.iter().all(|file| is_dependency_file(&file.path))
+        && infer_type(files).is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::parse_diff;
+
+    fn paths(p: &[&str]) -> Vec<String> {
+        p.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_is_ambiguous() {
+        let file = |path: &str, hunk: &str| FileDiff {
+            path: path.to_string(),
+            header: format!("diff --git a/{0} b/{0}\n--- a/{0}\n+++ b/{0}", path),
+            hunks: vec![hunk.to_string()],
+        };
+        let timeout = file("src/client.rs", "@@ -3 +3 @@\n-const TIMEOUT: u64 = 30;\n+const TIMEOUT: u64 = 90;\n");
+        assert!(is_ambiguous(std::slice::from_ref(&timeout)));
+        assert!(!is_ambiguous(&[file("README.md", "@@ -1 +1 @@\n-Hello\n+Hi\n")]));
+        let big = file("src/client.rs", &format!("@@ -1,5 +1,5 @@\n{}", "+line\n".repeat(5)));
+        assert!(!is_ambiguous(&[timeout, big]));
+        assert!(!is_ambiguous(&[]));
+    }
+
+    fn touched(p: &[&str]) -> Vec<FileDiff> {
+        p.iter()
+            .map(|path| FileDiff {
+                path: path.to_string(),
+                header: format!("diff --git a/{0} b/{0}\n--- a/{0}\n+++ b/{0}", path),
+                hunks: vec!["@@ -1 +1 @@\n-a\n+b\n".to_string()],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_infer_type() {
+        assert_eq!(infer_type(&touched(&["tests/cli.rs", "src/foo_test.go"])).unwrap().kind, "test");
+        assert_eq!(infer_type(&touched(&["docs/guide.md", "README.md"])).unwrap().kind, "docs");
+        assert_eq!(infer_type(&touched(&[".github/workflows/ci.yml"])).unwrap().kind, "ci");
+        assert_eq!(infer_type(&touched(&["src/main.rs", "tests/cli.rs"])), None);
+    }
+
+    #[test]
+    fn test_infer_type_dependencies() {
+        let bump = parse_diff(
+            "diff --git a/Cargo.toml b/Cargo.toml\n--- a/Cargo.toml\n+++ b/Cargo.toml\n@@ -8 +8 @@\n-serde = \"1.0.1\"\n+serde = \"1.0.2\"\n",
+        );
+        let hint = infer_type(&bump).unwrap();
+        assert_eq!(hint.kind, "chore(deps)");
+        assert!(hint.advisory);
+
+        // A new feature flag or script in a manifest is not a dependency bump
+        let feature = parse_diff(
+            "diff --git a/Cargo.toml b/Cargo.toml\n--- a/Cargo.toml\n+++ b/Cargo.toml\n@@ -20,0 +21,2 @@\n+[features]\n+tls = []\n",
+        );
+        assert_eq!(infer_type(&feature), None);
+    }
+
+    #[test]
+    fn test_depth_scope() {
+        let packages = [ScopeDepth { prefix: Some("packages".into()), depth: 2 }];
+        let staged = paths(&["packages/auth/src/token.rs", "packages/auth/README.md", "Cargo.lock"]);
+        assert_eq!(depth_scope(&packages, &staged).as_deref(), Some("auth"));
+        assert_eq!(depth_scope(&packages, &paths(&["packages/auth/a.rs", "packages/ui/b.rs"])), None);
+        assert_eq!(depth_scope(&packages, &paths(&["packages/README.md"])), None);
+        assert_eq!(depth_scope(&packages, &paths(&["src/main.rs"])), None);
+        let top = [ScopeDepth { prefix: None, depth: 1 }];
+        assert_eq!(depth_scope(&top, &paths(&["api/routes.rs", "api/v1/users.rs"])).as_deref(), Some("api"));
+    }
+
+    #[test]
+    fn test_accepts() {
+        let hint = infer_type(&touched(&["docs/guide.md"])).unwrap();
+        assert!(hint.accepts("DOCS"));
+        assert!(!hint.accepts("feat"));
+        let advice = TypeHint { kind: "chore(deps)", accepted: &["chore"], reason: "", advisory: true };
+        assert!(advice.accepts("feat"));
+    }
+}
@@ -0,0 +1,207 @@
+use std::fmt;
+
+use crate::config::{Config, HistoryFormat};
+use crate::template::ticket_from_branch;
+use crate::vcs::{LogEntry, Vcs};
+
+/// Commits to the staged files searched for issue references
+const RELATED_COMMITS: usize = 20;
+
+/// Most related issues mentioned in the prompt
+const MAX_RELATED: usize = 5;
+
+/// Trailer keywords whose lines may reference tracker tickets such as `ABC-123`
+const REF_TRAILERS: &[&str] = &["refs", "ref", "closes", "fixes", "resolves", "related", "see"];
+
+/// Author name/email fragments identifying automated committers
+const BOT_MARKERS: &[&str] = &["[bot]", "dependabot", "renovate", "github-actions", "greenkeeper", "snyk-bot"];
+
+/// Whether a commit would teach the model the wrong style
+fn is_noise(entry: &LogEntry) -> bool {
+    let subject = entry.subject();
+    let author = format!("{} {}", entry.author_name, entry.author_email).to_lowercase();
+
+    subject.starts_with("Merge ")
+        || subject.starts_with("Revert \"")
+        || BOT_MARKERS.iter().any(|marker| author.contains(marker))
+}
+
+/// Commits up to `tip` (default: the latest) rendered as style reference for the prompt
+pub fn load_history(vcs: Option<&dyn Vcs>, config: &Config, tip: Option<&str>) -> String {
+    let Some(vcs) = vcs.filter(|_| config.history_count > 0 && config.context.history) else {
+        return String::new();
+    };
+
+    let author = match config.history_author.as_deref() {
+        Some("me") => vcs.config_value("user.email"),
+        other => other.map(String::from),
+    };
+
+    if !config.history_filter {
+        let entries = vcs.history(config.history_count, tip, author.as_deref(), false).unwrap_or_default();
+        return render_history(&entries, config.history_format);
+    }
+
+    // Over-fetch so filtering still leaves enough examples
+    let entries: Vec<LogEntry> = vcs.history(config.history_count * 3, tip, author.as_deref(), true)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|e| !is_noise(e))
+        .take(config.history_count)
+        .collect();
+    render_history(&entries, config.history_format)
+}
+
+/// An issue or pull request a recent commit to the same files referenced
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelatedIssue {
+    /// `#456`, `owner/repo#456` or `ABC-123`
+    pub reference: String,
+    /// Subject of the latest commit referencing it
+    pub subject: String,
+}
+
+impl fmt::Display for RelatedIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.reference, self.subject)
+    }
+}
+
+/// Issues referenced by recent commits touching `paths`, most recent first
+pub fn related_issues(vcs: Option<&dyn Vcs>, config: &Config, paths: &[String]) -> Vec<RelatedIssue> {
+    let Some(vcs) = vcs.filter(|_| config.context.issues && !paths.is_empty()) else {
+        return Vec::new();
+    };
+    let mut related: Vec<RelatedIssue> = Vec::new();
+    for entry in vcs.file_history(paths, RELATED_COMMITS).iter().filter(|e| !is_noise(e)) {
+        for reference in issue_refs(&entry.message) {
+            if !related.iter().any(|r| r.reference == reference) {
+                related.push(RelatedIssue {
+                    reference,
+                    subject: entry.subject().to_string(),
+                });
+            }
+        }
+    }
+    related.truncate(MAX_RELATED);
+    related
+}
+
+/// The latest commit that touched any of `paths`, which the staged change likely continues
+pub fn previous_commit(vcs: Option<&dyn Vcs>, config: &Config, paths: &[String]) -> Option<LogEntry> {
+    let vcs = vcs.filter(|_| config.context.previous && !paths.is_empty())?;
+    vcs.file_history(paths, RELATED_COMMITS).into_iter().find(|e| !is_noise(e))
+}
+
+/// `#456` and `owner/repo#456` anywhere in `message`, and tickets on `Refs:`-style lines
+fn issue_refs(message: &str) -> Vec<String> {
+    let mut refs = Vec::new();
+    for (at, _) in message.match_indices('#') {
+        let number: String = message[at + 1..].chars().take_while(char::is_ascii_digit).collect();
+        if number.is_empty() {
+            continue;
+        }
+        let prefix_start = message[..at]
+            .rfind(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '/')))
+            .map_or(0, |i| i + 1);
+        let prefix = &message[prefix_start..at];
+        // `abc#12` and `&#39;` aren't references; `owner/repo#12` is
+        if prefix.is_empty() && !message[..at].ends_with('&') || prefix.contains('/') {
+            refs.push(format!("{}#{}", prefix, number));
+        }
+    }
+    for line in message.lines() {
+        let keyword = line.split([':', ' ']).next().unwrap_or_default().to_lowercase();
+        if REF_TRAILERS.contains(&keyword.as_str()) {
+            refs.extend(line.split([' ', ',']).filter_map(ticket_from_branch));
+        }
+    }
+    let mut unique = Vec::new();
+    for reference in refs {
+        if !unique.contains(&reference) {
+            unique.push(reference);
+        }
+    }
+    unique
+}
+
+fn render_history(entries: &[LogEntry], format: HistoryFormat) -> String {
+    match format {
+        HistoryFormat::Oneline => entries
+            .iter()
+            .map(|e| format!("{} {}\n", e.hash, e.subject()))
+            .collect(),
+        HistoryFormat::Full => entries
+            .iter()
+            .map(|e| format!("{} {}\n", e.hash, e.message))
+            .collect::<Vec<_>>()
+            .join("---\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(hash: &str, message: &str) -> LogEntry {
+        LogEntry {
+            hash: hash.to_string(),
+            author_name: "A".to_string(),
+            author_email: "a@example.com".to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_is_noise() {
+        assert!(is_noise(&entry("1", "Merge branch 'main' into feature")));
+        assert!(is_noise(&entry("2", "Revert \"feat: add x\"\n\nThis reverts commit abc.")));
+        assert!(!is_noise(&entry("3", "feat: add x")));
+
+        let mut bot = entry("4", "chore(deps): bump serde from 1.0.1 to 1.0.2");
+        bot.author_name = "dependabot[bot]".to_string();
+        assert!(is_noise(&bot));
+    }
+
+    #[test]
+    fn test_previous_commit() {
+        use crate::harness::{config, TempRepo};
+        use crate::vcs::Git;
+
+        let repo = TempRepo::new();
+        repo.stage("src/auth.rs", "fn token() {}\n").commit("feat(auth): add tokens\n\nFirst step of #12.");
+        repo.stage("README.md", "# Auth\n").commit("docs: add readme");
+        let _entered = repo.enter();
+        let paths = vec!["src/auth.rs".to_string()];
+        let previous = previous_commit(Some(&Git), &config(), &paths).unwrap();
+        assert_eq!(previous.message, "feat(auth): add tokens\n\nFirst step of #12.");
+
+        let mut config = config();
+        config.context.previous = false;
+        assert_eq!(previous_commit(Some(&Git), &config, &paths), None);
+    }
+
+    #[test]
+    fn test_issue_refs() {
+        assert_eq!(issue_refs("fix: refresh auth token (#456)"), vec!["#456"]);
+        assert_eq!(
+            issue_refs("feat: sync\n\nFollows up acme/api#12.\n\nRefs: AUTH-7, #9"),
+            vec!["acme/api#12", "#9", "AUTH-7"]
+        );
+        assert!(issue_refs("fix: escape &#39; in C# and abc#1").is_empty());
+        assert!(issue_refs("build: support UTF-8 paths").is_empty());
+    }
+
+    #[test]
+    fn test_render_history() {
+        let entries = vec![
+            entry("abc123", "feat: add x\n\n- does y"),
+            entry("def456", "fix: z"),
+        ];
+        assert_eq!(render_history(&entries, HistoryFormat::Oneline), "abc123 feat: add x\ndef456 fix: z\n");
+        assert_eq!(
+            render_history(&entries, HistoryFormat::Full),
+            "abc123 feat: add x\n\n- does y\n---\ndef456 fix: z\n"
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::diff::normalize_newlines;
+use crate::editor;
+use crate::message::split_trailers;
+
+/// `prepare-commit-msg` sources for which the message was already written
+const SKIPPED_SOURCES: &[&str] = &["message", "merge", "squash", "commit"];
+
+/// The line `git commit -v` puts above the diff; git drops it and everything below
+const SCISSORS: &str = "------------------------ >8 ------------------------";
+
+/// Whether to leave the message alone: `-m`/`-F`, merges, squashes and amends
+pub fn skips(source: Option<&str>) -> bool {
+    source.is_some_and(|source| SKIPPED_SOURCES.contains(&source))
+}
+
+/// `message` merged with what git put in the file
+///
+/// With `commit.template` set that is the template: its text, such as a
+/// checklist, goes under the message body and above any trailers, and its
+/// comment lines join git's help comments at the bottom. With `git commit -v`
+/// the scissors line and the diff under it are kept verbatim at the end.
+fn merge_message(message: &str, existing: &str, comment: char) -> String {
+    let scissors = format!("{} {}", comment, SCISSORS);
+    let line_start = |&(at, _): &(usize, &str)| at == 0 || existing[..at].ends_with('\n');
+    let (existing, verbose) = match existing.match_indices(&scissors).find(line_start) {
+        Some((at, _)) => existing.split_at(at),
+        None => (existing, ""),
+    };
+    let (comments, template): (Vec<&str>, Vec<&str>) = existing.lines().partition(|line| line.starts_with(comment));
+    let template = template.join("\n");
+    let (body, trailers) = split_trailers(message);
+    let mut merged = [Some(body), Some(template.trim()).filter(|t| !t.is_empty()), trailers]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    merged.push('\n');
+    if !comments.is_empty() {
+        merged.push('\n');
+        merged.push_str(&comments.join("\n"));
+        merged.push('\n');
+    }
+    if !verbose.is_empty() {
+        merged.push('\n');
+        merged.push_str(verbose);
+    }
+    merged
+}
+
+/// Write `message` to the commit message file git handed the hook
+pub fn write_message(path: &Path, message: &str) -> io::Result<()> {
+    let existing = normalize_newlines(&fs::read_to_string(path).unwrap_or_default());
+    fs::write(path, merge_message(message, &existing, editor::comment_char()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skips() {
+        assert!(!skips(None));
+        assert!(!skips(Some("template")));
+        assert!(skips(Some("message")));
+        assert!(skips(Some("commit")));
+    }
+
+    #[test]
+    fn test_merge_message() {
+        let existing = "\n# Please enter the commit message for your changes.\n";
+        assert_eq!(
+            merge_message("feat: add parser", existing, '#'),
+            "feat: add parser\n\n# Please enter the commit message for your changes.\n"
+        );
+        assert_eq!(merge_message("fix: typo", "", '#'), "fix: typo\n");
+
+        // A commit.template with a checklist, followed by git's own comments
+        let template = "# Why is this change needed?\n\nChecklist:\n- [ ] Tests added\n- [ ] Docs updated\n\n# Please enter the commit message.\n";
+        assert_eq!(
+            merge_message("fix(auth): refresh tokens\n\nExpired tokens failed.\n\nRefs: AUTH-7", template, '#'),
+            "fix(auth): refresh tokens\n\nExpired tokens failed.\n\nChecklist:\n- [ ] Tests added\n- [ ] Docs updated\n\nRefs: AUTH-7\n\n\
+             # Why is this change needed?\n# Please enter the commit message.\n"
+        );
+        assert_eq!(
+            merge_message("fix: typo", "; note\n", ';'),
+            "fix: typo\n\n; note\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_message_verbose() {
+        // `git commit -v`: the diff below the scissors must stay below it
+        let verbose = "\n# Please enter the commit message.\n\
+                       # ------------------------ >8 ------------------------\n\
+                       # Do not modify or remove the line above.\n\
+                       diff --git a/src/lib.rs b/src/lib.rs\n\
+                       +pub fn parse() {}\n";
+        assert_eq!(
+            merge_message("feat: add parser", verbose, '#'),
+            "feat: add parser\n\n# Please enter the commit message.\n\n\
+             # ------------------------ >8 ------------------------\n\
+             # Do not modify or remove the line above.\n\
+             diff --git a/src/lib.rs b/src/lib.rs\n\
+             +pub fn parse() {}\n"
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use std::fs;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use reqwest::{Certificate, Client, Proxy};
+
+use crate::config::{ConfigError, HttpOptions};
+
+const USER_AGENT: &str = concat!("cm/", env!("CARGO_PKG_VERSION"));
+
+/// Giving up on connecting sooner than on a slow answer
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The client every provider shares, so they pool connections and use the same settings
+static CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// Build the shared client from `options`; the first configuration loaded wins
+pub fn init(options: &HttpOptions) -> Result<(), ConfigError> {
+    if CLIENT.get().is_none() {
+        let client = build(options).map_err(ConfigError::InvalidHttp)?;
+        let _ = CLIENT.set(client);
+    }
+    Ok(())
+}
+
+/// The shared client, with default settings if no configuration was loaded
+pub fn client() -> Client {
+    CLIENT
+        .get_or_init(|| build(&HttpOptions::default()).unwrap_or_default())
+        .clone()
+}
+
+fn build(options: &HttpOptions) -> Result<Client, String> {
+    let mut builder = Client::builder().user_agent(USER_AGENT).connect_timeout(CONNECT_TIMEOUT);
+    if let Some(timeout) = options.timeout {
+        builder = builder.timeout(timeout);
+    }
+    if let Some(proxy) = &options.proxy {
+        let proxy = Proxy::all(proxy).map_err(|e| format!("CM_HTTP_PROXY '{}': {}", proxy, e))?;
+        builder = builder.proxy(proxy);
+    }
+    if let Some(path) = &options.ca_cert {
+        let pem = fs::read(path).map_err(|e| format!("CM_CA_CERT '{}': {}", path.display(), e))?;
+        let certificate = Certificate::from_pem(&pem).map_err(|e| format!("CM_CA_CERT '{}': {}", path.display(), e))?;
+        builder = builder.add_root_certificate(certificate);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build() {
+        assert!(build(&HttpOptions::default()).is_ok());
+        let bad_proxy = HttpOptions {
+            proxy: Some("not a url".into()),
+            ..HttpOptions::default()
+        };
+        assert!(build(&bad_proxy).unwrap_err().starts_with("CM_HTTP_PROXY"));
+        let missing_cert = HttpOptions {
+            ca_cert: Some("/nonexistent/ca.pem".into()),
+            ..HttpOptions::default()
+        };
+        assert!(build(&missing_cert).unwrap_err().starts_with("CM_CA_CERT"));
+    }
+}
@@ -0,0 +1,641 @@
+use std::env;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Languages the interface is available in
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+    Spanish,
+    French,
+}
+
+impl Language {
+    /// Match a code such as `de`, `de_DE.UTF-8` or `fr-CA`
+    fn from_code(code: &str) -> Option<Self> {
+        match code.get(..2)?.to_lowercase().as_str() {
+            "en" => Some(Self::English),
+            "de" => Some(Self::German),
+            "es" => Some(Self::Spanish),
+            "fr" => Some(Self::French),
+            _ => None,
+        }
+    }
+}
+
+/// `CM_UI_LANG`, falling back to the usual locale variables, then English
+pub fn detect() -> Language {
+    ["CM_UI_LANG", "LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|key| env::var(key).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| Language::from_code(&value))
+        .unwrap_or_default()
+}
+
+static LANGUAGE: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_language(language: Language) {
+    LANGUAGE.store(language as u8, Ordering::Relaxed);
+}
+
+fn language() -> Language {
+    match LANGUAGE.load(Ordering::Relaxed) {
+        x if x == Language::German as u8 => Language::German,
+        x if x == Language::Spanish as u8 => Language::Spanish,
+        x if x == Language::French as u8 => Language::French,
+        _ => Language::English,
+    }
+}
+
+/// A user-facing interface string; `{}` marks where arguments go
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Msg {
+    Error,
+    Warning,
+    NoStagedChanges,
+    NoDiffOnStdin,
+    SecretsFound,
+    SecretsRefused,
+    LicenseHeaderMissing,
+    FileTooLarge,
+    DisallowedExtension,
+    SuggestedReviewers,
+    RunningCheck,
+    CheckPassed,
+    CheckFailed,
+    CheckNotRun,
+    GeneratedOmitted,
+    PushingTo,
+    PushedTo,
+    PushesFailed,
+    PushBehind,
+    PushNoUpstream,
+    PushAuth,
+    PushProtected,
+    RunFixPrompt,
+    OnProtectedBranch,
+    CreateBranchPrompt,
+    BranchCreated,
+    ProtectedBranchBlocked,
+    HookVetoed,
+    ContextSize,
+    ContextConfirmPrompt,
+    ContextTooLarge,
+    EditorMissing,
+    NoEditor,
+    EditorHint,
+    EditedProblem,
+    SuspiciousResponse,
+    RawTitle,
+    MoreLines,
+    RawActions,
+    DiscardSuspicious,
+    ModelFallback,
+    OllamaPullPrompt,
+    OllamaPulling,
+    OllamaPulled,
+    ConcludingMerge,
+    HookFailed,
+    NoWorkingCopyChanges,
+    NoChangesIn,
+    NoStagedMatch,
+    UnstagedIncluded,
+    Using,
+    FilesChanged,
+    PrivacyMode,
+    PrivacyModeHashing,
+    LocalDependencies,
+    Generating,
+    GeneratingMany,
+    OverBudget,
+    ApiErrorRetrying,
+    TooShort,
+    DiscardBanned,
+    DiscardQuality,
+    DiscardPlugin,
+    DiscardHook,
+    PluginFailed,
+    Quality,
+    DiscardType,
+    ContainsBanned,
+    Committing,
+    Committed,
+    Pushing,
+    Pushed,
+    EmptyMessage,
+    Regenerating,
+    RegeneratingWithout,
+    Aborted,
+    ChooseMessage,
+    ChooseMessagePlain,
+    InvalidNumber,
+    ToggleItems,
+    IgnoringItem,
+    FileItem,
+    HunkItem,
+    Withheld,
+    Accept,
+    Edit,
+    Regenerate,
+    Exclude,
+    Quit,
+    ActionsPlain,
+    InvalidAction,
+    PushPrompt,
+    PushPromptPlain,
+    InterviewAnswer,
+    BreakingPrompt,
+    BreakingPromptPlain,
+    BreakingDeclined,
+    OpeningEditor,
+    EditorFailed,
+    GeneratedTitle,
+    CandidateTitle,
+    Via,
+    Subject,
+    Body,
+    NoBody,
+}
+
+fn english(msg: Msg) -> &'static str {
+    match msg {
+        Msg::Error => "Error: {}",
+        Msg::Warning => "Warning: ",
+        Msg::NoStagedChanges => "No staged changes. Use 'git add <files>' to stage changes first.",
+        Msg::NoDiffOnStdin => "No diff on stdin. Pipe one in, e.g. 'git diff | cm --stdin'.",
+        Msg::SecretsFound => "Possible credentials in the staged changes; check them before pushing:",
+        Msg::SecretsRefused => "Refusing to commit likely credentials:\n{}\nRemove them from the index, or pass --allow-secrets if they are safe to commit.",
+        Msg::LicenseHeaderMissing => "{} is a new source file without the license header '{}'",
+        Msg::FileTooLarge => "{} is {}, over the {} limit",
+        Msg::DisallowedExtension => "{} has the disallowed extension .{}",
+        Msg::SuggestedReviewers => "Suggested reviewers (CODEOWNERS): {}",
+        Msg::RunningCheck => "Running '{}'...",
+        Msg::CheckPassed => "'{}' passed in {}s",
+        Msg::CheckFailed => "'{}' failed after {}s; fix it, or pass --allow-failing-check to commit anyway:\n{}",
+        Msg::CheckNotRun => "Couldn't run '{}': {}",
+        Msg::GeneratedOmitted => "Leaving {} generated file(s) out of the prompt",
+        Msg::PushingTo => "Pushing to {}...",
+        Msg::PushedTo => "Pushed to {}",
+        Msg::PushesFailed => "The commit was made, but pushing failed for:\n{}",
+        Msg::PushBehind => "The remote has commits this branch doesn't; bring them in with `{}` and push again",
+        Msg::PushNoUpstream => "The branch doesn't track a remote branch yet; set one with `{}`",
+        Msg::PushAuth => "The remote refused your credentials; check your SSH key or credential helper and push again",
+        Msg::PushProtected => "The remote doesn't accept direct pushes to this branch; push to a new branch and open a pull request",
+        Msg::RunFixPrompt => "Run `{}` now? [y/N]: ",
+        Msg::OnProtectedBranch => "You're committing directly on the protected branch '{}'",
+        Msg::CreateBranchPrompt => "Create branch `{}` and commit there? [y/N]: ",
+        Msg::BranchCreated => "Switched to new branch '{}'",
+        Msg::ProtectedBranchBlocked => "Not committing on the protected branch '{}' (CM_PROTECTED_BRANCH_ACTION=block); create a branch first",
+        Msg::HookVetoed => "The {} hook stopped the run: {}",
+        Msg::ContextSize => "Sending {} files, {} changed lines (~{} tokens) to {} ({})",
+        Msg::ContextConfirmPrompt => "That's over CM_CONFIRM_LINES/CM_CONFIRM_TOKENS. Send it? [y/N]: ",
+        Msg::ContextTooLarge => "The context is over CM_CONFIRM_LINES/CM_CONFIRM_TOKENS and there's no terminal to confirm on; raise the limits or commit fewer changes",
+        Msg::EditorMissing => "Editor '{}' from {} isn't installed; trying the next one",
+        Msg::NoEditor => "No editor found (tried {}). Set one with `git config --global core.editor <command>` or CM_EDITOR",
+        Msg::EditorHint => "Lines starting with '{}' will be ignored, and an empty message aborts the commit.",
+        Msg::EditedProblem => "Edited message: {}",
+        Msg::SuspiciousResponse => "This doesn't look like a commit message: {}",
+        Msg::RawTitle => "Raw response",
+        Msg::MoreLines => "… {} more lines (v shows all)",
+        Msg::RawActions => "[u]se as-is  [p] re-parse  [r]egenerate  [v]iew all : ",
+        Msg::DiscardSuspicious => "Discarding '{}': {}",
+        Msg::ModelFallback => "Model '{}' isn't available; using '{}' instead",
+        Msg::OllamaPullPrompt => "Model '{}' isn't pulled on the Ollama server. Pull it now? [y/N]: ",
+        Msg::OllamaPulling => "Pulling '{}'; large models can take a while...",
+        Msg::OllamaPulled => "Pulled '{}'",
+        Msg::ConcludingMerge => "Concluding the merge: {} ({} conflicted file(s))",
+        Msg::HookFailed => "Couldn't write a commit message ({}); continuing with git's default.",
+        Msg::NoWorkingCopyChanges => "No changes in the working copy.",
+        Msg::NoChangesIn => "No changes in {}.",
+        Msg::NoStagedMatch => "No staged changes match '{}'.",
+        Msg::UnstagedIncluded => "Unstaged changes in {} will be included in the commit",
+        Msg::Using => "Using {} ({})",
+        Msg::FilesChanged => "{} file(s) changed",
+        Msg::PrivacyMode => "Privacy mode: redacting identities",
+        Msg::PrivacyModeHashing => "Privacy mode: redacting identities and hashing directories",
+        Msg::LocalDependencies => "Dependency update detected; message built locally",
+        Msg::Generating => "Generating commit message (attempt {})...",
+        Msg::GeneratingMany => "Generating {} commit messages (attempt {})...",
+        Msg::OverBudget => "Over budget: {}",
+        Msg::ApiErrorRetrying => "API error: {}. Retrying...",
+        Msg::TooShort => "Generated message too short: '{}'",
+        Msg::DiscardBanned => "Discarding '{}': contains banned phrase(s) {}",
+        Msg::DiscardQuality => "Discarding '{}': quality {}/100 is under CM_MIN_QUALITY ({})",
+        Msg::DiscardPlugin => "Discarding '{}': plugin {} rejected it: {}",
+        Msg::DiscardHook => "Discarding '{}': the post_generate hook vetoed it: {}",
+        Msg::PluginFailed => "Plugin {} failed, skipping it: {}",
+        Msg::Quality => "Quality {}/100",
+        Msg::DiscardType => "Discarding '{}': expected type '{}' ({})",
+        Msg::ContainsBanned => "Contains banned phrase(s): {}",
+        Msg::Committing => "Committing...",
+        Msg::Committed => "Committed successfully!",
+        Msg::Pushing => "Pushing...",
+        Msg::Pushed => "Pushed successfully!",
+        Msg::EmptyMessage => "Empty message, aborting commit",
+        Msg::Regenerating => "Regenerating...",
+        Msg::RegeneratingWithout => "Regenerating without {} withheld item(s)...",
+        Msg::Aborted => "Aborted",
+        Msg::ChooseMessage => "Choose a message [1-{}]: ",
+        Msg::ChooseMessagePlain => "Choose a message, 1 to {}: ",
+        Msg::InvalidNumber => "Invalid choice. Please enter a number from 1 to {}.",
+        Msg::ToggleItems => "Toggle items to withhold from the AI (e.g. '2 3.1'), Enter when done: ",
+        Msg::IgnoringItem => "Ignoring '{}': no such file or hunk",
+        Msg::FileItem => "File {}: {}",
+        Msg::HunkItem => "Hunk {}: {}",
+        Msg::Withheld => " (withheld)",
+        Msg::Accept => "[A]ccept",
+        Msg::Edit => "[E]dit",
+        Msg::Regenerate => "[R]egenerate",
+        Msg::Exclude => "e[X]clude",
+        Msg::Quit => "[Q]uit",
+        Msg::ActionsPlain => "Actions: accept (a), edit (e), regenerate (r), exclude (x), quit (q): ",
+        Msg::InvalidAction => "Invalid choice. Please enter A, E, R, X, or Q.",
+        Msg::PushPrompt => "Push to remote? [y/N]: ",
+        Msg::PushPromptPlain => "Push to remote? yes (y) or no (n, default): ",
+        Msg::InterviewAnswer => "Your answer (Enter to skip): ",
+        Msg::BreakingPrompt => "This message marks a BREAKING CHANGE, which means a major release. Commit it anyway? [y/N]: ",
+        Msg::BreakingPromptPlain => "This message marks a breaking change, which means a major release. Commit it anyway? yes (y) or no (n, default): ",
+        Msg::BreakingDeclined => "Not committed. Edit the message to drop the breaking marker, or regenerate.",
+        Msg::OpeningEditor => "Opening {}...",
+        Msg::EditorFailed => "Editor '{}' exited with error",
+        Msg::GeneratedTitle => "Generated Commit Message",
+        Msg::CandidateTitle => "Candidate {}",
+        Msg::Via => "via {} ({})",
+        Msg::Subject => "Subject: {}",
+        Msg::Body => "Body: {}",
+        Msg::NoBody => "none",
+    }
+}
+
+fn german(msg: Msg) -> &'static str {
+    match msg {
+        Msg::Error => "Fehler: {}",
+        Msg::Warning => "Warnung: ",
+        Msg::NoStagedChanges => "Keine vorgemerkten Änderungen. Merke Änderungen zuerst mit 'git add <Dateien>' vor.",
+        Msg::NoDiffOnStdin => "Kein Diff auf stdin. Leite einen weiter, z. B. 'git diff | cm --stdin'.",
+        Msg::SecretsFound => "Mögliche Zugangsdaten in den vorgemerkten Änderungen; vor dem Pushen prüfen:",
+        Msg::SecretsRefused => "Commit mit wahrscheinlichen Zugangsdaten abgelehnt:\n{}\nEntferne sie aus dem Index oder übergib --allow-secrets, wenn sie unbedenklich sind.",
+        Msg::LicenseHeaderMissing => "{} ist eine neue Quelldatei ohne den Lizenzkopf '{}'",
+        Msg::FileTooLarge => "{} ist {} groß und überschreitet das Limit von {}",
+        Msg::DisallowedExtension => "{} hat die nicht erlaubte Endung .{}",
+        Msg::SuggestedReviewers => "Vorgeschlagene Reviewer (CODEOWNERS): {}",
+        Msg::RunningCheck => "Führe '{}' aus...",
+        Msg::CheckPassed => "'{}' erfolgreich in {}s",
+        Msg::CheckFailed => "'{}' nach {}s fehlgeschlagen; behebe den Fehler oder übergib --allow-failing-check, um trotzdem zu committen:\n{}",
+        Msg::CheckNotRun => "'{}' konnte nicht ausgeführt werden: {}",
+        Msg::GeneratedOmitted => "{} generierte Datei(en) werden nicht an das Modell gesendet",
+        Msg::PushingTo => "Pushe nach {}...",
+        Msg::PushedTo => "Nach {} gepusht",
+        Msg::PushesFailed => "Der Commit wurde erstellt, aber das Pushen ist fehlgeschlagen für:\n{}",
+        Msg::PushBehind => "Der Remote hat Commits, die diesem Branch fehlen; hole sie mit `{}` und pushe erneut",
+        Msg::PushNoUpstream => "Der Branch verfolgt noch keinen Remote-Branch; lege ihn mit `{}` fest",
+        Msg::PushAuth => "Der Remote hat deine Zugangsdaten abgelehnt; prüfe deinen SSH-Schlüssel oder Credential-Helper und pushe erneut",
+        Msg::PushProtected => "Der Remote nimmt keine direkten Pushes auf diesen Branch an; pushe auf einen neuen Branch und öffne einen Pull Request",
+        Msg::RunFixPrompt => "`{}` jetzt ausführen? [y/N]: ",
+        Msg::OnProtectedBranch => "Du committest direkt auf den geschützten Branch '{}'",
+        Msg::CreateBranchPrompt => "Branch `{}` anlegen und dort committen? [y/N]: ",
+        Msg::BranchCreated => "Zum neuen Branch '{}' gewechselt",
+        Msg::ProtectedBranchBlocked => "Kein Commit auf den geschützten Branch '{}' (CM_PROTECTED_BRANCH_ACTION=block); lege zuerst einen Branch an",
+        Msg::HookVetoed => "Der {}-Hook hat den Lauf gestoppt: {}",
+        Msg::ContextSize => "Sende {} Dateien, {} geänderte Zeilen (~{} Tokens) an {} ({})",
+        Msg::ContextConfirmPrompt => "Das liegt über CM_CONFIRM_LINES/CM_CONFIRM_TOKENS. Trotzdem senden? [y/N]: ",
+        Msg::ContextTooLarge => "Der Kontext liegt über CM_CONFIRM_LINES/CM_CONFIRM_TOKENS und es gibt kein Terminal zur Bestätigung; erhöhe die Grenzen oder committe weniger Änderungen",
+        Msg::EditorMissing => "Editor '{}' aus {} ist nicht installiert; versuche den nächsten",
+        Msg::NoEditor => "Kein Editor gefunden (versucht: {}). Lege einen mit `git config --global core.editor <befehl>` oder CM_EDITOR fest",
+        Msg::EditorHint => "Zeilen, die mit '{}' beginnen, werden ignoriert; eine leere Nachricht bricht den Commit ab.",
+        Msg::EditedProblem => "Bearbeitete Nachricht: {}",
+        Msg::SuspiciousResponse => "Das sieht nicht wie eine Commit-Nachricht aus: {}",
+        Msg::RawTitle => "Rohe Antwort",
+        Msg::MoreLines => "… {} weitere Zeilen (v zeigt alle)",
+        Msg::RawActions => "[u] so übernehmen  [p] neu parsen  [r] neu generieren  [v] alles zeigen : ",
+        Msg::DiscardSuspicious => "Verwerfe '{}': {}",
+        Msg::ModelFallback => "Modell '{}' ist nicht verfügbar; verwende stattdessen '{}'",
+        Msg::OllamaPullPrompt => "Modell '{}' ist auf dem Ollama-Server nicht vorhanden. Jetzt herunterladen? [y/N]: ",
+        Msg::OllamaPulling => "Lade '{}' herunter; große Modelle können eine Weile dauern...",
+        Msg::OllamaPulled => "'{}' heruntergeladen",
+        Msg::ConcludingMerge => "Schließe den Merge ab: {} ({} Datei(en) mit Konflikten)",
+        Msg::HookFailed => "Keine Commit-Nachricht erzeugt ({}); es geht mit der Vorgabe von git weiter.",
+        Msg::NoWorkingCopyChanges => "Keine Änderungen in der Arbeitskopie.",
+        Msg::NoChangesIn => "Keine Änderungen in {}.",
+        Msg::NoStagedMatch => "Keine vorgemerkten Änderungen passen zu '{}'.",
+        Msg::UnstagedIncluded => "Nicht vorgemerkte Änderungen in {} werden mit committet",
+        Msg::Using => "Verwende {} ({})",
+        Msg::FilesChanged => "{} Datei(en) geändert",
+        Msg::PrivacyMode => "Datenschutzmodus: Identitäten werden entfernt",
+        Msg::PrivacyModeHashing => "Datenschutzmodus: Identitäten werden entfernt und Verzeichnisse gehasht",
+        Msg::LocalDependencies => "Abhängigkeitsupdate erkannt; Nachricht lokal erstellt",
+        Msg::Generating => "Erzeuge Commit-Nachricht (Versuch {})...",
+        Msg::GeneratingMany => "Erzeuge {} Commit-Nachrichten (Versuch {})...",
+        Msg::OverBudget => "Budget überschritten: {}",
+        Msg::ApiErrorRetrying => "API-Fehler: {}. Neuer Versuch...",
+        Msg::TooShort => "Erzeugte Nachricht zu kurz: '{}'",
+        Msg::DiscardBanned => "Verwerfe '{}': enthält verbotene Wendung(en) {}",
+        Msg::DiscardQuality => "Verwerfe '{}': Qualität {}/100 liegt unter CM_MIN_QUALITY ({})",
+        Msg::DiscardPlugin => "Verwerfe '{}': Plugin {} lehnt sie ab: {}",
+        Msg::DiscardHook => "Verwerfe '{}': der post_generate-Hook hat sie abgelehnt: {}",
+        Msg::PluginFailed => "Plugin {} fehlgeschlagen, wird übersprungen: {}",
+        Msg::Quality => "Qualität {}/100",
+        Msg::DiscardType => "Verwerfe '{}': Typ '{}' erwartet ({})",
+        Msg::ContainsBanned => "Enthält verbotene Wendung(en): {}",
+        Msg::Committing => "Committe...",
+        Msg::Committed => "Erfolgreich committet!",
+        Msg::Pushing => "Pushe...",
+        Msg::Pushed => "Erfolgreich gepusht!",
+        Msg::EmptyMessage => "Leere Nachricht, Commit abgebrochen",
+        Msg::Regenerating => "Erzeuge neu...",
+        Msg::RegeneratingWithout => "Erzeuge neu ohne {} zurückgehaltene(s) Element(e)...",
+        Msg::Aborted => "Abgebrochen",
+        Msg::ChooseMessage => "Nachricht wählen [1-{}]: ",
+        Msg::ChooseMessagePlain => "Nachricht wählen, 1 bis {}: ",
+        Msg::InvalidNumber => "Ungültige Auswahl. Bitte eine Zahl von 1 bis {} eingeben.",
+        Msg::ToggleItems => "Elemente für die KI zurückhalten oder freigeben (z. B. '2 3.1'), Enter zum Beenden: ",
+        Msg::IgnoringItem => "Ignoriere '{}': keine solche Datei und kein solcher Hunk",
+        Msg::FileItem => "Datei {}: {}",
+        Msg::HunkItem => "Hunk {}: {}",
+        Msg::Withheld => " (zurückgehalten)",
+        Msg::Accept => "[A] Übernehmen",
+        Msg::Edit => "[E] Bearbeiten",
+        Msg::Regenerate => "[R] Neu erzeugen",
+        Msg::Exclude => "[X] Zurückhalten",
+        Msg::Quit => "[Q] Beenden",
+        Msg::ActionsPlain => "Aktionen: übernehmen (a), bearbeiten (e), neu erzeugen (r), zurückhalten (x), beenden (q): ",
+        Msg::InvalidAction => "Ungültige Auswahl. Bitte A, E, R, X oder Q eingeben.",
+        Msg::PushPrompt => "Zum Remote pushen? [y/N]: ",
+        Msg::PushPromptPlain => "Zum Remote pushen? ja (y) oder nein (n, Standard): ",
+        Msg::InterviewAnswer => "Deine Antwort (Enter zum Überspringen): ",
+        Msg::BreakingPrompt => "Diese Nachricht kennzeichnet eine BREAKING CHANGE, also ein Major-Release. Trotzdem committen? [y/N]: ",
+        Msg::BreakingPromptPlain => "Diese Nachricht kennzeichnet eine inkompatible Änderung, also ein Major-Release. Trotzdem committen? ja (y) oder nein (n, Standard): ",
+        Msg::BreakingDeclined => "Nicht committet. Entferne die Breaking-Markierung beim Bearbeiten oder generiere neu.",
+        Msg::OpeningEditor => "Öffne {}...",
+        Msg::EditorFailed => "Editor '{}' wurde mit einem Fehler beendet",
+        Msg::GeneratedTitle => "Erzeugte Commit-Nachricht",
+        Msg::CandidateTitle => "Vorschlag {}",
+        Msg::Via => "über {} ({})",
+        Msg::Subject => "Betreff: {}",
+        Msg::Body => "Text: {}",
+        Msg::NoBody => "keiner",
+    }
+}
+
+fn spanish(msg: Msg) -> &'static str {
+    match msg {
+        Msg::Error => "Error: {}",
+        Msg::Warning => "Aviso: ",
+        Msg::NoStagedChanges => "No hay cambios preparados. Usa 'git add <archivos>' para prepararlos primero.",
+        Msg::NoDiffOnStdin => "No hay diff en stdin. Pasa uno por tubería, p. ej. 'git diff | cm --stdin'.",
+        Msg::SecretsFound => "Posibles credenciales en los cambios preparados; revísalos antes de hacer push:",
+        Msg::SecretsRefused => "Se rechaza confirmar credenciales probables:\n{}\nQuítalas del índice o usa --allow-secrets si es seguro confirmarlas.",
+        Msg::LicenseHeaderMissing => "{} es un archivo fuente nuevo sin la cabecera de licencia '{}'",
+        Msg::FileTooLarge => "{} ocupa {}, por encima del límite de {}",
+        Msg::DisallowedExtension => "{} tiene la extensión no permitida .{}",
+        Msg::SuggestedReviewers => "Revisores sugeridos (CODEOWNERS): {}",
+        Msg::RunningCheck => "Ejecutando '{}'...",
+        Msg::CheckPassed => "'{}' pasó en {}s",
+        Msg::CheckFailed => "'{}' falló tras {}s; corrígelo o usa --allow-failing-check para confirmar de todos modos:\n{}",
+        Msg::CheckNotRun => "No se pudo ejecutar '{}': {}",
+        Msg::GeneratedOmitted => "Se omiten {} archivo(s) generado(s) del prompt",
+        Msg::PushingTo => "Haciendo push a {}...",
+        Msg::PushedTo => "Push a {} completado",
+        Msg::PushesFailed => "El commit se creó, pero el push falló para:\n{}",
+        Msg::PushBehind => "El remoto tiene commits que esta rama no tiene; tráelos con `{}` y vuelve a hacer push",
+        Msg::PushNoUpstream => "La rama aún no sigue una rama remota; configúrala con `{}`",
+        Msg::PushAuth => "El remoto rechazó tus credenciales; revisa tu clave SSH o el asistente de credenciales y vuelve a hacer push",
+        Msg::PushProtected => "El remoto no acepta push directo a esta rama; haz push a una rama nueva y abre un pull request",
+        Msg::RunFixPrompt => "¿Ejecutar `{}` ahora? [y/N]: ",
+        Msg::OnProtectedBranch => "Estás haciendo commit directamente en la rama protegida '{}'",
+        Msg::CreateBranchPrompt => "¿Crear la rama `{}` y hacer commit allí? [y/N]: ",
+        Msg::BranchCreated => "Cambiado a la nueva rama '{}'",
+        Msg::ProtectedBranchBlocked => "No se hace commit en la rama protegida '{}' (CM_PROTECTED_BRANCH_ACTION=block); crea primero una rama",
+        Msg::HookVetoed => "El hook {} detuvo la ejecución: {}",
+        Msg::ContextSize => "Enviando {} archivos, {} líneas cambiadas (~{} tokens) a {} ({})",
+        Msg::ContextConfirmPrompt => "Supera CM_CONFIRM_LINES/CM_CONFIRM_TOKENS. ¿Enviarlo? [y/N]: ",
+        Msg::ContextTooLarge => "El contexto supera CM_CONFIRM_LINES/CM_CONFIRM_TOKENS y no hay terminal para confirmar; sube los límites o confirma menos cambios",
+        Msg::EditorMissing => "El editor '{}' de {} no está instalado; probando el siguiente",
+        Msg::NoEditor => "No se encontró ningún editor (probados: {}). Configura uno con `git config --global core.editor <comando>` o CM_EDITOR",
+        Msg::EditorHint => "Las líneas que empiezan por '{}' se ignorarán, y un mensaje vacío cancela el commit.",
+        Msg::EditedProblem => "Mensaje editado: {}",
+        Msg::SuspiciousResponse => "Esto no parece un mensaje de commit: {}",
+        Msg::RawTitle => "Respuesta sin procesar",
+        Msg::MoreLines => "… {} líneas más (v muestra todo)",
+        Msg::RawActions => "[u] usar tal cual  [p] volver a analizar  [r] regenerar  [v] ver todo : ",
+        Msg::DiscardSuspicious => "Descartando '{}': {}",
+        Msg::ModelFallback => "El modelo '{}' no está disponible; se usa '{}' en su lugar",
+        Msg::OllamaPullPrompt => "El modelo '{}' no está descargado en el servidor Ollama. ¿Descargarlo ahora? [y/N]: ",
+        Msg::OllamaPulling => "Descargando '{}'; los modelos grandes pueden tardar un rato...",
+        Msg::OllamaPulled => "'{}' descargado",
+        Msg::ConcludingMerge => "Concluyendo la fusión: {} ({} archivo(s) con conflictos)",
+        Msg::HookFailed => "No se pudo escribir un mensaje de commit ({}); se continúa con el de git.",
+        Msg::NoWorkingCopyChanges => "No hay cambios en la copia de trabajo.",
+        Msg::NoChangesIn => "No hay cambios en {}.",
+        Msg::NoStagedMatch => "Ningún cambio preparado coincide con '{}'.",
+        Msg::UnstagedIncluded => "Los cambios sin preparar en {} se incluirán en el commit",
+        Msg::Using => "Usando {} ({})",
+        Msg::FilesChanged => "{} archivo(s) modificado(s)",
+        Msg::PrivacyMode => "Modo privado: ocultando identidades",
+        Msg::PrivacyModeHashing => "Modo privado: ocultando identidades y cifrando nombres de directorios",
+        Msg::LocalDependencies => "Actualización de dependencias detectada; mensaje creado localmente",
+        Msg::Generating => "Generando mensaje de commit (intento {})...",
+        Msg::GeneratingMany => "Generando {} mensajes de commit (intento {})...",
+        Msg::OverBudget => "Presupuesto superado: {}",
+        Msg::ApiErrorRetrying => "Error de API: {}. Reintentando...",
+        Msg::TooShort => "Mensaje generado demasiado corto: '{}'",
+        Msg::DiscardBanned => "Descartando '{}': contiene expresión(es) prohibida(s) {}",
+        Msg::DiscardQuality => "Descartando '{}': la calidad {}/100 está por debajo de CM_MIN_QUALITY ({})",
+        Msg::DiscardPlugin => "Descartando '{}': el plugin {} lo rechazó: {}",
+        Msg::DiscardHook => "Descartando '{}': el hook post_generate lo vetó: {}",
+        Msg::PluginFailed => "El plugin {} falló, se omite: {}",
+        Msg::Quality => "Calidad {}/100",
+        Msg::DiscardType => "Descartando '{}': se esperaba el tipo '{}' ({})",
+        Msg::ContainsBanned => "Contiene expresión(es) prohibida(s): {}",
+        Msg::Committing => "Creando commit...",
+        Msg::Committed => "¡Commit creado correctamente!",
+        Msg::Pushing => "Enviando...",
+        Msg::Pushed => "¡Enviado correctamente!",
+        Msg::EmptyMessage => "Mensaje vacío, commit cancelado",
+        Msg::Regenerating => "Regenerando...",
+        Msg::RegeneratingWithout => "Regenerando sin {} elemento(s) retenido(s)...",
+        Msg::Aborted => "Cancelado",
+        Msg::ChooseMessage => "Elige un mensaje [1-{}]: ",
+        Msg::ChooseMessagePlain => "Elige un mensaje, del 1 al {}: ",
+        Msg::InvalidNumber => "Opción no válida. Introduce un número del 1 al {}.",
+        Msg::ToggleItems => "Marca elementos que no se enviarán a la IA (p. ej. '2 3.1'), Intro para terminar: ",
+        Msg::IgnoringItem => "Ignorando '{}': no existe ese archivo o fragmento",
+        Msg::FileItem => "Archivo {}: {}",
+        Msg::HunkItem => "Fragmento {}: {}",
+        Msg::Withheld => " (retenido)",
+        Msg::Accept => "[A] Aceptar",
+        Msg::Edit => "[E] Editar",
+        Msg::Regenerate => "[R] Regenerar",
+        Msg::Exclude => "[X] Excluir",
+        Msg::Quit => "[Q] Salir",
+        Msg::ActionsPlain => "Acciones: aceptar (a), editar (e), regenerar (r), excluir (x), salir (q): ",
+        Msg::InvalidAction => "Opción no válida. Introduce A, E, R, X o Q.",
+        Msg::PushPrompt => "¿Enviar al remoto? [y/N]: ",
+        Msg::PushPromptPlain => "¿Enviar al remoto? sí (y) o no (n, predeterminado): ",
+        Msg::InterviewAnswer => "Tu respuesta (Enter para omitir): ",
+        Msg::BreakingPrompt => "Este mensaje marca un BREAKING CHANGE, lo que implica una versión mayor. ¿Hacer commit de todos modos? [y/N]: ",
+        Msg::BreakingPromptPlain => "Este mensaje marca un cambio incompatible, lo que implica una versión mayor. ¿Hacer commit de todos modos? sí (y) o no (n, predeterminado): ",
+        Msg::BreakingDeclined => "No se hizo commit. Edita el mensaje para quitar la marca de cambio incompatible, o regenera.",
+        Msg::OpeningEditor => "Abriendo {}...",
+        Msg::EditorFailed => "El editor '{}' terminó con un error",
+        Msg::GeneratedTitle => "Mensaje de commit generado",
+        Msg::CandidateTitle => "Opción {}",
+        Msg::Via => "con {} ({})",
+        Msg::Subject => "Asunto: {}",
+        Msg::Body => "Cuerpo: {}",
+        Msg::NoBody => "ninguno",
+    }
+}
+
+fn french(msg: Msg) -> &'static str {
+    match msg {
+        Msg::Error => "Erreur : {}",
+        Msg::Warning => "Attention : ",
+        Msg::NoStagedChanges => "Aucune modification indexée. Utilisez 'git add <fichiers>' pour les indexer d'abord.",
+        Msg::NoDiffOnStdin => "Aucun diff sur stdin. Redirigez-en un, par ex. 'git diff | cm --stdin'.",
+        Msg::SecretsFound => "Identifiants possibles dans les modifications indexées ; vérifiez-les avant de pousser :",
+        Msg::SecretsRefused => "Refus de valider des identifiants probables :\n{}\nRetirez-les de l'index, ou passez --allow-secrets s'ils peuvent être validés sans risque.",
+        Msg::LicenseHeaderMissing => "{} est un nouveau fichier source sans l'en-tête de licence '{}'",
+        Msg::FileTooLarge => "{} fait {}, au-delà de la limite de {}",
+        Msg::DisallowedExtension => "{} a l'extension interdite .{}",
+        Msg::SuggestedReviewers => "Relecteurs suggérés (CODEOWNERS) : {}",
+        Msg::RunningCheck => "Exécution de '{}'...",
+        Msg::CheckPassed => "'{}' réussi en {}s",
+        Msg::CheckFailed => "'{}' a échoué après {}s ; corrigez-le, ou passez --allow-failing-check pour valider quand même :\n{}",
+        Msg::CheckNotRun => "Impossible d'exécuter '{}' : {}",
+        Msg::GeneratedOmitted => "{} fichier(s) généré(s) exclu(s) du prompt",
+        Msg::PushingTo => "Push vers {}...",
+        Msg::PushedTo => "Poussé vers {}",
+        Msg::PushesFailed => "Le commit a été créé, mais le push a échoué pour :\n{}",
+        Msg::PushBehind => "Le remote contient des commits absents de cette branche ; récupérez-les avec `{}` puis poussez à nouveau",
+        Msg::PushNoUpstream => "La branche ne suit pas encore de branche distante ; définissez-la avec `{}`",
+        Msg::PushAuth => "Le remote a refusé vos identifiants ; vérifiez votre clé SSH ou votre gestionnaire d'identifiants puis poussez à nouveau",
+        Msg::PushProtected => "Le remote n'accepte pas de push direct sur cette branche ; poussez sur une nouvelle branche et ouvrez une pull request",
+        Msg::RunFixPrompt => "Exécuter `{}` maintenant ? [y/N] : ",
+        Msg::OnProtectedBranch => "Vous committez directement sur la branche protégée '{}'",
+        Msg::CreateBranchPrompt => "Créer la branche `{}` et y committer ? [y/N] : ",
+        Msg::BranchCreated => "Basculé sur la nouvelle branche '{}'",
+        Msg::ProtectedBranchBlocked => "Pas de commit sur la branche protégée '{}' (CM_PROTECTED_BRANCH_ACTION=block) ; créez d'abord une branche",
+        Msg::HookVetoed => "Le hook {} a arrêté l'exécution : {}",
+        Msg::ContextSize => "Envoi de {} fichiers, {} lignes modifiées (~{} tokens) à {} ({})",
+        Msg::ContextConfirmPrompt => "C'est au-delà de CM_CONFIRM_LINES/CM_CONFIRM_TOKENS. L'envoyer ? [y/N] : ",
+        Msg::ContextTooLarge => "Le contexte dépasse CM_CONFIRM_LINES/CM_CONFIRM_TOKENS et aucun terminal ne permet de confirmer ; relevez les limites ou committez moins de changements",
+        Msg::EditorMissing => "L'éditeur '{}' défini par {} n'est pas installé ; essai du suivant",
+        Msg::NoEditor => "Aucun éditeur trouvé (essayés : {}). Définissez-en un avec `git config --global core.editor <commande>` ou CM_EDITOR",
+        Msg::EditorHint => "Les lignes commençant par '{}' seront ignorées, et un message vide annule le commit.",
+        Msg::EditedProblem => "Message modifié : {}",
+        Msg::SuspiciousResponse => "Cela ne ressemble pas à un message de commit : {}",
+        Msg::RawTitle => "Réponse brute",
+        Msg::MoreLines => "… {} lignes de plus (v affiche tout)",
+        Msg::RawActions => "[u] utiliser tel quel  [p] réanalyser  [r] régénérer  [v] tout voir : ",
+        Msg::DiscardSuspicious => "Abandon de '{}' : {}",
+        Msg::ModelFallback => "Le modèle '{}' n'est pas disponible ; utilisation de '{}' à la place",
+        Msg::OllamaPullPrompt => "Le modèle '{}' n'est pas présent sur le serveur Ollama. Le télécharger maintenant ? [y/N] : ",
+        Msg::OllamaPulling => "Téléchargement de '{}' ; les gros modèles peuvent prendre un moment...",
+        Msg::OllamaPulled => "'{}' téléchargé",
+        Msg::ConcludingMerge => "Conclusion de la fusion : {} ({} fichier(s) en conflit)",
+        Msg::HookFailed => "Impossible d'écrire un message de commit ({}) ; git continue avec son message par défaut.",
+        Msg::NoWorkingCopyChanges => "Aucune modification dans la copie de travail.",
+        Msg::NoChangesIn => "Aucune modification dans {}.",
+        Msg::NoStagedMatch => "Aucune modification indexée ne correspond à '{}'.",
+        Msg::UnstagedIncluded => "Les modifications non indexées de {} seront incluses dans le commit",
+        Msg::Using => "Utilisation de {} ({})",
+        Msg::FilesChanged => "{} fichier(s) modifié(s)",
+        Msg::PrivacyMode => "Mode confidentiel : identités masquées",
+        Msg::PrivacyModeHashing => "Mode confidentiel : identités masquées et répertoires hachés",
+        Msg::LocalDependencies => "Mise à jour de dépendances détectée ; message créé localement",
+        Msg::Generating => "Génération du message de commit (essai {})...",
+        Msg::GeneratingMany => "Génération de {} messages de commit (essai {})...",
+        Msg::OverBudget => "Budget dépassé : {}",
+        Msg::ApiErrorRetrying => "Erreur d'API : {}. Nouvel essai...",
+        Msg::TooShort => "Message généré trop court : '{}'",
+        Msg::DiscardBanned => "'{}' écarté : contient une ou des expressions interdites {}",
+        Msg::DiscardQuality => "'{}' écarté : qualité {}/100 sous CM_MIN_QUALITY ({})",
+        Msg::DiscardPlugin => "'{}' écarté : rejeté par le plugin {} : {}",
+        Msg::DiscardHook => "'{}' écarté : refusé par le hook post_generate : {}",
+        Msg::PluginFailed => "Le plugin {} a échoué, ignoré : {}",
+        Msg::Quality => "Qualité {}/100",
+        Msg::DiscardType => "'{}' écarté : type '{}' attendu ({})",
+        Msg::ContainsBanned => "Contient une ou des expressions interdites : {}",
+        Msg::Committing => "Création du commit...",
+        Msg::Committed => "Commit créé avec succès !",
+        Msg::Pushing => "Envoi...",
+        Msg::Pushed => "Envoyé avec succès !",
+        Msg::EmptyMessage => "Message vide, commit annulé",
+        Msg::Regenerating => "Nouvelle génération...",
+        Msg::RegeneratingWithout => "Nouvelle génération sans {} élément(s) retenu(s)...",
+        Msg::Aborted => "Annulé",
+        Msg::ChooseMessage => "Choisissez un message [1-{}] : ",
+        Msg::ChooseMessagePlain => "Choisissez un message, de 1 à {} : ",
+        Msg::InvalidNumber => "Choix invalide. Entrez un nombre de 1 à {}.",
+        Msg::ToggleItems => "Choisissez les éléments à cacher à l'IA (ex. '2 3.1'), Entrée pour terminer : ",
+        Msg::IgnoringItem => "'{}' ignoré : fichier ou bloc inexistant",
+        Msg::FileItem => "Fichier {} : {}",
+        Msg::HunkItem => "Bloc {} : {}",
+        Msg::Withheld => " (retenu)",
+        Msg::Accept => "[A] Accepter",
+        Msg::Edit => "[E] Modifier",
+        Msg::Regenerate => "[R] Régénérer",
+        Msg::Exclude => "[X] Exclure",
+        Msg::Quit => "[Q] Quitter",
+        Msg::ActionsPlain => "Actions : accepter (a), modifier (e), régénérer (r), exclure (x), quitter (q) : ",
+        Msg::InvalidAction => "Choix invalide. Entrez A, E, R, X ou Q.",
+        Msg::PushPrompt => "Envoyer vers le dépôt distant ? [y/N] : ",
+        Msg::PushPromptPlain => "Envoyer vers le dépôt distant ? oui (y) ou non (n, par défaut) : ",
+        Msg::InterviewAnswer => "Votre réponse (Entrée pour passer) : ",
+        Msg::BreakingPrompt => "Ce message signale un BREAKING CHANGE, donc une version majeure. Committer quand même ? [y/N] : ",
+        Msg::BreakingPromptPlain => "Ce message signale un changement incompatible, donc une version majeure. Committer quand même ? oui (y) ou non (n, par défaut) : ",
+        Msg::BreakingDeclined => "Rien n'a été commité. Modifiez le message pour retirer la marque de changement incompatible, ou régénérez.",
+        Msg::OpeningEditor => "Ouverture de {}...",
+        Msg::EditorFailed => "L'éditeur '{}' s'est terminé avec une erreur",
+        Msg::GeneratedTitle => "Message de commit généré",
+        Msg::CandidateTitle => "Proposition {}",
+        Msg::Via => "via {} ({})",
+        Msg::Subject => "Sujet : {}",
+        Msg::Body => "Corps : {}",
+        Msg::NoBody => "aucun",
+    }
+}
+
+/// `msg` in the interface language
+pub fn t(msg: Msg) -> &'static str {
+    match language() {
+        Language::English => english(msg),
+        Language::German => german(msg),
+        Language::Spanish => spanish(msg),
+        Language::French => french(msg),
+    }
+}
+
+/// `msg` in the interface language with each `{}` replaced by the next argument
+pub fn tf(msg: Msg, args: &[&dyn Display]) -> String {
+    let mut parts = t(msg).split("{}");
+    let mut out = parts.next().unwrap_or_default().to_string();
+    for (i, part) in parts.enumerate() {
+        if let Some(arg) = args.get(i) {
+            out.push_str(&arg.to_string());
+        }
+        out.push_str(part);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_code() {
+        assert_eq!(Language::from_code("de_DE.UTF-8"), Some(Language::German));
+        assert_eq!(Language::from_code("fr-CA"), Some(Language::French));
+        assert_eq!(Language::from_code("C"), None);
+    }
+
+    #[test]
+    fn test_tf() {
+        assert_eq!(tf(Msg::DiscardType, &[&"x", &"fix", &"tests"]), "Discarding 'x': expected type 'fix' (tests)");
+    }
+}
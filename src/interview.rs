@@ -0,0 +1,32 @@
+use std::io::{self, Write};
+
+use crate::i18n::{t, Msg};
+use crate::prompt::{build_question_prompt, interview_section};
+use crate::provider::AiProvider;
+use crate::ui;
+
+/// Ask the author the question the model finds most useful about `diff`, and
+/// return their answer as a prompt section
+///
+/// Returns `None` if the question couldn't be generated or the author skipped it;
+/// a failed question never stops the commit.
+pub async fn interview(provider: &dyn AiProvider, diff: &str) -> Option<String> {
+    let question = match provider.generate(&build_question_prompt(diff)).await {
+        Ok(text) => text.trim().lines().next().unwrap_or_default().trim_matches(['"', '`', ' ']).to_string(),
+        Err(e) => {
+            log::debug!("No interview question: {}", e);
+            return None;
+        }
+    };
+    if question.is_empty() {
+        return None;
+    }
+
+    println!("{}", ui::line("\n❓ ", &question));
+    print!("{}", ui::line("   ", t(Msg::InterviewAnswer)));
+    io::stdout().flush().ok()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).ok()?;
+    let answer = answer.trim();
+    (!answer.is_empty()).then(|| interview_section(&question, answer))
+}
@@ -0,0 +1,136 @@
+use std::cmp::Reverse;
+use std::path::Path;
+
+use crate::diff::FileDiff;
+
+/// Share of changed lines above which a language gets its guidance
+const DOMINANT_SHARE: usize = 50;
+
+/// Languages that appear in the prompt summary, most changed first
+const MAX_LISTED: usize = 3;
+
+/// A language's share of the changed lines
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LanguageShare {
+    pub language: &'static str,
+    /// Percentage of changed lines, 0 to 100
+    pub percent: usize,
+}
+
+/// The language of `path`, from its name, extension or directory
+fn classify(path: &str) -> Option<&'static str> {
+    let p = Path::new(path);
+    let name = p.file_name()?.to_str()?;
+    let ext = p.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+    if name == "Dockerfile" || name.ends_with(".dockerfile") {
+        return Some("Dockerfile");
+    }
+    if name == "Makefile" || name == "CMakeLists.txt" {
+        return Some("Build script");
+    }
+    if path.starts_with(".github/workflows/") && matches!(ext.as_str(), "yml" | "yaml") {
+        return Some("CI config");
+    }
+    Some(match ext.as_str() {
+        "rs" => "Rust",
+        "go" => "Go",
+        "py" => "Python",
+        "js" | "jsx" | "mjs" | "cjs" => "JavaScript",
+        "ts" | "tsx" => "TypeScript",
+        "java" | "kt" | "scala" => "JVM",
+        "c" | "h" | "cc" | "cpp" | "hpp" => "C/C++",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "swift" => "Swift",
+        "cs" => "C#",
+        "sql" => "SQL",
+        "css" | "scss" | "sass" | "less" => "CSS",
+        "html" | "htm" | "vue" | "svelte" => "Markup",
+        "sh" | "bash" | "zsh" | "ps1" => "Shell",
+        "proto" | "graphql" | "gql" => "Schema",
+        "md" | "rst" | "adoc" | "txt" => "Docs",
+        "json" | "yml" | "yaml" | "toml" | "ini" => "Config",
+        _ => return None,
+    })
+}
+
+/// Guidance for commits dominated by `language`
+fn guidance(language: &str) -> Option<&'static str> {
+    Some(match language {
+        "SQL" => "SQL changes are usually schema or data migrations: say which tables, columns or indexes change, and whether the migration can be rolled back",
+        "CSS" => "Stylesheet-only changes use the `style` type unless they fix a visual bug (`fix`) or add a visible feature (`feat`); name the component or page affected",
+        "Schema" => "Schema changes affect API consumers: call out removed or renamed fields and types, which are breaking",
+        "Shell" => "Script changes are usually `build`, `ci` or `chore`; say which workflow the script supports",
+        "Dockerfile" => "Container image changes are usually `build`; mention base image or layer changes",
+        "CI config" => "Workflow changes use the `ci` type; name the job or trigger that changed",
+        "Build script" => "Build script changes use the `build` type",
+        "Config" => "For configuration-only changes, say which setting changed and its effect",
+        _ => return None,
+    })
+}
+
+/// Languages of the changed lines in `files`, largest share first
+///
+/// Files are weighted by how many lines they change, so a one-line tweak to a
+/// config file doesn't outweigh a large code change.
+pub fn detect(files: &[FileDiff]) -> Vec<LanguageShare> {
+    let mut counts: Vec<(&'static str, usize)> = Vec::new();
+    for file in files {
+        let Some(language) = classify(&file.path) else {
+            continue;
+        };
+        let lines = file.changed_lines().count().max(1);
+        match counts.iter_mut().find(|(l, _)| *l == language) {
+            Some((_, count)) => *count += lines,
+            None => counts.push((language, lines)),
+        }
+    }
+    let total: usize = counts.iter().map(|(_, count)| count).sum();
+    counts.sort_by_key(|(_, count)| Reverse(*count));
+    counts
+        .into_iter()
+        .map(|(language, count)| LanguageShare {
+            language,
+            percent: count * 100 / total,
+        })
+        .collect()
+}
+
+/// Prompt section naming the main languages, with guidance for a dominant one
+pub fn prompt_section(shares: &[LanguageShare]) -> Option<String> {
+    let main = shares.first()?;
+    let listed = shares
+        .iter()
+        .take(MAX_LISTED)
+        .map(|share| format!("{} ({}%)", share.language, share.percent))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut section = format!("## Languages\nChanged lines by language: {}", listed);
+    if let Some(hint) = guidance(main.language).filter(|_| main.percent > DOMINANT_SHARE) {
+        section.push_str(&format!("\n- {}", hint));
+    }
+    Some(section)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::parse_diff;
+
+    #[test]
+    fn test_detect() {
+        let diff = "diff --git a/migrations/001_users.sql b/migrations/001_users.sql\n--- /dev/null\n+++ b/migrations/001_users.sql\n@@ -0,0 +1,3 @@\n+CREATE TABLE users (\n+  id INT\n+);\n\
+                    diff --git a/src/db.rs b/src/db.rs\n--- a/src/db.rs\n+++ b/src/db.rs\n@@ -1 +1 @@\n-a\n+b\n";
+        let shares = detect(&parse_diff(diff));
+        assert_eq!(shares[0], LanguageShare { language: "SQL", percent: 60 });
+        assert_eq!(shares[1], LanguageShare { language: "Rust", percent: 40 });
+        let section = prompt_section(&shares).unwrap();
+        assert!(section.contains("SQL (60%), Rust (40%)"));
+        assert!(section.contains("migrations"));
+
+        assert_eq!(classify(".github/workflows/ci.yml"), Some("CI config"));
+        assert_eq!(classify("web/app.module.scss"), Some("CSS"));
+        assert_eq!(classify("LICENSE"), None);
+        assert!(prompt_section(&[]).is_none());
+    }
+}
@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::config::cache_dir;
+use crate::vault;
+
+/// How many times a phrase must be deleted before it is called out in the prompt
+const PHRASE_THRESHOLD: u32 = 3;
+
+/// Minimum number of reviewed messages before body preferences are inferred
+const MIN_SAMPLES: u32 = 5;
+
+/// What the user did with generated messages, accumulated across runs
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Preferences {
+    pub accepted: u32,
+    pub edited: u32,
+    /// Body present in the generated message but deleted when editing
+    pub body_dropped: u32,
+    /// Phrases the user deleted, with how often
+    pub removed_phrases: HashMap<String, u32>,
+}
+
+fn preferences_path() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("preferences.json"))
+}
+
+impl Preferences {
+    /// Load saved preferences, starting fresh if none exist
+    pub fn load() -> Self {
+        preferences_path()
+            .and_then(|path| vault::read(&path))
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        let Some(path) = preferences_path() else {
+            return;
+        };
+        if let Ok(json) = serde_json::to_string_pretty(self) {
+            vault::write(&path, &json);
+        }
+    }
+
+    /// Record a message committed exactly as generated
+    pub fn record_accept(&mut self) {
+        self.accepted += 1;
+    }
+
+    /// Record a message the user changed in their editor
+    pub fn record_edit(&mut self, generated: &str, edited: &str) {
+        if generated.trim() == edited.trim() {
+            self.accepted += 1;
+            return;
+        }
+        self.edited += 1;
+
+        let had_body = generated.trim().lines().count() > 1;
+        let has_body = edited.trim().lines().count() > 1;
+        if had_body && !has_body {
+            self.body_dropped += 1;
+        }
+
+        for phrase in removed_phrases(generated, edited) {
+            *self.removed_phrases.entry(phrase).or_insert(0) += 1;
+        }
+    }
+
+    /// Prompt section describing learned preferences, if there is anything to say
+    pub fn prompt_section(&self) -> Option<String> {
+        let mut rules = Vec::new();
+
+        let mut phrases: Vec<(&String, &u32)> = self
+            .removed_phrases
+            .iter()
+            .filter(|(_, count)| **count >= PHRASE_THRESHOLD)
+            .collect();
+        phrases.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        if !phrases.is_empty() {
+            let list = phrases
+                .iter()
+                .take(10)
+                .map(|(p, _)| format!("\"{}\"", p))
+                .collect::<Vec<_>>()
+                .join(", ");
+            rules.push(format!("- Never use these phrases, the user always deletes them: {}", list));
+        }
+
+        let reviewed = self.accepted + self.edited;
+        if reviewed >= MIN_SAMPLES && self.body_dropped * 2 > self.edited.max(1) {
+            rules.push("- The user usually deletes the body; answer \"none\" unless the change is complex".to_string());
+        }
+
+        if rules.is_empty() {
+            None
+        } else {
+            Some(format!("## User Preferences (learned from past edits)\n{}", rules.join("\n")))
+        }
+    }
+}
+
+fn normalize(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase()
+}
+
+/// Runs of words present in `generated` but deleted from `edited`
+///
+/// Short runs are kept whole; for longer deletions (usually a whole sentence)
+/// only the two opening words are kept, since that is what tends to repeat
+/// ("this commit ...").
+fn removed_phrases(generated: &str, edited: &str) -> Vec<String> {
+    let a: Vec<String> = generated.split_whitespace().map(normalize).filter(|w| !w.is_empty()).collect();
+    let b: Vec<String> = edited.split_whitespace().map(normalize).filter(|w| !w.is_empty()).collect();
+
+    // Longest common subsequence table, filled from the end
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut runs: Vec<Vec<&str>> = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() {
+        if j < b.len() && a[i] == b[j] {
+            if !current.is_empty() {
+                runs.push(std::mem::take(&mut current));
+            }
+            i += 1;
+            j += 1;
+        } else if j < b.len() && lcs[i][j + 1] >= lcs[i + 1][j] {
+            j += 1;
+        } else {
+            current.push(&a[i]);
+            i += 1;
+        }
+    }
+    if !current.is_empty() {
+        runs.push(current);
+    }
+
+    runs.into_iter()
+        .filter_map(|run| match run.len() {
+            1 if run[0].len() < 4 => None,
+            1..=3 => Some(run.join(" ")),
+            _ => Some(run[..2].join(" ")),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_removed_phrases() {
+        let generated = "feat: add cache\n\nThis commit adds a simple cache for responses.";
+        let edited = "feat: add cache\n\nAdds a cache for responses.";
+        assert_eq!(removed_phrases(generated, edited), vec!["this commit", "simple"]);
+    }
+
+    #[test]
+    fn test_prompt_section_threshold() {
+        let mut prefs = Preferences::default();
+        for _ in 0..PHRASE_THRESHOLD {
+            prefs.record_edit("fix: various minor fixes to parser", "fix: handle empty input to parser");
+        }
+        let section = prefs.prompt_section().unwrap();
+        assert!(section.contains("\"various minor fixes\""));
+    }
+}
@@ -0,0 +1,214 @@
+use crate::config::LintOptions;
+use crate::emoji::{get_emoji, remove_emoji_prefix};
+use crate::exit::Exit;
+use crate::message::CommitMessage;
+use crate::ui;
+use crate::vcs::git::{commit_files, commit_messages};
+
+/// Longest subject line before it gets cut off in `git log --oneline` and PR lists
+const MAX_SUBJECT_LENGTH: usize = 72;
+
+/// How `cm lint` reports violations (`--format`)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Format {
+    /// Readable list for terminals
+    #[default]
+    Text,
+    /// GitHub Actions `::error` workflow commands, shown inline on pull requests
+    Github,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Self::Text),
+            "github" => Some(Self::Github),
+            _ => None,
+        }
+    }
+}
+
+/// A commit whose message breaks at least one rule
+#[derive(Debug)]
+struct Violation {
+    hash: String,
+    subject: String,
+    problems: Vec<String>,
+}
+
+/// Check the messages of the commits in `range`, exiting non-zero if any break the rules
+pub fn run_lint(range: &str, format: Format) -> Result<Exit, Box<dyn std::error::Error>> {
+    let options = LintOptions::from_env();
+    let commits = commit_messages(range)?;
+
+    let violations: Vec<Violation> = commits
+        .iter()
+        .filter_map(|(hash, message)| {
+            let problems = check(message, &options);
+            (!problems.is_empty()).then(|| Violation {
+                hash: hash.clone(),
+                subject: message.lines().next().unwrap_or_default().to_string(),
+                problems,
+            })
+        })
+        .collect();
+
+    for violation in &violations {
+        match format {
+            Format::Text => print_text(violation),
+            Format::Github => {
+                // The annotation lands on the first file the commit touched, so it shows in the PR diff
+                let file = commit_files(&violation.hash).into_iter().next();
+                for problem in &violation.problems {
+                    println!("{}", annotation(violation, problem, file.as_deref()));
+                }
+            }
+        }
+    }
+
+    if violations.is_empty() {
+        ui::status("✓ ", format!("{} commit message(s) look good", commits.len()));
+        Ok(Exit::Success)
+    } else {
+        ui::error(
+            "❌ ",
+            format!("{} of {} commit message(s) need fixing", violations.len(), commits.len()),
+        );
+        Ok(Exit::LintFailed)
+    }
+}
+
+/// Problems with `message`, empty if it passes
+pub fn check(message: &str, options: &LintOptions) -> Vec<String> {
+    let mut lines = message.lines();
+    let subject = lines.next().unwrap_or_default().trim_end();
+    // Merges and reverts keep the subject git wrote for them
+    if subject.starts_with("Merge ") || subject.starts_with("Revert \"") {
+        return Vec::new();
+    }
+
+    let mut problems = Vec::new();
+    if let Err(problem) = check_header(&remove_emoji_prefix(subject)) {
+        problems.push(problem);
+    }
+
+    let length = subject.chars().count();
+    if length > MAX_SUBJECT_LENGTH {
+        problems.push(format!("Subject is {} characters, over the limit of {}", length, MAX_SUBJECT_LENGTH));
+    } else if length < options.min_length {
+        problems.push(format!("Subject is {} characters, under the minimum of {}", length, options.min_length));
+    }
+
+    if lines.next().is_some_and(|line| !line.trim().is_empty()) {
+        problems.push("Missing blank line between subject and body".to_string());
+    }
+
+    let parsed = CommitMessage::parse_from_ai_response(message);
+    for phrase in parsed.banned_phrases(&options.banned_words) {
+        problems.push(format!("Contains banned phrase '{}'", phrase));
+    }
+    problems
+}
+
+/// Check `type(scope)!: description`
+pub fn check_header(subject: &str) -> Result<(), String> {
+    let (header, description) = subject
+        .split_once(':')
+        .ok_or("Subject is not a Conventional Commits header (type(scope): description)")?;
+    let header = header.strip_suffix('!').unwrap_or(header);
+    let commit_type = match header.split_once('(') {
+        Some((commit_type, scope)) if scope.ends_with(')') && scope.len() > 1 => commit_type,
+        Some(_) => return Err(format!("Malformed scope in '{}'", header)),
+        None => header,
+    };
+    if get_emoji(commit_type).is_none() || commit_type.chars().any(|c| c.is_ascii_uppercase()) {
+        return Err(format!("Unknown commit type '{}'", commit_type));
+    }
+    if !description.starts_with(' ') || description.trim().is_empty() {
+        return Err("Expected ': ' followed by a description".to_string());
+    }
+    Ok(())
+}
+
+fn print_text(violation: &Violation) {
+    println!(
+        "{} {}",
+        ui::paint(&violation.hash[..violation.hash.len().min(10)], ui::theme().reference),
+        violation.subject
+    );
+    for problem in &violation.problems {
+        println!("{}", ui::line("    ✗ ", problem));
+    }
+}
+
+/// A GitHub Actions `::error` workflow command for one problem
+fn annotation(violation: &Violation, problem: &str, file: Option<&str>) -> String {
+    let short = &violation.hash[..violation.hash.len().min(7)];
+    let mut properties = Vec::new();
+    if let Some(file) = file {
+        properties.push(format!("file={},line=1", escape_property(file)));
+    }
+    properties.push(format!("title={}", escape_property(&format!("Commit message {}", short))));
+    format!(
+        "::error {}::{}",
+        properties.join(","),
+        escape_data(&format!("{}: {}\n{}", short, problem, violation.subject))
+    )
+}
+
+/// Escape a workflow command message
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+/// Escape a workflow command property value, where `:` and `,` are separators
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let options = LintOptions {
+            min_length: 10,
+            banned_words: vec!["various".to_string()],
+        };
+        assert!(check("feat(parser): add nested list support", &options).is_empty());
+        assert!(check("✨ feat!: drop the legacy config format\n\nBREAKING CHANGE: removed.", &options).is_empty());
+        assert!(check("Merge branch 'main' into feature", &options).is_empty());
+
+        assert_eq!(check("updated stuff here", &options).len(), 1);
+        assert_eq!(
+            check("feature: add nested list support", &options),
+            vec!["Unknown commit type 'feature'"]
+        );
+        assert_eq!(check("fix: typo", &options).len(), 1);
+        assert_eq!(
+            check("fix: handle various edge cases\nin the parser", &options),
+            vec![
+                "Missing blank line between subject and body".to_string(),
+                "Contains banned phrase 'various'".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_annotation() {
+        let violation = Violation {
+            hash: "abc1234def".to_string(),
+            subject: "fix: 100% done".to_string(),
+            problems: Vec::new(),
+        };
+        assert_eq!(
+            annotation(&violation, "Bad", Some("src/a,b.rs")),
+            "::error file=src/a%2Cb.rs,line=1,title=Commit message abc1234::abc1234: Bad%0Afix: 100%25 done"
+        );
+        assert_eq!(
+            annotation(&violation, "Bad", None),
+            "::error title=Commit message abc1234::abc1234: Bad%0Afix: 100%25 done"
+        );
+    }
+}
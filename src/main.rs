@@ -1,209 +1,1040 @@
+mod batch;
+mod api;
+mod audit;
+mod branch;
+mod budget;
+mod capture;
+mod check;
+mod codeowners;
+mod cli;
 mod config;
+mod context;
+mod deps;
+mod diff;
+mod editor;
 mod provider;
-mod git;
+mod heuristics;
+mod help;
+mod history;
+mod hook;
+mod http;
+mod i18n;
+mod interview;
+mod language;
+mod learn;
+mod manifest;
+mod lint;
 mod emoji;
+mod exit;
+mod generated;
+#[cfg(test)]
+mod harness;
 mod message;
+mod migration;
+mod migrate_history;
+mod ping;
+mod plugin;
+mod pipe;
+mod privacy;
+mod policy;
+mod postprocess;
 mod prompt;
+mod quality;
+mod spend;
+mod report;
+mod repo_stats;
+mod review;
+mod schema;
+mod scripts;
+mod secrets;
+mod serve;
+mod session;
+mod telemetry;
+mod template;
+mod translate;
 mod ui;
+mod unattended;
+mod vault;
+mod vcs;
+mod watch;
+mod why;
+mod wip;
 
-use config::Config;
-use provider::create_provider;
-use git::{is_git_repo, get_commit_history, get_staged_diff, get_diff_stat, count_staged_files, commit, push, GitError};
-use emoji::{add_emoji_prefix, remove_emoji_prefix};
+use cli::{Args, Command, Source};
+use budget::{diff_token_budget, estimate_tokens, truncate_diff};
+use config::{BannedAction, Config, ConfigError, ProtectedBranchAction, PushRemote, ScopeMode};
+use context::GitContext;
+use deps::{bump_message, dependency_changes};
+use diff::{normalize_newlines, parse_diff, render_diff, Exclusion, FileDiff};
+use futures::stream::{FuturesUnordered, StreamExt};
+use provider::{create_provider, AiProvider, ProviderError};
+use emoji::extract_type;
+use exit::{Exit, Failure};
+use api::ApiChange;
+use heuristics::{depth_scope, infer_type, is_ambiguous, TypeHint};
+use i18n::{t, tf, Msg};
+use learn::Preferences;
 use message::CommitMessage;
-use prompt::build_commit_prompt;
-use ui::{display_commit_message, UserAction};
-use std::io::{self, Write};
+use privacy::Privacy;
+use session::{PromptParts, Session, Terminal};
+use spend::prompt_tokens;
+use prompt::{api_section, body_section, build_commit_prompt, response_format, depth_scope_section, merge_section, owner_scope_section, packages_section, previous_commit_section, rejected_section, related_issues_section, Prompt, CONVENTIONAL_COMMITS_SPEC};
+use postprocess::{Origin, Pipeline};
+use vcs::{git, GitError, GitResult, PushProblem, Vcs};
+use ui::{display_candidate, UserAction};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
 use std::process;
 
 #[tokio::main]
 async fn main() {
-    if let Err(e) = run().await {
-        eprintln!("❌ Error: {}", e);
-        process::exit(1);
+    // Rewording history (`cm translate`, `cm migrate-history`) runs a rebase with this binary as git's sequence editor
+    if let Some(result) = vcs::git::sequence_editor() {
+        process::exit(if result.is_ok() { 0 } else { 1 });
+    }
+    ui::init_logging();
+    i18n::set_language(i18n::detect());
+    let args = match Args::from_env() {
+        Ok(args) => args,
+        Err(e) => {
+            ui::error("❌ ", tf(Msg::Error, &[&e]));
+            process::exit(Exit::Usage.code());
+        }
+    };
+    // Porcelain output stays free of icons and colors so callers can parse it
+    ui::set_accessible(args.a11y || args.porcelain);
+    ui::set_verbosity(args.verbosity);
+    if args.describes_only() {
+        ui::status_to_stderr();
+    }
+
+    let result = match &args.command {
+        Command::Report => {
+            report::print_report();
+            return;
+        }
+        Command::Help => {
+            help::print_help();
+            return;
+        }
+        Command::Translate { range, language, rewrite } => {
+            translate::run_translate(range, language, *rewrite, args.no_cache).await
+        }
+        Command::Lint { range, format } => lint::run_lint(range, *format),
+        Command::Serve { addr } => serve::run_serve(addr).await,
+        Command::Wip { pop: false } => wip::run_wip(args.allow_secrets).await,
+        Command::Wip { pop: true } => wip::pop_wip(),
+        Command::Watch => watch::run_watch(args.allow_secrets).await,
+        Command::Ping => ping::run_ping().await,
+        Command::RepoStats { range } => repo_stats::run_repo_stats(range),
+        Command::Review { target } => review::run_review(target.as_deref(), args.allow_secrets, args.no_cache).await,
+        Command::AuditExport => audit::run_export(),
+        Command::ConfigCheck => schema::run_check(),
+        Command::Telemetry { action } => telemetry::run_telemetry(*action),
+        Command::Why { target } => why::run_why(target, args.no_cache).await,
+        Command::MigrateHistory { range, dry_run } => migrate_history::run_migrate_history(range, *dry_run, args.no_cache).await,
+        Command::Generate => match &args.message_file {
+            Some(_) if hook::skips(args.message_source.as_deref()) => Ok(Exit::Success),
+            // As a prepare-commit-msg hook, never block the commit; git opens the editor anyway
+            Some(_) => run(&args).await.or_else(|e| {
+                ui::warn("⚠️  ", tf(Msg::HookFailed, &[&e]));
+                Ok(Exit::Success)
+            }),
+            None if args.recurse => batch::run_batch(&args).await,
+            None if args.per_file => batch::run_per_file(&args).await,
+            None => run(&args).await,
+        },
+    };
+    if args.command == Command::Generate {
+        telemetry::flush().await;
+    }
+
+    match capture::flush(serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "error": result.as_ref().err().map(|e| e.to_string()),
+    })) {
+        Ok(Some(path)) => ui::warn("📝 ", format!("Captured provider traffic to {}", path.display())),
+        Ok(None) => {}
+        Err(e) => ui::warn("⚠️  ", format!("Failed to write capture: {}", e)),
+    }
+
+    match result {
+        Ok(exit) => process::exit(exit.code()),
+        Err(e) => {
+            ui::error("❌ ", tf(Msg::Error, &[&e]));
+            process::exit(exit::classify(e.as_ref()).code());
+        }
     }
 }
 
-async fn run() -> Result<(), Box<dyn std::error::Error>> {
-    // Verify we're in a git repository
-    if !is_git_repo() {
-        return Err("Not in a git repository. Please run this command from within a git repository.".into());
+async fn run(args: &Args) -> Result<Exit, Box<dyn std::error::Error>> {
+    // Verify we're in a repository; piped diffs and patch files may come from anywhere
+    let repo = vcs::detect();
+    let needs_repo = matches!(args.source, Source::Index | Source::Revision(_));
+    if needs_repo && repo.is_none() {
+        return Err(GitError::NotARepository.into());
+    }
+    let repo = repo.as_deref();
+    if let Some(repo) = repo {
+        log::debug!("Found a {} repository", repo.name());
+    }
+
+    // Load configuration, walking new users through setup instead of failing
+    let mut config = match Config::from_env() {
+        Err(ConfigError::MissingApiKey(_)) if help::is_first_run() && io::stdin().is_terminal() => {
+            help::onboard()?;
+            return Ok(Exit::AuthFailed);
+        }
+        result => result?,
+    };
+    for problem in schema::check_env() {
+        ui::warn("⚠️  ", problem.to_string());
+    }
+    if args.no_cache {
+        config.cache_ttl = 0;
+    }
+    if let Some(model) = &args.model {
+        config.select_model(model);
+    }
+    if args.deterministic {
+        config.sampling = config.sampling.deterministic();
+    }
+    if args.subject_only {
+        config.subject_only();
+    }
+    // Identical requests would only produce identical candidates
+    if config.sampling.temperature == 0.0 {
+        config.candidates = 1;
+    }
+    // Anything but the index, and messages for a hook, are described without committing
+    let print_only = args.describes_only();
+    if print_only {
+        config.candidates = 1;
+    }
+    vault::configure(config.encrypt_store);
+    ui::set_theme(config.theme);
+
+    if config.capture {
+        capture::enable(vec![config.api_key().to_string()]);
     }
 
-    // Load configuration
-    let config = Config::from_env()?;
+    // Get the changes and history from the index, stdin, a commit or a patch file
+    let external = match &args.source {
+        Source::Index => None,
+        Source::Stdin => {
+            let input = pipe::read_stdin()?;
+            if input.diff.trim().is_empty() {
+                return Err(Failure::new(Exit::NoStagedChanges, t(Msg::NoDiffOnStdin)).into());
+            }
+            let history = if config.context.history {
+                input.history.lines().take(config.history_count).map(|line| format!("{}\n", line)).collect()
+            } else {
+                String::new()
+            };
+            Some((history, input.diff, "stdin"))
+        }
+        // History up to the commit's parent, so its own message isn't shown to the model
+        Source::Revision(rev) => {
+            let repo = repo.ok_or(GitError::NotARepository)?;
+            let history = history::load_history(Some(repo), &config, Some(&repo.parent(rev)));
+            Some((history, repo.commit_diff(rev)?, rev.as_str()))
+        }
+        Source::PatchFile(path) => {
+            let history = history::load_history(repo, &config, None);
+            Some((history, pipe::read_patch(Path::new(path))?, path.as_str()))
+        }
+    };
 
-    // Get git context
-    let commit_history = get_commit_history(10).unwrap_or_default();
+    let context = if let Some((history, diff, origin)) = external {
+        if diff.trim().is_empty() {
+            return Err(Failure::new(Exit::NoStagedChanges, tf(Msg::NoChangesIn, &[&origin])).into());
+        }
+        let context = GitContext::from_diff(diff, history);
+        match repo {
+            Some(repo) => context.with_repo(repo),
+            None => context,
+        }
+    } else {
+        let repo = repo.ok_or(GitError::NotARepository)?;
+        let staged_diff = match repo.diff(&args.only) {
+            Ok(diff) => diff,
+            Err(GitError::NoStagedChanges) if !args.only.is_empty() => {
+                return Err(Failure::new(Exit::NoStagedChanges, tf(Msg::NoStagedMatch, &[&args.only.join(" ")])).into());
+            }
+            Err(GitError::NoStagedChanges) if !repo.has_index() => {
+                return Err(Failure::new(Exit::NoStagedChanges, t(Msg::NoWorkingCopyChanges)).into());
+            }
+            Err(GitError::NoStagedChanges) => {
+                return Err(Failure::new(Exit::NoStagedChanges, t(Msg::NoStagedChanges)).into());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        // Only changes about to be committed are scanned; the other sources are just described
+        secrets::check(&staged_diff, config::secret_action()?, args.allow_secrets)?;
+        let root = repo.root().unwrap_or_default();
+        for warning in policy::warnings(&policy::checks(&config.policy, Path::new(&root)), &parse_diff(&staged_diff)) {
+            ui::warn("📋 ", warning);
+        }
+        let history = history::load_history(Some(repo), &config, None);
+        GitContext {
+            stat: repo.diff_stat(&args.only),
+            ..GitContext::from_diff(staged_diff, history)
+        }
+        .with_repo(repo)
+    };
 
-    let staged_diff = match get_staged_diff(config.max_diff_lines) {
-        Ok(diff) => diff,
-        Err(GitError::NoStagedChanges) => {
-            return Err("No staged changes. Use 'git add <files>' to stage changes first.".into());
+    // The check runs before anything is spent on generation; its outcome becomes prompt context
+    let check_result = match (&args.source, &config.check_command) {
+        (Source::Index, Some(command)) => {
+            let result = check::run(command)?;
+            check::enforce(&result, args.allow_failing_check)?;
+            Some(result)
         }
-        Err(e) => return Err(e.into()),
+        _ => None,
     };
 
-    let diff_stat = get_diff_stat();
-    let file_count = count_staged_files();
+    // With --only, commit exactly the matching staged files and leave the rest staged
+    let commit_paths = match repo {
+        Some(repo) if !args.only.is_empty() => {
+            let paths = repo.changed_files(&args.only);
+            let dirty = repo.unstaged_files(&paths);
+            if !dirty.is_empty() {
+                ui::warn("⚠️  ", tf(Msg::UnstagedIncluded, &[&dirty.join(", ")]));
+            }
+            paths
+        }
+        _ => Vec::new(),
+    };
 
     // Create AI provider
     let provider = create_provider(&config);
-    println!("🚀 Using {} ({})", provider.name(), provider.model());
-    println!("📁 {} file(s) changed", file_count);
+    telemetry::record_run(config.provider.id());
+    ui::status("🚀 ", tf(Msg::Using, &[&provider.name(), &provider.model()]));
+    ui::status("📁 ", tf(Msg::FilesChanged, &[&context.files.len()]));
 
     // Build prompt
-    let prompt_text = build_commit_prompt(&staged_diff, &commit_history, &diff_stat);
+    let diff_files = &context.files;
+    let paths = context.paths();
+    // Generated files are summarized instead of sent, so they can't crowd out the real change
+    let generated = generated::detect(diff_files);
+    let prompt_files: Vec<FileDiff> =
+        diff_files.iter().filter(|f| !generated.iter().any(|g| g.path == f.path)).cloned().collect();
+    let prompt_diff = if generated.is_empty() {
+        context.diff.clone()
+    } else {
+        ui::status("🏭 ", tf(Msg::GeneratedOmitted, &[&generated.len()]));
+        render_diff(&prompt_files, &HashSet::new())
+    };
+    // A conflicted merge gets a merge message, whatever the files suggest
+    let merge = match (&args.source, repo) {
+        (Source::Index, Some(repo)) => repo.merge_state(),
+        _ => None,
+    };
+    let type_hint = if merge.is_some() {
+        None
+    } else {
+        infer_type(diff_files).or_else(|| generated::type_hint(&generated, paths.len()))
+    };
+
+    // Everything below is prompt context, so scrub it first in privacy mode
+    let privacy = Privacy::new(config.privacy, config.privacy_hash_paths, &paths);
+    if privacy.is_active() {
+        let notice = if config.privacy_hash_paths { Msg::PrivacyModeHashing } else { Msg::PrivacyMode };
+        ui::status("🕶️  ", t(notice));
+    }
+    let prompt_context = context.scrubbed(&privacy);
+    let template_vars = template_vars(&context, &config, &privacy);
+    let mut preferences = if config.learn { Some(Preferences::load()) } else { None };
+    let learned = preferences.as_ref().and_then(Preferences::prompt_section);
+    let merge_context = merge.map(|merge| {
+        ui::status("🔀 ", tf(Msg::ConcludingMerge, &[&merge.summary, &merge.conflicts.len()]));
+        // The resolution is diff content too, so it follows CM_CONTEXT like the diff itself
+        let theirs = if config.context.diff {
+            truncate_diff(&merge.theirs_diff, config.max_diff_lines, config.context_tokens / 4)
+        } else {
+            String::new()
+        };
+        privacy.apply(&merge_section(&merge.summary, &merge.conflicts, &theirs))
+    });
+    let related = match &args.source {
+        Source::Index => history::related_issues(repo, &config, &paths),
+        _ => Vec::new(),
+    };
+    let related_context = (!related.is_empty()).then(|| {
+        let lines: Vec<String> = related.iter().map(ToString::to_string).collect();
+        privacy.apply(&related_issues_section(&lines))
+    });
+    let previous_context = match &args.source {
+        Source::Index => history::previous_commit(repo, &config, &paths)
+            .map(|entry| privacy.apply(&previous_commit_section(&entry.hash, &entry.message))),
+        _ => None,
+    };
+    let root = &context.root;
+    let owners = match root {
+        Some(root) if config.codeowners || config.suggest_reviewers => codeowners::load(Path::new(root)),
+        _ => Vec::new(),
+    };
+    // A scope from CM_SCOPE_DEPTH is enforced, so it takes precedence over the CODEOWNERS suggestion
+    let depth_scope = depth_scope(&config.scope_depth, &paths).filter(|_| config.scope_mode != ScopeMode::Omit);
+    let owner_scope = match &depth_scope {
+        Some(scope) => Some(privacy.apply(&depth_scope_section(scope))),
+        None => codeowners::scope(&owners, &paths)
+            .filter(|_| config.codeowners && config.scope_mode != ScopeMode::Omit)
+            .map(|scope| privacy.apply(&owner_scope_section(&scope))),
+    };
+    let packages = match root {
+        Some(root) if config.context.packages => manifest::affected_packages(Path::new(root), diff_files),
+        _ => Vec::new(),
+    };
+    let api_changes = match root {
+        Some(root) => api::api_changes(Path::new(root), &prompt_files),
+        None => Vec::new(),
+    };
+    let breaking: Vec<String> = api_changes
+        .iter()
+        .filter(|item| item.is_breaking())
+        .map(|item| match item.change {
+            ApiChange::Removed => format!("`{}` was removed", item.name),
+            _ => format!("`{}` changed its signature", item.name),
+        })
+        .collect();
+    // Signatures are taken from the changed code, so they're only sent with the diff
+    let api_context = (config.context.diff && !api_changes.is_empty()).then(|| {
+        let lines: Vec<String> = api_changes.iter().map(ToString::to_string).collect();
+        privacy.apply(&api_section(&lines, !breaking.is_empty()))
+    });
+    let breaking_note = (config.breaking_footer && !breaking.is_empty()).then(|| breaking.join("; "));
+    let packages_context = (!packages.is_empty()).then(|| {
+        let lines: Vec<String> = packages.iter().map(ToString::to_string).collect();
+        privacy.apply(&packages_section(&lines))
+    });
+    let footer_refs: Vec<String> = if config.refs_footer {
+        related.iter().map(|issue| issue.reference.clone()).collect()
+    } else {
+        Vec::new()
+    };
+    // Without a ticket in CM_TICKET or the branch, reference the issue the last related commit did
+    let footer = config.footer_template.as_deref().and_then(|footer| {
+        let mut vars = template_vars.clone();
+        if let Some(issue) = related.first().filter(|_| vars["ticket"].is_empty()) {
+            vars.insert("ticket_url", template::ticket_url(config.ticket_url.as_deref(), &issue.reference));
+            vars.insert("ticket", issue.reference.clone());
+        }
+        template::footer(footer, &vars)
+    });
+    let language_context = language::prompt_section(&language::detect(&prompt_files));
+    // Operations are read from the diff, so they're only sent with it
+    let migration_context = migration::prompt_section(&migration::migrations(diff_files))
+        .filter(|_| config.context.diff)
+        .map(|s| privacy.apply(&s));
+    // The summary names generated paths, which `stat` in CM_CONTEXT covers
+    let generated_context = generated::prompt_section(&generated)
+        .filter(|_| config.context.stat)
+        .map(|s| privacy.apply(&s));
+    // Check output can quote source lines and test names, so it goes with the diff
+    let check_context = check_result
+        .filter(|_| config.context.diff)
+        .map(|result| privacy.apply(&result.prompt_section()));
+    // Only someone at the terminal can answer, and only when the diff leaves the reason open;
+    // the questions are drawn from the diff, so not when CM_CONTEXT withholds it
+    let interview_context = if config.interview && config.context.diff && !print_only && merge_context.is_none() && is_ambiguous(&prompt_files) {
+        let diff = truncate_diff(&privacy.apply(&prompt_diff), config.max_diff_lines, config.context_tokens / 2);
+        interview::interview(provider.as_ref(), &diff).await
+    } else {
+        None
+    };
+    let extra_sections = [
+        learned,
+        merge_context,
+        related_context,
+        previous_context,
+        owner_scope,
+        packages_context,
+        api_context,
+        check_context,
+        interview_context,
+        language_context,
+        migration_context,
+        generated_context,
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join("\n\n");
+    let prompt = fit_prompt(
+        &config,
+        &template_vars,
+        with_rejected(&extra_sections, &[]).as_deref(),
+        type_hint.as_ref(),
+        &privacy.apply(&prompt_diff),
+        &prompt_context,
+    );
 
-    // Main interaction loop
-    let mut attempts = 0u32;
+    // Dependency bumps get a precise message without calling the AI
+    let local_message = if config.local_deps {
+        bump_message(&dependency_changes(diff_files))
+    } else {
+        None
+    };
 
-    loop {
-        attempts += 1;
+    let reviewers = if config.suggest_reviewers { codeowners::reviewers(&owners, &paths) } else { Vec::new() };
+    let session = Session {
+        config: &config,
+        provider: provider.as_ref(),
+        repo,
+        ui: &mut Terminal,
+        parts: PromptParts {
+            vars: &template_vars,
+            extra: &extra_sections,
+            type_hint: type_hint.as_ref(),
+            privacy: &privacy,
+            context: &prompt_context,
+            files: &prompt_files,
+        },
+        prompt,
+        local_message,
+        pipeline: Pipeline {
+            config: &config,
+            scope: depth_scope.as_deref(),
+            breaking_note: breaking_note.as_deref(),
+            footer_refs: &footer_refs,
+            footer: footer.as_deref(),
+        },
+        print_only,
+        message_file: args.message_file.as_deref(),
+        commit_paths: &commit_paths,
+        preferences: preferences.as_mut(),
+        exclude_first: args.exclude,
+        reviewers,
+    };
+    session.run().await
+}
 
-        if attempts > config.max_retries {
-            return Err(format!(
-                "Failed to generate a valid commit message after {} attempts",
-                config.max_retries
-            ).into());
-        }
+/// Request `config.candidates` messages concurrently
+///
+/// With more than one candidate, each is shown as soon as its response
+/// arrives. Each goes through `pipeline` before validation; those that fail
+/// it are dropped, and an error is returned only if no request succeeded.
+async fn generate_candidates(
+    provider: &dyn AiProvider,
+    prompt: &Prompt,
+    config: &Config,
+    type_hint: Option<&TypeHint>,
+    pipeline: &Pipeline<'_>,
+    interactive: bool,
+) -> Result<Vec<CommitMessage>, ProviderError> {
+    let mut pending: FuturesUnordered<_> =
+        (0..config.candidates).map(|_| provider.generate(prompt)).collect();
 
-        // Generate message
-        println!("\n⏳ Generating commit message (attempt {})...", attempts);
+    let mut candidates = Vec::new();
+    let mut last_error = None;
+    let mut any_succeeded = false;
+    let mut spent = 0;
 
-        let response = match provider.generate(&prompt_text).await {
-            Ok(r) => r,
+    while let Some(result) = pending.next().await {
+        let response = match result {
+            Ok(r) => {
+                spent += prompt_tokens(prompt) + estimate_tokens(&r) as u64;
+                r
+            }
+            Err(e) if !e.is_retryable() => return Err(e),
             Err(e) => {
-                eprintln!("⚠️  API error: {}. Retrying...", e);
+                last_error = Some(e);
                 continue;
             }
         };
+        any_succeeded = true;
 
-        // Parse response
+        let response = pipeline.response(&response);
         let mut commit_msg = CommitMessage::parse_from_ai_response(&response);
-
-        // Apply emoji prefix if enabled
-        if config.emoji_enabled {
-            commit_msg.subject = add_emoji_prefix(&commit_msg.subject);
+        if let Some(reason) = commit_msg.suspicion() {
+            let reviewed = if interactive {
+                review_response(&response, reason, provider)
+            } else {
+                Some(CommitMessage::reparse(&response)).filter(|m| m.suspicion().is_none())
+            };
+            match reviewed {
+                Some(message) => commit_msg = message,
+                None => {
+                    ui::warn("⚠️  ", tf(Msg::DiscardSuspicious, &[&commit_msg.subject, &reason]));
+                    continue;
+                }
+            }
         }
+        pipeline.apply(&mut commit_msg, Origin::Model);
+        let plain = pipeline.plain(&commit_msg);
 
-        // Display the message with iocraft
-        display_commit_message(
-            &commit_msg.subject,
-            commit_msg.body.as_deref(),
-            provider.name(),
-            provider.model(),
-        );
+        if plain.subject.len() < config.min_message_length {
+            ui::warn("⚠️  ", tf(Msg::TooShort, &[&plain.subject]));
+            continue;
+        }
 
-        // Get user choice
-        let action = prompt_action()?;
-
-        match action {
-            UserAction::Accept => {
-                let git_message = commit_msg.to_git_message();
-                println!("\n⏳ Committing...");
-                commit(&git_message)?;
-                println!("✓ Committed successfully!");
-
-                // Ask about push
-                if ask_push()? {
-                    println!("⏳ Pushing...");
-                    push()?;
-                    println!("✓ Pushed successfully!");
-                }
+        if config.banned_action == BannedAction::Regenerate {
+            let found = commit_msg.banned_phrases(&config.banned_words);
+            if !found.is_empty() {
+                ui::warn(
+                    "⚠️  ",
+                    tf(Msg::DiscardBanned, &[&plain.subject, &found.join(", ")]),
+                );
+                continue;
+            }
+        }
 
-                break;
+        if let Some(hint) = type_hint {
+            let commit_type = extract_type(&plain.subject).unwrap_or_default();
+            if !hint.accepts(commit_type) {
+                ui::warn(
+                    "⚠️  ",
+                    tf(Msg::DiscardType, &[&plain.subject, &hint.kind, &hint.reason]),
+                );
+                continue;
             }
-            UserAction::Edit => {
-                let display = commit_msg.to_git_message();
-                // Remove emoji for editing (will be re-added after)
-                let for_edit = if config.emoji_enabled {
-                    let mut lines: Vec<&str> = display.lines().collect();
-                    if let Some(first) = lines.first_mut() {
-                        let stripped = remove_emoji_prefix(first);
-                        let mut result = vec![stripped];
-                        result.extend(lines.into_iter().skip(1).map(String::from));
-                        result.join("\n")
-                    } else {
-                        display
-                    }
-                } else {
-                    display
-                };
+        }
 
-                let edited = edit_message(&for_edit)?;
+        let quality = quality::score(&plain);
+        if let Some(min) = config.min_quality.filter(|min| quality.score < *min) {
+            ui::warn(
+                "⚠️  ",
+                format!(
+                    "{} ({})",
+                    tf(Msg::DiscardQuality, &[&plain.subject, &quality.score, &min]),
+                    quality.problems.join(", ")
+                ),
+            );
+            continue;
+        }
 
-                if edited.trim().is_empty() {
-                    println!("⚠️  Empty message, aborting commit");
-                    return Ok(());
-                }
+        if let Some((name, reason)) = plugin::rejections(&config.plugins, &commit_msg.to_git_message()).first() {
+            ui::warn("⚠️  ", tf(Msg::DiscardPlugin, &[&plain.subject, name, reason]));
+            continue;
+        }
 
-                // Re-add emoji if enabled
-                let final_message = if config.emoji_enabled {
-                    let mut lines: Vec<String> = edited.lines().map(String::from).collect();
-                    if let Some(first) = lines.first_mut() {
-                        *first = add_emoji_prefix(first);
-                    }
-                    lines.join("\n")
-                } else {
-                    edited
-                };
+        if let Err(reason) = scripts::post_generate(&config.hooks, &mut commit_msg) {
+            ui::warn("⚠️  ", tf(Msg::DiscardHook, &[&plain.subject, &reason]));
+            continue;
+        }
+
+        if config.candidates > 1 {
+            display_candidate(
+                candidates.len() + 1,
+                quality.score,
+                &commit_msg.subject,
+                commit_msg.body.as_deref(),
+                provider.name(),
+                provider.model(),
+            );
+        }
+        candidates.push(commit_msg);
+    }
 
-                println!("\n⏳ Committing...");
-                commit(&final_message)?;
-                println!("✓ Committed successfully!");
+    spend::record(&config.budget, config.provider.id(), spent);
 
-                if ask_push()? {
-                    println!("⏳ Pushing...");
-                    push()?;
-                    println!("✓ Pushed successfully!");
-                }
+    match last_error {
+        Some(e) if !any_succeeded => Err(e),
+        _ => Ok(candidates),
+    }
+}
+
+/// Ask which of `count` candidates to use, returning its zero-based index
+fn prompt_candidate(count: usize) -> io::Result<usize> {
+    if ui::accessible() {
+        print!("\n{}", tf(Msg::ChooseMessagePlain, &[&count]));
+    } else {
+        print!("\n🔢 {}", tf(Msg::ChooseMessage, &[&count]));
+    }
+    io::stdout().flush()?;
 
-                break;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+
+    match input.trim().parse::<usize>() {
+        Ok(n) if (1..=count).contains(&n) => Ok(n - 1),
+        _ => {
+            println!("{}", tf(Msg::InvalidNumber, &[&count]));
+            prompt_candidate(count)
+        }
+    }
+}
+
+/// Explain how to fix errors that retrying cannot recover from
+fn setup_guidance(error: &ProviderError, config: &Config) -> String {
+    match error {
+        ProviderError::AuthFailed(_) => format!(
+            "{}\n   Check that {} is set to a valid API key for {:?}.",
+            error,
+            config.api_key_var(),
+            config.provider
+        ),
+        ProviderError::QuotaExceeded(_) => format!(
+            "{}\n   Add credits or wait for your quota to reset, or switch providers with CM_PROVIDER.",
+            error
+        ),
+        ProviderError::ModelNotFound(_) => format!(
+            "{}\n   Set CM_MODEL to a model {:?} currently offers, or to an alias such as 'fast'.",
+            error, config.provider
+        ),
+        _ => error.to_string(),
+    }
+}
+
+/// The extra prompt sections, followed by the subjects rejected so far
+fn with_rejected(extra: &str, rejected: &[String]) -> Option<String> {
+    let rejected = (!rejected.is_empty()).then(|| rejected_section(rejected));
+    let joined = [Some(extra.to_string()).filter(|s| !s.is_empty()), rejected]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Some(joined).filter(|s| !s.is_empty())
+}
+
+/// Build the prompt, fitting the diff into what's left of the model's context window
+///
+/// A custom template from `CM_PROMPT_FILE` replaces the built-in instructions,
+/// up to the response format the reply is parsed in.
+/// `extra` sections (learned preferences, merge context, rejected subjects)
+/// differ from commit to commit, so they go into the user message.
+fn fit_prompt(
+    config: &Config,
+    vars: &HashMap<&str, String>,
+    extra: Option<&str>,
+    type_hint: Option<&TypeHint>,
+    diff: &str,
+    context: &GitContext,
+) -> Prompt {
+    let build = |diff: &str| {
+        let mut prompt = build_commit_prompt(diff, context, config.prompt_options(), type_hint);
+        if let Some(custom) = &config.prompt_template {
+            let (system, values) = template::render_split(custom, vars);
+            let format = response_format(config.prompt_options().subject_only);
+            prompt.system = format!("{}\n\n{}", system.trim_end(), format);
+            if let Some(values) = values {
+                prompt.add_context(&values);
             }
-            UserAction::Regenerate => {
-                println!("🔄 Regenerating...");
-                attempts = 0; // Reset attempts for regeneration
-                continue;
+        }
+        if let Some(section) = extra {
+            prompt.add_context(section);
+        }
+        if !config.banned_words.is_empty() {
+            prompt.system = format!(
+                "{}\n\n## Banned Phrases\nNever use any of these: {}",
+                prompt.system,
+                config.banned_words.join(", ")
+            );
+        }
+        prompt
+    };
+
+    let skeleton = build("");
+    let budget = diff_token_budget(
+        config.context_tokens,
+        &format!("{}{}", skeleton.system, skeleton.user),
+    );
+    build(&truncate_diff(diff, config.max_diff_lines, budget))
+}
+
+/// Values available to custom prompt templates as `{{name}}`
+fn template_vars(context: &GitContext, config: &Config, privacy: &Privacy) -> HashMap<&'static str, String> {
+    let branch = if config.context.branch {
+        context.branch.clone().unwrap_or_default()
+    } else {
+        String::new()
+    };
+    let ticket = std::env::var("CM_TICKET")
+        .ok()
+        .or_else(|| template::ticket_from_branch(&branch))
+        .unwrap_or_default();
+    let files = context.paths().join("\n");
+    let author = if config.privacy {
+        String::new()
+    } else {
+        context.author.clone().unwrap_or_default()
+    };
+
+    HashMap::from([
+        ("branch", branch),
+        ("author", author),
+        ("ticket_url", template::ticket_url(config.ticket_url.as_deref(), &ticket)),
+        ("ticket", ticket),
+        ("files", privacy.apply(&files)),
+        ("date", template::today()),
+        ("project_name", context.repo_name.clone().unwrap_or_default()),
+        ("spec", CONVENTIONAL_COMMITS_SPEC.to_string()),
+        ("style", body_section(config.style, config.body_style)),
+    ])
+}
+
+/// Let the user toggle files (`2`) or hunks (`2.1`) that must not be sent to the AI
+///
+/// Withheld changes are still committed; only the prompt is affected.
+fn select_exclusions(files: &[FileDiff], excluded: &mut HashSet<Exclusion>) -> io::Result<()> {
+    loop {
+        println!();
+        for (i, file) in files.iter().enumerate() {
+            let mark = if excluded.contains(&Exclusion::File(i)) { "✗" } else { " " };
+            if ui::accessible() {
+                let state = if mark == " " { "" } else { t(Msg::Withheld) };
+                println!("{}{}", tf(Msg::FileItem, &[&(i + 1), &file.path]), state);
+            } else {
+                println!("  {} {:>3}  {}", mark, i + 1, file.path);
+            }
+            for (j, hunk) in file.hunks.iter().enumerate() {
+                let mark = if excluded.contains(&Exclusion::Hunk(i, j)) { "✗" } else { " " };
+                let header = hunk.lines().next().unwrap_or_default();
+                if ui::accessible() {
+                    let state = if mark == " " { "" } else { t(Msg::Withheld) };
+                    let id = format!("{}.{}", i + 1, j + 1);
+                    println!("{}{}", tf(Msg::HunkItem, &[&id, &header]), state);
+                } else {
+                    let id = format!("{}.{}", i + 1, j + 1);
+                    println!("  {}   {:>5}  {}", mark, id, ui::paint(header, ui::theme().muted));
+                }
             }
-            UserAction::Quit => {
-                println!("👋 Aborted");
-                return Ok(());
+        }
+
+        print!(
+            "{}",
+            ui::line("\n🙈 ", t(Msg::ToggleItems))
+        );
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        if input.trim().is_empty() {
+            return Ok(());
+        }
+
+        for token in input.split_whitespace() {
+            match parse_exclusion(token, files) {
+                Some(item) => {
+                    if !excluded.remove(&item) {
+                        excluded.insert(item);
+                    }
+                }
+                None => println!("{}", tf(Msg::IgnoringItem, &[&token])),
             }
         }
     }
+}
 
-    Ok(())
+fn parse_exclusion(token: &str, files: &[FileDiff]) -> Option<Exclusion> {
+    let (file, hunk) = match token.split_once('.') {
+        Some((f, h)) => (f.parse::<usize>().ok()?, Some(h.parse::<usize>().ok()?)),
+        None => (token.parse::<usize>().ok()?, None),
+    };
+    let file_idx = file.checked_sub(1)?;
+    let diff = files.get(file_idx)?;
+    match hunk {
+        None => Some(Exclusion::File(file_idx)),
+        Some(h) => {
+            let hunk_idx = h.checked_sub(1)?;
+            diff.hunks.get(hunk_idx)?;
+            Some(Exclusion::Hunk(file_idx, hunk_idx))
+        }
+    }
 }
 
 fn prompt_action() -> io::Result<UserAction> {
     println!();
-    print!(
-        "  {}  {}  {}  {} : ",
-        "\x1b[32m[A]ccept\x1b[0m",
-        "\x1b[33m[E]dit\x1b[0m",
-        "\x1b[36m[R]egenerate\x1b[0m",
-        "\x1b[31m[Q]uit\x1b[0m"
-    );
+    print_actions();
     io::stdout().flush()?;
 
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
+    // A resized terminal gets the message and the actions again, laid out for its new width
+    let input = ui::read_line(|| {
+        if ui::redraw_message_box() {
+            println!();
+            print_actions();
+            let _ = io::stdout().flush();
+        }
+    })?;
 
     match input.trim().to_lowercase().chars().next() {
         Some('a') | Some('y') => Ok(UserAction::Accept),
         Some('e') => Ok(UserAction::Edit),
         Some('r') => Ok(UserAction::Regenerate),
+        Some('x') => Ok(UserAction::Exclude),
         Some('q') | Some('n') => Ok(UserAction::Quit),
         _ => {
-            println!("Invalid choice. Please enter A, E, R, or Q.");
+            println!("{}", t(Msg::InvalidAction));
             prompt_action()
         }
     }
 }
 
+fn print_actions() {
+    if ui::accessible() {
+        print!("{}", t(Msg::ActionsPlain));
+    } else {
+        let theme = ui::theme();
+        print!(
+            "  {}  {}  {}  {}  {} : ",
+            ui::paint(t(Msg::Accept), theme.accept),
+            ui::paint(t(Msg::Edit), theme.edit),
+            ui::paint(t(Msg::Regenerate), theme.regenerate),
+            ui::paint(t(Msg::Exclude), theme.exclude),
+            ui::paint(t(Msg::Quit), theme.quit)
+        );
+    }
+}
+
+/// Push to each enabled remote, or the default one, reporting every failure together
+fn push_all(repo: &dyn Vcs, remotes: &[PushRemote]) -> Result<(), Failure> {
+    if remotes.is_empty() {
+        ui::status("⏳ ", t(Msg::Pushing));
+        push_with_recovery(repo, None).map_err(|e| Failure::new(Exit::Error, e.to_string()))?;
+        ui::status("✓ ", t(Msg::Pushed));
+        return Ok(());
+    }
+
+    let mut failed = Vec::new();
+    for remote in remotes.iter().filter(|r| r.enabled) {
+        ui::status("⏳ ", tf(Msg::PushingTo, &[&remote.name]));
+        match push_with_recovery(repo, Some(&remote.name)) {
+            Ok(()) => ui::status("✓ ", tf(Msg::PushedTo, &[&remote.name])),
+            Err(e) => failed.push(format!("  {}: {}", remote.name, e)),
+        }
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(Failure::new(Exit::Error, tf(Msg::PushesFailed, &[&failed.join("\n")])))
+    }
+}
+
+/// Show a response that doesn't look like a commit message and let the user use
+/// it as-is, re-parse it, or drop it (`None`) to regenerate
+fn review_response(response: &str, reason: &str, provider: &dyn AiProvider) -> Option<CommitMessage> {
+    let mut expanded = false;
+    loop {
+        ui::display_raw_response(response, reason, expanded, provider.name(), provider.model());
+        print!("{}", t(Msg::RawActions));
+        let mut input = String::new();
+        if io::stdout().flush().and_then(|_| io::stdin().read_line(&mut input)).is_err() {
+            return None;
+        }
+        match input.trim().to_lowercase().chars().next() {
+            Some('u') => return CommitMessage::parse_edited(response),
+            Some('p') => return Some(CommitMessage::reparse(response)),
+            Some('v') => expanded = true,
+            _ => return None,
+        }
+    }
+}
+
+/// Say how much is about to be sent where, and over `CM_CONFIRM_LINES` or
+/// `CM_CONFIRM_TOKENS` ask first; without a terminal to ask on, stop
+fn confirm_context(config: &Config, provider: &dyn AiProvider, files: usize, lines: usize, tokens: u64) -> Result<(), Failure> {
+    ui::status("📦 ", tf(Msg::ContextSize, &[&files, &lines, &tokens, &provider.name(), &provider.model()]));
+    let over_lines = config.confirm_lines.is_some_and(|limit| lines > limit);
+    let over_tokens = config.confirm_tokens.is_some_and(|limit| tokens > limit);
+    if !over_lines && !over_tokens {
+        return Ok(());
+    }
+    if !io::stdin().is_terminal() {
+        return Err(Failure::new(Exit::Aborted, t(Msg::ContextTooLarge)));
+    }
+    print!("{}", t(Msg::ContextConfirmPrompt));
+    let mut input = String::new();
+    io::stdout()
+        .flush()
+        .and_then(|_| io::stdin().read_line(&mut input))
+        .map_err(|e| Failure::new(Exit::Error, e.to_string()))?;
+    if input.trim().to_lowercase() == "y" {
+        Ok(())
+    } else {
+        Err(Failure::new(Exit::Aborted, t(Msg::Aborted)))
+    }
+}
+
+/// Offer to move onto a new branch before committing directly on a protected one;
+/// with `CM_PROTECTED_BRANCH_ACTION=block`, staying there is refused
+fn guard_branch(repo: &dyn Vcs, config: &Config, subject: &str) -> Result<(), Failure> {
+    if config.protected_branch_action == ProtectedBranchAction::Off {
+        return Ok(());
+    }
+    let Some(current) = repo.branch().filter(|b| branch::is_protected(b, &config.protected_branches)) else {
+        return Ok(());
+    };
+    ui::warn("🛡️  ", tf(Msg::OnProtectedBranch, &[&current]));
+
+    if io::stdin().is_terminal() {
+        let suggested = branch::suggest_name(subject.lines().next().unwrap_or_default());
+        print!("{}", tf(Msg::CreateBranchPrompt, &[&suggested]));
+        let mut input = String::new();
+        io::stdout()
+            .flush()
+            .and_then(|_| io::stdin().read_line(&mut input))
+            .map_err(|e| Failure::new(Exit::Error, e.to_string()))?;
+        if input.trim().to_lowercase() == "y" {
+            repo.create_branch(&suggested).map_err(|e| Failure::new(Exit::Error, e.to_string()))?;
+            ui::status("✓ ", tf(Msg::BranchCreated, &[&suggested]));
+            return Ok(());
+        }
+    }
+    match config.protected_branch_action {
+        ProtectedBranchAction::Block => Err(Failure::new(Exit::ProtectedBranch, tf(Msg::ProtectedBranchBlocked, &[&current]))),
+        _ => Ok(()),
+    }
+}
+
+/// Push, and when git says why it was rejected, explain it and offer to run the fix
+fn push_with_recovery(repo: &dyn Vcs, remote: Option<&str>) -> GitResult<()> {
+    let (problem, stderr) = match repo.push(remote) {
+        Err(GitError::PushFailed(problem, stderr)) => (problem, stderr),
+        other => return other,
+    };
+    let branch = repo.branch().unwrap_or_else(|| "HEAD".to_string());
+    let fix = problem.fix(remote, &branch);
+    let command = fix.as_ref().map(|args| format!("git {}", args.join(" "))).unwrap_or_default();
+    let advice = match problem {
+        PushProblem::NonFastForward => tf(Msg::PushBehind, &[&command]),
+        PushProblem::NoUpstream => tf(Msg::PushNoUpstream, &[&command]),
+        PushProblem::Auth => t(Msg::PushAuth).to_string(),
+        PushProblem::ProtectedBranch => t(Msg::PushProtected).to_string(),
+        PushProblem::Other => return Err(GitError::PushFailed(problem, stderr)),
+    };
+    ui::warn("💡 ", advice);
+
+    let Some(args) = fix.filter(|_| io::stdin().is_terminal()) else {
+        return Err(GitError::PushFailed(problem, stderr));
+    };
+    print!("{}", tf(Msg::RunFixPrompt, &[&command]));
+    io::stdout().flush().map_err(|e| GitError::CommandFailed(e.to_string()))?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).map_err(|e| GitError::CommandFailed(e.to_string()))?;
+    if input.trim().to_lowercase() != "y" {
+        return Err(GitError::PushFailed(problem, stderr));
+    }
+    git::run_attached(&args)?;
+    match problem {
+        // `push --set-upstream` was the push
+        PushProblem::NoUpstream => Ok(()),
+        _ => repo.push(remote),
+    }
+}
+
 fn ask_push() -> io::Result<bool> {
-    print!("\n🔼 Push to remote? [y/N]: ");
+    if ui::accessible() {
+        print!("\n{}", t(Msg::PushPromptPlain));
+    } else {
+        print!("\n🔼 {}", t(Msg::PushPrompt));
+    }
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+
+    Ok(input.trim().to_lowercase() == "y")
+}
+
+/// Whether to commit a message that marks a breaking change
+fn ask_breaking() -> io::Result<bool> {
+    if ui::accessible() {
+        print!("\n{}", t(Msg::BreakingPromptPlain));
+    } else {
+        print!("\n💥 {}", t(Msg::BreakingPrompt));
+    }
     io::stdout().flush()?;
 
     let mut input = String::new();
@@ -214,26 +1045,25 @@ fn ask_push() -> io::Result<bool> {
 
 fn edit_message(message: &str) -> Result<String, Box<dyn std::error::Error>> {
     use std::fs;
-    use std::env;
     use std::process::Command;
 
-    let path = env::temp_dir().join(".cm_commit_msg_edit");
-    fs::write(&path, message)?;
+    let comment = editor::comment_char();
+    let path = editor::message_path();
+    fs::write(&path, format!("{}\n\n{} {}\n", message, comment, tf(Msg::EditorHint, &[&comment])))?;
 
-    let editor = env::var("EDITOR")
-        .or_else(|_| env::var("VISUAL"))
-        .unwrap_or_else(|_| "nano".to_string());
+    let editor = editor::resolve().map_err(|tried| tf(Msg::NoEditor, &[&tried]))?;
 
-    println!("📝 Opening {}...", editor);
+    log::debug!("Editor {:?} from {}", editor.program, editor.source);
+    ui::status("📝 ", tf(Msg::OpeningEditor, &[&editor.program]));
 
-    let status = Command::new(&editor).arg(&path).status()?;
+    let status = Command::new(&editor.program).args(&editor.args).arg(&path).status()?;
 
     if !status.success() {
-        return Err(format!("Editor '{}' exited with error", editor).into());
+        return Err(tf(Msg::EditorFailed, &[&editor.program]).into());
     }
 
     let edited = fs::read_to_string(&path)?;
     let _ = fs::remove_file(&path);
 
-    Ok(edited.trim().to_string())
+    Ok(editor::strip_comments(&normalize_newlines(&edited), comment))
 }
@@ -0,0 +1,163 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::diff::FileDiff;
+use crate::heuristics::is_test;
+
+/// Manifests naming a package, checked in this order in each directory
+const MANIFESTS: &[&str] = &["Cargo.toml", "package.json", "pyproject.toml"];
+
+/// A package containing some of the staged files
+#[derive(Debug, Clone, PartialEq)]
+pub struct Package {
+    /// Manifest path relative to the repository root
+    pub manifest: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// Whether the change adds, removes or edits exported items
+    pub public_api: bool,
+}
+
+impl fmt::Display for Package {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` ({})", self.name, self.manifest)?;
+        if let Some(description) = &self.description {
+            write!(f, ": {}", description)?;
+        }
+        if self.public_api {
+            write!(f, " [public API changed]")?;
+        }
+        Ok(())
+    }
+}
+
+/// The packages `files` belong to, each found through the nearest manifest above the file
+pub fn affected_packages(root: &Path, files: &[FileDiff]) -> Vec<Package> {
+    let mut packages: Vec<Package> = Vec::new();
+    for file in files {
+        let Some((manifest, text)) = nearest_manifest(root, &file.path) else {
+            continue;
+        };
+        let public_api = touches_public_api(file);
+        if let Some(known) = packages.iter_mut().find(|p| p.manifest == manifest) {
+            known.public_api |= public_api;
+            continue;
+        }
+        let file_name = Path::new(&manifest).file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        if let Some((name, description)) = parse(file_name, &text) {
+            packages.push(Package {
+                manifest,
+                name,
+                description,
+                public_api,
+            });
+        }
+    }
+    packages
+}
+
+/// Path and contents of the first manifest in the directories above `path`
+fn nearest_manifest(root: &Path, path: &str) -> Option<(String, String)> {
+    let mut dir = Path::new(path).parent();
+    while let Some(current) = dir {
+        for name in MANIFESTS {
+            let manifest = current.join(name);
+            if let Ok(text) = fs::read_to_string(root.join(&manifest)) {
+                return Some((manifest.to_string_lossy().to_string(), text));
+            }
+        }
+        dir = current.parent();
+    }
+    None
+}
+
+/// Package name and description from a manifest; `None` for workspace-only manifests
+fn parse(file_name: &str, text: &str) -> Option<(String, Option<String>)> {
+    match file_name {
+        "Cargo.toml" => toml_package(text, &["package"]),
+        "pyproject.toml" => toml_package(text, &["project", "tool.poetry"]),
+        "package.json" => {
+            let json: serde_json::Value = serde_json::from_str(text).ok()?;
+            let name = json["name"].as_str()?.to_string();
+            let description = json["description"].as_str().filter(|d| !d.is_empty()).map(String::from);
+            Some((name, description))
+        }
+        _ => None,
+    }
+}
+
+/// `name` and `description` from the first of `sections` that has a name
+fn toml_package(text: &str, sections: &[&str]) -> Option<(String, Option<String>)> {
+    sections.iter().find_map(|section| {
+        let header = format!("[{}]", section);
+        let body = text
+            .lines()
+            .skip_while(|line| line.trim() != header)
+            .skip(1)
+            .take_while(|line| !line.trim_start().starts_with('['));
+        let mut name = None;
+        let mut description = None;
+        for line in body {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim().trim_matches(|c| c == '"' || c == '\'').to_string();
+            match key.trim() {
+                "name" => name = Some(value),
+                "description" if !value.is_empty() => description = Some(value),
+                _ => {}
+            }
+        }
+        Some((name?, description))
+    })
+}
+
+/// Whether `file`'s changed lines declare exported items
+///
+/// Line-based: an exported declaration added, removed or edited counts, so
+/// does a private one made public. Tests never count.
+fn touches_public_api(file: &FileDiff) -> bool {
+    if is_test(&file.path) {
+        return false;
+    }
+    let ext = Path::new(&file.path).extension().and_then(|e| e.to_str()).unwrap_or_default();
+    let exported: fn(&str) -> bool = match ext {
+        "rs" => |line| {
+            let line = line.trim_start();
+            line.starts_with("pub ") || line.starts_with("pub async ") || line.starts_with("pub unsafe ")
+        },
+        "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" => |line| line.trim_start().starts_with("export "),
+        // Top-level definitions without a leading underscore
+        "py" => |line| {
+            ["def ", "async def ", "class "]
+                .iter()
+                .any(|keyword| line.strip_prefix(keyword).is_some_and(|name| !name.starts_with('_')))
+        },
+        _ => return false,
+    };
+    file.changed_lines().any(exported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::parse_diff;
+
+    #[test]
+    fn test_manifests() {
+        let cargo = "[workspace]\nmembers = [\"cli\"]\n\n[package]\nname = \"cm\"\ndescription = \"Commit messages\"\n\n[dependencies]\nname = \"x\"\n";
+        assert_eq!(parse("Cargo.toml", cargo), Some(("cm".into(), Some("Commit messages".into()))));
+        assert_eq!(parse("Cargo.toml", "[workspace]\nmembers = []\n"), None);
+        let poetry = "[tool.poetry]\nname = 'tool'\n";
+        assert_eq!(parse("pyproject.toml", poetry), Some(("tool".into(), None)));
+        let npm = r#"{"name": "@acme/web", "description": "Web app"}"#;
+        assert_eq!(parse("package.json", npm), Some(("@acme/web".into(), Some("Web app".into()))));
+
+        let diff = "diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-fn parse() {}\n+pub fn parse() {}\n\
+                    diff --git a/src/util.py b/src/util.py\n--- a/src/util.py\n+++ b/src/util.py\n@@ -1 +1 @@\n-def _helper():\n+def _helper(x):\n";
+        let files = parse_diff(diff);
+        assert!(touches_public_api(&files[0]));
+        assert!(!touches_public_api(&files[1]));
+    }
+}
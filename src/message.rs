@@ -1,3 +1,12 @@
+use crate::config::{BodyStyle, ScopeMode};
+use crate::emoji::canonical_type;
+
+/// Width body lines are wrapped to, as `git log` and most review tools expect
+const BODY_WIDTH: usize = 72;
+
+/// Openings of chatty responses that talk about the message instead of being it
+const PREAMBLES: &[&str] = &["here is", "here's", "sure", "certainly", "okay", "the commit message", "commit message:"];
+
 /// A structured commit message with subject and optional body
 #[derive(Debug, Clone, PartialEq)]
 pub struct CommitMessage {
@@ -6,11 +15,6 @@ pub struct CommitMessage {
 }
 
 impl CommitMessage {
-    /// Create a new commit message with just a subject
-    pub fn new(subject: String) -> Self {
-        Self { subject, body: None }
-    }
-
     /// Parse a message from AI response
     ///
     /// Expected format:
@@ -67,6 +71,244 @@ impl CommitMessage {
         }
     }
 
+    /// Parse a message the user wrote or edited: the first non-empty line is
+    /// the subject, everything after it the body
+    ///
+    /// Returns `None` for an empty message.
+    pub fn parse_edited(text: &str) -> Option<Self> {
+        let mut lines = text.lines().skip_while(|l| l.trim().is_empty());
+        let subject = lines.next()?.trim().to_string();
+        let body = lines
+            .skip_while(|l| l.trim().is_empty())
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\n");
+        Some(Self {
+            subject,
+            body: (!body.trim().is_empty()).then(|| body.trim_end().to_string()),
+        })
+    }
+
+    /// Why the subject doesn't look like a commit message, if it doesn't
+    ///
+    /// This catches responses the parser fell back on, such as
+    /// "Here is the commit message:" or a Markdown code fence.
+    pub fn suspicion(&self) -> Option<&'static str> {
+        let subject = self.subject.trim();
+        let lower = subject.to_lowercase();
+        if PREAMBLES.iter().any(|p| lower.starts_with(p)) {
+            Some("it starts with a preamble")
+        } else if subject.contains("```") || subject.contains("**") || subject.starts_with(['#', '`', '>']) {
+            Some("it contains Markdown")
+        } else if lower.starts_with("subject") || lower.starts_with("body:") {
+            Some("it still has a format label")
+        } else {
+            None
+        }
+    }
+
+    /// Dig the message out of a chatty response: drop code fences, Markdown
+    /// emphasis and everything before the first Conventional Commits header
+    pub fn reparse(response: &str) -> Self {
+        let cleaned: Vec<String> = response
+            .lines()
+            .filter(|line| !line.trim_start().starts_with("```"))
+            .map(|line| line.replace("**", "").trim_start_matches(['#', '>']).trim_start().to_string())
+            .collect();
+        let cleaned = cleaned.join("\n");
+        if cleaned.contains("SUBJECT:") {
+            return Self::parse_from_ai_response(&cleaned);
+        }
+        let lines: Vec<&str> = cleaned.lines().collect();
+        let start = lines
+            .iter()
+            .position(|line| is_header(line.trim().trim_matches('`')))
+            .or_else(|| {
+                lines.iter().position(|line| {
+                    let lower = line.trim().to_lowercase();
+                    !lower.is_empty() && !PREAMBLES.iter().any(|p| lower.starts_with(p))
+                })
+            })
+            .unwrap_or_default();
+        let mut message = Self::parse_edited(&lines[start..].join("\n")).unwrap_or(Self {
+            subject: String::new(),
+            body: None,
+        });
+        message.subject = message.subject.trim_matches('`').to_string();
+        message
+    }
+
+    /// Replace a synonym of a conventional type (`feature`, `bugfix`, `Docs`)
+    /// with the type itself
+    pub fn normalize_type(&mut self) {
+        let Some(end) = self.subject.find(['!', '(', ':']) else {
+            return;
+        };
+        let commit_type = &self.subject[..end];
+        if let Some(canonical) = canonical_type(commit_type).filter(|c| *c != commit_type) {
+            self.subject.replace_range(..end, canonical);
+        }
+    }
+
+    /// Byte range of the scope inside `type(scope):`, excluding the parentheses
+    fn scope_range(&self) -> Option<(usize, usize)> {
+        let colon = self.subject.find(':')?;
+        let header = &self.subject[..colon];
+        let open = header.find('(')?;
+        let close = header.rfind(')')?;
+        (open < close).then_some((open + 1, close))
+    }
+
+    /// Bring the scope in line with the configured mode
+    ///
+    /// `omit` drops the scope and `dominant` keeps only the first of a stacked
+    /// list; models order stacked scopes by importance often enough that this
+    /// beats another round trip.
+    pub fn apply_scope_mode(&mut self, mode: ScopeMode) {
+        let Some((start, end)) = self.scope_range() else {
+            return;
+        };
+        match mode {
+            ScopeMode::Omit => {
+                self.subject.replace_range(start - 1..end + 1, "");
+            }
+            ScopeMode::Dominant => {
+                let first = self.subject[start..end].split(',').next().unwrap_or_default().trim().to_string();
+                self.subject.replace_range(start..end, &first);
+            }
+            ScopeMode::Stack => {
+                let stacked = self.subject[start..end]
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(",");
+                self.subject.replace_range(start..end, &stacked);
+            }
+            ScopeMode::Auto => {}
+        }
+    }
+
+    /// Lay the body out as `style` asks, whatever the model wrote
+    ///
+    /// Prose becomes one bullet per sentence and bullets are joined into a
+    /// paragraph. Paragraphs with indented lines (code, output) and trailers
+    /// such as `Refs: #12` are kept as they are.
+    pub fn apply_body_style(&mut self, style: BodyStyle) {
+        let Some(body) = self.body.take() else {
+            return;
+        };
+        if style == BodyStyle::Omit {
+            return;
+        }
+        let paragraphs: Vec<String> = body
+            .split("\n\n")
+            .map(|paragraph| {
+                let lines: Vec<&str> = paragraph.lines().filter(|l| !l.trim().is_empty()).collect();
+                let bulleted = lines.iter().all(|l| is_bullet(l) || l.starts_with("  ") && !l.starts_with("    "));
+                let fixed = lines.iter().any(|l| l.starts_with("    ") || l.starts_with('\t') || is_trailer(l));
+                match style {
+                    _ if fixed || lines.is_empty() => paragraph.to_string(),
+                    BodyStyle::Bullets if !bulleted => sentences(&lines.join(" "))
+                        .iter()
+                        .map(|sentence| wrap_line(&format!("- {}", sentence.trim_end_matches('.')), BODY_WIDTH))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    BodyStyle::Paragraphs if bulleted => {
+                        let items = bullet_items(&lines)
+                            .into_iter()
+                            .map(|item| if item.ends_with(['.', '!', '?']) { item } else { format!("{}.", item) })
+                            .collect::<Vec<_>>();
+                        wrap_line(&items.join(" "), BODY_WIDTH)
+                    }
+                    _ => paragraph.to_string(),
+                }
+            })
+            .collect();
+        self.body = Some(paragraphs.join("\n\n"));
+    }
+
+    /// Break overlong prose lines of the body at 72 columns
+    pub fn wrap_body(&mut self) {
+        if let Some(body) = self.body.as_mut() {
+            *body = body.lines().map(|line| wrap_line(line, BODY_WIDTH)).collect::<Vec<_>>().join("\n");
+        }
+    }
+
+    /// Make `scope` the scope of the header, replacing any other
+    pub fn set_scope(&mut self, scope: &str) {
+        if let Some((start, end)) = self.scope_range() {
+            self.subject.replace_range(start..end, scope);
+        } else if let Some(colon) = self.subject.find(':') {
+            // Before any `!` marking the commit breaking
+            let at = if self.subject[..colon].ends_with('!') { colon - 1 } else { colon };
+            if is_header(&self.subject) {
+                self.subject.insert_str(at, &format!("({})", scope));
+            }
+        }
+    }
+
+    /// Whether the message marks a breaking change, with `!` in the header or a
+    /// `BREAKING CHANGE:` footer
+    pub fn is_breaking(&self) -> bool {
+        let header_bang = self.subject.split_once(':').is_some_and(|(header, _)| header.ends_with('!'));
+        header_bang
+            || self.body.as_deref().is_some_and(|body| {
+                body.lines().any(|line| line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:"))
+            })
+    }
+
+    /// Banned phrases that appear in the message, matched case-insensitively on word boundaries
+    pub fn banned_phrases<'a>(&self, banned: &'a [String]) -> Vec<&'a str> {
+        let text = self.to_git_message().to_lowercase();
+        banned
+            .iter()
+            .filter(|phrase| contains_phrase(&text, &phrase.to_lowercase()))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Mark the header breaking with `!` and add a `BREAKING CHANGE:` footer unless one exists
+    pub fn mark_breaking(&mut self, note: &str) {
+        if let Some(colon) = self.subject.find(':') {
+            if !self.subject[..colon].ends_with('!') {
+                self.subject.insert(colon, '!');
+            }
+        }
+        if self.body.as_deref().is_some_and(|body| body.contains("BREAKING CHANGE:") || body.contains("BREAKING-CHANGE:")) {
+            return;
+        }
+        self.add_footer(&format!("BREAKING CHANGE: {}", note));
+    }
+
+    /// Append a `Refs:` footer for the `refs` the message doesn't mention yet
+    pub fn add_refs_footer(&mut self, refs: &[String]) {
+        let text = self.to_git_message();
+        let missing: Vec<&str> = refs.iter().map(String::as_str).filter(|r| !text.contains(r)).collect();
+        if missing.is_empty() {
+            return;
+        }
+        self.add_footer(&format!("Refs: {}", missing.join(", ")));
+    }
+
+    /// Append the trailer `footer` unless the message already has it
+    ///
+    /// It joins the trailers ending the body, or starts a block of its own
+    /// after a blank line, as git expects.
+    pub fn add_footer(&mut self, footer: &str) {
+        if self.body.as_deref().is_some_and(|body| body.lines().any(|line| line.trim() == footer)) {
+            return;
+        }
+        self.body = Some(match self.body.take() {
+            Some(body) => {
+                let last = body.rsplit("\n\n").next().unwrap_or_default();
+                let separator = if last.lines().all(is_trailer) { "\n" } else { "\n\n" };
+                format!("{}{}{}", body.trim_end(), separator, footer)
+            }
+            None => footer.to_string(),
+        });
+    }
+
     /// Format as a git commit message (with blank line between subject and body)
     pub fn to_git_message(&self) -> String {
         match &self.body {
@@ -74,11 +316,115 @@ impl CommitMessage {
             None => self.subject.clone(),
         }
     }
+}
 
-    /// Get a display string for the interactive prompt
-    pub fn to_display_string(&self) -> String {
-        self.to_git_message()
+/// `line` broken at spaces to fit `width`, with list items indented under their text
+///
+/// Indented lines (code, quoted output) and trailers such as `Refs: #12` are
+/// left alone.
+fn wrap_line(line: &str, width: usize) -> String {
+    if line.chars().count() <= width || line.starts_with([' ', '\t']) || is_trailer(line) {
+        return line.to_string();
+    }
+    let marker = ["- ", "* ", "+ "].iter().find(|m| line.starts_with(**m));
+    let indent = if marker.is_some() { "  " } else { "" };
+    let mut wrapped = String::new();
+    let mut current = String::new();
+    for word in line.split(' ').filter(|w| !w.is_empty()) {
+        if !current.trim().is_empty() && current.chars().count() + 1 + word.chars().count() > width {
+            wrapped.push_str(&current);
+            wrapped.push('\n');
+            current = indent.to_string();
+        } else if !current.trim().is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
     }
+    wrapped.push_str(&current);
+    wrapped
+}
+
+/// `message` split before the block of trailers (`Refs: #12`, `Signed-off-by: ...`) ending it
+///
+/// The subject alone is never taken for a trailer.
+pub fn split_trailers(message: &str) -> (&str, Option<&str>) {
+    let message = message.trim_end();
+    match message.rsplit_once("\n\n") {
+        Some((rest, last)) if last.lines().all(is_trailer) => (rest.trim_end(), Some(last)),
+        _ => (message, None),
+    }
+}
+
+/// A `- `, `* ` or `+ ` list item
+fn is_bullet(line: &str) -> bool {
+    line.starts_with("- ") || line.starts_with("* ") || line.starts_with("+ ")
+}
+
+/// Text of each list item, with wrapped continuation lines joined back on
+fn bullet_items(lines: &[&str]) -> Vec<String> {
+    let mut items: Vec<String> = Vec::new();
+    for line in lines {
+        match (is_bullet(line), items.last_mut()) {
+            (false, Some(item)) => {
+                item.push(' ');
+                item.push_str(line.trim());
+            }
+            _ => items.push(line[2..].trim().to_string()),
+        }
+    }
+    items
+}
+
+/// `text` split after `.`, `!` or `?` where the next word starts with a capital
+///
+/// Abbreviations such as "e.g. the" stay in their sentence.
+fn sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+    let mut words = text.split_whitespace().peekable();
+    while let Some(word) = words.next() {
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+        let next_starts = words.peek().is_none_or(|next| next.starts_with(char::is_uppercase));
+        if word.ends_with(['.', '!', '?']) && next_starts {
+            sentences.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        sentences.push(current);
+    }
+    sentences
+}
+
+/// `type(scope)!: description` with a lowercase type
+fn is_header(line: &str) -> bool {
+    let Some((header, description)) = line.split_once(": ") else {
+        return false;
+    };
+    let header = header.strip_suffix('!').unwrap_or(header);
+    let kind = header.split_once('(').map_or(header, |(kind, scope)| if scope.ends_with(')') { kind } else { "" });
+    !description.trim().is_empty() && !kind.is_empty() && kind.chars().all(|c| c.is_ascii_lowercase())
+}
+
+/// `Token: value` footers, which git and tools read one per line
+fn is_trailer(line: &str) -> bool {
+    line.split_once(": ").is_some_and(|(token, _)| {
+        token == "BREAKING CHANGE" || !token.is_empty() && token.chars().all(|c| c.is_alphanumeric() || c == '-')
+    })
+}
+
+fn contains_phrase(text: &str, phrase: &str) -> bool {
+    if phrase.is_empty() {
+        return false;
+    }
+    let is_word = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    text.match_indices(phrase).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + phrase.len()..].chars().next();
+        !is_word(before) && !is_word(after)
+    })
 }
 
 #[cfg(test)]
@@ -101,6 +447,117 @@ mod tests {
         assert_eq!(msg.body, None);
     }
 
+    #[test]
+    fn test_parse_edited() {
+        let text = "\nfeat(cli): add --dry-run\nPrint the message instead of committing. This is handy when wiring cm into other tools.\n\n- Works with every provider, including the local ones that some people run offline\n    cm --dry-run | less\nRefs: #12, #34, #56, #78, #90, #112, #134, #156, #178, #200, #222, #244, #266\n\n";
+        let mut msg = CommitMessage::parse_edited(text).unwrap();
+        assert_eq!(msg.subject, "feat(cli): add --dry-run");
+        msg.wrap_body();
+        assert_eq!(
+            msg.body.as_deref(),
+            Some(
+                "Print the message instead of committing. This is handy when wiring cm\ninto other tools.\n\n\
+                 - Works with every provider, including the local ones that some people\n  run offline\n    cm --dry-run | less\n\
+                 Refs: #12, #34, #56, #78, #90, #112, #134, #156, #178, #200, #222, #244, #266"
+            )
+        );
+        assert_eq!(CommitMessage::parse_edited(" \n\n"), None);
+        assert_eq!(CommitMessage::parse_edited("fix: typo").unwrap().body, None);
+    }
+
+    #[test]
+    fn test_normalize_type() {
+        let normalized = |subject: &str| {
+            let mut message = CommitMessage {
+                subject: subject.to_string(),
+                body: None,
+            };
+            message.normalize_type();
+            message.subject
+        };
+        assert_eq!(normalized("feature(api): add search"), "feat(api): add search");
+        assert_eq!(normalized("bugfix: handle empty input"), "fix: handle empty input");
+        assert_eq!(normalized("Documentation!: drop v1 guide"), "docs!: drop v1 guide");
+        assert_eq!(normalized("tests: cover parser"), "test: cover parser");
+        assert_eq!(normalized("fix: unchanged"), "fix: unchanged");
+        assert_eq!(normalized("update: unknown types stay"), "update: unknown types stay");
+        assert_eq!(normalized("Add a feature"), "Add a feature");
+    }
+
+    #[test]
+    fn test_set_scope() {
+        let mut message = CommitMessage {
+            subject: "feat(api,ui)!: drop v1 routes".into(),
+            body: None,
+        };
+        message.set_scope("api");
+        assert_eq!(message.subject, "feat(api)!: drop v1 routes");
+        message.subject = "fix!: handle empty input".into();
+        message.set_scope("parser");
+        assert_eq!(message.subject, "fix(parser)!: handle empty input");
+        message.subject = "Handle empty input: somehow".into();
+        message.set_scope("parser");
+        assert_eq!(message.subject, "Handle empty input: somehow");
+    }
+
+    #[test]
+    fn test_add_footer() {
+        let mut message = CommitMessage {
+            subject: "fix: refresh tokens".into(),
+            body: None,
+        };
+        message.add_footer("Refs: AUTH-7");
+        assert_eq!(message.to_git_message(), "fix: refresh tokens\n\nRefs: AUTH-7");
+        message.body = Some("Expired tokens failed requests.".into());
+        message.add_footer("Refs: AUTH-7");
+        message.add_footer("Signed-off-by: A <a@example.com>");
+        message.add_footer("Refs: AUTH-7");
+        assert_eq!(
+            message.body.as_deref(),
+            Some("Expired tokens failed requests.\n\nRefs: AUTH-7\nSigned-off-by: A <a@example.com>")
+        );
+    }
+
+    #[test]
+    fn test_apply_body_style() {
+        let prose = CommitMessage {
+            subject: "fix(auth): refresh expired tokens".into(),
+            body: Some("Tokens expired mid-session, e.g. after sleep. Refresh them before\neach request!\n\nRefs: #12".into()),
+        };
+        let mut bullets = prose.clone();
+        bullets.apply_body_style(BodyStyle::Bullets);
+        assert_eq!(
+            bullets.body.as_deref(),
+            Some("- Tokens expired mid-session, e.g. after sleep\n- Refresh them before each request!\n\nRefs: #12")
+        );
+
+        let mut paragraphs = bullets.clone();
+        paragraphs.apply_body_style(BodyStyle::Paragraphs);
+        assert_eq!(
+            paragraphs.body.as_deref(),
+            Some("Tokens expired mid-session, e.g. after sleep. Refresh them before each\nrequest!\n\nRefs: #12")
+        );
+
+        let mut none = prose.clone();
+        none.apply_body_style(BodyStyle::Omit);
+        assert_eq!(none.body, None);
+    }
+
+    #[test]
+    fn test_suspicious_response() {
+        let response = "Here is a commit message for your changes:\n\n```\nfix(parser): handle empty input\n\nReturn an empty tree instead of panicking.\n```";
+        let fallback = CommitMessage::parse_from_ai_response(response);
+        assert_eq!(fallback.suspicion(), Some("it starts with a preamble"));
+        let reparsed = CommitMessage::reparse(response);
+        assert_eq!(reparsed.subject, "fix(parser): handle empty input");
+        assert_eq!(reparsed.body.as_deref(), Some("Return an empty tree instead of panicking."));
+        assert_eq!(reparsed.suspicion(), None);
+
+        let bold = CommitMessage::reparse("**SUBJECT:** feat: add search\n**BODY:** none");
+        assert_eq!(bold.subject, "feat: add search");
+        assert_eq!(CommitMessage::parse_from_ai_response("## feat: add search").suspicion(), Some("it contains Markdown"));
+    }
+
     #[test]
     fn test_to_git_message() {
         let msg = CommitMessage {
@@ -109,4 +566,66 @@ mod tests {
         };
         assert_eq!(msg.to_git_message(), "feat: add feature\n\nThis is the body.");
     }
+
+    #[test]
+    fn test_mark_breaking() {
+        let mut msg = CommitMessage {
+            subject: "feat(parser): add strict mode".to_string(),
+            body: Some("Adds a flag.".to_string()),
+        };
+        assert!(!msg.is_breaking());
+        msg.mark_breaking("`parse` takes a `strict` flag");
+        assert_eq!(
+            msg.to_git_message(),
+            "feat(parser)!: add strict mode\n\nAdds a flag.\n\nBREAKING CHANGE: `parse` takes a `strict` flag"
+        );
+        msg.mark_breaking("again");
+        assert_eq!(msg.subject, "feat(parser)!: add strict mode");
+        assert!(!msg.to_git_message().contains("again"));
+        assert!(msg.is_breaking());
+        msg.subject = "feat(parser): add strict mode".to_string();
+        assert!(msg.is_breaking(), "the footer alone marks it breaking");
+    }
+
+    #[test]
+    fn test_add_refs_footer() {
+        let mut msg = CommitMessage {
+            subject: "fix(auth): retry token refresh (#456)".to_string(),
+            body: None,
+        };
+        msg.add_refs_footer(&["#456".to_string(), "AUTH-7".to_string()]);
+        assert_eq!(msg.body.as_deref(), Some("Refs: AUTH-7"));
+        msg.add_refs_footer(&["AUTH-7".to_string()]);
+        assert_eq!(msg.body.as_deref(), Some("Refs: AUTH-7"));
+    }
+
+    #[test]
+    fn test_banned_phrases() {
+        let msg = CommitMessage {
+            subject: "fix: Minor fixes in parser".to_string(),
+            body: Some("Part of project-falcon work.".to_string()),
+        };
+        let banned = vec!["minor fixes".to_string(), "falcon".to_string(), "pars".to_string()];
+        assert_eq!(msg.banned_phrases(&banned), vec!["minor fixes", "falcon"]);
+    }
+
+    #[test]
+    fn test_apply_scope_mode() {
+        let stacked = CommitMessage {
+            subject: "feat(api, ui)!: add search".to_string(),
+            body: None,
+        };
+
+        let mut msg = stacked.clone();
+        msg.apply_scope_mode(ScopeMode::Dominant);
+        assert_eq!(msg.subject, "feat(api)!: add search");
+
+        let mut msg = stacked.clone();
+        msg.apply_scope_mode(ScopeMode::Stack);
+        assert_eq!(msg.subject, "feat(api,ui)!: add search");
+
+        let mut msg = stacked;
+        msg.apply_scope_mode(ScopeMode::Omit);
+        assert_eq!(msg.subject, "feat!: add search");
+    }
 }
@@ -0,0 +1,195 @@
+use std::io::{self, Write};
+
+use crate::budget::{estimate_tokens, truncate_diff};
+use crate::config::{BudgetAction, Config};
+use crate::emoji::{add_emoji, remove_emoji_prefix};
+use crate::exit::{provider_failure, Exit, Failure};
+use crate::lint::check_header;
+use crate::message::CommitMessage;
+use crate::prompt::build_migrate_prompt;
+use crate::provider::{create_provider, AiProvider};
+use crate::setup_guidance;
+use crate::spend::{self, prompt_tokens};
+use crate::translate::rewrite_base;
+use crate::ui;
+use crate::vault;
+use crate::vcs::git::{commit_diff, commit_messages, remote_branches_containing, reword_commits};
+
+/// Diff lines of each commit shown to the model; the old message carries most of the meaning
+const MIGRATE_DIFF_LINES: usize = 200;
+
+/// Rewrite the messages in `range` that aren't Conventional Commits, or only
+/// show the new subjects with `dry_run`
+pub async fn run_migrate_history(range: &str, dry_run: bool, no_cache: bool) -> Result<Exit, Box<dyn std::error::Error>> {
+    let mut config = Config::from_env()?;
+    if no_cache {
+        config.cache_ttl = 0;
+    }
+    vault::configure(config.encrypt_store);
+    ui::set_theme(config.theme);
+
+    let base = rewrite_base(range)?;
+    let commits = commit_messages(range)?;
+    let pending: Vec<_> = commits.iter().filter(|(_, message)| needs_migration(message)).collect();
+    if pending.is_empty() {
+        ui::status("✓ ", format!("All {} commit message(s) in {} already follow Conventional Commits", commits.len(), range));
+        return Ok(Exit::Success);
+    }
+
+    let provider = create_provider(&config);
+    ui::status(
+        "🔁 ",
+        format!(
+            "Rewriting {} of {} commit subject(s) with {} ({})",
+            pending.len(),
+            commits.len(),
+            provider.name(),
+            provider.model()
+        ),
+    );
+
+    let mut rewritten = Vec::with_capacity(commits.len());
+    let mut changed = 0;
+    for (hash, message) in &commits {
+        if !pending.iter().any(|(h, _)| h == hash) {
+            rewritten.push((hash.clone(), message.clone()));
+            continue;
+        }
+        let short = &hash[..hash.len().min(10)];
+        let diff = truncate_diff(&commit_diff(hash)?, Some(MIGRATE_DIFF_LINES), config.context_tokens / 2);
+        match migrate_subject(provider.as_ref(), &config, message, &diff).await? {
+            Some(subject) => {
+                println!("{} {}", ui::paint(short, ui::theme().reference), message.lines().next().unwrap_or_default());
+                println!("{:width$} → {}", "", subject, width = short.len());
+                rewritten.push((hash.clone(), with_subject(message, &subject)));
+                changed += 1;
+            }
+            None => {
+                ui::warn("⚠️  ", format!("{}: no conventional subject was generated; keeping the original", short));
+                rewritten.push((hash.clone(), message.clone()));
+            }
+        }
+    }
+
+    if dry_run {
+        ui::status("ℹ️  ", "Dry run: history left unchanged");
+        return Ok(Exit::Success);
+    }
+    if changed == 0 {
+        return Err(Failure::new(Exit::GenerationFailed, "No commit message could be rewritten").into());
+    }
+
+    let oldest = &commits[0].0;
+    let published = remote_branches_containing(oldest);
+    ui::warn(
+        "⚠️  ",
+        format!(
+            "This rewrites {} commit(s) after {}: every commit hash changes, and anyone who has them must reset onto the new history",
+            commits.len(),
+            &base[..base.len().min(10)]
+        ),
+    );
+    if !published.is_empty() {
+        ui::warn(
+            "⚠️  ",
+            format!(
+                "These commits are already on {}; publishing the rewrite needs a force push",
+                published.join(", ")
+            ),
+        );
+    }
+    print!("{}", ui::line("\n⚠️  ", &format!("Rewrite {} commit message(s)? [y/N]: ", changed)));
+    io::stdout().flush()?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    if input.trim().to_lowercase() != "y" {
+        ui::status("👋 ", "History left unchanged");
+        return Ok(Exit::Aborted);
+    }
+
+    reword_commits(&base, &rewritten)?;
+    ui::status("✓ ", format!("Rewrote {} commit message(s)", changed));
+    Ok(Exit::Success)
+}
+
+/// Whether the subject of `message` isn't a Conventional Commits header
+///
+/// Merges and reverts keep the subject git wrote for them.
+fn needs_migration(message: &str) -> bool {
+    let subject = message.lines().next().unwrap_or_default();
+    if subject.starts_with("Merge ") || subject.starts_with("Revert \"") {
+        return false;
+    }
+    check_header(&remove_emoji_prefix(subject)).is_err()
+}
+
+/// `message` with its first line replaced by `subject`, body and trailers untouched
+fn with_subject(message: &str, subject: &str) -> String {
+    match message.split_once('\n') {
+        Some((_, rest)) => format!("{}\n{}", subject, rest),
+        None => subject.to_string(),
+    }
+}
+
+/// A conventional subject for `message`, or `None` if the model never produced a valid one
+async fn migrate_subject(
+    provider: &dyn AiProvider,
+    config: &Config,
+    message: &str,
+    diff: &str,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let prompt = build_migrate_prompt(message, diff);
+    let estimate = prompt_tokens(&prompt);
+    if let Some(reason) = spend::over_budget(&config.budget, config.provider.id(), estimate) {
+        if config.budget.action == BudgetAction::Refuse {
+            let message = format!("{}. Set CM_BUDGET_ACTION=warn to continue anyway.", reason);
+            return Err(Failure::new(Exit::QuotaExceeded, message).into());
+        }
+        ui::warn("⚠️  ", format!("Over budget: {}", reason));
+    }
+
+    for _ in 0..config.max_retries {
+        match provider.generate(&prompt).await {
+            Ok(text) => {
+                spend::record(&config.budget, config.provider.id(), estimate + estimate_tokens(&text) as u64);
+                let mut parsed = CommitMessage::reparse(&text);
+                parsed.normalize_type();
+                if check_header(&parsed.subject).is_ok() {
+                    return Ok(Some(if config.emoji_enabled {
+                        add_emoji(&parsed.subject, config.emoji_placement)
+                    } else {
+                        parsed.subject
+                    }));
+                }
+            }
+            Err(e) if !e.is_retryable() => return Err(provider_failure(&e, setup_guidance(&e, config)).into()),
+            Err(e) => ui::warn("⚠️  ", format!("API error: {}. Retrying...", e)),
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needs_migration() {
+        assert!(needs_migration("Fixed the login bug\n\nTokens expired."));
+        assert!(needs_migration("WIP"));
+        assert!(!needs_migration("fix(auth): refresh expired tokens"));
+        assert!(!needs_migration("✨ feat: add search"));
+        // Too long, but conventional: not for this command to fix
+        assert!(!needs_migration(&format!("docs: {}", "x".repeat(80))));
+        assert!(!needs_migration("Merge branch 'main' into feature"));
+    }
+
+    #[test]
+    fn test_with_subject() {
+        assert_eq!(
+            with_subject("Fixed login\n\nTokens expired.\n\nSigned-off-by: A <a@b>", "fix(auth): refresh expired tokens"),
+            "fix(auth): refresh expired tokens\n\nTokens expired.\n\nSigned-off-by: A <a@b>"
+        );
+        assert_eq!(with_subject("typo", "docs: fix typo"), "docs: fix typo");
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::diff::FileDiff;
+
+/// A database migration among the staged files
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migration {
+    pub path: String,
+    pub framework: &'static str,
+    /// Migration name without its version or timestamp, e.g. `add_email_to_users`
+    pub name: String,
+    /// Schema operations found in the added lines, e.g. `add column users.email`
+    pub ops: Vec<String>,
+}
+
+impl fmt::Display for Migration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` ({}, {})", self.name, self.framework, self.path)?;
+        if !self.ops.is_empty() {
+            write!(f, ": {}", self.ops.join("; "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Migrations among `files`, with the operations they add
+pub fn migrations(files: &[FileDiff]) -> Vec<Migration> {
+    files
+        .iter()
+        .filter(|file| !file.is_deleted())
+        .filter_map(|file| {
+            let (framework, name) = identify(&file.path)?;
+            let parse: fn(&str) -> Option<String> = match framework {
+                "Django" => django_op,
+                "Rails" => rails_op,
+                _ => sql_op,
+            };
+            let mut ops: Vec<String> = Vec::new();
+            for op in file.added_lines().filter_map(parse) {
+                if !ops.contains(&op) {
+                    ops.push(op);
+                }
+            }
+            Some(Migration {
+                path: file.path.clone(),
+                framework,
+                name,
+                ops,
+            })
+        })
+        .collect()
+}
+
+/// Prompt section steering the message toward the schema change
+pub fn prompt_section(migrations: &[Migration]) -> Option<String> {
+    if migrations.is_empty() {
+        return None;
+    }
+    let list = migrations.iter().map(|m| format!("- {}", m)).collect::<Vec<_>>().join("\n");
+    Some(format!(
+        r#"## Database Migrations
+{}
+- Use the `db` scope: `feat(db)` when the schema gains tables, columns or indexes the application relies on, `chore(db)` for cleanups, index tuning and data fixes
+- Name the tables and columns affected in the subject or body
+- Dropping or renaming a table or column breaks code still using it; say so"#,
+        list
+    ))
+}
+
+/// Framework and migration name for a migration file path
+fn identify(path: &str) -> Option<(&'static str, String)> {
+    let p = Path::new(path);
+    let file_name = p.file_name()?.to_str()?;
+    let parent = p.parent().and_then(|d| d.file_name()).and_then(|d| d.to_str()).unwrap_or_default();
+    let in_dir = |dir: &str| path.starts_with(dir) || path.contains(&format!("/{}", dir));
+
+    if let Some(stem) = file_name.strip_suffix(".py").filter(|_| parent == "migrations") {
+        return versioned_name(stem).map(|name| ("Django", name));
+    }
+    if let Some(stem) = file_name.strip_suffix(".rb").filter(|_| in_dir("db/migrate/")) {
+        return versioned_name(stem).map(|name| ("Rails", name));
+    }
+    let stem = file_name.strip_suffix(".sql")?;
+    if let Some((version, name)) = stem.split_once("__") {
+        let flyway = version.starts_with(['V', 'U']) && version[1..].chars().all(|c| c.is_ascii_digit() || c == '_' || c == '.')
+            || version == "R";
+        if flyway {
+            return Some(("Flyway", name.to_string()));
+        }
+    }
+    let stem = stem.trim_end_matches(".up").trim_end_matches(".down");
+    versioned_name(stem).filter(|_| parent == "migrations").map(|name| ("SQL", name))
+}
+
+/// `add_email` from `0003_add_email` or `20240101120000_add_email`
+fn versioned_name(stem: &str) -> Option<String> {
+    let (version, name) = stem.split_once('_')?;
+    (!version.is_empty() && version.chars().all(|c| c.is_ascii_digit()) && !name.is_empty()).then(|| name.to_string())
+}
+
+/// Operation performed by a SQL statement line
+fn sql_op(line: &str) -> Option<String> {
+    let words: Vec<String> = line
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ';')
+        .filter(|w| !w.is_empty())
+        .map(|w| w.trim_matches(['"', '`', '[', ']']).to_string())
+        .collect();
+    let upper: Vec<String> = words.iter().map(|w| w.to_uppercase()).collect();
+    let word = |i: usize| upper.get(i).map(String::as_str).unwrap_or_default();
+    // Skip `IF [NOT] EXISTS` to reach the object name
+    let name_at = |mut at: usize| {
+        while matches!(word(at), "IF" | "NOT" | "EXISTS" | "CONCURRENTLY" | "ONLY") {
+            at += 1;
+        }
+        at
+    };
+    let name_after = |i: usize| words.get(name_at(i)).map(|name| name.to_lowercase());
+    match (word(0), word(1)) {
+        ("CREATE", "TABLE") => Some(format!("create table {}", name_after(2)?)),
+        ("DROP", "TABLE") => Some(format!("drop table {}", name_after(2)?)),
+        ("CREATE", "INDEX") | ("CREATE", "UNIQUE") => {
+            let on = upper.iter().position(|w| w == "ON")?;
+            Some(format!("create index on {}", words.get(on + 1)?.to_lowercase()))
+        }
+        ("DROP", "INDEX") => Some(format!("drop index {}", name_after(2)?)),
+        ("ALTER", "TABLE") => {
+            let table_at = name_at(2);
+            let table = words.get(table_at)?.to_lowercase();
+            let action = (table_at + 1..upper.len()).find(|&i| matches!(word(i), "ADD" | "DROP" | "RENAME" | "ALTER"))?;
+            let column_at = if word(action + 1) == "COLUMN" { action + 2 } else { action + 1 };
+            let column = name_after(column_at).unwrap_or_default();
+            let verb = match word(action) {
+                "ADD" if word(action + 1) == "CONSTRAINT" => return Some(format!("add constraint on {}", table)),
+                "ADD" => "add column",
+                "DROP" => "drop column",
+                "RENAME" => "rename column",
+                _ => "alter column",
+            };
+            Some(format!("{} {}.{}", verb, table, column))
+        }
+        _ => None,
+    }
+}
+
+/// Operation performed by a Django `migrations.Operation(...)` line
+fn django_op(line: &str) -> Option<String> {
+    let rest = line.trim().strip_prefix("migrations.")?;
+    let (operation, args) = rest.split_once('(')?;
+    // `name=` must not match the end of `model_name=`
+    let arg = |key: &str| {
+        let pattern = format!("{}=", key);
+        let (at, _) = args
+            .match_indices(&pattern)
+            .find(|(at, _)| !args[..*at].ends_with(|c: char| c.is_alphanumeric() || c == '_'))?;
+        let value = args[at + pattern.len()..].trim_start();
+        let quote = value.chars().next().filter(|c| *c == '\'' || *c == '"')?;
+        value[1..].split(quote).next().map(str::to_lowercase)
+    };
+    let model = || arg("model_name").or_else(|| arg("name"));
+    Some(match operation {
+        "CreateModel" => format!("create table {}", arg("name")?),
+        "DeleteModel" => format!("drop table {}", arg("name")?),
+        "AddField" => format!("add column {}.{}", model()?, arg("name")?),
+        "RemoveField" => format!("drop column {}.{}", model()?, arg("name")?),
+        "AlterField" => format!("alter column {}.{}", model()?, arg("name")?),
+        "RenameField" => format!("rename column {}.{} to {}", model()?, arg("old_name")?, arg("new_name")?),
+        "AddIndex" => format!("create index on {}", model()?),
+        "RunPython" | "RunSQL" => "data migration".to_string(),
+        _ => return None,
+    })
+}
+
+/// Operation performed by a Rails migration DSL line
+fn rails_op(line: &str) -> Option<String> {
+    let line = line.trim();
+    let (method, args) = line.split_once(char::is_whitespace)?;
+    let symbols: Vec<&str> = args
+        .split(',')
+        .map(|arg| arg.trim().trim_start_matches(':').trim_matches(['"', '\'']))
+        .collect();
+    let symbol = |i: usize| symbols.get(i).filter(|s| !s.is_empty() && !s.contains(' ')).copied();
+    Some(match method {
+        "create_table" => format!("create table {}", symbol(0)?),
+        "drop_table" => format!("drop table {}", symbol(0)?),
+        "add_column" => format!("add column {}.{}", symbol(0)?, symbol(1)?),
+        "remove_column" => format!("drop column {}.{}", symbol(0)?, symbol(1)?),
+        "change_column" => format!("alter column {}.{}", symbol(0)?, symbol(1)?),
+        "rename_column" => format!("rename column {}.{} to {}", symbol(0)?, symbol(1)?, symbol(2)?),
+        "add_index" => format!("create index on {}", symbol(0)?),
+        "add_reference" => format!("add reference {}.{}", symbol(0)?, symbol(1)?),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::parse_diff;
+
+    #[test]
+    fn test_migrations() {
+        assert_eq!(identify("app/users/migrations/0003_add_email.py"), Some(("Django", "add_email".into())));
+        assert_eq!(identify("app/users/migrations/__init__.py"), None);
+        assert_eq!(identify("db/migrate/20240101120000_add_email_to_users.rb"), Some(("Rails", "add_email_to_users".into())));
+        assert_eq!(identify("migrations/20240101120000_add_email.up.sql"), Some(("SQL", "add_email".into())));
+        assert_eq!(identify("src/main/resources/db/V2_1__add_email.sql"), Some(("Flyway", "add_email".into())));
+        assert_eq!(identify("queries/report.sql"), None);
+
+        assert_eq!(sql_op("ALTER TABLE \"users\" ADD COLUMN email TEXT NOT NULL;").as_deref(), Some("add column users.email"));
+        assert_eq!(sql_op("create table if not exists orders (").as_deref(), Some("create table orders"));
+        assert_eq!(sql_op("CREATE UNIQUE INDEX idx_email ON users (email);").as_deref(), Some("create index on users"));
+        assert_eq!(
+            django_op("        migrations.AddField(model_name='user', name='email', field=models.EmailField()),").as_deref(),
+            Some("add column user.email")
+        );
+        assert_eq!(rails_op("    rename_column :users, :mail, :email").as_deref(), Some("rename column users.mail to email"));
+
+        let diff = "diff --git a/db/migrate/20240101_add_email.rb b/db/migrate/20240101_add_email.rb\n--- /dev/null\n+++ b/db/migrate/20240101_add_email.rb\n@@ -0,0 +1,4 @@\n\
+                    +class AddEmail < ActiveRecord::Migration[7.1]\n+  def change\n+    add_column :users, :email, :string\n+    add_index :users, :email\n";
+        let found = migrations(&parse_diff(diff));
+        assert_eq!(found[0].ops, vec!["add column users.email", "create index on users"]);
+        assert!(prompt_section(&found).unwrap().contains("`add_email` (Rails, db/migrate/20240101_add_email.rb): add column users.email"));
+    }
+}
@@ -0,0 +1,86 @@
+use std::error::Error;
+use std::time::Instant;
+
+use crate::config::{selected_provider, Config, ConfigError, Provider};
+use crate::exit::{provider_failure, Exit, Failure};
+use crate::prompt::Prompt;
+use crate::provider::{build_provider, ProviderError, ProviderResult};
+use crate::ui;
+
+/// Providers checked by `cm ping`, in the order they are reported
+const PROVIDERS: &[Provider] = &[Provider::OpenRouter, Provider::Gemini, Provider::Ollama];
+
+/// Send a minimal request to every provider with an API key and report
+/// latency, whether the key is accepted and whether the model exists
+///
+/// Exits with the code of the first failing provider, so scripts can tell an
+/// auth problem from an outage.
+pub async fn run_ping() -> Result<Exit, Box<dyn Error>> {
+    let prompt = Prompt {
+        system: "Reply with the single word OK.".to_string(),
+        user: "ping".to_string(),
+    };
+    let mut first_failure = None;
+    let mut checked = 0;
+    for provider in PROVIDERS {
+        // Ollama needs no key, so only check it when it's the selected provider
+        if *provider == Provider::Ollama && selected_provider().ok() != Some(Provider::Ollama) {
+            println!("{}", ui::line("➖ ", &format!("{:<10} not selected (CM_PROVIDER=ollama)", provider.id())));
+            continue;
+        }
+        let config = match Config::for_provider(provider.clone()) {
+            Ok(config) => config,
+            Err(ConfigError::MissingApiKey(var)) => {
+                println!("{}", ui::line("➖ ", &format!("{:<10} not configured ({} not set)", provider.id(), var)));
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        checked += 1;
+        let client = build_provider(&config, &config.model);
+        let started = Instant::now();
+        let result = client.generate(&prompt).await;
+        let elapsed = started.elapsed().as_millis();
+        let (icon, verdict) = outcome(&result);
+        let text = format!("{:<10} {} ({}, {} ms)", provider.id(), verdict, config.model, elapsed);
+        println!("{}", ui::line(icon, &text));
+        if let Err(e) = result {
+            log::debug!("{}: {}", provider.id(), e);
+            first_failure.get_or_insert(e);
+        }
+    }
+    if checked == 0 {
+        return Err(Failure::new(Exit::AuthFailed, "No provider has an API key; set OPENROUTER_API_KEY or GOOGLE_API_KEY").into());
+    }
+    match first_failure {
+        Some(e) => Err(provider_failure(&e, format!("Ping failed: {}", e)).into()),
+        None => Ok(Exit::Success),
+    }
+}
+
+/// Icon and one-word verdict for a ping result
+fn outcome(result: &ProviderResult<String>) -> (&'static str, &'static str) {
+    match result {
+        Ok(_) => ("✅ ", "ok"),
+        Err(ProviderError::AuthFailed(_)) => ("🔑 ", "key rejected"),
+        Err(ProviderError::ModelNotFound(_)) => ("❓ ", "model not found"),
+        // The API answered, so the key and network are fine
+        Err(ProviderError::RateLimited { .. }) => ("⏳ ", "reachable, rate limited"),
+        Err(ProviderError::QuotaExceeded(_)) => ("⏳ ", "reachable, quota exceeded"),
+        Err(ProviderError::NetworkError(_)) => ("🔌 ", "unreachable"),
+        Err(_) => ("❌ ", "error"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcome() {
+        assert_eq!(outcome(&Ok("OK".into())).1, "ok");
+        assert_eq!(outcome(&Err(ProviderError::AuthFailed("401".into()))).1, "key rejected");
+        assert_eq!(outcome(&Err(ProviderError::ModelNotFound("gone".into()))).1, "model not found");
+        assert_eq!(outcome(&Err(ProviderError::NetworkError("dns".into()))).1, "unreachable");
+    }
+}
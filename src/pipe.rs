@@ -0,0 +1,71 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::diff::normalize_newlines;
+
+/// Context piped in with `--stdin`
+#[derive(Debug, Default, PartialEq)]
+pub struct PipedInput {
+    /// Commit subjects given before the diff, one per line
+    pub history: String,
+    pub diff: String,
+}
+
+/// Read the diff, optionally preceded by past commit subjects, from stdin
+pub fn read_stdin() -> io::Result<PipedInput> {
+    let mut text = String::new();
+    io::stdin().read_to_string(&mut text)?;
+    Ok(split_input(&normalize_newlines(&text)))
+}
+
+/// The diff in a patch file, skipping any `git format-patch` mail headers and message
+pub fn read_patch(path: &Path) -> io::Result<String> {
+    Ok(split_input(&normalize_newlines(&fs::read_to_string(path)?)).diff)
+}
+
+/// Whether `line` starts a git or plain unified diff
+fn starts_diff(line: &str) -> bool {
+    line.starts_with("diff ") || line.starts_with("--- ") || line.starts_with("Index: ")
+}
+
+/// Lines before the first diff header are history, the rest is the diff
+///
+/// This lets `{ git log --format=%s -10; git diff --cached; } | cm --stdin`
+/// pass both at once.
+fn split_input(text: &str) -> PipedInput {
+    let mut history = String::new();
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if starts_diff(line) {
+            break;
+        }
+        if !line.trim().is_empty() {
+            history.push_str(line.trim_end());
+            history.push('\n');
+        }
+        offset += line.len();
+    }
+    PipedInput {
+        history,
+        diff: text[offset..].to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_input() {
+        let input = split_input("feat: add parser\nfix(ui): wrap lines\n\ndiff --git a/x b/x\n--- a/x\n+++ b/x\n");
+        assert_eq!(input.history, "feat: add parser\nfix(ui): wrap lines\n");
+        assert!(input.diff.starts_with("diff --git"));
+
+        let plain = split_input("--- a\n+++ b\n@@ -1 +1 @@\n-x\n+y\n");
+        assert_eq!(plain.history, "");
+        assert_eq!(plain.diff, "--- a\n+++ b\n@@ -1 +1 @@\n-x\n+y\n");
+
+        assert_eq!(split_input("just text\n").diff, "");
+    }
+}
@@ -0,0 +1,296 @@
+use std::env::consts::{DLL_EXTENSION, DLL_PREFIX};
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::ptr;
+use std::thread;
+
+use libloading::{Library, Symbol};
+
+/// What a plugin does, from the prefix of its file name
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// `provider-<name>`: answers prompts, selected with `CM_PROVIDER=plugin:<name>`
+    Provider,
+    /// `transform-<name>`: rewrites every message
+    Transform,
+    /// `validate-<name>`: accepts or rejects every message
+    Validator,
+}
+
+impl Kind {
+    const PREFIXES: &'static [(Kind, &'static str)] =
+        &[(Kind::Provider, "provider-"), (Kind::Transform, "transform-"), (Kind::Validator, "validate-")];
+}
+
+/// How a plugin is run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// A program, in any language, getting its input on stdin
+    Program,
+    /// A shared library (`.so`, `.dylib` or `.dll`) exporting `cm_plugin_run` and `cm_plugin_free`
+    Library,
+}
+
+/// `int cm_plugin_run(const char *input, char **output)`, returning 0 on success
+type RunFn = unsafe extern "C" fn(*const c_char, *mut *mut c_char) -> c_int;
+
+/// `void cm_plugin_free(char *output)`, releasing what `cm_plugin_run` wrote
+type FreeFn = unsafe extern "C" fn(*mut c_char);
+
+/// A program or shared library in the plugin directory
+///
+/// Programs can be written in any language and can't crash `cm`. Each gets
+/// its input on stdin and answers on stdout; a non-zero exit is a failure or,
+/// for validators, a rejection. Libraries skip the process start and get the
+/// same input and answer the same way through `cm_plugin_run`, but run inside
+/// `cm`, so a crashing library takes it down.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plugin {
+    pub kind: Kind,
+    pub name: String,
+    pub path: PathBuf,
+    pub format: Format,
+}
+
+/// The plugins in `dir`, ordered by file name
+///
+/// Files without a known prefix, or that are neither executable nor a shared
+/// library, are ignored. Libraries may carry the platform's `lib` prefix and
+/// an underscore after the kind, so cargo's `libtransform_upper.so` works as built.
+pub fn discover(dir: &Path) -> Vec<Plugin> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut plugins: Vec<Plugin> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let format = if path.is_file() && path.extension().is_some_and(|ext| ext == DLL_EXTENSION) {
+                Format::Library
+            } else if is_executable(&path) {
+                Format::Program
+            } else {
+                return None;
+            };
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let mut stem = Path::new(&file_name).file_stem()?.to_string_lossy().into_owned();
+            if format == Format::Library {
+                stem = stem.strip_prefix(DLL_PREFIX).unwrap_or(&stem).to_string();
+            }
+            let (kind, name) = Kind::PREFIXES.iter().find_map(|(kind, prefix)| {
+                let name = match format {
+                    Format::Program => stem.strip_prefix(prefix),
+                    Format::Library => stem
+                        .strip_prefix(prefix)
+                        .or_else(|| stem.strip_prefix(prefix.trim_end_matches('-'))?.strip_prefix('_')),
+                };
+                name.map(|name| (*kind, name.to_string()))
+            })?;
+            Some(Plugin { kind, name, path, format })
+        })
+        .filter(|plugin| !plugin.name.is_empty())
+        .collect();
+    plugins.sort_by(|a, b| a.path.cmp(&b.path));
+    log::debug!("{} plugin(s) in {}", plugins.len(), dir.display());
+    plugins
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file() && path.extension().is_some_and(|ext| ext == "exe" || ext == "bat" || ext == "cmd")
+}
+
+impl Plugin {
+    /// Run the plugin with `input`, returning its answer, or why it failed
+    pub fn run(&self, input: &str) -> Result<String, String> {
+        match self.format {
+            Format::Program => self.run_program(input),
+            Format::Library => self.run_library(input),
+        }
+    }
+
+    fn run_program(&self, input: &str) -> Result<String, String> {
+        let mut child = Command::new(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| e.to_string())?;
+        // Written meanwhile, so a plugin that answers while it reads can't fill
+        // its stdout pipe and wait on us. It may also exit without reading
+        // everything; its exit status says what happened.
+        let writer = child.stdin.take().map(|mut stdin| {
+            let input = input.to_string();
+            thread::spawn(move || {
+                let _ = stdin.write_all(input.as_bytes());
+            })
+        });
+        let output = child.wait_with_output().map_err(|e| e.to_string())?;
+        if let Some(writer) = writer {
+            let _ = writer.join();
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        log::debug!("Plugin {} ({}) -> {}", self.name, self.path.display(), output.status);
+        if output.status.success() {
+            return Ok(stdout);
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = [stdout.as_str(), stderr.trim()]
+            .into_iter()
+            .find_map(|text| text.lines().find(|line| !line.trim().is_empty()))
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("exited with {}", output.status));
+        Err(reason)
+    }
+
+    /// Load the library and call `cm_plugin_run`; what it writes to `output`
+    /// is the answer, or on a non-zero return the reason
+    fn run_library(&self, input: &str) -> Result<String, String> {
+        let input = CString::new(input).map_err(|e| e.to_string())?;
+        // SAFETY: libraries are only loaded from the user's own plugin
+        // directory, and both functions are called with the documented signatures.
+        // `output` is read before it is handed back to the library to free.
+        let (code, answer) = unsafe {
+            let library = Library::new(&self.path).map_err(|e| e.to_string())?;
+            let run: Symbol<RunFn> = library.get(b"cm_plugin_run\0").map_err(|e| e.to_string())?;
+            let free: Symbol<FreeFn> = library.get(b"cm_plugin_free\0").map_err(|e| e.to_string())?;
+            let mut output: *mut c_char = ptr::null_mut();
+            let code = run(input.as_ptr(), &mut output);
+            let answer = if output.is_null() {
+                String::new()
+            } else {
+                let text = CStr::from_ptr(output).to_string_lossy().trim().to_string();
+                free(output);
+                text
+            };
+            (code, answer)
+        };
+        log::debug!("Plugin {} ({}) -> {}", self.name, self.path.display(), code);
+        if code == 0 {
+            return Ok(answer);
+        }
+        Err(answer
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("returned {}", code)))
+    }
+}
+
+/// The provider plugin called `name`
+pub fn provider<'a>(plugins: &'a [Plugin], name: &str) -> Option<&'a Plugin> {
+    plugins.iter().find(|p| p.kind == Kind::Provider && p.name == name)
+}
+
+/// Plugins of `kind`, in the order they run
+pub fn of_kind(plugins: &[Plugin], kind: Kind) -> impl Iterator<Item = &Plugin> {
+    plugins.iter().filter(move |p| p.kind == kind)
+}
+
+/// What every validator has against `message`, as `(plugin, reason)`
+pub fn rejections(plugins: &[Plugin], message: &str) -> Vec<(String, String)> {
+    of_kind(plugins, Kind::Validator)
+        .filter_map(|plugin| plugin.run(message).err().map(|reason| (plugin.name.clone(), reason)))
+        .collect()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::harness::TempRepo;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn script(dir: &Path, name: &str, body: &str) {
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_plugins() {
+        let repo = TempRepo::new();
+        let dir = repo.path.join("plugins");
+        fs::create_dir(&dir).unwrap();
+        script(&dir, "transform-upper", "tr a-z A-Z");
+        script(&dir, "validate-ticket", "grep -q 'Refs:' || { echo 'no ticket reference'; exit 1; }");
+        script(&dir, "provider-echo.sh", "cat");
+        fs::write(dir.join("transform-not-executable"), "").unwrap();
+        fs::write(dir.join("README"), "").unwrap();
+
+        let plugins = discover(&dir);
+        let names: Vec<(Kind, &str)> = plugins.iter().map(|p| (p.kind, p.name.as_str())).collect();
+        assert_eq!(
+            names,
+            vec![(Kind::Provider, "echo"), (Kind::Transform, "upper"), (Kind::Validator, "ticket")]
+        );
+        assert!(provider(&plugins, "echo").is_some());
+        assert!(provider(&plugins, "upper").is_none());
+
+        assert_eq!(plugins[1].run("feat: add x").as_deref(), Ok("FEAT: ADD X"));
+        assert_eq!(
+            rejections(&plugins, "feat: add x"),
+            vec![("ticket".to_string(), "no ticket reference".to_string())]
+        );
+        assert!(rejections(&plugins, "feat: add x\n\nRefs: #1").is_empty());
+        assert!(discover(&dir.join("missing")).is_empty());
+
+        // More than a pipe holds, echoed back while it's still being written
+        let large = "x".repeat(256 * 1024);
+        assert_eq!(plugins[0].run(&large).map(|out| out.len()), Ok(large.len()));
+    }
+
+    /// Source of a library plugin: uppercases its input, and rejects empty input
+    const LIBRARY: &str = r#"
+use std::ffi::{c_char, c_int, CStr, CString};
+
+#[no_mangle]
+pub unsafe extern "C" fn cm_plugin_run(input: *const c_char, output: *mut *mut c_char) -> c_int {
+    let input = CStr::from_ptr(input).to_string_lossy();
+    let (code, text) = if input.is_empty() { (1, "empty message".to_string()) } else { (0, input.to_uppercase()) };
+    *output = CString::new(text).unwrap().into_raw();
+    code
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cm_plugin_free(output: *mut c_char) {
+    drop(CString::from_raw(output));
+}
+"#;
+
+    #[test]
+    fn test_library_plugins() {
+        let repo = TempRepo::new();
+        let dir = repo.path.join("plugins");
+        fs::create_dir(&dir).unwrap();
+        let source = repo.path.join("upper.rs");
+        fs::write(&source, LIBRARY).unwrap();
+        let library = dir.join(format!("{}transform_upper.{}", DLL_PREFIX, DLL_EXTENSION));
+        let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+        let status = Command::new(rustc)
+            .args(["--crate-type", "cdylib", "--edition", "2021", "-o"])
+            .arg(&library)
+            .arg(&source)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        fs::write(dir.join(format!("validate-broken.{}", DLL_EXTENSION)), "not a library").unwrap();
+
+        let plugins = discover(&dir);
+        let names: Vec<(Kind, &str, Format)> = plugins.iter().map(|p| (p.kind, p.name.as_str(), p.format)).collect();
+        assert_eq!(names, vec![(Kind::Transform, "upper", Format::Library), (Kind::Validator, "broken", Format::Library)]);
+        let upper = &plugins[0];
+        assert_eq!(upper.run("feat: add x").as_deref(), Ok("FEAT: ADD X"));
+        assert_eq!(upper.run(""), Err("empty message".to_string()));
+        // A file that can't be loaded fails like a plugin that can't start
+        assert_eq!(rejections(&plugins, "feat: add x").len(), 1);
+    }
+}
@@ -0,0 +1,142 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::PolicyOptions;
+use crate::diff::FileDiff;
+use crate::i18n::{tf, Msg};
+
+/// Extensions of files expected to start with a license header
+const SOURCE_EXTENSIONS: &[&str] = &[
+    "rs", "py", "js", "jsx", "ts", "tsx", "go", "java", "kt", "c", "h", "cc", "cpp", "hpp", "cs", "swift", "rb",
+    "php", "scala", "sh",
+];
+
+/// How many lines at the top of a file may hold the license header
+const HEADER_LINES: usize = 15;
+
+/// A repository rule checked before any tokens are spent
+///
+/// Checks only warn: the commit may still be fine, but a reviewer would
+/// likely send it back.
+pub trait Check {
+    /// Warnings about one changed file
+    fn check(&self, file: &FileDiff) -> Vec<String>;
+}
+
+/// New source files must carry the configured license marker near the top
+struct LicenseHeader(String);
+
+impl Check for LicenseHeader {
+    fn check(&self, file: &FileDiff) -> Vec<String> {
+        let is_source = SOURCE_EXTENSIONS.contains(&extension(&file.path).as_str());
+        if !file.is_new() || !is_source || file.added_lines().take(HEADER_LINES).any(|line| line.contains(&self.0)) {
+            return Vec::new();
+        }
+        vec![tf(Msg::LicenseHeaderMissing, &[&file.path, &self.0])]
+    }
+}
+
+/// Files under the repository root must stay under a size limit, in bytes
+struct MaxFileSize {
+    limit: u64,
+    root: PathBuf,
+}
+
+impl Check for MaxFileSize {
+    fn check(&self, file: &FileDiff) -> Vec<String> {
+        if file.is_deleted() {
+            return Vec::new();
+        }
+        match fs::metadata(self.root.join(&file.path)) {
+            Ok(meta) if meta.len() > self.limit => {
+                vec![tf(Msg::FileTooLarge, &[&file.path, &human_size(meta.len()), &human_size(self.limit)])]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Files with these extensions must not be committed at all
+struct DisallowedExtensions(Vec<String>);
+
+impl Check for DisallowedExtensions {
+    fn check(&self, file: &FileDiff) -> Vec<String> {
+        let ext = extension(&file.path);
+        if file.is_deleted() || ext.is_empty() || !self.0.iter().any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(&ext)) {
+            return Vec::new();
+        }
+        vec![tf(Msg::DisallowedExtension, &[&file.path, &ext])]
+    }
+}
+
+/// The checks enabled by `options`, for a repository at `root`
+pub fn checks(options: &PolicyOptions, root: &Path) -> Vec<Box<dyn Check>> {
+    let mut checks: Vec<Box<dyn Check>> = Vec::new();
+    if let Some(marker) = &options.license_header {
+        checks.push(Box::new(LicenseHeader(marker.clone())));
+    }
+    if let Some(limit) = options.max_file_size {
+        checks.push(Box::new(MaxFileSize {
+            limit,
+            root: root.to_path_buf(),
+        }));
+    }
+    if !options.disallowed_extensions.is_empty() {
+        checks.push(Box::new(DisallowedExtensions(options.disallowed_extensions.clone())));
+    }
+    checks
+}
+
+/// Every warning `checks` raise for `files`
+pub fn warnings(checks: &[Box<dyn Check>], files: &[FileDiff]) -> Vec<String> {
+    files.iter().flat_map(|file| checks.iter().flat_map(move |check| check.check(file))).collect()
+}
+
+fn extension(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// `1536` as `1.5 KB`
+fn human_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::parse_diff;
+
+    #[test]
+    fn test_warnings() {
+        let diff = "diff --git a/src/new.rs b/src/new.rs\nnew file mode 100644\n--- /dev/null\n+++ b/src/new.rs\n@@ -0,0 +1,2 @@\n+fn main() {}\n+\n\
+                    diff --git a/src/lib.rs b/src/lib.rs\nnew file mode 100644\n--- /dev/null\n+++ b/src/lib.rs\n@@ -0,0 +1 @@\n+// SPDX-License-Identifier: MIT\n\
+                    diff --git a/build/app.EXE b/build/app.EXE\nnew file mode 100644\nBinary files /dev/null and b/build/app.EXE differ\n";
+        let options = PolicyOptions {
+            license_header: Some("SPDX-License-Identifier".to_string()),
+            max_file_size: None,
+            disallowed_extensions: vec![".exe".to_string()],
+        };
+        let warnings = warnings(&checks(&options, Path::new(".")), &parse_diff(diff));
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("src/new.rs"));
+        assert!(warnings[1].contains("build/app.EXE"));
+
+        assert_eq!(human_size(512), "512 B");
+        assert_eq!(human_size(1536), "1.5 KB");
+        assert_eq!(human_size(5 * 1024 * 1024), "5.0 MB");
+    }
+}
@@ -0,0 +1,227 @@
+use crate::config::{Config, LintOptions, Transform};
+use crate::emoji::{add_emoji, remove_emoji_prefix};
+use crate::i18n::{tf, Msg};
+use crate::lint;
+use crate::message::CommitMessage;
+use crate::plugin::{self, Kind};
+use crate::ui;
+
+/// Tags reasoning models wrap their thinking in
+const REASONING_TAGS: &[&str] = &["think", "thinking", "reasoning"];
+
+/// Where a message came from, which decides the steps it goes through
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Origin {
+    /// Parsed from the model's response
+    Model,
+    /// Written without the AI, such as a dependency bump
+    Local,
+    /// Written or edited by the user
+    User,
+}
+
+impl Transform {
+    /// Whether this step applies to messages from `origin`
+    ///
+    /// What the user wrote keeps its type, scope and layout; model output
+    /// is checked by the candidate filters rather than linted.
+    fn applies_to(self, origin: Origin) -> bool {
+        match self {
+            Transform::StripReasoning | Transform::NormalizeType | Transform::Scope | Transform::BodyStyle => {
+                origin == Origin::Model
+            }
+            Transform::Plugins | Transform::WrapBody | Transform::Trailers | Transform::Emoji => true,
+            Transform::Lint => origin == Origin::User,
+        }
+    }
+}
+
+/// The `CM_POSTPROCESS` chain with what its steps need beyond the config
+///
+/// Every message goes through the same pipeline whether it was generated,
+/// written locally or edited, so accepting and editing give the same result.
+#[derive(Clone, Copy)]
+pub struct Pipeline<'a> {
+    pub config: &'a Config,
+    /// Scope every generated message gets (`CM_SCOPE_DEPTH`)
+    pub scope: Option<&'a str>,
+    /// Reason for marking messages breaking, if public items were removed
+    pub breaking_note: Option<&'a str>,
+    pub footer_refs: &'a [String],
+    /// Rendered `CM_FOOTER`, added under the body
+    pub footer: Option<&'a str>,
+}
+
+impl<'a> Pipeline<'a> {
+    /// A pipeline without scope or trailers
+    pub fn new(config: &'a Config) -> Self {
+        Self {
+            config,
+            scope: None,
+            breaking_note: None,
+            footer_refs: &[],
+            footer: None,
+        }
+    }
+
+    fn enabled(&self, transform: Transform) -> bool {
+        self.config.postprocess.contains(&transform)
+    }
+
+    /// The raw response, ready to be parsed
+    pub fn response(&self, response: &str) -> String {
+        if self.enabled(Transform::StripReasoning) {
+            strip_reasoning(response)
+        } else {
+            response.to_string()
+        }
+    }
+
+    /// Run the steps that apply to `origin` on `message`, in the configured order
+    pub fn apply(&self, message: &mut CommitMessage, origin: Origin) {
+        let config = self.config;
+        for transform in config.postprocess.iter().filter(|t| t.applies_to(origin)) {
+            match transform {
+                // Applied to the text before parsing, see `response`
+                Transform::StripReasoning => {}
+                Transform::NormalizeType => message.normalize_type(),
+                Transform::Scope => {
+                    message.apply_scope_mode(config.scope_mode);
+                    if let Some(scope) = self.scope {
+                        message.set_scope(scope);
+                    }
+                }
+                Transform::BodyStyle => {
+                    if config.subject_only {
+                        message.body = None;
+                    } else if let Some(style) = config.body_style {
+                        message.apply_body_style(style);
+                    }
+                }
+                Transform::Plugins => {
+                    for plugin in plugin::of_kind(&config.plugins, Kind::Transform) {
+                        match plugin.run(&message.to_git_message()) {
+                            Ok(text) => match CommitMessage::parse_edited(&text) {
+                                Some(rewritten) => *message = rewritten,
+                                None => ui::warn("⚠️  ", tf(Msg::PluginFailed, &[&plugin.name, &"it printed nothing"])),
+                            },
+                            Err(reason) => ui::warn("⚠️  ", tf(Msg::PluginFailed, &[&plugin.name, &reason])),
+                        }
+                    }
+                }
+                Transform::WrapBody => message.wrap_body(),
+                Transform::Trailers => {
+                    if let Some(note) = self.breaking_note {
+                        message.mark_breaking(note);
+                    }
+                    message.add_refs_footer(self.footer_refs);
+                    if let Some(footer) = self.footer {
+                        message.add_footer(footer);
+                    }
+                }
+                Transform::Emoji => {
+                    if config.emoji_enabled {
+                        message.subject = add_emoji(&message.subject, config.emoji_placement);
+                    }
+                }
+                Transform::Lint => {
+                    let text = message.to_git_message();
+                    for problem in lint::check(&text, &LintOptions::from_env()) {
+                        ui::warn("⚠️  ", tf(Msg::EditedProblem, &[&problem]));
+                    }
+                    for (name, reason) in plugin::rejections(&config.plugins, &text) {
+                        ui::warn("⚠️  ", tf(Msg::EditedProblem, &[&format!("{}: {}", name, reason)]));
+                    }
+                }
+            }
+        }
+    }
+
+    /// `message` without the emoji the pipeline adds, as the user edits it
+    /// and as learned preferences compare it
+    pub fn plain(&self, message: &CommitMessage) -> CommitMessage {
+        let mut plain = message.clone();
+        if self.config.emoji_enabled && self.enabled(Transform::Emoji) {
+            plain.subject = remove_emoji_prefix(&plain.subject);
+        }
+        plain
+    }
+}
+
+/// `response` without the `<think>…</think>` blocks reasoning models put
+/// before their answer; an unclosed block runs to the end
+fn strip_reasoning(response: &str) -> String {
+    let mut kept = String::new();
+    let mut rest = response;
+    while let Some((start, tag)) = REASONING_TAGS
+        .iter()
+        .filter_map(|tag| rest.find(&format!("<{}>", tag)).map(|start| (start, *tag)))
+        .min()
+    {
+        kept.push_str(&rest[..start]);
+        let close = format!("</{}>", tag);
+        rest = match rest[start..].find(&close) {
+            Some(end) => &rest[start + end + close.len()..],
+            None => "",
+        };
+    }
+    kept.push_str(rest);
+    kept.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness;
+
+    #[test]
+    fn test_strip_reasoning() {
+        assert_eq!(strip_reasoning("<think>\nIt adds a flag.\n</think>\n\nfeat: add --dry-run"), "feat: add --dry-run");
+        assert_eq!(
+            strip_reasoning("feat: add x\n<reasoning>why</reasoning>\n\nBody"),
+            "feat: add x\n\n\nBody"
+        );
+        assert_eq!(strip_reasoning("fix: escape <b> in titles"), "fix: escape <b> in titles");
+        assert_eq!(strip_reasoning("<thinking>cut off"), "");
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let mut config = harness::config();
+        config.emoji_enabled = true;
+        let refs = vec!["#12".to_string()];
+        let pipeline = Pipeline {
+            scope: Some("api"),
+            footer_refs: &refs,
+            ..Pipeline::new(&config)
+        };
+        let body = "A sentence that goes on and on until it is far too long for a single line of git log.";
+        let message = || CommitMessage {
+            subject: "feature: add pagination".to_string(),
+            body: Some(body.to_string()),
+        };
+
+        let mut generated = message();
+        pipeline.apply(&mut generated, Origin::Model);
+        assert_eq!(generated.subject, "✨ feat(api): add pagination");
+        assert_eq!(
+            generated.body.as_deref(),
+            Some("A sentence that goes on and on until it is far too long for a single\nline of git log.\n\nRefs: #12")
+        );
+        assert_eq!(pipeline.plain(&generated).subject, "feat(api): add pagination");
+
+        // The user's type and scope stay; footers and emoji don't double
+        let mut edited = message();
+        pipeline.apply(&mut edited, Origin::User);
+        pipeline.apply(&mut edited, Origin::User);
+        assert_eq!(edited.subject, "feature: add pagination");
+        assert!(edited.body.as_deref().unwrap().ends_with("line of git log.\n\nRefs: #12"));
+
+        // Only the configured steps, in their order
+        config.postprocess = vec![Transform::NormalizeType];
+        let mut generated = message();
+        Pipeline::new(&config).apply(&mut generated, Origin::Model);
+        assert_eq!(generated.subject, "feat: add pagination");
+        assert_eq!(generated.body.as_deref(), Some(body));
+    }
+}
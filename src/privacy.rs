@@ -0,0 +1,139 @@
+use std::collections::BTreeSet;
+
+/// Scrubs identifying details from prompt context before it leaves the machine
+#[derive(Debug, Default)]
+pub struct Privacy {
+    redact_people: bool,
+    /// `(directory name, replacement)`, longest names first
+    directories: Vec<(String, String)>,
+}
+
+impl Privacy {
+    /// `redact_people` strips names and emails; `hash_paths` hashes the directory
+    /// names of `paths` (file names are kept so the model still sees file types)
+    pub fn new(redact_people: bool, hash_paths: bool, paths: &[String]) -> Self {
+        let mut directories: Vec<(String, String)> = if hash_paths {
+            paths
+                .iter()
+                .flat_map(|p| {
+                    let mut parts: Vec<&str> = p.split('/').collect();
+                    parts.pop();
+                    parts
+                })
+                .filter(|d| !d.is_empty())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .map(|d| (d.to_string(), format!("d{:06x}", fnv1a(d) & 0xff_ffff)))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        // Replace longer names first so `api` doesn't clobber part of `api-client`
+        directories.sort_by_key(|d| std::cmp::Reverse(d.0.len()));
+
+        Self {
+            redact_people,
+            directories,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.redact_people || !self.directories.is_empty()
+    }
+
+    /// Apply every enabled transformation to a piece of prompt context
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = if self.redact_people {
+            redact_people(text)
+        } else {
+            text.to_string()
+        };
+        for (name, hashed) in &self.directories {
+            text = replace_directory(&text, name, hashed);
+        }
+        text
+    }
+}
+
+/// Replace `name/` where `name` is a whole path component
+fn replace_directory(text: &str, name: &str, hashed: &str) -> String {
+    let needle = format!("{}/", name);
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(pos) = rest.find(&needle) {
+        let before = rest[..pos].chars().next_back();
+        let boundary = before.is_none_or(|c| !(c.is_alphanumeric() || c == '_' || c == '-' || c == '.'));
+        out.push_str(&rest[..pos]);
+        if boundary {
+            out.push_str(hashed);
+            out.push('/');
+        } else {
+            out.push_str(&needle);
+        }
+        rest = &rest[pos + needle.len()..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Remove email addresses and the names attached to them in trailers like `Signed-off-by:`
+fn redact_people(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            let is_trailer = trimmed
+                .split_once(": ")
+                .is_some_and(|(key, value)| key.ends_with("-by") && value.contains('@'));
+            if is_trailer {
+                let (key, _) = trimmed.split_once(": ").unwrap_or_default();
+                format!("{}: [redacted]", key)
+            } else {
+                line.split(' ')
+                    .map(|word| if is_email(word) { "[redacted]" } else { word })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        + if text.ends_with('\n') { "\n" } else { "" }
+}
+
+fn is_email(word: &str) -> bool {
+    let word = word.trim_matches(|c: char| matches!(c, '<' | '>' | '(' | ')' | ',' | ';' | '"'));
+    word.split_once('@')
+        .is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.') && !domain.starts_with('.'))
+}
+
+/// 32-bit FNV-1a, stable across runs and platforms
+fn fnv1a(text: &str) -> u32 {
+    text.bytes()
+        .fold(0x811c_9dc5, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_people() {
+        let text = "fix: x\n\nReported by jane@corp.com\nSigned-off-by: Jane Doe <jane@corp.com>\n";
+        assert_eq!(
+            redact_people(text),
+            "fix: x\n\nReported by [redacted]\nSigned-off-by: [redacted]\n"
+        );
+    }
+
+    #[test]
+    fn test_hash_directories() {
+        let paths = vec!["src/acme/billing.rs".to_string()];
+        let privacy = Privacy::new(false, true, &paths);
+        let out = privacy.apply("diff --git a/src/acme/billing.rs b/src/acme/billing.rs\n+use nosrc/acme;");
+        assert!(!out.contains("acme/billing"));
+        assert!(out.contains("/billing.rs"));
+        assert!(out.contains("nosrc/"));
+        assert_eq!(out, privacy.apply("diff --git a/src/acme/billing.rs b/src/acme/billing.rs\n+use nosrc/acme;"));
+    }
+}
@@ -1,10 +1,125 @@
-/// Build the prompt for AI commit message generation
-pub fn build_commit_prompt(diff_content: &str, commit_history: &str, diff_stat: &str) -> String {
+use crate::config::{BodyStyle, ContextSections, PromptStyle, ScopeMode};
+use crate::context::GitContext;
+use crate::heuristics::TypeHint;
+use crate::vcs::LogEntry;
+
+/// A prompt split into static instructions and per-commit context
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prompt {
+    /// Instructions sent as the system prompt
+    pub system: String,
+    /// Repository context sent as the user message
+    pub user: String,
+}
+
+/// Closing line of the user message, after all the context
+const GENERATE_NOW: &str = "\nGenerate the commit message now:";
+
+impl Prompt {
+    /// Add a per-commit `section` to the user message, ahead of its closing line
+    ///
+    /// Anything that varies between commits goes here rather than into the
+    /// system prompt, which stays identical across runs so provider-side
+    /// prompt caches keep matching.
+    pub fn add_context(&mut self, section: &str) {
+        let at = self.user.rfind(GENERATE_NOW).unwrap_or(self.user.len());
+        self.user.insert_str(at, &format!("\n{}\n", section.trim_end()));
+    }
+}
+
+/// Settings that change the built-in instructions
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PromptOptions {
+    pub style: PromptStyle,
+    pub scope_mode: ScopeMode,
+    /// Context sections to include in the user message
+    pub context: ContextSections,
+    /// Body layout that overrides the style preset's
+    pub body_style: Option<BodyStyle>,
+    /// Ask for the subject line alone, with shorter instructions
+    pub subject_only: bool,
+}
+
+/// The Conventional Commits summary included in the instructions
+pub const CONVENTIONAL_COMMITS_SPEC: &str = include_str!("../conventional_commits.txt");
+
+/// Body requirements for each style preset
+pub fn body_requirements(style: PromptStyle) -> &'static str {
+    match style {
+        PromptStyle::Balanced => {
+            r#"### Body (OPTIONAL but recommended for complex changes)
+- Explain WHAT changed and WHY (not HOW - the code shows that)
+- Wrap at 72 characters
+- Use bullet points for multiple changes"#
+        }
+        PromptStyle::Concise => {
+            r#"### Body (OMIT)
+- Do not write a body; answer "none"
+- Keep the subject as short as possible while staying specific"#
+        }
+        PromptStyle::Detailed => {
+            r#"### Body (REQUIRED)
+- Always write a body, even for small changes
+- One bullet point ("- ") per logical change, starting with a verb
+- Wrap at 72 characters"#
+        }
+        PromptStyle::Explanatory => {
+            r#"### Body (REQUIRED)
+- Focus on WHY: the problem, the motivation, and any trade-offs made
+- Mention what was broken or missing before this change
+- Write short prose paragraphs, wrapped at 72 characters"#
+        }
+    }
+}
+
+/// Body rules for `style`, with the layout `body_style` asks for taking precedence
+pub fn body_section(style: PromptStyle, body_style: Option<BodyStyle>) -> String {
+    let layout = match body_style {
+        None => return body_requirements(style).to_string(),
+        // A preset that already omits the body needs nothing more
+        Some(BodyStyle::Omit) => return body_requirements(PromptStyle::Concise).to_string(),
+        Some(BodyStyle::Bullets) => "- Layout: bullet points only (\"- \"), one per logical change, no prose paragraphs",
+        Some(BodyStyle::Paragraphs) => "- Layout: short prose paragraphs only, no bullet points or lists",
+    };
+    // Asking for a layout means asking for a body, which the concise preset omits
+    let style = if style == PromptStyle::Concise { PromptStyle::Balanced } else { style };
+    format!("{}\n{}", body_requirements(style), layout)
+}
+
+/// Scope rule for each scope mode
+fn scope_requirement(mode: ScopeMode) -> &'static str {
+    match mode {
+        ScopeMode::Auto => "- Scope: optional, describes the affected component (e.g., auth, api, ui)",
+        ScopeMode::Dominant => {
+            "- Scope: optional, a SINGLE component (e.g., auth, api, ui); if several areas changed, use the one with most of the changes"
+        }
+        ScopeMode::Stack => {
+            "- Scope: optional; if several areas changed, list each one separated by commas without spaces (e.g., `feat(api,ui): ...`)"
+        }
+        ScopeMode::Omit => "- Scope: NEVER include a scope; use `type: description`",
+    }
+}
+
+/// The format the response is parsed in, which every set of instructions ends with
+pub fn response_format(subject_only: bool) -> &'static str {
+    if subject_only {
+        "## Response Format\nRespond in EXACTLY this format (no markdown, no extra text):\n\nSUBJECT: <your subject line here>"
+    } else {
+        "## Response Format\nRespond in EXACTLY this format (no markdown, no extra text):\n\n\
+         SUBJECT: <your subject line here>\nBODY: <your body here, or just \"none\" if not needed>"
+    }
+}
+
+/// Build the system instructions for commit message generation
+fn build_system_prompt(options: PromptOptions) -> String {
+    if options.subject_only {
+        return build_subject_prompt(options);
+    }
     format!(
         r#"You are an expert at writing clear, professional git commit messages following the Conventional Commits specification.
 
 ## Your Task
-Generate a commit message for the staged changes shown below.
+Generate a commit message for the staged changes provided by the user.
 
 ## Conventional Commits Specification
 {}
@@ -14,42 +129,387 @@ Generate a commit message for the staged changes shown below.
 ### Subject Line (REQUIRED)
 - Format: `type(scope): description` or `type: description`
 - Types: feat, fix, docs, style, refactor, test, chore, perf, ci, build, revert
-- Scope: optional, describes the affected component (e.g., auth, api, ui)
+{}
 - Description: imperative mood, lowercase, no period at end, max 72 chars
 - Be specific! Avoid vague words like "update", "fix issue", "changes"
 
-### Body (OPTIONAL but recommended for complex changes)
-- Explain WHAT changed and WHY (not HOW - the code shows that)
-- Wrap at 72 characters
-- Use bullet points for multiple changes
+{}
 
-## Context
+{}"#,
+        CONVENTIONAL_COMMITS_SPEC,
+        scope_requirement(options.scope_mode),
+        body_section(options.style, options.body_style),
+        response_format(false),
+    )
+}
 
-### Recent Commit History (for style reference)
-```
-{}
-```
+/// Shorter instructions for `--subject-only`: no specification summary and no body
+fn build_subject_prompt(options: PromptOptions) -> String {
+    format!(
+        r#"You are an expert at writing clear, professional git commit messages following the Conventional Commits specification.
+
+## Your Task
+Write a one-line commit message (a subject line, no body) for the staged changes provided by the user.
 
-### Change Statistics
-```
+## Subject Line
+- Format: `type(scope): description` or `type: description`
+- Types: feat, fix, docs, style, refactor, test, chore, perf, ci, build, revert
 {}
-```
+- Description: imperative mood, lowercase, no period at end, max 72 chars
+- Be specific! Avoid vague words like "update", "fix issue", "changes"
 
-### Actual Diff Content
-```diff
+{}"#,
+        scope_requirement(options.scope_mode),
+        response_format(true),
+    )
+}
+
+/// Build the prompt for AI commit message generation
+///
+/// History and change statistics come from `context`; `diff_content` is passed
+/// separately because it may have been trimmed or had files withheld.
+pub fn build_commit_prompt(
+    diff_content: &str,
+    context: &GitContext,
+    options: PromptOptions,
+    type_hint: Option<&TypeHint>,
+) -> Prompt {
+    let hint = type_hint
+        .map(|hint| {
+            if hint.advisory {
+                format!(
+                    "\n### Commit Type\nThe type is probably `{}`: {}. Use another type if the diff shows more than that.\n",
+                    hint.kind, hint.reason
+                )
+            } else {
+                format!("\n### Commit Type\nThe type MUST be `{}`: {}.\n", hint.kind, hint.reason)
+            }
+        })
+        .unwrap_or_default();
+
+    let mut user = String::from("## Context\n");
+    if options.context.history {
+        let history = if context.history.is_empty() { "(no previous commits)" } else { &context.history };
+        user.push_str(&format!(
+            "\n### Recent Commit History (for style reference)\n```\n{}\n```\n",
+            history
+        ));
+    }
+    if options.context.stat {
+        user.push_str(&format!("\n### Change Statistics\n```\n{}\n```\n", context.stat));
+    }
+    if options.context.diff {
+        user.push_str(&format!("\n### Actual Diff Content\n```diff\n{}\n```\n", diff_content));
+    }
+    user.push_str(&hint);
+    user.push_str(GENERATE_NOW);
+
+    Prompt {
+        system: build_system_prompt(options),
+        user,
+    }
+}
+
+/// Prompt section for a commit that concludes a conflicted merge
+pub fn merge_section(summary: &str, conflicts: &[String], theirs_diff: &str) -> String {
+    let mut section = format!(
+        "## Merge Commit\nThis commit concludes a merge: \"{}\".\n\
+         - Subject: `chore(merge): ` followed by the branches merged, e.g. `chore(merge): merge feature/login into main`\n\
+         - Body: one bullet per conflicted file saying how the conflict was resolved (which side was kept, or how both were combined)",
+        summary
+    );
+    if !conflicts.is_empty() {
+        section.push_str(&format!("\n\n### Conflicted Files\n{}", conflicts.join("\n")));
+    }
+    if !theirs_diff.trim().is_empty() {
+        section.push_str(&format!(
+            "\n\n### Resolution Compared to the Merged Branch\n```diff\n{}\n```",
+            theirs_diff.trim_end()
+        ));
+    }
+    section
+}
+
+/// Prompt section listing the public Rust items the change adds, removes or edits
+pub fn api_section(changes: &[String], breaking: bool) -> String {
+    let mut section = format!(
+        "## Public API Changes\n{}",
+        changes.iter().map(|change| format!("- {}", change)).collect::<Vec<_>>().join("\n")
+    );
+    if breaking {
+        section.push_str("\n\nRemoved and changed items break existing callers: mark the type with `!` (e.g. `feat(api)!:`) and end the body with a `BREAKING CHANGE:` footer saying what callers must change.");
+    }
+    section
+}
+
+/// Prompt section describing the packages the staged files belong to
+pub fn packages_section(packages: &[String]) -> String {
+    format!(
+        r#"## Affected Packages
 {}
-```
+- When the change stays within one package of a multi-package repository, its name is a good scope
+- A package marked [public API changed] exposes different items to its users; if existing callers would break, mark the commit breaking with `!`"#,
+        packages.iter().map(|package| format!("- {}", package)).collect::<Vec<_>>().join("\n")
+    )
+}
+
+/// Prompt section naming the scope CODEOWNERS assigns to every staged file
+pub fn owner_scope_section(scope: &str) -> String {
+    format!(
+        "## Scope\nCODEOWNERS assigns every staged file to `{}`. Use it as the scope unless the change clearly belongs to a narrower one.",
+        scope
+    )
+}
+
+/// Prompt section naming the scope `CM_SCOPE_DEPTH` derives from the staged paths
+pub fn depth_scope_section(scope: &str) -> String {
+    format!("## Scope\nEvery staged file is under `{}`. Use it as the scope: `type({}): description`.", scope, scope)
+}
+
+/// Prompt section listing issues that recent commits to the same files referenced
+pub fn related_issues_section(issues: &[String]) -> String {
+    format!(
+        r#"## Related Issues
+Recent commits to these files referenced these issues. If this change continues that work, mention the issue it relates to; otherwise ignore them.
+{}"#,
+        issues.iter().map(|issue| format!("- {}", issue)).collect::<Vec<_>>().join("\n")
+    )
+}
+
+/// Build the prompt asking for the one question about `diff` whose answer would best explain it
+pub fn build_question_prompt(diff: &str) -> Prompt {
+    let system = r#"A developer is about to commit the change below. The diff shows what changed but not why.
+
+## Rules
+- Ask the single question whose answer would best explain the motivation, e.g. "Why was the timeout raised to 90 seconds?"
+- One short sentence, ending with a question mark
+- Ask about the reason, not about what the code does
 
 ## Response Format
-Respond in EXACTLY this format (no markdown, no extra text):
+Respond with ONLY the question."#;
 
-SUBJECT: <your subject line here>
-BODY: <your body here, or just "none" if not needed>
+    Prompt {
+        system: system.to_string(),
+        user: diff.to_string(),
+    }
+}
 
-Generate the commit message now:"#,
-        include_str!("../conventional_commits.txt"),
-        if commit_history.is_empty() { "(no previous commits)" } else { commit_history },
-        diff_stat,
-        diff_content,
+/// Prompt section with the author's answer to the question about their change
+pub fn interview_section(question: &str, answer: &str) -> String {
+    format!(
+        r#"## Author's Explanation
+The author was asked: {}
+They answered: {}
+Use this as the reason for the change in the body, in your own words."#,
+        question, answer
+    )
+}
+
+/// Prompt section with the full message of the last commit to the same files
+pub fn previous_commit_section(hash: &str, message: &str) -> String {
+    format!(
+        r#"## Previous Commit to These Files
+The last commit that touched these files ({}) was:
+{}
+If this change continues that work, describe it consistently: same scope, same names for things, and the same issue reference. Don't repeat what that commit already did."#,
+        hash, message
     )
 }
+
+/// Section listing messages the user regenerated away from
+pub fn rejected_section(subjects: &[String]) -> String {
+    format!(
+        r#"## Rejected Messages
+The user rejected these messages for this change. Write a materially different alternative: change the angle, emphasis or level of detail, not just a few words. Keep the type only if it is clearly right.
+{}"#,
+        subjects.iter().map(|subject| format!("- {}", subject)).collect::<Vec<_>>().join("\n")
+    )
+}
+
+/// Build the prompt for translating an existing commit message
+pub fn build_translate_prompt(message: &str, language: &str) -> Prompt {
+    let system = format!(
+        r#"You translate git commit messages into {}.
+
+## Rules
+- Keep the Conventional Commits prefix (`type(scope)!:`) exactly as written, in English
+- Do not translate code identifiers, file paths, command names, or issue references
+- Keep trailers such as `Signed-off-by:` or `BREAKING CHANGE:` keys unchanged
+- Preserve the line structure: subject line, blank line, body paragraphs and bullets
+- If the message is already in {}, return it unchanged
+
+## Response Format
+Respond with ONLY the translated commit message (no markdown, no extra text)."#,
+        language, language
+    );
+
+    Prompt {
+        system,
+        user: message.to_string(),
+    }
+}
+
+/// Build the prompt for drafting review notes on a diff before it is pushed
+pub fn build_review_prompt(diff: &str, stat: &str) -> Prompt {
+    let system = r#"You are a careful code reviewer drafting notes on a change before it is pushed.
+
+## Rules
+- Only report problems you can point to in the diff; no praise, no summary of the change
+- Each note is one line: `- path:line: problem, and what to do about it`
+- Prefer a few real problems over many speculative ones; say "None found." for an empty section
+- Do not comment on formatting a formatter would fix
+
+## Response Format
+Respond with ONLY these sections, in this order, as plain text:
+Potential bugs
+Naming
+Missing tests
+Other"#;
+
+    Prompt {
+        system: system.to_string(),
+        user: format!("## Files Changed\n{}\n\n## Diff\n{}", stat, diff),
+    }
+}
+
+/// Build the prompt for explaining why code looks the way it does, from the commits that shaped it
+///
+/// `commits` are newest first; `code` is the current text of the lines asked about.
+pub fn build_why_prompt(target: &str, code: &str, commits: &[LogEntry]) -> Prompt {
+    let system = r#"You explain why a piece of code is the way it is, using the git history that shaped it.
+
+## Rules
+- Start with a one-paragraph answer: what the code does and the main reasons it looks like this today
+- Then list the turning points, oldest first, citing the short commit hash in brackets, e.g. [a1b2c3d]
+- Only state reasons the commit messages support; say so when the history doesn't explain something
+- Mention reverted or repeatedly changed behavior, since it usually marks a trade-off
+- Keep it under 250 words, plain text, no headings
+
+## Response Format
+Respond with ONLY the explanation."#;
+
+    let history = commits
+        .iter()
+        .map(|entry| format!("[{}] {} <{}>\n{}", entry.hash, entry.author_name, entry.author_email, entry.message))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Prompt {
+        system: system.to_string(),
+        user: format!("## Target\n{}\n\n## Current Code\n{}\n\n## Commits (newest first)\n{}", target, code, history),
+    }
+}
+
+/// Build the prompt for turning an old, free-form commit message into a Conventional Commits subject
+pub fn build_migrate_prompt(message: &str, diff: &str) -> Prompt {
+    let system = r#"You rewrite the subject lines of old git commits into Conventional Commits format.
+
+## Rules
+- Format: `type(scope): description`, with type one of feat, fix, docs, style, refactor, perf, test, build, ci, chore, revert
+- Keep the meaning of the original message; use the diff only to pick the type and scope
+- Keep issue references and identifiers from the original subject
+- Imperative mood, lowercase description, no trailing period, at most 72 characters
+
+## Response Format
+Respond with ONLY the new subject line (no markdown, no body, no extra text)."#;
+
+    Prompt {
+        system: system.to_string(),
+        user: format!("## Original Message\n{}\n\n## Diff\n{}", message, diff),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::truncate_diff;
+    use crate::harness::assert_golden;
+
+    #[test]
+    fn test_omitted_context_sections() {
+        let options = PromptOptions {
+            context: ContextSections {
+                history: false,
+                stat: false,
+                ..ContextSections::default()
+            },
+            ..PromptOptions::default()
+        };
+        let context = GitContext {
+            history: "abc feat: x".to_string(),
+            stat: "1 file changed".to_string(),
+            ..GitContext::default()
+        };
+        let prompt = build_commit_prompt("+fn main() {}", &context, options, None);
+        assert!(prompt.user.contains("+fn main() {}"));
+        assert!(!prompt.user.contains("abc feat: x"));
+        assert!(!prompt.user.contains("1 file changed"));
+    }
+
+    #[test]
+    fn test_subject_only_prompt() {
+        let options = PromptOptions {
+            subject_only: true,
+            ..PromptOptions::default()
+        };
+        let prompt = build_commit_prompt("+fn main() {}", &GitContext::default(), options, None);
+        assert!(prompt.system.contains("no body"));
+        assert!(!prompt.system.contains("BODY:"));
+        assert!(!prompt.system.contains(CONVENTIONAL_COMMITS_SPEC));
+        let full = build_commit_prompt("+fn main() {}", &GitContext::default(), PromptOptions::default(), None);
+        assert!(prompt.system.len() < full.system.len() / 2);
+    }
+
+    #[test]
+    fn test_body_section() {
+        assert_eq!(body_section(PromptStyle::Detailed, None), body_requirements(PromptStyle::Detailed));
+        let bullets = body_section(PromptStyle::Concise, Some(BodyStyle::Bullets));
+        assert!(bullets.starts_with("### Body (OPTIONAL"));
+        assert!(bullets.ends_with("no prose paragraphs"));
+        assert!(body_section(PromptStyle::Detailed, Some(BodyStyle::Omit)).contains("Do not write a body"));
+    }
+
+    #[test]
+    fn test_merge_section() {
+        let section = merge_section("Merge branch 'feature' into main", &["src/auth.rs".to_string()], "");
+        assert!(section.contains("Merge branch 'feature' into main"));
+        assert!(section.contains("### Conflicted Files\nsrc/auth.rs"));
+        assert!(!section.contains("```diff"));
+        assert!(section.contains("main\".\n- Subject: `chore(merge): `"));
+
+        let section = merge_section("Merge branch 'feature'", &[], "-old\n+new\n");
+        assert!(section.ends_with("### Resolution Compared to the Merged Branch\n```diff\n-old\n+new\n```"));
+    }
+
+    /// The user message for `diff`, with the summary computed as for staged changes
+    fn user_prompt(diff: &str) -> String {
+        let context = GitContext {
+            history: "abc1234 feat(auth): add login form\ndef5678 fix: handle empty config".to_string(),
+            ..GitContext::from_diff(diff.to_string(), String::new())
+        };
+        build_commit_prompt(&context.diff, &context, PromptOptions::default(), None).user
+    }
+
+    #[test]
+    fn test_prompt_snapshots() {
+        let rename = "diff --git a/src/util.rs b/src/helpers.rs\nsimilarity index 92%\nrename from src/util.rs\nrename to src/helpers.rs\n--- a/src/util.rs\n+++ b/src/helpers.rs\n@@ -1,3 +1,3 @@\n-pub fn trim(s: &str) -> &str {\n+pub fn trim_all(s: &str) -> &str {\n     s.trim()\n }\n";
+        assert_golden("prompt_rename.txt", &user_prompt(rename));
+
+        let binary = "diff --git a/assets/logo.png b/assets/logo.png\nindex 3b18e51..a0b1c2d 100644\nBinary files a/assets/logo.png and b/assets/logo.png differ\n";
+        assert_golden("prompt_binary.txt", &user_prompt(binary));
+
+        let multi_file = "diff --git a/src/auth.rs b/src/auth.rs\n--- a/src/auth.rs\n+++ b/src/auth.rs\n@@ -10,2 +10,3 @@ fn login() {\n     let token = fetch();\n+    cache(&token);\n     token\n\
+                          diff --git a/src/cache.rs b/src/cache.rs\nnew file mode 100644\n--- /dev/null\n+++ b/src/cache.rs\n@@ -0,0 +1,3 @@\n+pub fn cache(token: &str) {\n+    let _ = token;\n+}\n\
+                          diff --git a/README.md b/README.md\n--- a/README.md\n+++ b/README.md\n@@ -1 +1 @@\n-Logs in.\n+Logs in and caches the token.\n";
+        assert_golden("prompt_multi_file.txt", &user_prompt(multi_file));
+
+        // Large diffs are cut to the budget before they reach the prompt
+        let added: String = (0..2000).map(|i| format!("+    \"entry {}\",\n", i)).collect();
+        let huge = format!("diff --git a/data/list.rs b/data/list.rs\nnew file mode 100644\n--- /dev/null\n+++ b/data/list.rs\n@@ -0,0 +1,2000 @@\n{}", added);
+        let context = GitContext {
+            diff: truncate_diff(&huge, Some(20), 4000),
+            ..GitContext::from_diff(huge, String::new())
+        };
+        let prompt = build_commit_prompt(&context.diff, &context, PromptOptions::default(), None);
+        assert_golden("prompt_huge.txt", &prompt.user);
+    }
+}
@@ -0,0 +1,39 @@
+use super::{AiProvider, ProviderResult};
+use crate::audit;
+use crate::prompt::Prompt;
+use async_trait::async_trait;
+use std::path::PathBuf;
+
+/// Records every request the wrapped provider makes in the audit log (`CM_AUDIT`)
+///
+/// It sits under the response cache, so replayed responses, which never
+/// reach a model, aren't logged as requests.
+pub struct AuditedProvider {
+    inner: Box<dyn AiProvider>,
+    log: PathBuf,
+}
+
+impl AuditedProvider {
+    pub fn new(inner: Box<dyn AiProvider>, log: PathBuf) -> Self {
+        Self { inner, log }
+    }
+}
+
+#[async_trait]
+impl AiProvider for AuditedProvider {
+    async fn generate(&self, prompt: &Prompt) -> ProviderResult<String> {
+        let result = self.inner.generate(prompt).await;
+        let text = format!("{}\n{}", prompt.system, prompt.user);
+        let outcome = result.as_ref().map(String::as_str);
+        audit::generation(&self.log, self.inner.name(), self.inner.model(), &text, outcome);
+        result
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+}
@@ -0,0 +1,141 @@
+use super::{AiProvider, ProviderResult};
+use crate::audit::sha256;
+use crate::config::cache_dir;
+use crate::prompt::Prompt;
+use crate::ui;
+use crate::vault;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Appends of one process, so concurrent candidates don't drop each other's responses
+static APPEND: Mutex<()> = Mutex::new(());
+
+/// Responses previously received for one prompt
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Entry {
+    created: u64,
+    responses: Vec<String>,
+}
+
+/// Replays responses saved on disk for identical prompts
+///
+/// Each prompt keeps every response it received, in order. Within a run the
+/// n-th request for a prompt gets the n-th saved response, so concurrent
+/// candidates and regenerations still see different messages; once the saved
+/// ones are used up, requests go to the wrapped provider and are appended.
+pub struct CachedProvider {
+    inner: Box<dyn AiProvider>,
+    ttl_secs: u64,
+    /// Responses handed out so far in this run, per key
+    served: Mutex<HashMap<String, usize>>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn cache_path(key: &str) -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("responses").join(format!("{}.json", key)))
+}
+
+impl CachedProvider {
+    pub fn new(inner: Box<dyn AiProvider>, ttl_secs: u64) -> Self {
+        Self {
+            inner,
+            ttl_secs,
+            served: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// SHA-256 of everything that shapes the response, stable across Rust versions
+    fn key(&self, prompt: &Prompt) -> String {
+        let fields = [self.inner.name(), self.inner.model(), &prompt.system, &prompt.user];
+        sha256(&fields.join("\0"))
+    }
+
+    /// The saved entry for `key`, or an empty one if missing or expired
+    fn load(&self, key: &str) -> Entry {
+        cache_path(key)
+            .and_then(|path| vault::read(&path))
+            .and_then(|s| serde_json::from_str::<Entry>(&s).ok())
+            .filter(|entry| now().saturating_sub(entry.created) < self.ttl_secs)
+            .unwrap_or_else(|| Entry {
+                created: now(),
+                responses: Vec::new(),
+            })
+    }
+
+    fn append(&self, key: &str, response: &str) {
+        let Some(path) = cache_path(key) else {
+            return;
+        };
+        let _guard = APPEND.lock().unwrap_or_else(|e| e.into_inner());
+        let mut entry = self.load(key);
+        entry.responses.push(response.to_string());
+
+        if let Some(dir) = path.parent() {
+            self.prune(dir);
+        }
+        if let Ok(json) = serde_json::to_string(&entry) {
+            vault::write(&path, &json);
+        }
+    }
+
+    /// Delete entries older than the TTL so the directory doesn't grow forever
+    fn prune(&self, dir: &std::path::Path) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        let ttl = Duration::from_secs(self.ttl_secs);
+        for entry in entries.flatten() {
+            let expired = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > ttl);
+            if expired {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AiProvider for CachedProvider {
+    async fn generate(&self, prompt: &Prompt) -> ProviderResult<String> {
+        let key = self.key(prompt);
+        let index = {
+            let mut served = self.served.lock().unwrap();
+            let count = served.entry(key.clone()).or_insert(0);
+            *count += 1;
+            *count - 1
+        };
+
+        if let Some(response) = self.load(&key).responses.into_iter().nth(index) {
+            ui::status("♻️  ", "Using cached response");
+            log::debug!("Response cache hit {} (#{})", key, index + 1);
+            return Ok(response);
+        }
+
+        let response = self.inner.generate(prompt).await?;
+        self.append(&key, &response);
+        Ok(response)
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+}
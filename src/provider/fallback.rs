@@ -0,0 +1,118 @@
+use super::{AiProvider, ProviderError, ProviderResult};
+use crate::i18n::{tf, Msg};
+use crate::prompt::Prompt;
+use crate::ui;
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Switches to the provider's default model for the rest of the run once the
+/// configured one turns out not to exist
+pub struct ModelFallback {
+    primary: Box<dyn AiProvider>,
+    fallback: Box<dyn AiProvider>,
+    switched: AtomicBool,
+}
+
+impl ModelFallback {
+    pub fn new(primary: Box<dyn AiProvider>, fallback: Box<dyn AiProvider>) -> Self {
+        Self {
+            primary,
+            fallback,
+            switched: AtomicBool::new(false),
+        }
+    }
+
+    fn current(&self) -> &dyn AiProvider {
+        if self.switched.load(Ordering::Relaxed) {
+            self.fallback.as_ref()
+        } else {
+            self.primary.as_ref()
+        }
+    }
+}
+
+#[async_trait]
+impl AiProvider for ModelFallback {
+    async fn generate(&self, prompt: &Prompt) -> ProviderResult<String> {
+        if !self.switched.load(Ordering::Relaxed) {
+            match self.primary.generate(prompt).await {
+                Err(ProviderError::ModelNotFound(message)) => {
+                    log::debug!("{}", message);
+                    // Concurrent candidates can all fail; only the first says so
+                    if !self.switched.swap(true, Ordering::Relaxed) {
+                        ui::warn("🔀 ", tf(Msg::ModelFallback, &[&self.primary.model(), &self.fallback.model()]));
+                    }
+                }
+                other => return other,
+            }
+        }
+        self.fallback.generate(prompt).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.current().name()
+    }
+
+    fn model(&self) -> &str {
+        self.current().model()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed {
+        model: &'static str,
+        result: fn() -> ProviderResult<String>,
+    }
+
+    #[async_trait]
+    impl AiProvider for Fixed {
+        async fn generate(&self, _prompt: &Prompt) -> ProviderResult<String> {
+            (self.result)()
+        }
+
+        fn name(&self) -> &'static str {
+            "Test"
+        }
+
+        fn model(&self) -> &str {
+            self.model
+        }
+    }
+
+    #[tokio::test]
+    async fn test_model_fallback() {
+        let prompt = Prompt {
+            system: String::new(),
+            user: String::new(),
+        };
+        let provider = ModelFallback::new(
+            Box::new(Fixed {
+                model: "gone/model:free",
+                result: || Err(ProviderError::ModelNotFound("gone/model:free is not a valid model ID".into())),
+            }),
+            Box::new(Fixed {
+                model: "default/model",
+                result: || Ok("fix: something".into()),
+            }),
+        );
+        assert_eq!(provider.model(), "gone/model:free");
+        assert_eq!(provider.generate(&prompt).await.unwrap(), "fix: something");
+        assert_eq!(provider.model(), "default/model");
+
+        let failing = ModelFallback::new(
+            Box::new(Fixed {
+                model: "a",
+                result: || Err(ProviderError::AuthFailed("bad key".into())),
+            }),
+            Box::new(Fixed {
+                model: "b",
+                result: || Ok("unused".into()),
+            }),
+        );
+        assert!(matches!(failing.generate(&prompt).await, Err(ProviderError::AuthFailed(_))));
+        assert_eq!(failing.model(), "a");
+    }
+}
@@ -1,49 +1,241 @@
-use super::{AiProvider, ProviderError, ProviderResult};
+use super::{with_headers, AiProvider, ProviderError, ProviderResult};
+use crate::capture;
+use crate::config::{cache_dir, GeminiOptions, Sampling};
+use crate::prompt::Prompt;
 use async_trait::async_trait;
 use reqwest::Client;
 use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 const GEMINI_API_URL: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+const GEMINI_CACHE_URL: &str = "https://generativelanguage.googleapis.com/v1beta/cachedContents";
+
+/// Lifetime requested for cached system prompts
+const CONTEXT_CACHE_TTL_SECS: u64 = 3600;
+
+/// Harm categories accepted by `safetySettings`, keyed by their short names
+const HARM_CATEGORIES: &[(&str, &str)] = &[
+    ("harassment", "HARM_CATEGORY_HARASSMENT"),
+    ("hate", "HARM_CATEGORY_HATE_SPEECH"),
+    ("sexual", "HARM_CATEGORY_SEXUALLY_EXPLICIT"),
+    ("dangerous", "HARM_CATEGORY_DANGEROUS_CONTENT"),
+];
 
 pub struct GeminiProvider {
     api_key: String,
     model: String,
+    options: GeminiOptions,
+    sampling: Sampling,
     client: Client,
 }
 
 impl GeminiProvider {
-    pub fn new(api_key: String) -> Self {
+    pub fn new(api_key: String, model: String, options: GeminiOptions, sampling: Sampling, client: Client) -> Self {
         Self {
             api_key,
-            model: "gemini-flash-lite-latest".to_string(),
-            client: Client::new(),
+            model,
+            options,
+            sampling,
+            client,
+        }
+    }
+
+    /// Name of a `cachedContents` entry holding the system prompt, creating one if needed
+    ///
+    /// The entry is remembered on disk so consecutive runs share it until it
+    /// expires. Any failure (including prompts below the model's minimum
+    /// cacheable size) falls back to sending the instructions inline.
+    async fn cached_content(&self, system: &str) -> Option<String> {
+        let key = context_cache_key(&self.model, system);
+        let path = context_cache_path()?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+
+        if let Some(entry) = fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str::<Value>(&s).ok())
+        {
+            // Leave a minute of slack so the entry does not expire mid-request;
+            // a `null` name is a failed create, not retried until it expires
+            if entry["key"] == key && entry["expires"].as_u64().unwrap_or(0) > now + 60 {
+                return entry["name"].as_str().map(String::from);
+            }
+        }
+
+        // A failure is remembered too, so requests don't each pay for a
+        // create that can't succeed, such as for a prompt under the minimum size
+        let name = self.create_cached_content(system).await;
+        let entry = json!({ "key": key, "name": name, "expires": now + CONTEXT_CACHE_TTL_SECS });
+        let _ = fs::create_dir_all(path.parent()?);
+        let _ = fs::write(&path, entry.to_string());
+        name
+    }
+
+    /// Create a `cachedContents` entry for `system`, returning its name
+    async fn create_cached_content(&self, system: &str) -> Option<String> {
+        let body = json!({
+            "model": format!("models/{}", self.model),
+            "systemInstruction": { "parts": [{ "text": system }] },
+            "ttl": format!("{}s", CONTEXT_CACHE_TTL_SECS),
+        });
+        let response = with_headers(self.client.post(GEMINI_CACHE_URL), &self.options.headers)
+            .header("x-goog-api-key", &self.api_key)
+            .json(&body)
+            .send()
+            .await
+            .ok()?;
+        let status = response.status().as_u16();
+        let text = response.text().await.ok()?;
+        capture::record(self.name(), GEMINI_CACHE_URL, &body, status, &text);
+
+        if !(200..300).contains(&status) {
+            log::debug!("Context cache not created ({}): {}", status, text);
+            return None;
         }
+        Some(serde_json::from_str::<Value>(&text).ok()?["name"].as_str()?.to_string())
     }
 }
 
+/// The error for a failed request with HTTP `status` and response `text`
+fn status_error(status: u16, text: String) -> ProviderError {
+    match status {
+        401 | 403 => ProviderError::AuthFailed(text),
+        // Gemini reports both per-minute throttling and exhausted
+        // daily/billing quota as 429; only the latter is worth giving up on
+        429 if text.contains("exceeded your current quota") => ProviderError::QuotaExceeded(text),
+        429 => ProviderError::RateLimited {
+            retry_after: None,
+            request_id: None,
+        },
+        404 => ProviderError::ModelNotFound(text),
+        _ => ProviderError::ApiError { status, message: text },
+    }
+}
+
+fn context_cache_path() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("gemini_context_cache.json"))
+}
+
+fn context_cache_key(model: &str, system: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    model.hash(&mut hasher);
+    system.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Build `safetySettings` from `CM_GEMINI_SAFETY`
+///
+/// A bare threshold (e.g. `BLOCK_ONLY_HIGH`) applies to every category;
+/// otherwise entries are `category=threshold` with short or full category names.
+fn safety_settings(spec: &str) -> Vec<Value> {
+    let spec = spec.trim();
+    if !spec.contains('=') {
+        return HARM_CATEGORIES
+            .iter()
+            .map(|(_, category)| json!({ "category": category, "threshold": spec.to_uppercase() }))
+            .collect();
+    }
+
+    spec.split(',')
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(category, threshold)| {
+            let category = category.trim().to_lowercase();
+            let category = HARM_CATEGORIES
+                .iter()
+                .find(|(short, _)| *short == category)
+                .map(|(_, full)| full.to_string())
+                .unwrap_or_else(|| category.to_uppercase());
+            json!({ "category": category, "threshold": threshold.trim().to_uppercase() })
+        })
+        .collect()
+}
+
+/// Pull the generated text out of a `generateContent` response
+///
+/// Blocked prompts and candidates that stopped for safety reasons are reported
+/// as [`ProviderError::ContentBlocked`]; multi-part answers are concatenated.
+fn extract_text(json: &Value) -> ProviderResult<String> {
+    if let Some(reason) = json["promptFeedback"]["blockReason"].as_str() {
+        return Err(ProviderError::ContentBlocked(format!("prompt blocked ({})", reason)));
+    }
+
+    let candidate = &json["candidates"][0];
+    if candidate.is_null() {
+        return Err(ProviderError::ParseError("No candidates in response".to_string()));
+    }
+
+    let text: String = candidate["content"]["parts"]
+        .as_array()
+        .map(|parts| {
+            parts
+                .iter()
+                .filter(|part| !part["thought"].as_bool().unwrap_or(false))
+                .filter_map(|part| part["text"].as_str())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let finish_reason = candidate["finishReason"].as_str().unwrap_or("STOP");
+    if text.trim().is_empty() {
+        return Err(match finish_reason {
+            "STOP" | "MAX_TOKENS" => {
+                ProviderError::ParseError("Failed to extract text from response".to_string())
+            }
+            other => ProviderError::ContentBlocked(format!("finish reason {}", other)),
+        });
+    }
+
+    if matches!(finish_reason, "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT") {
+        return Err(ProviderError::ContentBlocked(format!("finish reason {}", finish_reason)));
+    }
+
+    Ok(text.trim().to_string())
+}
+
 #[async_trait]
 impl AiProvider for GeminiProvider {
-    async fn generate(&self, prompt: &str) -> ProviderResult<String> {
+    async fn generate(&self, prompt: &Prompt) -> ProviderResult<String> {
         let url = format!(
             "{}/{}:generateContent",
             GEMINI_API_URL, self.model
         );
 
-        let body = json!({
+        let cached = if self.options.prompt_cache {
+            self.cached_content(&prompt.system).await
+        } else {
+            None
+        };
+
+        let mut body = json!({
             "contents": [{
+                "role": "user",
                 "parts": [{
-                    "text": prompt
+                    "text": prompt.user
                 }]
             }],
             "generationConfig": {
-                "temperature": 0.7,
+                "temperature": self.sampling.temperature,
                 "maxOutputTokens": 500
             }
         });
+        if let Some(seed) = self.sampling.seed {
+            body["generationConfig"]["seed"] = json!(seed);
+        }
+
+        match &cached {
+            Some(name) => body["cachedContent"] = json!(name),
+            None => body["systemInstruction"] = json!({ "parts": [{ "text": prompt.system }] }),
+        }
+
+        if let Some(spec) = &self.options.safety {
+            body["safetySettings"] = Value::Array(safety_settings(spec));
+        }
 
-        let response = self
-            .client
-            .post(&url)
+        let start = Instant::now();
+        let response = with_headers(self.client.post(&url), &self.options.headers)
             .header("x-goog-api-key", &self.api_key)
             .json(&body)
             .send()
@@ -51,30 +243,36 @@ impl AiProvider for GeminiProvider {
             .map_err(|e| ProviderError::NetworkError(e.to_string()))?;
 
         let status = response.status().as_u16();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ProviderError::NetworkError(e.to_string()))?;
+        let json: Result<Value, _> = serde_json::from_str(&text);
+        let response_id = json.as_ref().ok().and_then(|json| json["responseId"].as_str()).map(String::from);
+        log::debug!(
+            "POST {} -> {} in {:?}, response ID {}",
+            url,
+            status,
+            start.elapsed(),
+            response_id.as_deref().unwrap_or("none")
+        );
+        log::trace!("  cached instructions: {}, {} bytes received", cached.is_some(), text.len());
+        capture::record(self.name(), &url, &body, status, &text);
 
-        if status == 429 {
-            return Err(ProviderError::RateLimited { retry_after: None });
-        }
-
-        if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(ProviderError::ApiError {
-                status,
-                message: error_text,
-            });
+        if !(200..300).contains(&status) {
+            // A cache entry deleted or expired server-side; forget it so the
+            // retry sends the instructions inline and recreates the cache
+            if cached.is_some() && status != 429 {
+                if let Some(path) = context_cache_path() {
+                    let _ = fs::remove_file(path);
+                }
+            }
+            let error = status_error(status, text);
+            return Err(error.with_request_id(response_id.as_deref()));
         }
 
-        let json: Value = response
-            .json()
-            .await
-            .map_err(|e| ProviderError::ParseError(e.to_string()))?;
-
-        json["candidates"][0]["content"]["parts"][0]["text"]
-            .as_str()
-            .map(|s| s.trim().to_string())
-            .ok_or_else(|| {
-                ProviderError::ParseError("Failed to extract text from response".to_string())
-            })
+        let json = json.map_err(|e| ProviderError::ParseError(e.to_string()))?;
+        extract_text(&json).map_err(|e| e.with_request_id(response_id.as_deref()))
     }
 
     fn name(&self) -> &'static str {
@@ -85,3 +283,60 @@ impl AiProvider for GeminiProvider {
         &self.model
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_error() {
+        let error = |status: u16, text: &str| status_error(status, text.to_string());
+        assert!(matches!(error(401, "API key not valid"), ProviderError::AuthFailed(_)));
+        assert!(matches!(error(403, "permission denied"), ProviderError::AuthFailed(_)));
+        assert!(matches!(
+            error(429, "You exceeded your current quota, please check your plan"),
+            ProviderError::QuotaExceeded(_)
+        ));
+        assert!(matches!(error(429, "Resource exhausted"), ProviderError::RateLimited { .. }));
+        assert!(matches!(error(404, "models/nope is not found"), ProviderError::ModelNotFound(_)));
+        assert!(matches!(error(500, "internal"), ProviderError::ApiError { status: 500, .. }));
+        assert!(!error(401, "").is_retryable() && error(429, "").is_retryable());
+    }
+
+    #[test]
+    fn test_extract_text_concatenates_parts() {
+        let json = json!({
+            "candidates": [{
+                "content": { "parts": [
+                    { "text": "thinking...", "thought": true },
+                    { "text": "SUBJECT: feat: add x\n" },
+                    { "text": "BODY: none" }
+                ]},
+                "finishReason": "STOP"
+            }]
+        });
+        assert_eq!(extract_text(&json).unwrap(), "SUBJECT: feat: add x\nBODY: none");
+    }
+
+    #[test]
+    fn test_extract_text_reports_blocks() {
+        let blocked_prompt = json!({ "promptFeedback": { "blockReason": "SAFETY" } });
+        assert!(matches!(extract_text(&blocked_prompt), Err(ProviderError::ContentBlocked(_))));
+
+        let blocked_candidate = json!({ "candidates": [{ "finishReason": "SAFETY" }] });
+        assert!(matches!(extract_text(&blocked_candidate), Err(ProviderError::ContentBlocked(_))));
+    }
+
+    #[test]
+    fn test_safety_settings() {
+        let all = safety_settings("block_none");
+        assert_eq!(all.len(), HARM_CATEGORIES.len());
+        assert_eq!(all[0]["threshold"], "BLOCK_NONE");
+
+        let some = safety_settings("dangerous=BLOCK_ONLY_HIGH");
+        assert_eq!(some, vec![json!({
+            "category": "HARM_CATEGORY_DANGEROUS_CONTENT",
+            "threshold": "BLOCK_ONLY_HIGH"
+        })]);
+    }
+}
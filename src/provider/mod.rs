@@ -1,10 +1,22 @@
+mod audited;
+mod cache;
+mod fallback;
 mod openrouter;
 mod gemini;
+mod ollama;
+mod plugin;
 
+pub use audited::AuditedProvider;
+pub use cache::CachedProvider;
+pub use fallback::ModelFallback;
 pub use openrouter::OpenRouterProvider;
 pub use gemini::GeminiProvider;
+pub use ollama::OllamaProvider;
+pub use plugin::PluginProvider;
 
 use crate::config::{Config, Provider};
+use crate::http;
+use crate::prompt::Prompt;
 use async_trait::async_trait;
 
 pub type ProviderResult<T> = Result<T, ProviderError>;
@@ -14,7 +26,61 @@ pub enum ProviderError {
     NetworkError(String),
     ApiError { status: u16, message: String },
     ParseError(String),
-    RateLimited { retry_after: Option<u64> },
+    RateLimited {
+        retry_after: Option<u64>,
+        request_id: Option<String>,
+    },
+    AuthFailed(String),
+    QuotaExceeded(String),
+    ContentBlocked(String),
+    /// The requested model doesn't exist (any more)
+    ModelNotFound(String),
+}
+
+impl ProviderError {
+    /// Whether another attempt with the same credentials could succeed
+    pub fn is_retryable(&self) -> bool {
+        !matches!(self, Self::AuthFailed(_) | Self::QuotaExceeded(_) | Self::ModelNotFound(_))
+    }
+
+    /// Coarse category of the error, which never includes its message
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::NetworkError(_) => "network",
+            Self::ApiError { .. } => "api",
+            Self::ParseError(_) => "parse",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::AuthFailed(_) => "auth",
+            Self::QuotaExceeded(_) => "quota",
+            Self::ContentBlocked(_) => "blocked",
+            Self::ModelNotFound(_) => "model_not_found",
+        }
+    }
+
+    /// Tag the error with the provider's ID for the request, so it can be
+    /// quoted when escalating to the provider's support
+    pub fn with_request_id(self, id: Option<&str>) -> Self {
+        let Some(id) = id else {
+            return self;
+        };
+        let tag = |message: String| format!("{} (request ID: {})", message, id);
+        match self {
+            Self::NetworkError(msg) => Self::NetworkError(tag(msg)),
+            Self::ApiError { status, message } => Self::ApiError {
+                status,
+                message: tag(message),
+            },
+            Self::ParseError(msg) => Self::ParseError(tag(msg)),
+            Self::RateLimited { retry_after, .. } => Self::RateLimited {
+                retry_after,
+                request_id: Some(id.to_string()),
+            },
+            Self::AuthFailed(msg) => Self::AuthFailed(tag(msg)),
+            Self::QuotaExceeded(msg) => Self::QuotaExceeded(tag(msg)),
+            Self::ContentBlocked(msg) => Self::ContentBlocked(tag(msg)),
+            Self::ModelNotFound(msg) => Self::ModelNotFound(tag(msg)),
+        }
+    }
 }
 
 impl std::fmt::Display for ProviderError {
@@ -23,13 +89,21 @@ impl std::fmt::Display for ProviderError {
             Self::NetworkError(msg) => write!(f, "Network error: {}", msg),
             Self::ApiError { status, message } => write!(f, "API error ({}): {}", status, message),
             Self::ParseError(msg) => write!(f, "Failed to parse response: {}", msg),
-            Self::RateLimited { retry_after } => {
+            Self::RateLimited { retry_after, request_id } => {
                 if let Some(secs) = retry_after {
-                    write!(f, "Rate limited. Retry after {} seconds", secs)
+                    write!(f, "Rate limited. Retry after {} seconds", secs)?;
                 } else {
-                    write!(f, "Rate limited. Please try again later")
+                    write!(f, "Rate limited. Please try again later")?;
+                }
+                match request_id {
+                    Some(id) => write!(f, " (request ID: {})", id),
+                    None => Ok(()),
                 }
             }
+            Self::AuthFailed(msg) => write!(f, "Authentication failed: {}", msg),
+            Self::QuotaExceeded(msg) => write!(f, "Quota exceeded: {}", msg),
+            Self::ContentBlocked(reason) => write!(f, "Response blocked by provider: {}", reason),
+            Self::ModelNotFound(msg) => write!(f, "Model not found: {}", msg),
         }
     }
 }
@@ -38,19 +112,86 @@ impl std::error::Error for ProviderError {}
 
 #[async_trait]
 pub trait AiProvider: Send + Sync {
-    async fn generate(&self, prompt: &str) -> ProviderResult<String>;
+    async fn generate(&self, prompt: &Prompt) -> ProviderResult<String>;
     fn name(&self) -> &'static str;
     fn model(&self) -> &str;
 }
 
 pub fn create_provider(config: &Config) -> Box<dyn AiProvider> {
-    match config.provider {
+    // Free model IDs come and go; fall back to the default rather than fail the run
+    let default_model = config.provider.default_model();
+    let mut provider = if config.model == default_model {
+        build_provider(config, &config.model)
+    } else {
+        Box::new(ModelFallback::new(
+            build_provider(config, &config.model),
+            build_provider(config, default_model),
+        ))
+    };
+    if let Some(log) = &config.audit_log {
+        provider = Box::new(AuditedProvider::new(provider, log.clone()));
+    }
+
+    if config.cache_ttl > 0 {
+        Box::new(CachedProvider::new(provider, config.cache_ttl))
+    } else {
+        provider
+    }
+}
+
+/// Add the user's extra headers (`CM_<PROVIDER>_HEADERS`) to a request
+fn with_headers(request: reqwest::RequestBuilder, headers: &[(String, String)]) -> reqwest::RequestBuilder {
+    headers
+        .iter()
+        .fold(request, |request, (name, value)| request.header(name.as_str(), value.as_str()))
+}
+
+/// The selected provider's client for `model`, without caching or fallback
+pub fn build_provider(config: &Config, model: &str) -> Box<dyn AiProvider> {
+    match &config.provider {
         Provider::OpenRouter => Box::new(OpenRouterProvider::new(
-            config.openrouter_api_key.clone().unwrap(),
-            config.model.clone(),
+            config.api_key().to_string(),
+            model.to_string(),
+            config.openrouter.clone(),
+            config.sampling,
+            http::client(),
         )),
         Provider::Gemini => Box::new(GeminiProvider::new(
-            config.google_api_key.clone().unwrap(),
+            config.api_key().to_string(),
+            model.to_string(),
+            config.gemini.clone(),
+            config.sampling,
+            http::client(),
+        )),
+        Provider::Ollama => Box::new(OllamaProvider::new(
+            config.api_key().to_string(),
+            model.to_string(),
+            config.ollama.clone(),
+            config.sampling,
+            http::client(),
         )),
+        Provider::Plugin(name) => {
+            // Config loading checked the plugin exists
+            let found = crate::plugin::provider(&config.plugins, name).expect("provider plugin").clone();
+            Box::new(PluginProvider::new(found, config.api_key().to_string(), model.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_id() {
+        let error = ProviderError::AuthFailed("bad key".into()).with_request_id(Some("abc123"));
+        assert_eq!(error.to_string(), "Authentication failed: bad key (request ID: abc123)");
+        let limited = ProviderError::RateLimited {
+            retry_after: Some(5),
+            request_id: None,
+        }
+        .with_request_id(Some("abc123"));
+        assert_eq!(limited.to_string(), "Rate limited. Retry after 5 seconds (request ID: abc123)");
+        assert_eq!(ProviderError::ParseError("x".into()).with_request_id(None).to_string(), "Failed to parse response: x");
     }
 }
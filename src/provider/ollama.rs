@@ -0,0 +1,245 @@
+use super::{with_headers, AiProvider, ProviderError, ProviderResult};
+use crate::capture;
+use crate::config::{OllamaOptions, Sampling};
+use crate::i18n::{tf, Msg};
+use crate::prompt::Prompt;
+use crate::ui;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Pulls download gigabytes, so they get far longer than `CM_HTTP_TIMEOUT`
+const PULL_TIMEOUT: Duration = Duration::from_secs(3600);
+
+pub struct OllamaProvider {
+    api_key: String,
+    model: String,
+    options: OllamaOptions,
+    sampling: Sampling,
+    client: Client,
+    /// Whether a missing model was pulled (`Some(true)`) or the user declined
+    /// (`Some(false)`); concurrent candidates wait here so they ask only once
+    pull: Mutex<Option<bool>>,
+}
+
+impl OllamaProvider {
+    pub fn new(api_key: String, model: String, options: OllamaOptions, sampling: Sampling, client: Client) -> Self {
+        Self {
+            api_key,
+            model,
+            options,
+            sampling,
+            client,
+            pull: Mutex::new(None),
+        }
+    }
+
+    /// POST `body` as JSON to `path` on the server, returning status and body text
+    async fn post(&self, path: &str, body: &Value, timeout: Option<Duration>) -> ProviderResult<(u16, String)> {
+        let url = format!("{}{}", self.options.host, path);
+        let start = Instant::now();
+        let (status, text) = match self.options.host.strip_prefix("unix:") {
+            Some(socket) => post_unix(socket, path, body, &self.api_key, &self.options.headers).await?,
+            None => {
+                let mut request = with_headers(self.client.post(&url), &self.options.headers).json(body);
+                if !self.api_key.is_empty() {
+                    request = request.bearer_auth(&self.api_key);
+                }
+                if let Some(timeout) = timeout {
+                    request = request.timeout(timeout);
+                }
+                let response = request
+                    .send()
+                    .await
+                    .map_err(|e| ProviderError::NetworkError(e.to_string()))?;
+                let status = response.status().as_u16();
+                let text = response
+                    .text()
+                    .await
+                    .map_err(|e| ProviderError::NetworkError(e.to_string()))?;
+                (status, text)
+            }
+        };
+        log::debug!("POST {} ({}) -> {} in {:?}", url, self.model, status, start.elapsed());
+        log::trace!("  {} bytes received", text.len());
+        capture::record(self.name(), &url, body, status, &text);
+        Ok((status, text))
+    }
+
+    async fn chat(&self, prompt: &Prompt) -> ProviderResult<String> {
+        let mut body = json!({
+            "model": self.model,
+            "messages": [
+                { "role": "system", "content": prompt.system },
+                { "role": "user", "content": prompt.user },
+            ],
+            "stream": false,
+            "options": {
+                "temperature": self.sampling.temperature,
+                "num_predict": 500,
+            },
+        });
+        if let Some(seed) = self.sampling.seed {
+            body["options"]["seed"] = json!(seed);
+        }
+        if let Some(keep_alive) = &self.options.keep_alive {
+            // Durations are strings ("30m"); a bare number of seconds must be sent as one
+            body["keep_alive"] = keep_alive.parse::<i64>().map(Value::from).unwrap_or_else(|_| json!(keep_alive));
+        }
+
+        let (status, text) = self.post("/api/chat", &body, None).await?;
+        let json: Value = serde_json::from_str(&text).map_err(|e| ProviderError::ParseError(e.to_string()))?;
+        if let Some(error) = json["error"].as_str() {
+            let message = error.to_string();
+            return Err(match status {
+                401 | 403 => ProviderError::AuthFailed(message),
+                404 => ProviderError::ModelNotFound(message),
+                _ if message.contains("not found") => ProviderError::ModelNotFound(message),
+                _ => ProviderError::ApiError { status, message },
+            });
+        }
+        json["message"]["content"]
+            .as_str()
+            .map(|content| content.trim().to_string())
+            .ok_or_else(|| ProviderError::ParseError("No message in response".to_string()))
+    }
+
+    /// Offer to pull the missing model; `true` once it is available
+    async fn offer_pull(&self) -> ProviderResult<bool> {
+        let mut pull = self.pull.lock().await;
+        if let Some(pulled) = *pull {
+            return Ok(pulled);
+        }
+        if !io::stdin().is_terminal() {
+            return Ok(false);
+        }
+        print!("{}", tf(Msg::OllamaPullPrompt, &[&self.model]));
+        let mut input = String::new();
+        let answer = io::stdout().flush().and_then(|_| io::stdin().read_line(&mut input));
+        if answer.is_err() || input.trim().to_lowercase() != "y" {
+            *pull = Some(false);
+            return Ok(false);
+        }
+
+        ui::status("⬇️  ", tf(Msg::OllamaPulling, &[&self.model]));
+        let request = json!({ "model": self.model, "stream": false });
+        let (status, text) = self.post("/api/pull", &request, Some(PULL_TIMEOUT)).await?;
+        let json: Value = serde_json::from_str(&text).unwrap_or_default();
+        if !(200..300).contains(&status) || json["error"].is_string() {
+            let message = json["error"].as_str().map(String::from).unwrap_or(text);
+            return Err(ProviderError::ApiError { status, message });
+        }
+        ui::status("✓ ", tf(Msg::OllamaPulled, &[&self.model]));
+        *pull = Some(true);
+        Ok(true)
+    }
+}
+
+/// Send one HTTP/1.1 request over the unix socket at `socket`
+#[cfg(unix)]
+async fn post_unix(
+    socket: &str,
+    path: &str,
+    body: &Value,
+    api_key: &str,
+    headers: &[(String, String)],
+) -> ProviderResult<(u16, String)> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let network = |e: io::Error| ProviderError::NetworkError(format!("{}: {}", socket, e));
+    let mut stream = tokio::net::UnixStream::connect(socket).await.map_err(network)?;
+    let body = body.to_string();
+    let mut extra: String = headers.iter().map(|(name, value)| format!("{}: {}\r\n", name, value)).collect();
+    if !api_key.is_empty() {
+        extra.push_str(&format!("Authorization: Bearer {}\r\n", api_key));
+    }
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        path,
+        body.len(),
+        extra,
+        body
+    );
+    stream.write_all(request.as_bytes()).await.map_err(network)?;
+    let mut raw = Vec::new();
+    stream.read_to_end(&mut raw).await.map_err(network)?;
+    parse_response(&raw).ok_or_else(|| ProviderError::ParseError("malformed HTTP response".to_string()))
+}
+
+#[cfg(not(unix))]
+async fn post_unix(
+    socket: &str,
+    _path: &str,
+    _body: &Value,
+    _api_key: &str,
+    _headers: &[(String, String)],
+) -> ProviderResult<(u16, String)> {
+    Err(ProviderError::NetworkError(format!("unix sockets aren't supported on this platform ({})", socket)))
+}
+
+/// Status and body of a raw HTTP/1.1 response read until the connection closed
+fn parse_response(raw: &[u8]) -> Option<(u16, String)> {
+    let text = String::from_utf8_lossy(raw);
+    let (head, body) = text.split_once("\r\n\r\n")?;
+    let status = head.split_whitespace().nth(1)?.parse().ok()?;
+    let chunked = head
+        .lines()
+        .any(|line| line.to_lowercase().starts_with("transfer-encoding:") && line.to_lowercase().contains("chunked"));
+    if !chunked {
+        return Some((status, body.to_string()));
+    }
+    // Each chunk is a hex length line followed by that many bytes; 0 ends the body
+    let mut rest = body;
+    let mut decoded = String::new();
+    loop {
+        let (size, after) = rest.split_once("\r\n")?;
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        if size == 0 {
+            break;
+        }
+        decoded.push_str(after.get(..size)?);
+        rest = after.get(size..)?.trim_start_matches("\r\n");
+    }
+    Some((status, decoded))
+}
+
+#[async_trait]
+impl AiProvider for OllamaProvider {
+    async fn generate(&self, prompt: &Prompt) -> ProviderResult<String> {
+        match self.chat(prompt).await {
+            Err(ProviderError::ModelNotFound(message)) => {
+                if self.offer_pull().await? {
+                    return self.chat(prompt).await;
+                }
+                Err(ProviderError::ModelNotFound(format!("{}; run `ollama pull {}`", message, self.model)))
+            }
+            other => other,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "Ollama"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        let plain = b"HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: 42\r\n\r\n{\"error\":\"model \\\"x\\\" not found\"}";
+        assert_eq!(parse_response(plain), Some((404, "{\"error\":\"model \\\"x\\\" not found\"}".to_string())));
+
+        let chunked = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\n{\"a\":\r\n3\r\n 1}\r\n0\r\n\r\n";
+        assert_eq!(parse_response(chunked), Some((200, "{\"a\": 1}".to_string())));
+        assert_eq!(parse_response(b"garbage"), None);
+    }
+}
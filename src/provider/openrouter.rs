@@ -1,22 +1,94 @@
-use super::{AiProvider, ProviderError, ProviderResult};
+use super::{with_headers, AiProvider, ProviderError, ProviderResult};
+use crate::capture;
+use crate::config::{OpenRouterOptions, Sampling};
+use crate::prompt::Prompt;
 use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::time::Instant;
 
 const OPENROUTER_API_URL: &str = "https://openrouter.ai/api/v1/chat/completions";
 
 pub struct OpenRouterProvider {
     api_key: String,
     model: String,
+    options: OpenRouterOptions,
+    sampling: Sampling,
     client: Client,
 }
 
 impl OpenRouterProvider {
-    pub fn new(api_key: String, model: String) -> Self {
+    pub fn new(api_key: String, model: String, options: OpenRouterOptions, sampling: Sampling, client: Client) -> Self {
         Self {
             api_key,
             model,
-            client: Client::new(),
+            options,
+            sampling,
+            client,
+        }
+    }
+
+    fn provider_preferences(&self) -> Option<ProviderPreferences> {
+        if self.options.provider_order.is_empty() && self.options.allow_fallbacks.is_none() {
+            return None;
+        }
+        Some(ProviderPreferences {
+            order: self.options.provider_order.clone(),
+            allow_fallbacks: self.options.allow_fallbacks,
+        })
+    }
+
+    /// System message content, marked as a cache breakpoint for Anthropic models
+    ///
+    /// Other upstreams either cache prefixes automatically or ignore the hint,
+    /// so plain text is sent to them.
+    fn system_content(&self, system: &str) -> MessageContent {
+        let is_anthropic = self.model.starts_with("anthropic/");
+        if !self.options.prompt_cache || !is_anthropic {
+            return MessageContent::Text(system.to_string());
+        }
+        MessageContent::Parts(vec![ContentPart {
+            kind: "text",
+            text: system.to_string(),
+            cache_control: Some(CacheControl { kind: "ephemeral" }),
+        }])
+    }
+
+    /// The chat completion request for `prompt`, with the routing options
+    fn request(&self, prompt: &Prompt) -> OpenRouterRequest {
+        OpenRouterRequest {
+            model: self.model.clone(),
+            messages: vec![
+                Message {
+                    role: "system".to_string(),
+                    content: self.system_content(&prompt.system),
+                },
+                Message {
+                    role: "user".to_string(),
+                    content: MessageContent::Text(prompt.user.clone()),
+                },
+            ],
+            max_tokens: 500,
+            temperature: self.sampling.temperature,
+            seed: self.sampling.seed,
+            models: self.options.fallback_models.clone(),
+            route: self.options.route.clone(),
+            provider: self.provider_preferences(),
+            transforms: self.options.transforms.clone(),
+            reasoning: self.reasoning(),
+        }
+    }
+
+    fn reasoning(&self) -> Option<Reasoning> {
+        match self.options.reasoning.as_deref()? {
+            "off" | "none" | "0" | "false" => Some(Reasoning {
+                effort: None,
+                enabled: Some(false),
+            }),
+            effort => Some(Reasoning {
+                effort: Some(effort.to_string()),
+                enabled: None,
+            }),
         }
     }
 }
@@ -27,16 +99,68 @@ struct OpenRouterRequest {
     messages: Vec<Message>,
     max_tokens: u32,
     temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    models: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    route: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    provider: Option<ProviderPreferences>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    transforms: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning: Option<Reasoning>,
+}
+
+#[derive(Serialize)]
+struct ProviderPreferences {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    order: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    allow_fallbacks: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct Reasoning {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    effort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    enabled: Option<bool>,
 }
 
 #[derive(Serialize)]
 struct Message {
     role: String,
-    content: String,
+    content: MessageContent,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Serialize)]
+struct ContentPart {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_control: Option<CacheControl>,
+}
+
+#[derive(Serialize)]
+struct CacheControl {
+    #[serde(rename = "type")]
+    kind: &'static str,
 }
 
 #[derive(Deserialize)]
 struct OpenRouterResponse {
+    /// Generation ID, which OpenRouter support can look up
+    id: Option<String>,
     choices: Option<Vec<Choice>>,
     error: Option<ApiErrorResponse>,
 }
@@ -56,22 +180,24 @@ struct ApiErrorResponse {
     message: String,
 }
 
+/// The error OpenRouter reported with HTTP `status`
+fn status_error(status: u16, message: String) -> ProviderError {
+    match status {
+        401 | 403 => ProviderError::AuthFailed(message),
+        402 => ProviderError::QuotaExceeded(message),
+        404 => ProviderError::ModelNotFound(message),
+        400 if message.contains("not a valid model") => ProviderError::ModelNotFound(message),
+        _ => ProviderError::ApiError { status, message },
+    }
+}
+
 #[async_trait]
 impl AiProvider for OpenRouterProvider {
-    async fn generate(&self, prompt: &str) -> ProviderResult<String> {
-        let request = OpenRouterRequest {
-            model: self.model.clone(),
-            messages: vec![Message {
-                role: "user".to_string(),
-                content: prompt.to_string(),
-            }],
-            max_tokens: 500,
-            temperature: 0.7,
-        };
+    async fn generate(&self, prompt: &Prompt) -> ProviderResult<String> {
+        let request = self.request(prompt);
 
-        let response = self
-            .client
-            .post(OPENROUTER_API_URL)
+        let start = Instant::now();
+        let response = with_headers(self.client.post(OPENROUTER_API_URL), &self.options.headers)
             .header("Authorization", format!("Bearer {}", self.api_key))
             .header(
                 "HTTP-Referer",
@@ -84,6 +210,19 @@ impl AiProvider for OpenRouterProvider {
             .map_err(|e| ProviderError::NetworkError(e.to_string()))?;
 
         let status = response.status().as_u16();
+        let header_id = response
+            .headers()
+            .get("x-request-id")
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        log::debug!(
+            "POST {} ({}) -> {} in {:?}, request ID {}",
+            OPENROUTER_API_URL,
+            self.model,
+            status,
+            start.elapsed(),
+            header_id.as_deref().unwrap_or("none")
+        );
 
         if status == 429 {
             let retry_after = response
@@ -91,25 +230,43 @@ impl AiProvider for OpenRouterProvider {
                 .get("retry-after")
                 .and_then(|v| v.to_str().ok())
                 .and_then(|v| v.parse().ok());
-            return Err(ProviderError::RateLimited { retry_after });
+            return Err(ProviderError::RateLimited {
+                retry_after,
+                request_id: header_id,
+            });
         }
 
-        let body: OpenRouterResponse = response
-            .json()
+        let text = response
+            .text()
             .await
-            .map_err(|e| ProviderError::ParseError(e.to_string()))?;
+            .map_err(|e| ProviderError::NetworkError(e.to_string()).with_request_id(header_id.as_deref()))?;
+        log::trace!("  {} bytes received", text.len());
+        capture::record(
+            self.name(),
+            OPENROUTER_API_URL,
+            &serde_json::to_value(&request).unwrap_or_default(),
+            status,
+            &text,
+        );
+
+        let body: OpenRouterResponse = serde_json::from_str(&text)
+            .map_err(|e| ProviderError::ParseError(e.to_string()).with_request_id(header_id.as_deref()))?;
+        let request_id = header_id.or(body.id);
+        if let Some(id) = &request_id {
+            log::debug!("  generation ID {}", id);
+        }
 
         if let Some(error) = body.error {
-            return Err(ProviderError::ApiError {
-                status,
-                message: error.message,
-            });
+            let error = status_error(status, error.message);
+            return Err(error.with_request_id(request_id.as_deref()));
         }
 
         body.choices
             .and_then(|c| c.into_iter().next())
             .map(|choice| choice.message.content.trim().to_string())
-            .ok_or_else(|| ProviderError::ParseError("No choices in response".to_string()))
+            .ok_or_else(|| {
+                ProviderError::ParseError("No choices in response".to_string()).with_request_id(request_id.as_deref())
+            })
     }
 
     fn name(&self) -> &'static str {
@@ -120,3 +277,76 @@ impl AiProvider for OpenRouterProvider {
         &self.model
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_request_payload() {
+        let options = OpenRouterOptions {
+            provider_order: vec!["Together".to_string(), "DeepInfra".to_string()],
+            allow_fallbacks: Some(false),
+            route: Some("fallback".to_string()),
+            fallback_models: vec!["mistralai/mistral-7b-instruct:free".to_string()],
+            transforms: vec!["middle-out".to_string()],
+            reasoning: Some("off".to_string()),
+            ..OpenRouterOptions::default()
+        };
+        let sampling = Sampling {
+            temperature: 0.5,
+            seed: Some(7),
+        };
+        let provider = OpenRouterProvider::new(String::new(), "meta/llama".to_string(), options, sampling, Client::new());
+        let prompt = Prompt {
+            system: "system".to_string(),
+            user: "user".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(provider.request(&prompt)).unwrap(),
+            json!({
+                "model": "meta/llama",
+                "messages": [
+                    { "role": "system", "content": "system" },
+                    { "role": "user", "content": "user" }
+                ],
+                "max_tokens": 500,
+                "temperature": 0.5,
+                "seed": 7,
+                "models": ["mistralai/mistral-7b-instruct:free"],
+                "route": "fallback",
+                "provider": { "order": ["Together", "DeepInfra"], "allow_fallbacks": false },
+                "transforms": ["middle-out"],
+                "reasoning": { "enabled": false }
+            })
+        );
+
+        // Without routing options only the basic fields are sent
+        let plain = OpenRouterProvider::new(
+            String::new(),
+            "meta/llama".to_string(),
+            OpenRouterOptions::default(),
+            Sampling { temperature: 0.5, seed: None },
+            Client::new(),
+        );
+        let keys: Vec<String> = match serde_json::to_value(plain.request(&prompt)).unwrap() {
+            serde_json::Value::Object(fields) => fields.keys().cloned().collect(),
+            _ => Vec::new(),
+        };
+        assert_eq!(keys, ["max_tokens", "messages", "model", "temperature"]);
+    }
+
+    #[test]
+    fn test_status_error() {
+        let error = |status: u16, message: &str| status_error(status, message.to_string());
+        assert!(matches!(error(401, "No auth credentials found"), ProviderError::AuthFailed(_)));
+        assert!(matches!(error(403, "Key disabled"), ProviderError::AuthFailed(_)));
+        assert!(matches!(error(402, "Insufficient credits"), ProviderError::QuotaExceeded(_)));
+        assert!(matches!(error(404, "No endpoints found"), ProviderError::ModelNotFound(_)));
+        assert!(matches!(error(400, "x/y is not a valid model ID"), ProviderError::ModelNotFound(_)));
+        assert!(matches!(error(400, "Bad request"), ProviderError::ApiError { status: 400, .. }));
+        assert!(matches!(error(502, "Upstream error"), ProviderError::ApiError { status: 502, .. }));
+        assert!(!error(402, "").is_retryable() && error(502, "").is_retryable());
+    }
+}
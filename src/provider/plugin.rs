@@ -0,0 +1,52 @@
+use super::{AiProvider, ProviderError, ProviderResult};
+use crate::plugin::Plugin;
+use crate::prompt::Prompt;
+use async_trait::async_trait;
+use serde_json::json;
+
+/// A `provider-<name>` plugin
+///
+/// It gets `{"model", "system", "user", "api_key"}` as JSON (on stdin, or
+/// through `cm_plugin_run` for a library) and answers with the response; a
+/// non-zero exit fails the attempt, with the first line it printed as the reason.
+pub struct PluginProvider {
+    plugin: Plugin,
+    api_key: String,
+    model: String,
+}
+
+impl PluginProvider {
+    pub fn new(plugin: Plugin, api_key: String, model: String) -> Self {
+        Self { plugin, api_key, model }
+    }
+}
+
+#[async_trait]
+impl AiProvider for PluginProvider {
+    async fn generate(&self, prompt: &Prompt) -> ProviderResult<String> {
+        let request = json!({
+            "model": self.model,
+            "system": prompt.system,
+            "user": prompt.user,
+            "api_key": self.api_key,
+        })
+        .to_string();
+        let plugin = self.plugin.clone();
+        let result = tokio::task::spawn_blocking(move || plugin.run(&request))
+            .await
+            .map_err(|e| ProviderError::NetworkError(e.to_string()))?;
+        match result {
+            Ok(text) if text.is_empty() => Err(ProviderError::ParseError(format!("plugin {} printed nothing", self.plugin.name))),
+            Ok(text) => Ok(text),
+            Err(reason) => Err(ProviderError::NetworkError(format!("plugin {}: {}", self.plugin.name, reason))),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "plugin"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+}
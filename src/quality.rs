@@ -0,0 +1,112 @@
+use crate::emoji::remove_emoji_prefix;
+use crate::lint::check_header;
+use crate::message::CommitMessage;
+
+/// Words that say something changed without saying what
+const VAGUE_WORDS: &[&str] = &[
+    "update", "updates", "updated", "change", "changes", "changed", "stuff", "things", "misc", "various", "some",
+    "minor", "tweak", "tweaks", "improve", "improvements", "cleanup", "fixes", "work", "wip",
+];
+
+/// Longest subject before `git log --oneline` and PR lists cut it off
+const MAX_SUBJECT: usize = 72;
+
+/// Shortest description that can name both an action and what it acts on
+const MIN_DESCRIPTION: usize = 15;
+
+/// How good a message is likely to read in `git log`, from 0 to 100, with
+/// what cost it points
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quality {
+    pub score: u8,
+    pub problems: Vec<&'static str>,
+}
+
+/// Score `message` locally, without asking the model
+///
+/// Starts from 100 and takes points off for a subject that isn't a
+/// Conventional Commits header, is too short or too long, leans on vague
+/// words, or names nothing specific.
+pub fn score(message: &CommitMessage) -> Quality {
+    let subject = remove_emoji_prefix(&message.subject);
+    let mut penalty = 0;
+    let mut problems = Vec::new();
+    let mut take = |points: u32, problem: &'static str| {
+        penalty += points;
+        problems.push(problem);
+    };
+
+    if check_header(&subject).is_err() {
+        take(30, "not a Conventional Commits header");
+    }
+    let (header, description) = subject.split_once(": ").unwrap_or(("", subject.as_str()));
+    let length = subject.chars().count();
+    if length > MAX_SUBJECT {
+        take(15, "subject too long");
+    } else if description.chars().count() < MIN_DESCRIPTION {
+        take(15, "description too short");
+    }
+
+    let words: Vec<String> = description
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let vague = words.iter().filter(|w| VAGUE_WORDS.contains(&w.as_str())).count();
+    if vague > 0 {
+        take(10 * vague.min(3) as u32, "vague wording");
+    }
+    if words.len() < 3 {
+        take(10, "too few words to be specific");
+    }
+    if !header.contains('(') && !names_something(description) && message.body.is_none() {
+        take(10, "no scope, identifier or body says what changed");
+    }
+
+    Quality {
+        score: 100u32.saturating_sub(penalty) as u8,
+        problems,
+    }
+}
+
+/// Whether `text` mentions code: an identifier in backticks, a path, or a
+/// `snake_case`, `CamelCase` or `module::item` name
+fn names_something(text: &str) -> bool {
+    text.contains('`')
+        || text.split_whitespace().any(|word| {
+            let word = word.trim_matches(|c: char| !c.is_alphanumeric() && c != '_');
+            word.contains(['_', '/', '.'])
+                || word.contains("::")
+                || word.chars().skip(1).any(|c| c.is_ascii_uppercase())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scored(subject: &str, body: Option<&str>) -> Quality {
+        score(&CommitMessage {
+            subject: subject.to_string(),
+            body: body.map(String::from),
+        })
+    }
+
+    #[test]
+    fn test_score() {
+        let good = scored("fix(auth): refresh expired tokens before each request", None);
+        assert_eq!(good, Quality { score: 100, problems: Vec::new() });
+        assert_eq!(scored("✨ feat: add `--dry-run` to migrate-history", None).score, 100);
+
+        let vague = scored("chore: update stuff", None);
+        assert!(vague.score <= 50, "{:?}", vague);
+        assert!(vague.problems.contains(&"vague wording"));
+
+        let plain = scored("Fixed the thing", None);
+        assert!(plain.problems.contains(&"not a Conventional Commits header"));
+        assert!(plain.score < vague.score + 20);
+
+        let long = scored(&format!("docs(readme): {}", "explain ".repeat(10)), Some("Why."));
+        assert_eq!(long.problems, vec!["subject too long"]);
+    }
+}
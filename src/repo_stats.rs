@@ -0,0 +1,181 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+
+use crate::config::LintOptions;
+use crate::emoji::remove_emoji_prefix;
+use crate::exit::Exit;
+use crate::lint;
+use crate::ui;
+use crate::vcs::git::dated_subjects;
+
+/// Months shown in the trend, most recent last
+const TREND_MONTHS: usize = 12;
+
+/// Types and scopes listed, most used first
+const TOP_ENTRIES: usize = 10;
+
+/// What `cm repo-stats` reports about a run of commit subjects
+#[derive(Debug, Default, PartialEq)]
+pub struct Stats {
+    pub commits: usize,
+    /// Subjects `cm lint` has nothing to say about
+    pub compliant: usize,
+    pub subject_chars: usize,
+    pub types: HashMap<String, usize>,
+    pub scopes: HashMap<String, usize>,
+    /// Commits and compliant commits per `YYYY-MM`
+    pub months: BTreeMap<String, (usize, usize)>,
+}
+
+impl Stats {
+    /// Tally `(month, subject)` pairs
+    pub fn collect(subjects: &[(String, String)], options: &LintOptions) -> Self {
+        let mut stats = Self::default();
+        for (month, subject) in subjects {
+            let compliant = lint::check(subject, options).is_empty();
+            stats.commits += 1;
+            stats.compliant += usize::from(compliant);
+            stats.subject_chars += subject.chars().count();
+            let month = stats.months.entry(month.clone()).or_default();
+            month.0 += 1;
+            month.1 += usize::from(compliant);
+
+            let Some((commit_type, scopes)) = header(subject) else {
+                continue;
+            };
+            *stats.types.entry(commit_type).or_default() += 1;
+            for scope in scopes {
+                *stats.scopes.entry(scope).or_default() += 1;
+            }
+        }
+        stats
+    }
+
+    /// Share of compliant commits, in percent
+    pub fn compliance(&self) -> f64 {
+        percent(self.compliant, self.commits)
+    }
+
+    pub fn average_subject_length(&self) -> f64 {
+        if self.commits == 0 {
+            0.0
+        } else {
+            self.subject_chars as f64 / self.commits as f64
+        }
+    }
+}
+
+/// Report on the commits in `range` (all of `HEAD` by default)
+pub fn run_repo_stats(range: &str) -> Result<Exit, Box<dyn Error>> {
+    let subjects = dated_subjects(range)?;
+    if subjects.is_empty() {
+        ui::status("ℹ️  ", format!("No commits in {}", range));
+        return Ok(Exit::Success);
+    }
+    let stats = Stats::collect(&subjects, &LintOptions::from_env());
+    let typed: usize = stats.types.values().sum();
+
+    println!("{}", ui::line("📊 ", &format!("{} commits in {}", stats.commits, range)));
+    println!("  Conventional Commits compliance  {:.0}% ({} of {})", stats.compliance(), stats.compliant, stats.commits);
+    println!("  Average subject length           {:.1} characters", stats.average_subject_length());
+    println!("  Commits with a type              {:.0}%", percent(typed, stats.commits));
+
+    println!("\nTypes");
+    for (name, count) in ranked(&stats.types) {
+        println!("  {:<12} {:>6}  {:>3.0}%", name, count, percent(count, typed));
+    }
+    if stats.types.is_empty() {
+        println!("  (none)");
+    }
+
+    println!("\nScopes");
+    for (name, count) in ranked(&stats.scopes) {
+        println!("  {:<20} {:>6}", name, count);
+    }
+    if stats.scopes.is_empty() {
+        println!("  (none)");
+    }
+
+    println!("\nCompliance by month");
+    let skip = stats.months.len().saturating_sub(TREND_MONTHS);
+    for (month, (commits, compliant)) in stats.months.iter().skip(skip) {
+        let share = percent(*compliant, *commits);
+        println!("  {}  {:>6} commits  {:>3.0}%  {}", month, commits, share, bar(share));
+    }
+    Ok(Exit::Success)
+}
+
+/// Type and scopes of a `type(scope)!: description` subject, ignoring any emoji
+fn header(subject: &str) -> Option<(String, Vec<String>)> {
+    let subject = remove_emoji_prefix(subject);
+    let (header, _) = subject.split_once(": ")?;
+    let header = header.strip_suffix('!').unwrap_or(header);
+    let (commit_type, scopes) = match header.split_once('(') {
+        Some((commit_type, rest)) => (commit_type, rest.strip_suffix(')')?),
+        None => (header, ""),
+    };
+    if commit_type.is_empty() || !commit_type.chars().all(|c| c.is_ascii_lowercase()) {
+        return None;
+    }
+    let scopes = scopes.split(',').map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect();
+    Some((commit_type.to_string(), scopes))
+}
+
+/// The most used entries of `counts`, ties in name order
+fn ranked(counts: &HashMap<String, usize>) -> Vec<(&str, usize)> {
+    let mut ranked: Vec<_> = counts.iter().map(|(name, count)| (name.as_str(), *count)).collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    ranked.truncate(TOP_ENTRIES);
+    ranked
+}
+
+fn percent(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / whole as f64
+    }
+}
+
+/// A bar of up to 20 cells for a percentage
+fn bar(percent: f64) -> String {
+    "█".repeat((percent / 5.0).round() as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subjects(entries: &[(&str, &str)]) -> Vec<(String, String)> {
+        entries.iter().map(|(month, subject)| (month.to_string(), subject.to_string())).collect()
+    }
+
+    #[test]
+    fn test_collect() {
+        let options = LintOptions {
+            min_length: 10,
+            banned_words: Vec::new(),
+        };
+        let stats = Stats::collect(
+            &subjects(&[
+                ("2026-08", "Update stuff"),
+                ("2026-08", "fix(auth): refresh expired tokens"),
+                ("2026-09", "✨ feat(api,ui)!: add search endpoint"),
+                ("2026-09", "feat: add export button"),
+                ("2026-09", "Feature(ui): not a conventional type"),
+            ]),
+            &options,
+        );
+        assert_eq!(stats.commits, 5);
+        assert_eq!(stats.compliant, 3);
+        assert_eq!(stats.compliance(), 60.0);
+        assert_eq!(stats.types, HashMap::from([("feat".to_string(), 2), ("fix".to_string(), 1)]));
+        assert_eq!(ranked(&stats.scopes), vec![("api", 1), ("auth", 1), ("ui", 1)]);
+        assert_eq!(
+            stats.months,
+            BTreeMap::from([("2026-08".to_string(), (2, 1)), ("2026-09".to_string(), (3, 2))])
+        );
+        assert!(stats.average_subject_length() > 20.0);
+        assert_eq!(bar(60.0), "████████████");
+    }
+}
@@ -0,0 +1,102 @@
+use crate::budget::{diff_token_budget, estimate_tokens, truncate_diff};
+use crate::config::{secret_action, BudgetAction, Config};
+use crate::context::GitContext;
+use crate::diff::render_diff;
+use crate::exit::{provider_failure, Exit, Failure};
+use crate::generated;
+use crate::privacy::Privacy;
+use crate::prompt::{build_review_prompt, Prompt};
+use crate::provider::create_provider;
+use crate::spend::{self, prompt_tokens};
+use crate::vcs::git::{commit_diff, get_staged_diff, range_diff};
+use crate::vcs::Git;
+use crate::{secrets, setup_guidance, ui, vault};
+
+/// Draft review notes for the staged changes, a commit, or a range such as `main..HEAD`
+pub async fn run_review(target: Option<&str>, allow_secrets: bool, no_cache: bool) -> Result<Exit, Box<dyn std::error::Error>> {
+    let mut config = Config::from_env()?;
+    if no_cache {
+        config.cache_ttl = 0;
+    }
+    vault::configure(config.encrypt_store);
+    ui::set_theme(config.theme);
+
+    let (diff, what) = match target {
+        None => (get_staged_diff(&[])?, "the staged changes".to_string()),
+        Some(range) if range.contains("..") => (range_diff(range)?, range.to_string()),
+        Some(rev) => (commit_diff(rev)?, rev.to_string()),
+    };
+    if diff.trim().is_empty() {
+        return Err(Failure::new(Exit::NoStagedChanges, format!("Nothing to review in {}", what)).into());
+    }
+    secrets::check(&diff, secret_action()?, allow_secrets)?;
+
+    let prompt = review_prompt(&config, GitContext::from_diff(diff, String::new()).with_repo(&Git));
+    let provider = create_provider(&config);
+    ui::status("🧐 ", format!("Reviewing {} with {} ({})", what, provider.name(), provider.model()));
+
+    let estimate = prompt_tokens(&prompt);
+    if let Some(reason) = spend::over_budget(&config.budget, config.provider.id(), estimate) {
+        if config.budget.action == BudgetAction::Refuse {
+            let message = format!("{}. Set CM_BUDGET_ACTION=warn to continue anyway.", reason);
+            return Err(Failure::new(Exit::QuotaExceeded, message).into());
+        }
+        ui::warn("⚠️  ", format!("Over budget: {}", reason));
+    }
+
+    let mut last_error = None;
+    for _ in 0..config.max_retries {
+        match provider.generate(&prompt).await {
+            Ok(text) if !text.trim().is_empty() => {
+                spend::record(&config.budget, config.provider.id(), estimate + estimate_tokens(&text) as u64);
+                println!("\n{}", text.trim());
+                return Ok(Exit::Success);
+            }
+            Ok(_) => {}
+            Err(e) if !e.is_retryable() => return Err(provider_failure(&e, setup_guidance(&e, &config)).into()),
+            Err(e) => {
+                ui::warn("⚠️  ", format!("API error: {}. Retrying...", e));
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(match last_error {
+        Some(e) => e.into(),
+        None => Failure::new(Exit::GenerationFailed, "Provider returned no review").into(),
+    })
+}
+
+/// The review prompt for `context`, through the same steps as a commit prompt:
+/// generated files left out, privacy applied and the diff cut to the context window
+fn review_prompt(config: &Config, context: GitContext) -> Prompt {
+    let generated = generated::detect(&context.files);
+    let files: Vec<_> = context
+        .files
+        .iter()
+        .filter(|file| !generated.iter().any(|g| g.path == file.path))
+        .cloned()
+        .collect();
+    let privacy = Privacy::new(config.privacy, config.privacy_hash_paths, &context.paths());
+    let diff = privacy.apply(&render_diff(&files, &Default::default()));
+    let stat = privacy.apply(&context.stat);
+
+    let skeleton = build_review_prompt("", &stat);
+    let budget = diff_token_budget(config.context_tokens, &format!("{}{}", skeleton.system, skeleton.user));
+    build_review_prompt(&truncate_diff(&diff, config.max_diff_lines, budget), &stat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::config;
+
+    #[test]
+    fn test_review_prompt_skips_generated_files() {
+        let diff = "diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-fn a() {}\n+fn b() {}\n\
+                    diff --git a/src/api.pb.rs b/src/api.pb.rs\n--- a/src/api.pb.rs\n+++ b/src/api.pb.rs\n@@ -1 +1 @@\n-// old\n+// new\n";
+        let prompt = review_prompt(&config(), GitContext::from_diff(diff.to_string(), String::new()));
+        assert!(prompt.user.contains("+fn b() {}"));
+        assert!(!prompt.user.contains("+// new"));
+        assert!(prompt.system.contains("Missing tests"));
+    }
+}
@@ -0,0 +1,341 @@
+use std::env;
+use std::fmt;
+
+use crate::config::{ContextSections, Transform};
+use crate::exit::{Exit, Failure};
+use crate::ui;
+
+/// What a `CM_*` variable holds
+#[derive(Clone, Copy)]
+enum Kind {
+    /// `1`, `0`, `true` or `false`
+    Flag,
+    /// A whole number
+    Number,
+    /// A number such as `0.7`
+    Decimal,
+    /// One of the listed words
+    Choice(&'static [&'static str]),
+    /// Comma-separated words from the list
+    Names(fn() -> Vec<&'static str>),
+    /// A model, path, command, list or per-provider value, checked when it's used
+    Text,
+    /// A token, key or URL with credentials, never shown
+    Secret,
+    /// Comma-separated `Name: value` request headers, whose values may be keys
+    Headers,
+}
+
+const FLAG: &[&str] = &["1", "0", "true", "false"];
+
+/// Every variable `cm` reads, and what it holds
+const SETTINGS: &[(&str, Kind)] = &[
+    ("CM_A11Y", Kind::Flag),
+    ("CM_AUDIT", Kind::Flag),
+    ("CM_AUDIT_LOG", Kind::Text),
+    ("CM_BANNED_ACTION", Kind::Choice(&["regenerate", "flag"])),
+    ("CM_BANNED_WORDS", Kind::Text),
+    ("CM_BODY_STYLE", Kind::Choice(&["bullets", "paragraphs", "none"])),
+    ("CM_BREAKING_FOOTER", Kind::Flag),
+    ("CM_BUDGET_ACTION", Kind::Choice(&["refuse", "warn"])),
+    ("CM_BUDGET_DAILY", Kind::Text),
+    ("CM_BUDGET_MONTHLY", Kind::Text),
+    ("CM_CACHE_TTL", Kind::Number),
+    ("CM_CANDIDATES", Kind::Number),
+    ("CM_CAPTURE", Kind::Flag),
+    ("CM_CA_CERT", Kind::Text),
+    ("CM_CHECK_COMMAND", Kind::Text),
+    ("CM_CODEOWNERS", Kind::Flag),
+    ("CM_COMMIT_GROUPS", Kind::Text),
+    ("CM_CONFIRM_LINES", Kind::Number),
+    ("CM_CONFIRM_TOKENS", Kind::Number),
+    ("CM_CONTEXT", Kind::Names(|| ContextSections::NAMES.to_vec())),
+    ("CM_CONTEXT_TOKENS", Kind::Number),
+    ("CM_DETERMINISTIC", Kind::Flag),
+    ("CM_DISALLOWED_EXTENSIONS", Kind::Text),
+    ("CM_EDITOR", Kind::Text),
+    ("CM_EMOJI", Kind::Flag),
+    ("CM_EMOJI_POSITION", Kind::Choice(&["prefix", "after-type", "end"])),
+    ("CM_ENCRYPT_STORE", Kind::Flag),
+    ("CM_FOOTER", Kind::Text),
+    ("CM_GEMINI_HEADERS", Kind::Headers),
+    ("CM_GEMINI_MODELS", Kind::Text),
+    ("CM_GEMINI_SAFETY", Kind::Text),
+    ("CM_HISTORY_AUTHOR", Kind::Text),
+    ("CM_HISTORY_COUNT", Kind::Number),
+    ("CM_HISTORY_FILTER", Kind::Flag),
+    ("CM_HISTORY_FORMAT", Kind::Choice(&["oneline", "full"])),
+    // Set by `cm` for the hook scripts it runs
+    ("CM_HOOK", Kind::Text),
+    ("CM_HOOK_POST_GENERATE", Kind::Text),
+    ("CM_HOOK_PRE_COMMIT_MESSAGE", Kind::Text),
+    ("CM_HOOK_PRE_GENERATE", Kind::Text),
+    ("CM_HTTP_PROXY", Kind::Secret),
+    ("CM_HTTP_TIMEOUT", Kind::Number),
+    ("CM_INTERVIEW", Kind::Flag),
+    ("CM_LEARN", Kind::Flag),
+    ("CM_LICENSE_HEADER", Kind::Text),
+    ("CM_LOCAL_DEPS", Kind::Flag),
+    ("CM_MAX_DIFF_LINES", Kind::Number),
+    ("CM_MAX_FILE_SIZE", Kind::Text),
+    ("CM_MIN_LENGTH", Kind::Number),
+    ("CM_MIN_QUALITY", Kind::Number),
+    ("CM_MODEL", Kind::Text),
+    ("CM_OLLAMA_HEADERS", Kind::Headers),
+    ("CM_OLLAMA_HOST", Kind::Text),
+    ("CM_OLLAMA_KEEP_ALIVE", Kind::Text),
+    ("CM_OLLAMA_MODELS", Kind::Text),
+    ("CM_OPENROUTER_ALLOW_FALLBACKS", Kind::Flag),
+    ("CM_OPENROUTER_FALLBACK_MODELS", Kind::Text),
+    ("CM_OPENROUTER_HEADERS", Kind::Headers),
+    ("CM_OPENROUTER_MODELS", Kind::Text),
+    ("CM_OPENROUTER_PROVIDERS", Kind::Text),
+    ("CM_OPENROUTER_REASONING", Kind::Text),
+    ("CM_OPENROUTER_ROUTE", Kind::Text),
+    ("CM_OPENROUTER_TRANSFORMS", Kind::Text),
+    ("CM_PLUGIN_API_KEY", Kind::Secret),
+    ("CM_PLUGIN_DIR", Kind::Text),
+    ("CM_PLUGIN_MODELS", Kind::Text),
+    ("CM_POSTPROCESS", Kind::Names(|| Transform::ALL.iter().map(|(_, name)| *name).collect())),
+    ("CM_PRICE_PER_MTOK", Kind::Text),
+    ("CM_PRIVACY", Kind::Flag),
+    ("CM_PRIVACY_HASH_PATHS", Kind::Flag),
+    ("CM_PROMPT_CACHE", Kind::Flag),
+    ("CM_PROMPT_FILE", Kind::Text),
+    ("CM_PROTECTED_BRANCHES", Kind::Text),
+    ("CM_PROTECTED_BRANCH_ACTION", Kind::Choice(&["off", "warn", "block"])),
+    ("CM_PROVIDER", Kind::Text),
+    ("CM_PUSH_REMOTES", Kind::Text),
+    ("CM_REFS_FOOTER", Kind::Flag),
+    ("CM_SCOPE_DEPTH", Kind::Text),
+    ("CM_SCOPE_MODE", Kind::Choice(&["auto", "dominant", "stack", "omit"])),
+    ("CM_SECRET_SCAN", Kind::Choice(&["off", "warn", "refuse"])),
+    ("CM_SEED", Kind::Number),
+    ("CM_SERVE_TOKEN", Kind::Secret),
+    ("CM_STYLE", Kind::Choice(&["balanced", "concise", "detailed", "explanatory"])),
+    ("CM_SUGGEST_REVIEWERS", Kind::Flag),
+    ("CM_TELEMETRY", Kind::Flag),
+    ("CM_TELEMETRY_URL", Kind::Text),
+    ("CM_TEMPERATURE", Kind::Decimal),
+    ("CM_THEME", Kind::Choice(&["default", "deuteranopia", "high-contrast"])),
+    ("CM_TICKET", Kind::Text),
+    ("CM_TICKET_URL", Kind::Text),
+    ("CM_UI_LANG", Kind::Text),
+    ("CM_WATCH_INTERVAL", Kind::Number),
+    ("CM_WATCH_QUIET", Kind::Number),
+    ("CM_WORKSPACE_REPOS", Kind::Text),
+];
+
+/// Variables that were renamed, old name first; the old names are no longer read
+const RENAMED: &[(&str, &str)] = &[];
+
+/// Something wrong with a `CM_*` variable
+#[derive(Debug, PartialEq)]
+pub enum Problem {
+    /// Not a setting, probably a typo; it's ignored
+    Unknown { name: String, suggestion: Option<&'static str> },
+    /// A value that isn't of the setting's kind
+    Invalid {
+        name: String,
+        value: String,
+        expected: String,
+        suggestion: Option<String>,
+    },
+    /// An old name that is no longer read
+    Renamed { name: String, new: &'static str },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unknown { name, suggestion: Some(known) } => {
+                write!(f, "{} is not a setting and is ignored. Did you mean {}?", name, known)
+            }
+            Self::Unknown { name, suggestion: None } => {
+                write!(f, "{} is not a setting and is ignored. See 'cm help' for the settings", name)
+            }
+            Self::Invalid {
+                name,
+                value,
+                expected,
+                suggestion,
+            } => {
+                write!(f, "{}='{}' is not valid: use {}", name, value, expected)?;
+                match suggestion {
+                    Some(fixed) => write!(f, ". Did you mean {}={}?", name, fixed),
+                    None => Ok(()),
+                }
+            }
+            Self::Renamed { name, new } => write!(f, "{} was renamed and is ignored: use {}", name, new),
+        }
+    }
+}
+
+/// Check `vars` against the settings `cm` knows, in the order given
+///
+/// Only `CM_*` variables are checked; empty values count as unset.
+pub fn check(vars: impl IntoIterator<Item = (String, String)>) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for (name, value) in vars {
+        if !name.starts_with("CM_") {
+            continue;
+        }
+        if let Some((_, new)) = RENAMED.iter().find(|(old, _)| *old == name) {
+            problems.push(Problem::Renamed { name, new });
+            continue;
+        }
+        let Some((_, kind)) = SETTINGS.iter().find(|(known, _)| *known == name) else {
+            let suggestion = closest(&name, SETTINGS.iter().map(|(known, _)| *known));
+            problems.push(Problem::Unknown { name, suggestion });
+            continue;
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        let invalid = |expected: String, suggestion: Option<String>| Problem::Invalid {
+            name: name.clone(),
+            value: value.to_string(),
+            expected,
+            suggestion,
+        };
+        match *kind {
+            Kind::Flag if !FLAG.contains(&value.to_lowercase().as_str()) => {
+                let suggestion = match value.to_lowercase().as_str() {
+                    "yes" | "y" | "on" | "enabled" => Some("1"),
+                    "no" | "n" | "off" | "disabled" => Some("0"),
+                    _ => None,
+                };
+                problems.push(invalid("1, 0, true or false".to_string(), suggestion.map(String::from)));
+            }
+            Kind::Number if value.parse::<u64>().is_err() => {
+                problems.push(invalid("a whole number".to_string(), None));
+            }
+            Kind::Decimal if value.parse::<f64>().is_err() => {
+                problems.push(invalid("a number such as 0.7".to_string(), None));
+            }
+            Kind::Choice(choices) if !choices.contains(&value) => {
+                let suggestion = closest(value, choices.iter().copied()).map(String::from);
+                problems.push(invalid(choices.join(", "), suggestion));
+            }
+            Kind::Names(names) => {
+                let names = names();
+                let items: Vec<&str> = value.split(',').map(str::trim).filter(|item| !item.is_empty()).collect();
+                if items.iter().any(|item| !names.contains(item)) {
+                    let fixed: Option<Vec<&str>> = items
+                        .iter()
+                        .map(|item| if names.contains(item) { Some(*item) } else { closest(item, names.iter().copied()) })
+                        .collect();
+                    problems.push(invalid(format!("any of {}", names.join(", ")), fixed.map(|f| f.join(","))));
+                }
+            }
+            _ => {}
+        }
+    }
+    problems
+}
+
+/// The candidate closest to `word`, if it's close enough to be a typo of it
+fn closest<'a>(word: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let word = word.to_uppercase();
+    candidates
+        .map(|candidate| (distance(&word, &candidate.to_uppercase()), candidate))
+        .filter(|(d, candidate)| *d <= 2.max(candidate.len() / 4))
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Edit distance between `a` and `b`
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let next = (diagonal + usize::from(ca != *cb)).min(row[j] + 1).min(row[j + 1] + 1);
+            diagonal = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
+
+/// `value` of the setting `name` as it may be shown in a bug report
+///
+/// Secrets and variables `cm` doesn't know only show that they are set,
+/// and headers only their names.
+pub fn redacted(name: &str, value: &str) -> String {
+    match SETTINGS.iter().find(|(known, _)| *known == name).map(|(_, kind)| *kind) {
+        Some(Kind::Secret) | None => "(set)".to_string(),
+        Some(Kind::Headers) => {
+            let names: Vec<&str> = value
+                .split(',')
+                .filter_map(|header| header.split_once(':').map(|(name, _)| name.trim()))
+                .filter(|name| !name.is_empty())
+                .collect();
+            format!("(headers: {})", names.join(", "))
+        }
+        _ => value.to_string(),
+    }
+}
+
+/// The problems with the current environment, sorted by name
+pub fn check_env() -> Vec<Problem> {
+    let mut vars: Vec<(String, String)> = env::vars().collect();
+    vars.sort();
+    check(vars)
+}
+
+/// Report every problem with the `CM_*` settings (`cm config check`)
+///
+/// Reads only the environment, so it works without an API key and shows
+/// all problems at once instead of stopping at the first.
+pub fn run_check() -> Result<Exit, Box<dyn std::error::Error>> {
+    let problems = check_env();
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    if !problems.is_empty() {
+        return Err(Failure::new(Exit::InvalidConfig, format!("{} problem(s) with the CM_* settings", problems.len())).into());
+    }
+    let count = env::vars().filter(|(name, _)| name.starts_with("CM_")).count();
+    ui::status("✓ ", format!("{} CM_* setting(s), no problems", count));
+    Ok(Exit::Success)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_check() {
+        let problems = check(vars(&[
+            ("CM_EMOJY", "false"),
+            ("CM_EMOJI", "yes"),
+            ("CM_STYLE", "consise"),
+            ("CM_CONTEXT", "history,stats"),
+            ("CM_CANDIDATES", "three"),
+            ("CM_TEMPERATURE", "0.2"),
+            ("CM_BODY_STYLE", ""),
+            ("CM_NOTHING_LIKE_IT", "1"),
+            ("PATH", "/bin"),
+        ]));
+        assert_eq!(
+            problems.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "CM_EMOJY is not a setting and is ignored. Did you mean CM_EMOJI?",
+                "CM_EMOJI='yes' is not valid: use 1, 0, true or false. Did you mean CM_EMOJI=1?",
+                "CM_STYLE='consise' is not valid: use balanced, concise, detailed, explanatory. Did you mean CM_STYLE=concise?",
+                "CM_CONTEXT='history,stats' is not valid: use any of history, stat, diff, branch, issues, packages, previous. Did you mean CM_CONTEXT=history,stat?",
+                "CM_CANDIDATES='three' is not valid: use a whole number",
+                "CM_NOTHING_LIKE_IT is not a setting and is ignored. See 'cm help' for the settings",
+            ]
+        );
+        assert_eq!(distance("kitten", "sitting"), 3);
+    }
+}
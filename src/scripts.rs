@@ -0,0 +1,245 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use serde_json::{json, Value};
+
+use crate::config::HookScripts;
+use crate::context::GitContext;
+use crate::exit::{Exit, Failure};
+use crate::i18n::{tf, Msg};
+use crate::message::CommitMessage;
+use crate::prompt::Prompt;
+
+/// Where a `CM_HOOK_*` script runs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Point {
+    /// Before each request, with the prompt and the changes
+    PreGenerate,
+    /// For each generated message
+    PostGenerate,
+    /// Right before committing, with the final message
+    PreCommitMessage,
+}
+
+impl Point {
+    /// Name sent to the script in `hook` and `CM_HOOK`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PreGenerate => "pre_generate",
+            Self::PostGenerate => "post_generate",
+            Self::PreCommitMessage => "pre_commit_message",
+        }
+    }
+}
+
+/// What a hook script made of its input
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    /// Exit 0 with no output
+    Keep,
+    /// Exit 0 with a JSON object: the fields it sets replace those sent
+    Replace(Value),
+    /// Non-zero exit, with the first line it printed as the reason
+    Veto(String),
+}
+
+/// `command` run through the shell, as `CM_CHECK_COMMAND` and hook scripts are
+pub fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+/// Run `command` for `point` with `input` as JSON on stdin
+///
+/// A script that can't be run, or prints something other than a JSON
+/// object, vetoes: teams use hooks to enforce rules, so they fail closed.
+pub fn run(command: &str, point: Point, input: &Value) -> Verdict {
+    let spawned = shell(command)
+        .env("CM_HOOK", point.name())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => return Verdict::Veto(e.to_string()),
+    };
+    // Written meanwhile, so a script that prints while it reads, such as a
+    // `sed` filter, can't fill its stdout pipe and wait on us
+    let writer = child.stdin.take().map(|mut stdin| {
+        let input = input.to_string();
+        thread::spawn(move || {
+            let _ = stdin.write_all(input.as_bytes());
+        })
+    });
+    let output = child.wait_with_output();
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => return Verdict::Veto(e.to_string()),
+    };
+    log::debug!("{} hook `{}` -> {}", point.name(), command, output.status);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = [stdout.trim(), stderr.trim()]
+            .into_iter()
+            .find_map(|text| text.lines().find(|line| !line.trim().is_empty()))
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("exited with {}", output.status));
+        return Verdict::Veto(reason);
+    }
+    if stdout.trim().is_empty() {
+        return Verdict::Keep;
+    }
+    match serde_json::from_str::<Value>(&stdout) {
+        Ok(value) if value.is_object() => Verdict::Replace(value),
+        _ => Verdict::Veto("printed something other than a JSON object".to_string()),
+    }
+}
+
+/// Run the `pre_generate` script, which may rewrite the prompt or stop the run
+pub fn pre_generate(hooks: &HookScripts, prompt: &mut Prompt, context: &GitContext) -> Result<(), Failure> {
+    let Some(command) = &hooks.pre_generate else {
+        return Ok(());
+    };
+    let input = json!({
+        "hook": Point::PreGenerate.name(),
+        "branch": context.branch,
+        "files": context.paths(),
+        "diff": context.diff,
+        "prompt": { "system": prompt.system, "user": prompt.user },
+    });
+    match run(command, Point::PreGenerate, &input) {
+        Verdict::Keep => {}
+        Verdict::Replace(output) => {
+            if let Some(system) = output.pointer("/prompt/system").and_then(Value::as_str) {
+                prompt.system = system.to_string();
+            }
+            if let Some(user) = output.pointer("/prompt/user").and_then(Value::as_str) {
+                prompt.user = user.to_string();
+            }
+        }
+        Verdict::Veto(reason) => return Err(vetoed(Point::PreGenerate, &reason)),
+    }
+    Ok(())
+}
+
+/// Run the `post_generate` script on a generated message, returning why it
+/// vetoed it, if it did
+pub fn post_generate(hooks: &HookScripts, message: &mut CommitMessage) -> Result<(), String> {
+    let Some(command) = &hooks.post_generate else {
+        return Ok(());
+    };
+    let input = json!({
+        "hook": Point::PostGenerate.name(),
+        "subject": message.subject,
+        "body": message.body,
+    });
+    match run(command, Point::PostGenerate, &input) {
+        Verdict::Keep => {}
+        Verdict::Replace(output) => {
+            if let Some(subject) = output.get("subject").and_then(Value::as_str) {
+                message.subject = subject.trim().to_string();
+            }
+            if let Some(body) = output.get("body") {
+                message.body = body.as_str().map(str::trim).filter(|b| !b.is_empty()).map(String::from);
+            }
+        }
+        Verdict::Veto(reason) => return Err(reason),
+    }
+    Ok(())
+}
+
+/// Run the `pre_commit_message` script on the message about to be committed,
+/// returning the message to commit
+pub fn pre_commit_message(hooks: &HookScripts, message: &str) -> Result<String, Failure> {
+    let Some(command) = &hooks.pre_commit_message else {
+        return Ok(message.to_string());
+    };
+    let input = json!({
+        "hook": Point::PreCommitMessage.name(),
+        "message": message,
+    });
+    match run(command, Point::PreCommitMessage, &input) {
+        Verdict::Keep => Ok(message.to_string()),
+        Verdict::Replace(output) => Ok(output
+            .get("message")
+            .and_then(Value::as_str)
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty())
+            .unwrap_or_else(|| message.to_string())),
+        Verdict::Veto(reason) => Err(vetoed(Point::PreCommitMessage, &reason)),
+    }
+}
+
+fn vetoed(point: Point, reason: &str) -> Failure {
+    Failure::new(Exit::Vetoed, tf(Msg::HookVetoed, &[&point.name(), &reason]))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verdicts() {
+        let input = json!({ "hook": "post_generate", "subject": "feat: add x" });
+        assert_eq!(run("cat >/dev/null", Point::PostGenerate, &input), Verdict::Keep);
+        assert_eq!(
+            run("echo 'needs a ticket' >&2; exit 1", Point::PostGenerate, &input),
+            Verdict::Veto("needs a ticket".to_string())
+        );
+        assert_eq!(
+            run("echo not json", Point::PostGenerate, &input),
+            Verdict::Veto("printed something other than a JSON object".to_string())
+        );
+        assert_eq!(
+            run(r#"test "$CM_HOOK" = post_generate && echo '{"subject": "feat: add y"}'"#, Point::PostGenerate, &input),
+            Verdict::Replace(json!({ "subject": "feat: add y" }))
+        );
+
+        // More than a pipe buffer (64 KiB) passed straight through
+        let large = json!({ "hook": "pre_generate", "prompt": { "user": "x".repeat(256 * 1024) } });
+        assert_eq!(run("cat", Point::PreGenerate, &large), Verdict::Replace(large.clone()));
+    }
+
+    #[test]
+    fn test_hook_points() {
+        let hooks = HookScripts {
+            pre_generate: Some(r#"echo '{"prompt": {"user": "rewritten"}}'"#.to_string()),
+            post_generate: Some(r#"sed 's/"subject":"feat: /"subject":"feat(ui): /'"#.to_string()),
+            pre_commit_message: Some("echo 'blocked by policy'; exit 3".to_string()),
+        };
+
+        let mut prompt = Prompt {
+            system: "system".to_string(),
+            user: "user".to_string(),
+        };
+        pre_generate(&hooks, &mut prompt, &GitContext::from_diff(String::new(), String::new())).unwrap();
+        assert_eq!((prompt.system.as_str(), prompt.user.as_str()), ("system", "rewritten"));
+
+        let mut message = CommitMessage {
+            subject: "feat: add dark mode".to_string(),
+            body: Some("Follows the OS setting.".to_string()),
+        };
+        post_generate(&hooks, &mut message).unwrap();
+        assert_eq!(message.subject, "feat(ui): add dark mode");
+        assert_eq!(message.body.as_deref(), Some("Follows the OS setting."));
+
+        let error = pre_commit_message(&hooks, "feat: add x").unwrap_err();
+        assert_eq!(error.exit, Exit::Vetoed);
+        assert!(error.to_string().contains("blocked by policy"));
+        assert_eq!(pre_commit_message(&HookScripts::default(), "feat: add x").unwrap(), "feat: add x");
+    }
+}
@@ -0,0 +1,243 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::json;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::config::{serve_token, Config};
+use crate::exit::{Exit, Failure};
+use crate::provider::{create_provider, AiProvider};
+use crate::unattended::{self, Changes};
+use crate::{ui, vault};
+
+/// Largest request body accepted; diffs beyond this are truncated by the client anyway
+const MAX_BODY_BYTES: usize = 8 * 1024 * 1024;
+
+/// How long a client may take to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Body of `POST /generate`
+#[derive(Debug, Deserialize)]
+struct GenerateRequest {
+    diff: String,
+    /// Recent commit subjects, newest first, used as style reference
+    #[serde(default)]
+    history: Vec<String>,
+    /// Branch name for templates and ticket detection
+    branch: Option<String>,
+}
+
+/// Request line and the headers the server cares about
+#[derive(Debug, Default, PartialEq)]
+struct Head {
+    method: String,
+    path: String,
+    content_length: usize,
+    authorization: Option<String>,
+}
+
+/// An HTTP status with a JSON error message
+type HttpError = (u16, String);
+
+/// State shared by every connection
+struct Server {
+    config: Config,
+    provider: Box<dyn AiProvider>,
+    token: String,
+}
+
+/// Serve `POST /generate` on `addr` until interrupted
+pub async fn run_serve(addr: &str) -> Result<Exit, Box<dyn std::error::Error>> {
+    let token = serve_token().ok_or_else(|| {
+        Failure::new(Exit::InvalidConfig, "Set CM_SERVE_TOKEN to the bearer token clients must send")
+    })?;
+    let mut config = Config::from_env()?;
+    // Responses go to one caller, so there is nobody to pick between candidates
+    config.candidates = 1;
+    vault::configure(config.encrypt_store);
+
+    let provider = create_provider(&config);
+    let listener = TcpListener::bind(addr).await?;
+    ui::status(
+        "🌐 ",
+        format!(
+            "Serving POST /generate on http://{} with {} ({})",
+            listener.local_addr()?,
+            provider.name(),
+            provider.model()
+        ),
+    );
+    let server = Arc::new(Server { config, provider, token });
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let server = Arc::clone(&server);
+        tokio::spawn(async move {
+            if let Err(e) = server.handle(stream).await {
+                log::debug!("Connection from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+impl Server {
+    async fn handle(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        let start = Instant::now();
+        let (status, body) = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+            Ok(Ok((head, body))) => {
+                let result = self.route(&head, &body).await;
+                ui::status(
+                    "📨 ",
+                    format!(
+                        "{} {} -> {} in {:?}",
+                        head.method,
+                        head.path,
+                        result.as_ref().map_or_else(|(status, _)| *status, |_| 200),
+                        start.elapsed()
+                    ),
+                );
+                match result {
+                    Ok(body) => (200, body),
+                    Err((status, message)) => (status, json!({ "error": message })),
+                }
+            }
+            Ok(Err((status, message))) => (status, json!({ "error": message })),
+            Err(_) => (408, json!({ "error": "Timed out reading the request" })),
+        };
+
+        let body = body.to_string();
+        let response = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            reason(status),
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await
+    }
+
+    async fn route(&self, head: &Head, body: &[u8]) -> Result<serde_json::Value, HttpError> {
+        if head.path != "/generate" {
+            return Err((404, format!("No route for {}", head.path)));
+        }
+        if head.method != "POST" {
+            return Err((405, "Use POST /generate".to_string()));
+        }
+        if !authorized(head.authorization.as_deref(), &self.token) {
+            return Err((401, "Missing or wrong bearer token".to_string()));
+        }
+        let request: GenerateRequest =
+            serde_json::from_slice(body).map_err(|e| (400, format!("Invalid request body: {}", e)))?;
+
+        let changes = Changes {
+            diff: &request.diff,
+            history: &request.history,
+            branch: request.branch.as_deref(),
+        };
+        let message = unattended::generate(&self.config, self.provider.as_ref(), &changes)
+            .await
+            .map_err(|failure| (failure_status(failure.exit), failure.to_string()))?;
+        Ok(json!({
+            "subject": message.subject,
+            "body": message.body,
+            "message": message.to_git_message(),
+        }))
+    }
+}
+
+/// Read the request head and body, rejecting anything the API doesn't accept
+async fn read_request(stream: &mut TcpStream) -> Result<(Head, Vec<u8>), HttpError> {
+    let mut reader = BufReader::new(stream);
+    let mut text = String::new();
+    loop {
+        let mut line = String::new();
+        let read = reader.read_line(&mut line).await.map_err(|e| (400, e.to_string()))?;
+        if read == 0 || line.trim_end().is_empty() {
+            break;
+        }
+        text.push_str(&line);
+    }
+
+    let head = parse_head(&text).ok_or((400, "Malformed HTTP request".to_string()))?;
+    if head.content_length > MAX_BODY_BYTES {
+        return Err((413, format!("Request bodies are limited to {} bytes", MAX_BODY_BYTES)));
+    }
+    let mut body = vec![0; head.content_length];
+    reader.read_exact(&mut body).await.map_err(|e| (400, e.to_string()))?;
+    Ok((head, body))
+}
+
+fn parse_head(text: &str) -> Option<Head> {
+    let mut lines = text.lines();
+    let mut request_line = lines.next()?.split_whitespace();
+    let mut head = Head {
+        method: request_line.next()?.to_string(),
+        path: request_line.next()?.to_string(),
+        ..Head::default()
+    };
+    for line in lines {
+        let (name, value) = line.split_once(':')?;
+        match name.trim().to_lowercase().as_str() {
+            "content-length" => head.content_length = value.trim().parse().ok()?,
+            "authorization" => head.authorization = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+    Some(head)
+}
+
+/// Whether `header` carries `token`, compared in constant time
+fn authorized(header: Option<&str>, token: &str) -> bool {
+    let Some(sent) = header.and_then(|h| h.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    sent.len() == token.len() && sent.bytes().zip(token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// HTTP status for a failed generation
+fn failure_status(exit: Exit) -> u16 {
+    match exit {
+        Exit::NoStagedChanges => 400,
+        Exit::QuotaExceeded => 429,
+        // Including a rejected key: that's the server's, the client's request was fine
+        _ => 502,
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        429 => "Too Many Requests",
+        _ => "Bad Gateway",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_head() {
+        let head = parse_head(
+            "POST /generate HTTP/1.1\r\nHost: localhost\r\nContent-Length: 42\r\nAuthorization: Bearer s3cret\r\n",
+        )
+        .unwrap();
+        assert_eq!(head.method, "POST");
+        assert_eq!(head.path, "/generate");
+        assert_eq!(head.content_length, 42);
+        assert!(authorized(head.authorization.as_deref(), "s3cret"));
+        assert!(!authorized(head.authorization.as_deref(), "s3cre"));
+        assert!(!authorized(Some("Basic s3cret"), "s3cret"));
+        assert!(!authorized(None, "s3cret"));
+        assert!(parse_head("garbage").is_none());
+    }
+}
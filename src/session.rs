@@ -0,0 +1,516 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::io;
+use std::path::Path;
+
+use crate::audit;
+use crate::config::{BudgetAction, Config};
+use crate::context::GitContext;
+use crate::diff::{parse_diff, render_diff, Exclusion, FileDiff};
+use crate::exit::{provider_failure, Exit, Failure};
+use crate::heuristics::TypeHint;
+use crate::i18n::{t, tf, Msg};
+use crate::learn::Preferences;
+use crate::message::CommitMessage;
+use crate::postprocess::{Origin, Pipeline};
+use crate::privacy::Privacy;
+use crate::prompt::Prompt;
+use crate::quality;
+use crate::scripts;
+use crate::provider::AiProvider;
+use crate::spend::{self, prompt_tokens};
+use crate::telemetry;
+use crate::ui::{self, display_commit_message, UserAction};
+use crate::vcs::{GitError, Vcs};
+use crate::{confirm_context, fit_prompt, generate_candidates, guard_branch, hook, push_all, setup_guidance, with_rejected};
+
+/// The questions a session asks the user, so tests can script the answers
+pub trait Interaction {
+    /// Show the message about to be reviewed
+    fn show(&mut self, message: &CommitMessage, provider: &str, model: &str);
+    /// Pick one of `count` candidates, returning its zero-based index
+    fn choose(&mut self, count: usize) -> io::Result<usize>;
+    /// What to do with the message shown last
+    fn action(&mut self) -> io::Result<UserAction>;
+    /// Let the user rewrite `message`, returning the result
+    fn edit(&mut self, message: &str) -> Result<String, Box<dyn Error>>;
+    /// Toggle files and hunks to withhold from the prompt
+    fn exclude(&mut self, files: &[FileDiff], excluded: &mut HashSet<Exclusion>) -> io::Result<()>;
+    /// Whether to push after committing
+    fn confirm_push(&mut self) -> io::Result<bool>;
+    /// Whether to commit a message marking a breaking change
+    fn confirm_breaking(&mut self) -> io::Result<bool>;
+}
+
+/// The real terminal
+pub struct Terminal;
+
+impl Interaction for Terminal {
+    fn show(&mut self, message: &CommitMessage, provider: &str, model: &str) {
+        let quality = quality::score(message).score;
+        display_commit_message(&message.subject, message.body.as_deref(), Some(quality), provider, model);
+    }
+
+    fn choose(&mut self, count: usize) -> io::Result<usize> {
+        crate::prompt_candidate(count)
+    }
+
+    fn action(&mut self) -> io::Result<UserAction> {
+        crate::prompt_action()
+    }
+
+    fn edit(&mut self, message: &str) -> Result<String, Box<dyn Error>> {
+        crate::edit_message(message)
+    }
+
+    fn exclude(&mut self, files: &[FileDiff], excluded: &mut HashSet<Exclusion>) -> io::Result<()> {
+        crate::select_exclusions(files, excluded)
+    }
+
+    fn confirm_push(&mut self) -> io::Result<bool> {
+        crate::ask_push()
+    }
+
+    fn confirm_breaking(&mut self) -> io::Result<bool> {
+        crate::ask_breaking()
+    }
+}
+
+/// Where a session is between the user's decisions
+#[derive(Debug, Clone, PartialEq)]
+pub enum State {
+    /// A message has to be generated: the first one, or another after regenerating
+    Generating,
+    /// A message was generated and shown
+    Generated(CommitMessage),
+    /// The user decided what to do with the message
+    Reviewed(CommitMessage, UserAction),
+    /// The user rewrote the message they were given (`original`, without emoji)
+    Edited { original: String, edited: CommitMessage },
+    /// The message was committed
+    Committed,
+    /// The message was committed and pushed
+    Pushed,
+    /// The message was printed or written for a hook instead of committed
+    Printed,
+    /// The user quit or left the message empty
+    Aborted,
+}
+
+impl State {
+    /// Whether the session is over
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Committed | Self::Pushed | Self::Printed | Self::Aborted)
+    }
+}
+
+/// What the prompt is rebuilt from when the user regenerates or withholds files
+pub struct PromptParts<'a> {
+    pub vars: &'a HashMap<&'static str, String>,
+    /// Extra prompt sections, before the rejected subjects are added
+    pub extra: &'a str,
+    pub type_hint: Option<&'a TypeHint>,
+    pub privacy: &'a Privacy,
+    /// History and statistics, already scrubbed
+    pub context: &'a GitContext,
+    /// Files whose diff is sent, minus generated ones
+    pub files: &'a [FileDiff],
+}
+
+/// One interactive run, from the first generation to the commit
+pub struct Session<'a> {
+    pub config: &'a Config,
+    pub provider: &'a dyn AiProvider,
+    /// Required to commit; printing works without one
+    pub repo: Option<&'a dyn Vcs>,
+    pub ui: &'a mut dyn Interaction,
+    pub parts: PromptParts<'a>,
+    /// The first prompt; later ones are built from `parts`
+    pub prompt: Prompt,
+    /// A message written without the AI, used for the first attempt
+    pub local_message: Option<CommitMessage>,
+    /// Steps every message goes through, generated or edited
+    pub pipeline: Pipeline<'a>,
+    /// Print the message (or write it to `message_file`) instead of committing
+    pub print_only: bool,
+    pub message_file: Option<&'a str>,
+    /// Commit only these paths (`--only`); everything staged when empty
+    pub commit_paths: &'a [String],
+    pub preferences: Option<&'a mut Preferences>,
+    /// Let the user withhold files and hunks before anything is sent (`--exclude`)
+    pub exclude_first: bool,
+    /// Reviewers suggested once committed
+    pub reviewers: Vec<String>,
+}
+
+/// What changes while a session runs
+#[derive(Default)]
+struct Progress {
+    attempts: u32,
+    context_confirmed: bool,
+    excluded: HashSet<Exclusion>,
+    /// Subjects the user turned down with "regenerate", so the next attempt differs
+    rejected: Vec<String>,
+    /// Subjects shown by the last generation
+    shown: Vec<String>,
+}
+
+impl Session<'_> {
+    /// Run until the message is committed, printed or abandoned
+    pub async fn run(mut self) -> Result<Exit, Box<dyn Error>> {
+        let mut progress = Progress::default();
+        let mut state = State::Generating;
+        while !state.is_final() {
+            state = self.step(&mut progress, state).await?;
+        }
+        if matches!(state, State::Committed | State::Pushed) && !self.reviewers.is_empty() {
+            ui::status("👥 ", tf(Msg::SuggestedReviewers, &[&self.reviewers.join(", ")]));
+        }
+        Ok(if state == State::Aborted { Exit::Aborted } else { Exit::Success })
+    }
+
+    /// Move one state on
+    async fn step(&mut self, progress: &mut Progress, state: State) -> Result<State, Box<dyn Error>> {
+        Ok(match state {
+            State::Generating => self.generate(progress).await?,
+            State::Generated(message) => {
+                let banned = message.banned_phrases(&self.config.banned_words);
+                if !banned.is_empty() {
+                    let warning = tf(Msg::ContainsBanned, &[&banned.join(", ")]);
+                    ui::status("⛔ ", ui::paint(&warning, ui::theme().warning));
+                }
+                // Messages for stdin, --for and --patch-file are printed for the caller instead of committed
+                if self.print_only {
+                    let text = message.to_git_message();
+                    match self.message_file {
+                        Some(path) => hook::write_message(Path::new(path), &text)?,
+                        None => println!("{}", text),
+                    }
+                    audit::action(self.config, "print", Some(&text));
+                    telemetry::record_action("print");
+                    return Ok(State::Printed);
+                }
+                self.repo.ok_or(GitError::NotARepository)?;
+                let action = self.ui.action()?;
+                audit::action(self.config, action.name(), Some(&message.to_git_message()));
+                telemetry::record_action(action.name());
+                State::Reviewed(message, action)
+            }
+            State::Reviewed(message, UserAction::Accept) => {
+                if !self.breaking_confirmed(&message)? {
+                    return Ok(State::Generated(message));
+                }
+                self.commit(&message.to_git_message(), &message.subject)?;
+                if let Some(prefs) = self.preferences.as_mut() {
+                    prefs.record_accept();
+                    prefs.save();
+                }
+                self.push()?
+            }
+            State::Reviewed(message, UserAction::Edit) => {
+                // The user edits the message without emoji; the pipeline adds them back
+                let original = self.pipeline.plain(&message).to_git_message();
+                let edited = self.ui.edit(&original)?;
+                let Some(mut edited) = CommitMessage::parse_edited(&edited) else {
+                    ui::status("⚠️  ", t(Msg::EmptyMessage));
+                    return Ok(State::Aborted);
+                };
+                self.pipeline.apply(&mut edited, Origin::User);
+                State::Edited { original, edited }
+            }
+            State::Edited { original, edited } => {
+                if !self.breaking_confirmed(&edited)? {
+                    return Ok(State::Generated(edited));
+                }
+                let plain = self.pipeline.plain(&edited).to_git_message();
+                self.commit(&edited.to_git_message(), &plain)?;
+                if let Some(prefs) = self.preferences.as_mut() {
+                    prefs.record_edit(&original, &plain);
+                    prefs.save();
+                }
+                self.push()?
+            }
+            State::Reviewed(_, UserAction::Regenerate) => {
+                for subject in progress.shown.drain(..) {
+                    if !progress.rejected.contains(&subject) {
+                        progress.rejected.push(subject);
+                    }
+                }
+                self.prompt = self.rebuild_prompt(progress);
+                ui::status("🔄 ", t(Msg::Regenerating));
+                progress.attempts = 0;
+                State::Generating
+            }
+            State::Reviewed(_, UserAction::Exclude) => {
+                self.ui.exclude(self.parts.files, &mut progress.excluded)?;
+                self.prompt = self.rebuild_prompt(progress);
+                ui::status("🔄 ", tf(Msg::RegeneratingWithout, &[&progress.excluded.len()]));
+                progress.attempts = 0;
+                State::Generating
+            }
+            State::Reviewed(_, UserAction::Quit) => {
+                ui::status("👋 ", t(Msg::Aborted));
+                State::Aborted
+            }
+            done => done,
+        })
+    }
+
+    /// Produce and show the next message, or stay in [`State::Generating`] to retry
+    async fn generate(&mut self, progress: &mut Progress) -> Result<State, Box<dyn Error>> {
+        let config = self.config;
+        progress.attempts += 1;
+        if progress.attempts > config.max_retries {
+            let message = format!(
+                "Failed to generate a valid commit message after {} attempts",
+                config.max_retries
+            );
+            return Err(Failure::new(Exit::GenerationFailed, message).into());
+        }
+
+        // Dependency bumps get a precise message without calling the AI
+        if let Some(mut local) = self.local_message.take() {
+            ui::status("\n📌 ", t(Msg::LocalDependencies));
+            self.pipeline.apply(&mut local, Origin::Local);
+            if !self.print_only {
+                display_commit_message(&local.subject, local.body.as_deref(), None, "local", "deps");
+            }
+            return Ok(State::Generated(local));
+        }
+
+        if !progress.context_confirmed {
+            if self.exclude_first {
+                self.ui.exclude(self.parts.files, &mut progress.excluded)?;
+                if !progress.excluded.is_empty() {
+                    self.prompt = self.rebuild_prompt(progress);
+                }
+            }
+            let sent: Vec<FileDiff> = parse_diff(&render_diff(self.parts.files, &progress.excluded));
+            let lines: usize = sent.iter().map(|f| f.changed_lines().count()).sum();
+            confirm_context(config, self.provider, sent.len(), lines, prompt_tokens(&self.prompt))?;
+            progress.context_confirmed = true;
+        }
+
+        // The hook rewrites this attempt's prompt; the next one starts from the original again
+        let mut prompt = self.prompt.clone();
+        scripts::pre_generate(&config.hooks, &mut prompt, self.parts.context)?;
+
+        if config.candidates > 1 {
+            ui::status("\n⏳ ", tf(Msg::GeneratingMany, &[&config.candidates, &progress.attempts]));
+        } else {
+            ui::status("\n⏳ ", tf(Msg::Generating, &[&progress.attempts]));
+        }
+
+        let estimate = prompt_tokens(&prompt) * config.candidates as u64;
+        if let Some(reason) = spend::over_budget(&config.budget, config.provider.id(), estimate) {
+            if config.budget.action == BudgetAction::Refuse {
+                let message = format!(
+                    "{}.\nWrite this message yourself with 'git commit', raise CM_BUDGET_DAILY/CM_BUDGET_MONTHLY, or set CM_BUDGET_ACTION=warn.",
+                    reason
+                );
+                return Err(Failure::new(Exit::QuotaExceeded, message).into());
+            }
+            ui::warn("⚠️  ", tf(Msg::OverBudget, &[&reason]));
+        }
+
+        log::trace!(
+            "Prompt: {} system + {} user bytes, ~{} tokens",
+            prompt.system.len(),
+            prompt.user.len(),
+            prompt_tokens(&prompt)
+        );
+        let start = std::time::Instant::now();
+        let generated =
+            generate_candidates(self.provider, &prompt, config, self.parts.type_hint, &self.pipeline, !self.print_only)
+                .await;
+        log::debug!("Generation took {:?}", start.elapsed());
+        let mut candidates = match generated {
+            Ok(candidates) => candidates,
+            Err(e) if !e.is_retryable() => {
+                return Err(provider_failure(&e, setup_guidance(&e, config)).into());
+            }
+            Err(e) => {
+                ui::warn("⚠️  ", tf(Msg::ApiErrorRetrying, &[&e]));
+                return Ok(State::Generating);
+            }
+        };
+
+        progress.shown = candidates.iter().map(|c| self.pipeline.plain(c).subject).collect();
+
+        Ok(match candidates.len() {
+            0 => State::Generating,
+            1 => {
+                let only = candidates.remove(0);
+                if !self.print_only {
+                    self.ui.show(&only, self.provider.name(), self.provider.model());
+                }
+                State::Generated(only)
+            }
+            n => {
+                let choice = self.ui.choose(n)?;
+                State::Generated(candidates.swap_remove(choice))
+            }
+        })
+    }
+
+    /// The prompt with the current exclusions and rejected subjects
+    fn rebuild_prompt(&self, progress: &Progress) -> Prompt {
+        let parts = &self.parts;
+        let visible_diff = render_diff(parts.files, &progress.excluded);
+        fit_prompt(
+            self.config,
+            parts.vars,
+            with_rejected(parts.extra, &progress.rejected).as_deref(),
+            parts.type_hint,
+            &parts.privacy.apply(&visible_diff),
+            parts.context,
+        )
+    }
+
+    /// Whether `message` may be committed: release tools turn a breaking
+    /// marker into a major version, so one has to be confirmed separately
+    fn breaking_confirmed(&mut self, message: &CommitMessage) -> io::Result<bool> {
+        if !message.is_breaking() || self.ui.confirm_breaking()? {
+            return Ok(true);
+        }
+        ui::status("↩️  ", t(Msg::BreakingDeclined));
+        Ok(false)
+    }
+
+    /// Commit `message`, checking the branch against `summary` first
+    fn commit(&self, message: &str, summary: &str) -> Result<(), Box<dyn Error>> {
+        let repo = self.repo.ok_or(GitError::NotARepository)?;
+        let message = scripts::pre_commit_message(&self.config.hooks, message)?;
+        guard_branch(repo, self.config, summary)?;
+        ui::status("\n⏳ ", t(Msg::Committing));
+        repo.commit(&message, self.commit_paths)?;
+        audit::action(self.config, "commit", Some(&message));
+        ui::status("✓ ", t(Msg::Committed));
+        Ok(())
+    }
+
+    /// Push if the user wants to
+    fn push(&mut self) -> Result<State, Box<dyn Error>> {
+        if !self.ui.confirm_push()? {
+            return Ok(State::Committed);
+        }
+        let repo = self.repo.ok_or(GitError::NotARepository)?;
+        push_all(repo, &self.config.push_remotes)?;
+        Ok(State::Pushed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::{config, drive, MockProvider, Script};
+    use crate::vcs::{GitResult, LogEntry};
+    use std::cell::RefCell;
+
+    /// A repository that records commits and pushes instead of making them
+    #[derive(Default)]
+    struct FakeRepo {
+        commits: RefCell<Vec<String>>,
+        pushes: RefCell<usize>,
+    }
+
+    impl Vcs for FakeRepo {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+        fn has_index(&self) -> bool {
+            true
+        }
+        fn diff(&self, _pathspec: &[String]) -> GitResult<String> {
+            Ok(String::new())
+        }
+        fn diff_stat(&self, _pathspec: &[String]) -> String {
+            String::new()
+        }
+        fn changed_files(&self, _pathspec: &[String]) -> Vec<String> {
+            Vec::new()
+        }
+        fn unstaged_files(&self, _paths: &[String]) -> Vec<String> {
+            Vec::new()
+        }
+        fn commit_diff(&self, _rev: &str) -> GitResult<String> {
+            Ok(String::new())
+        }
+        fn parent(&self, rev: &str) -> String {
+            format!("{}^", rev)
+        }
+        fn history(&self, _count: usize, _tip: Option<&str>, _author: Option<&str>, _no_merges: bool) -> GitResult<Vec<LogEntry>> {
+            Ok(Vec::new())
+        }
+        fn config_value(&self, _key: &str) -> Option<String> {
+            None
+        }
+        fn branch(&self) -> Option<String> {
+            Some("feature".into())
+        }
+        fn root(&self) -> Option<String> {
+            None
+        }
+        fn commit(&self, message: &str, _paths: &[String]) -> GitResult<()> {
+            self.commits.borrow_mut().push(message.to_string());
+            Ok(())
+        }
+        fn push(&self, _remote: Option<&str>) -> GitResult<()> {
+            *self.pushes.borrow_mut() += 1;
+            Ok(())
+        }
+        fn create_branch(&self, _name: &str) -> GitResult<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_flows() {
+        let config = config();
+        let context = GitContext::default();
+
+        // Regenerating asks again, and the second message is the one committed and pushed
+        let (repo, provider) = (FakeRepo::default(), MockProvider::default());
+        let mut script = Script {
+            push: true,
+            ..Script::new([UserAction::Regenerate, UserAction::Accept])
+        };
+        assert_eq!(drive(&config, &repo, &provider, &mut script, &context).await.unwrap(), Exit::Success);
+        assert_eq!(script.shown.len(), 2);
+        assert_eq!(*repo.commits.borrow(), vec!["feat: add token refresh handling 2"]);
+        assert_eq!(*repo.pushes.borrow(), 1);
+
+        // An edited message is committed as edited
+        let (repo, provider) = (FakeRepo::default(), MockProvider::default());
+        let mut script = Script {
+            edit: Some("fix: handle expired tokens\n\nRefresh them first.".into()),
+            ..Script::new([UserAction::Edit])
+        };
+        assert_eq!(drive(&config, &repo, &provider, &mut script, &context).await.unwrap(), Exit::Success);
+        assert_eq!(*repo.commits.borrow(), vec!["fix: handle expired tokens\n\nRefresh them first."]);
+        assert_eq!(*repo.pushes.borrow(), 0);
+
+        // Quitting, or emptying the message in the editor, commits nothing
+        let (repo, provider) = (FakeRepo::default(), MockProvider::default());
+        let mut script = Script::new([UserAction::Exclude, UserAction::Quit]);
+        assert_eq!(drive(&config, &repo, &provider, &mut script, &context).await.unwrap(), Exit::Aborted);
+        let mut script = Script {
+            edit: Some("\n\n".into()),
+            ..Script::new([UserAction::Edit])
+        };
+        assert_eq!(drive(&config, &repo, &provider, &mut script, &context).await.unwrap(), Exit::Aborted);
+        assert!(repo.commits.borrow().is_empty());
+
+        // A breaking change is committed only once confirmed; declining goes back to the actions
+        let mut script = Script {
+            edit: Some("feat!: drop the v1 token endpoint".into()),
+            ..Script::new([UserAction::Edit, UserAction::Quit])
+        };
+        assert_eq!(drive(&config, &repo, &provider, &mut script, &context).await.unwrap(), Exit::Aborted);
+        assert!(repo.commits.borrow().is_empty());
+        script.actions.push_back(UserAction::Edit);
+        script.breaking = true;
+        assert_eq!(drive(&config, &repo, &provider, &mut script, &context).await.unwrap(), Exit::Success);
+        assert_eq!(*repo.commits.borrow(), vec!["feat!: drop the v1 token endpoint"]);
+    }
+}
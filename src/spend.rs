@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::budget::estimate_tokens;
+use crate::config::{cache_dir, BudgetOptions, SpendLimit};
+use crate::prompt::Prompt;
+use crate::template::today;
+
+/// Estimated tokens sent and received, per provider and day
+///
+/// Providers don't all report usage, so counts use the same estimate as the
+/// prompt budget. Only the current month is kept.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Ledger {
+    /// `provider/YYYY-MM-DD` → tokens
+    days: BTreeMap<String, u64>,
+}
+
+fn ledger_path() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("usage.json"))
+}
+
+impl Ledger {
+    pub fn load() -> Self {
+        ledger_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        let Some(path) = ledger_path() else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string_pretty(self) {
+            let _ = fs::write(path, json);
+        }
+    }
+
+    fn add(&mut self, provider: &str, date: &str, tokens: u64) {
+        let month = &date[..7];
+        self.days.retain(|key, _| key.split_once('/').is_some_and(|(_, d)| d.starts_with(month)));
+        *self.days.entry(format!("{}/{}", provider, date)).or_insert(0) += tokens;
+    }
+
+    /// Tokens used by `provider` on dates starting with `period` (a day or a month)
+    fn used(&self, provider: &str, period: &str) -> u64 {
+        let prefix = format!("{}/{}", provider, period);
+        self.days
+            .iter()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .map(|(_, tokens)| tokens)
+            .sum()
+    }
+
+    /// Which limit, if any, `estimate` more tokens would exceed
+    fn exceeded(&self, budget: &BudgetOptions, provider: &str, date: &str, estimate: u64) -> Option<String> {
+        let periods = [("daily", budget.daily, date), ("monthly", budget.monthly, &date[..7])];
+        periods.into_iter().find_map(|(label, limit, period)| {
+            let limit = limit?;
+            let cap = match limit {
+                SpendLimit::Tokens(tokens) => tokens,
+                SpendLimit::Dollars(dollars) => (dollars / budget.price_per_mtok? * 1_000_000.0) as u64,
+            };
+            let used = self.used(provider, period);
+            (used + estimate > cap).then(|| {
+                let shown = match limit {
+                    SpendLimit::Tokens(tokens) => format!("{} tokens", tokens),
+                    SpendLimit::Dollars(dollars) => format!("${:.2}", dollars),
+                };
+                format!(
+                    "{} budget of {} for {} reached ({} used, ~{} needed)",
+                    label, shown, provider, used, estimate
+                )
+            })
+        })
+    }
+}
+
+/// Estimated size of a prompt, in tokens
+pub fn prompt_tokens(prompt: &Prompt) -> u64 {
+    (estimate_tokens(&prompt.system) + estimate_tokens(&prompt.user)) as u64
+}
+
+/// Describe the exceeded limit if `estimate` more tokens would go over budget
+pub fn over_budget(budget: &BudgetOptions, provider: &str, estimate: u64) -> Option<String> {
+    if !budget.is_set() {
+        return None;
+    }
+    Ledger::load().exceeded(budget, provider, &today(), estimate)
+}
+
+/// Add tokens spent on a request to today's total
+pub fn record(budget: &BudgetOptions, provider: &str, tokens: u64) {
+    if !budget.is_set() || tokens == 0 {
+        return;
+    }
+    let mut ledger = Ledger::load();
+    ledger.add(provider, &today(), tokens);
+    ledger.save();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits() {
+        let mut ledger = Ledger::default();
+        ledger.add("gemini", "2026-03-01", 900);
+        ledger.add("gemini", "2026-03-02", 400);
+        ledger.add("openrouter", "2026-03-02", 5000);
+
+        let budget = BudgetOptions {
+            daily: Some(SpendLimit::Tokens(500)),
+            monthly: Some(SpendLimit::Dollars(0.0015)),
+            price_per_mtok: Some(1.0),
+            ..BudgetOptions::default()
+        };
+        assert!(ledger.exceeded(&budget, "gemini", "2026-03-02", 50).is_none());
+        assert!(ledger.exceeded(&budget, "gemini", "2026-03-02", 150).unwrap().starts_with("daily"));
+        assert!(ledger.exceeded(&budget, "gemini", "2026-03-03", 300).unwrap().starts_with("monthly"));
+    }
+
+    #[test]
+    fn test_old_months_are_dropped() {
+        let mut ledger = Ledger::default();
+        ledger.add("gemini", "2026-02-28", 100);
+        ledger.add("gemini", "2026-03-01", 10);
+        assert_eq!(ledger.used("gemini", "2026"), 10);
+    }
+}
@@ -0,0 +1,237 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::config::{cache_dir, telemetry_blocked, telemetry_url};
+use crate::exit::Exit;
+use crate::http;
+use crate::template::today;
+use crate::ui;
+
+/// Giving up on the endpoint rather than holding up the exit
+const SEND_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// `cm telemetry <action>`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    /// Whether telemetry is on, where it goes and what was counted
+    Status,
+    On,
+    /// Turn telemetry off and forget the counts
+    Off,
+    /// Print exactly what the next report would send
+    Preview,
+}
+
+impl Action {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "status" => Some(Self::Status),
+            "on" => Some(Self::On),
+            "off" => Some(Self::Off),
+            "preview" => Some(Self::Preview),
+            _ => None,
+        }
+    }
+}
+
+/// Usage counts kept while telemetry is on
+///
+/// Only how often `cm` ran, with which provider, and what was done with
+/// the messages is counted; never code, messages, paths, models or any
+/// identifier, so reports can't be told apart or tied to a repository.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Counts {
+    /// Set by `cm telemetry on`; off unless the user opted in
+    #[serde(default)]
+    enabled: bool,
+    /// Day of the last report, so at most one is sent a day
+    #[serde(default)]
+    last_sent: Option<String>,
+    #[serde(default)]
+    runs: u64,
+    /// Provider id → runs
+    #[serde(default)]
+    providers: BTreeMap<String, u64>,
+    /// `accept`, `edit`, `regenerate`, `exclude`, `quit` or `print` → times chosen
+    #[serde(default)]
+    actions: BTreeMap<String, u64>,
+}
+
+fn counts_path() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("telemetry.json"))
+}
+
+impl Counts {
+    pub fn load() -> Self {
+        counts_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        let Some(path) = counts_path() else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string_pretty(self) {
+            let _ = fs::write(path, json);
+        }
+    }
+
+    /// Opted in and not overridden by `CM_TELEMETRY=0` or `DO_NOT_TRACK=1`
+    fn active(&self) -> bool {
+        self.enabled && !telemetry_blocked()
+    }
+
+    fn add_run(&mut self, provider: &str) {
+        self.runs += 1;
+        *self.providers.entry(provider.to_string()).or_insert(0) += 1;
+    }
+
+    fn add_action(&mut self, action: &str) {
+        *self.actions.entry(action.to_string()).or_insert(0) += 1;
+    }
+
+    fn reset(&mut self) {
+        self.runs = 0;
+        self.providers.clear();
+        self.actions.clear();
+    }
+
+    /// The report, exactly as it is sent
+    pub fn payload(&self) -> Value {
+        json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "os": std::env::consts::OS,
+            "runs": self.runs,
+            "providers": self.providers,
+            "actions": self.actions,
+        })
+    }
+}
+
+/// Count a generation run with `provider`, if telemetry is on
+pub fn record_run(provider: &str) {
+    let mut counts = Counts::load();
+    if counts.active() {
+        counts.add_run(provider);
+        counts.save();
+    }
+}
+
+/// Count what the user did with a message, if telemetry is on
+pub fn record_action(action: &str) {
+    let mut counts = Counts::load();
+    if counts.active() {
+        counts.add_action(action);
+        counts.save();
+    }
+}
+
+/// Send the counts to `CM_TELEMETRY_URL`, at most once a day
+///
+/// The counts start over once they were delivered; failures are only
+/// logged so telemetry can never get in the way of committing.
+pub async fn flush() {
+    let mut counts = Counts::load();
+    let Some(url) = telemetry_url() else {
+        return;
+    };
+    let date = today();
+    if !counts.active() || counts.runs == 0 || counts.last_sent.as_deref() == Some(date.as_str()) {
+        return;
+    }
+    let sent = http::client()
+        .post(&url)
+        .timeout(SEND_TIMEOUT)
+        .json(&counts.payload())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    match sent {
+        Ok(_) => {
+            counts.reset();
+            counts.last_sent = Some(date);
+            counts.save();
+        }
+        Err(e) => log::debug!("Telemetry not sent to {}: {}", url, e),
+    }
+}
+
+/// Show, turn on, turn off or preview telemetry (`cm telemetry status|on|off|preview`)
+pub fn run_telemetry(action: Action) -> Result<Exit, Box<dyn std::error::Error>> {
+    let mut counts = Counts::load();
+    match action {
+        Action::Status => {
+            let state = match (counts.enabled, telemetry_blocked()) {
+                (true, true) => "on, but blocked by CM_TELEMETRY=0 or DO_NOT_TRACK=1",
+                (true, false) => "on",
+                (false, _) => "off",
+            };
+            println!("Telemetry: {}", state);
+            println!("Endpoint:  {}", telemetry_url().as_deref().unwrap_or("none (set CM_TELEMETRY_URL to send)"));
+            if let Some(path) = counts_path() {
+                println!("Counts:    {}", path.display());
+            }
+            println!("Last sent: {}", counts.last_sent.as_deref().unwrap_or("never"));
+            println!("Runs:      {}", counts.runs);
+        }
+        Action::On => {
+            counts.enabled = true;
+            counts.save();
+            ui::status("✓ ", "Telemetry is on. Only counts are kept; see them with 'cm telemetry preview'");
+            if telemetry_blocked() {
+                ui::warn("⚠️  ", "CM_TELEMETRY=0 or DO_NOT_TRACK=1 is set, so nothing is counted or sent");
+            }
+        }
+        Action::Off => {
+            counts.enabled = false;
+            counts.reset();
+            counts.save();
+            ui::status("✓ ", "Telemetry is off and the counts were deleted");
+        }
+        Action::Preview => {
+            println!("{}", serde_json::to_string_pretty(&counts.payload())?);
+            if !counts.active() {
+                ui::status("ℹ️  ", "Telemetry is off, so nothing is counted or sent");
+            }
+        }
+    }
+    Ok(Exit::Success)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload() {
+        let mut counts = Counts::default();
+        counts.add_run("ollama");
+        counts.add_run("ollama");
+        counts.add_run("gemini");
+        counts.add_action("accept");
+        counts.add_action("edit");
+        counts.add_action("accept");
+
+        let payload = counts.payload();
+        assert_eq!(payload["runs"], json!(3));
+        assert_eq!(payload["providers"], json!({ "gemini": 1, "ollama": 2 }));
+        assert_eq!(payload["actions"], json!({ "accept": 2, "edit": 1 }));
+        let keys: Vec<&String> = payload.as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["actions", "os", "providers", "runs", "version"]);
+
+        counts.reset();
+        assert_eq!(counts.payload()["runs"], json!(0));
+        assert!(!counts.active());
+        assert_eq!(Action::parse("preview"), Some(Action::Preview));
+        assert_eq!(Action::parse("upload"), None);
+    }
+}
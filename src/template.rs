@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Replace `{{name}}` placeholders with values from `vars`
+///
+/// Unknown placeholders are left untouched so typos stay visible in the prompt.
+pub fn render(template: &str, vars: &HashMap<&str, String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let name = after[..end].trim();
+                match vars.get(name) {
+                    Some(value) => out.push_str(value),
+                    None => out.push_str(&rest[start..start + 2 + end + 2]),
+                }
+                rest = &after[end + 2..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Template variables whose values change from run to run
+pub const RUN_VARS: &[&str] = &["branch", "author", "ticket", "ticket_url", "files", "date"];
+
+/// `template` (`CM_PROMPT_FILE`) rendered for the system prompt, and the
+/// values it refers to that change from run to run
+///
+/// Rendering the branch or date into the instructions would make them differ
+/// on every run and miss the provider's prompt cache, so those placeholders
+/// become `[name]` and their values are listed separately for the user message.
+pub fn render_split(template: &str, vars: &HashMap<&str, String>) -> (String, Option<String>) {
+    let mut fixed = vars.clone();
+    let mut values = Vec::new();
+    for &name in RUN_VARS {
+        fixed.insert(name, format!("[{}]", name));
+        if !render(template, &HashMap::from([(name, "\0".to_string())])).contains('\0') {
+            continue;
+        }
+        let value = vars.get(name).map(|v| v.trim()).unwrap_or_default();
+        values.push(match value {
+            "" => format!("- [{}]: (none)", name),
+            value if value.contains('\n') => format!("- [{}]:\n  {}", name, value.replace('\n', "\n  ")),
+            value => format!("- [{}]: {}", name, value),
+        });
+    }
+    let section = (!values.is_empty()).then(|| format!("## Template Values\n{}", values.join("\n")));
+    (render(template, &fixed), section)
+}
+
+/// Extract a ticket reference such as `ABC-123` from a branch name
+pub fn ticket_from_branch(branch: &str) -> Option<String> {
+    branch
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+        .flat_map(|segment| {
+            let parts: Vec<&str> = segment.split('-').collect();
+            parts
+                .windows(2)
+                .find(|w| {
+                    let key = w[0];
+                    let num = w[1];
+                    key.len() >= 2
+                        && key.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+                        && key.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+                        && !num.is_empty()
+                        && num.chars().all(|c| c.is_ascii_digit())
+                })
+                .map(|w| format!("{}-{}", w[0], w[1]))
+        })
+        .next()
+}
+
+/// `url` (`CM_TICKET_URL`) with `{{ticket}}` filled in, or empty without a URL or ticket
+///
+/// A GitHub-style `#123` is linked as `123`.
+pub fn ticket_url(url: Option<&str>, ticket: &str) -> String {
+    match url {
+        Some(url) if !ticket.is_empty() => {
+            render(url, &HashMap::from([("ticket", ticket.trim_start_matches('#').to_string())]))
+        }
+        _ => String::new(),
+    }
+}
+
+/// The footer `template` (`CM_FOOTER`) renders to, or `None` without a ticket to reference
+pub fn footer(template: &str, vars: &HashMap<&str, String>) -> Option<String> {
+    if vars.get("ticket").is_none_or(|ticket| ticket.is_empty()) {
+        return None;
+    }
+    Some(render(template, vars).trim().to_string()).filter(|footer| !footer.is_empty())
+}
+
+/// Today's date (UTC) as `YYYY-MM-DD`
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or(0) as i64;
+    let (y, m, d) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// The current time (UTC) as `YYYY-MM-DDTHH:MM:SSZ`
+pub fn timestamp() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (y, m, d) = civil_from_days((secs / 86_400) as i64);
+    let time = secs % 86_400;
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", y, m, d, time / 3600, time % 3600 / 60, time % 60)
+}
+
+/// Convert days since 1970-01-01 to a (year, month, day) civil date
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let vars = HashMap::from([("branch", "main".to_string()), ("ticket", "ABC-1".to_string())]);
+        assert_eq!(render("on {{branch}} ({{ ticket }})", &vars), "on main (ABC-1)");
+        assert_eq!(render("{{unknown}} {{branch", &vars), "{{unknown}} {{branch");
+    }
+
+    #[test]
+    fn test_render_split() {
+        let vars = HashMap::from([
+            ("branch", "feature/ABC-1".to_string()),
+            ("ticket", String::new()),
+            ("files", "src/a.rs\nsrc/b.rs".to_string()),
+            ("project_name", "cm".to_string()),
+        ]);
+        let (system, values) = render_split("For {{project_name}} on {{ branch }}: {{ticket}}\n{{files}}", &vars);
+        assert_eq!(system, "For cm on [branch]: [ticket]\n[files]");
+        assert_eq!(
+            values.as_deref(),
+            Some("## Template Values\n- [branch]: feature/ABC-1\n- [ticket]: (none)\n- [files]:\n  src/a.rs\n  src/b.rs")
+        );
+        assert_eq!(render_split("{{project_name}}", &vars), ("cm".to_string(), None));
+    }
+
+    #[test]
+    fn test_ticket_from_branch() {
+        assert_eq!(ticket_from_branch("feature/PROJ-42-login"), Some("PROJ-42".to_string()));
+        assert_eq!(ticket_from_branch("ABC-7"), Some("ABC-7".to_string()));
+        assert_eq!(ticket_from_branch("fix/typo"), None);
+    }
+
+    #[test]
+    fn test_footer() {
+        let url = ticket_url(Some("https://github.com/o/r/issues/{{ticket}}"), "#42");
+        assert_eq!(url, "https://github.com/o/r/issues/42");
+        assert_eq!(ticket_url(None, "ABC-1"), "");
+        let vars = HashMap::from([("ticket", "#42".to_string()), ("ticket_url", url)]);
+        assert_eq!(footer("Issue: {{ticket_url}}", &vars).as_deref(), Some("Issue: https://github.com/o/r/issues/42"));
+        assert_eq!(footer("Refs: {{ticket}}", &HashMap::from([("ticket", String::new())])), None);
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+}
@@ -0,0 +1,166 @@
+use std::io::{self, Write};
+
+use crate::budget::estimate_tokens;
+use crate::config::{BudgetAction, Config};
+use crate::exit::{provider_failure, Exit, Failure};
+use crate::vcs::git::{commit_messages, has_merges, merge_base, reword_commits, rev_parse};
+use crate::prompt::build_translate_prompt;
+use crate::provider::{create_provider, AiProvider};
+use crate::setup_guidance;
+use crate::spend::{self, prompt_tokens};
+use crate::ui;
+use crate::vault;
+
+/// Translate the messages of the commits in `range`, printing them or rewriting history
+pub async fn run_translate(
+    range: &str,
+    language: &str,
+    rewrite: bool,
+    no_cache: bool,
+) -> Result<Exit, Box<dyn std::error::Error>> {
+    let mut config = Config::from_env()?;
+    if no_cache {
+        config.cache_ttl = 0;
+    }
+    vault::configure(config.encrypt_store);
+    ui::set_theme(config.theme);
+
+    let commits = commit_messages(range)?;
+    if commits.is_empty() {
+        return Err(format!("No commits in '{}'", range).into());
+    }
+
+    let base = if rewrite { Some(rewrite_base(range)?) } else { None };
+
+    let provider = create_provider(&config);
+    ui::status(
+        "🌐 ",
+        format!(
+            "Translating {} commit(s) to {} with {} ({})",
+            commits.len(),
+            language,
+            provider.name(),
+            provider.model()
+        ),
+    );
+
+    let mut translated = Vec::with_capacity(commits.len());
+    for (hash, message) in &commits {
+        let text = translate_message(provider.as_ref(), &config, message, language).await?;
+        ui::status("\n", ui::paint(&hash[..hash.len().min(10)], ui::theme().reference));
+        println!("{}", text);
+        translated.push((hash.clone(), text));
+    }
+
+    let Some(base) = base else {
+        return Ok(Exit::Success);
+    };
+
+    print!(
+        "{}",
+        ui::line("\n⚠️  ", &format!("Rewrite {} commit(s) with these messages? [y/N]: ", translated.len()))
+    );
+    io::stdout().flush()?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    if input.trim().to_lowercase() != "y" {
+        ui::status("👋 ", "History left unchanged");
+        return Ok(Exit::Aborted);
+    }
+
+    reword_commits(&base, &translated)?;
+
+    ui::status("✓ ", format!("Rewrote {} commit(s)", translated.len()));
+    Ok(Exit::Success)
+}
+
+/// Check that `range` can be rewritten in place and return the rebase base
+///
+/// When the start of the range isn't an ancestor of HEAD, such as `main`
+/// after it moved on, the base is where HEAD forked from it, so the rebase
+/// only rewrites the commits in the range and doesn't move them onto it.
+pub fn rewrite_base(range: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let (base, tip) = range
+        .split_once("..")
+        .ok_or("Rewriting needs a range such as 'main..HEAD'")?;
+    let tip = if tip.is_empty() { "HEAD" } else { tip };
+
+    if rev_parse(tip) != rev_parse("HEAD") {
+        return Err("Rewriting only supports ranges ending at HEAD".into());
+    }
+    if has_merges(range) {
+        return Err("Rewriting cannot rebase ranges containing merge commits".into());
+    }
+
+    if rev_parse(base).is_none() {
+        return Err(format!("Unknown revision '{}'", base).into());
+    }
+    merge_base(base, "HEAD").ok_or_else(|| format!("'{}' has no history in common with HEAD", base).into())
+}
+
+async fn translate_message(
+    provider: &dyn AiProvider,
+    config: &Config,
+    message: &str,
+    language: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let prompt = build_translate_prompt(message, language);
+    let mut last_error = None;
+
+    let estimate = prompt_tokens(&prompt);
+    if let Some(reason) = spend::over_budget(&config.budget, config.provider.id(), estimate) {
+        if config.budget.action == BudgetAction::Refuse {
+            let message = format!("{}. Set CM_BUDGET_ACTION=warn to continue anyway.", reason);
+            return Err(Failure::new(Exit::QuotaExceeded, message).into());
+        }
+        ui::warn("⚠️  ", format!("Over budget: {}", reason));
+    }
+
+    for _ in 0..config.max_retries {
+        match provider.generate(&prompt).await {
+            Ok(text) => {
+                spend::record(&config.budget, config.provider.id(), estimate + estimate_tokens(&text) as u64);
+                if !text.trim().is_empty() {
+                    return Ok(text.trim().to_string());
+                }
+            }
+            Err(e) if !e.is_retryable() => return Err(provider_failure(&e, setup_guidance(&e, config)).into()),
+            Err(e) => {
+                ui::warn("⚠️  ", format!("API error: {}. Retrying...", e));
+                last_error = Some(e);
+            }
+        }
+    }
+
+    Err(match last_error {
+        Some(e) => e.into(),
+        None => Failure::new(Exit::GenerationFailed, "Provider returned an empty translation").into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::TempRepo;
+
+    #[test]
+    fn test_rewrite_base() {
+        let repo = TempRepo::new();
+        repo.stage("a.txt", "a").commit("chore: start");
+        let fork = repo.git(&["rev-parse", "HEAD"]).trim().to_string();
+        repo.git(&["branch", "main"]);
+        repo.stage("b.txt", "b").commit("feat: add b");
+        repo.git(&["checkout", "-q", "main"]);
+        repo.stage("c.txt", "c").commit("feat: add c");
+        let main = repo.git(&["rev-parse", "HEAD"]).trim().to_string();
+        repo.git(&["checkout", "-q", "feature"]);
+
+        let _entered = repo.enter();
+        // main moved on: rebasing onto it would pull its commits under the range
+        assert_eq!(rewrite_base("main..HEAD").unwrap(), fork);
+        repo.git(&["rebase", "-q", "main"]);
+        assert!(rewrite_base("main..").is_ok_and(|base| base == main));
+        assert!(rewrite_base("main").is_err());
+        assert!(rewrite_base("nope..HEAD").is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use super::{accessible, paint, strip_ansi, theme};
+
+/// How much `cm` prints besides prompts and the generated message
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verbosity {
+    /// Errors only (`-q`)
+    Quiet,
+    Normal,
+    /// Timings and git commands (`-v`)
+    Verbose,
+    /// Provider request details as well (`-vv`)
+    Debug,
+}
+
+impl Verbosity {
+    fn filter(self) -> LevelFilter {
+        match self {
+            Self::Quiet => LevelFilter::Error,
+            Self::Normal => LevelFilter::Info,
+            Self::Verbose => LevelFilter::Debug,
+            Self::Debug => LevelFilter::Trace,
+        }
+    }
+}
+
+/// Status lines go to stdout, everything else to stderr
+struct Logger {
+    start: OnceLock<Instant>,
+}
+
+static LOGGER: Logger = Logger { start: OnceLock::new() };
+
+/// Send status lines to stderr too, keeping stdout for the result
+static STATUS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // Dependencies (reqwest, keyring) log too; only show our own records
+        metadata.level() <= log::max_level() && metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            Level::Info if !STATUS_TO_STDERR.load(Ordering::Relaxed) => println!("{}", record.args()),
+            Level::Info => eprintln!("{}", record.args()),
+            Level::Warn | Level::Error => eprintln!("{}", record.args()),
+            Level::Debug | Level::Trace => {
+                let elapsed = self.start.get_or_init(Instant::now).elapsed();
+                let text = format!("[{:>7.3}s] {}", elapsed.as_secs_f64(), record.args());
+                if accessible() {
+                    eprintln!("{}", strip_ansi(&text));
+                } else {
+                    eprintln!("{}", paint(&text, theme().muted));
+                }
+            }
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Install the logger; call once at startup, before anything is printed
+pub fn init_logging() {
+    LOGGER.start.get_or_init(Instant::now);
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(Verbosity::Normal.filter());
+    }
+}
+
+pub fn set_verbosity(verbosity: Verbosity) {
+    log::set_max_level(verbosity.filter());
+}
+
+/// Print status lines to stderr so stdout carries only the generated message
+pub fn status_to_stderr() {
+    STATUS_TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbosity_filter() {
+        assert!(Level::Warn > Verbosity::Quiet.filter());
+        assert!(Level::Error <= Verbosity::Quiet.filter());
+        assert!(Level::Info <= Verbosity::Normal.filter());
+        assert!(Level::Debug > Verbosity::Normal.filter());
+        assert!(Level::Trace <= Verbosity::Debug.filter());
+    }
+}
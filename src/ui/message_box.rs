@@ -1,58 +1,94 @@
 use iocraft::prelude::*;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+use super::{capabilities, theme};
+use crate::emoji::remove_emoji_prefix;
+use crate::i18n::{t, tf, Msg};
+
+/// Scores from here on get the badge in the "accept" color instead of the warning one
+const GOOD_QUALITY: u8 = 70;
+
+/// Widest the box gets, so bodies wrap at a readable width on wide terminals
+const MAX_WIDTH: usize = 100;
+
+/// Below this many columns the box drops its margin and inner spacing
+const NARROW_WIDTH: usize = 60;
+
+/// Below this many columns there is no room for a box; the message is printed as labelled lines
+const MIN_WIDTH: usize = 30;
 
 #[derive(Default, Props)]
 pub struct MessageBoxProps<'a> {
+    pub title: &'a str,
+    /// Quality score shown next to the title
+    pub quality: Option<u8>,
     pub subject: &'a str,
     pub body: Option<&'a str>,
     pub provider: &'a str,
     pub model: &'a str,
+    /// ASCII borders for terminals without UTF-8
+    pub ascii: bool,
+    /// No margin or blank lines inside, for narrow terminals
+    pub compact: bool,
+    /// Columns the box takes, without its margin; text wraps inside it
+    pub width: u32,
 }
 
 #[component]
 pub fn MessageBox<'a>(props: &MessageBoxProps<'a>) -> impl Into<AnyElement<'a>> {
+    let theme = theme();
+    let spacing = if props.compact { 0 } else { 1 };
     element! {
         View(
             flex_direction: FlexDirection::Column,
-            border_style: BorderStyle::Round,
-            border_color: Color::Cyan,
+            border_style: if props.ascii { BorderStyle::Classic } else { BorderStyle::Round },
+            border_color: theme.border,
             padding_left: 1,
             padding_right: 1,
-            padding_top: 1,
-            padding_bottom: 1,
-            margin: 1,
+            padding_top: spacing,
+            padding_bottom: spacing,
+            margin: spacing,
+            width: props.width,
         ) {
             // Header
-            View(flex_direction: FlexDirection::Row, margin_bottom: 1) {
+            View(flex_direction: FlexDirection::Row, flex_wrap: FlexWrap::Wrap, margin_bottom: spacing) {
                 Text(
-                    content: "✨ Generated Commit Message",
-                    color: Color::White,
+                    content: props.title,
+                    color: theme.title,
                     weight: Weight::Bold,
                 )
+                #(props.quality.map(|score| element! {
+                    Text(
+                        content: format!("  [{}]", tf(Msg::Quality, &[&score])),
+                        color: if score >= GOOD_QUALITY { theme.accept } else { theme.warning },
+                    )
+                }))
             }
 
             // Provider info
-            View(margin_bottom: 1) {
+            View(margin_bottom: spacing) {
                 Text(
-                    content: format!("via {} ({})", props.provider, props.model),
-                    color: Color::DarkGrey,
+                    content: tf(Msg::Via, &[&props.provider, &props.model]),
+                    color: theme.muted,
                 )
             }
 
             // Subject line
-            View(margin_bottom: 1) {
+            View(margin_bottom: spacing) {
                 Text(
                     content: props.subject,
-                    color: Color::Green,
+                    color: theme.subject,
                     weight: Weight::Bold,
                 )
             }
 
             // Body (if present)
             #(props.body.map(|body| element! {
-                View(margin_top: 1) {
+                View(margin_top: spacing) {
                     Text(
                         content: body,
-                        color: Color::White,
+                        color: theme.body,
                         wrap: TextWrap::Wrap,
                     )
                 }
@@ -61,14 +97,173 @@ pub fn MessageBox<'a>(props: &MessageBoxProps<'a>) -> impl Into<AnyElement<'a>>
     }
 }
 
-/// Display a commit message using iocraft
-pub fn display_commit_message(subject: &str, body: Option<&str>, provider: &str, model: &str) {
-    element! {
+/// Display a commit message using iocraft, with its quality score if it has one
+pub fn display_commit_message(subject: &str, body: Option<&str>, quality: Option<u8>, provider: &str, model: &str) {
+    display_message_box("✨ ", t(Msg::GeneratedTitle), quality, subject, body, provider, model);
+}
+
+/// Display one of several generated candidates, numbered from 1
+pub fn display_candidate(index: usize, quality: u8, subject: &str, body: Option<&str>, provider: &str, model: &str) {
+    let title = tf(Msg::CandidateTitle, &[&index]);
+    display_message_box("✨ ", &title, Some(quality), subject, body, provider, model);
+}
+
+/// Lines of a raw response shown before it is folded
+const RAW_PREVIEW_LINES: usize = 8;
+
+/// Display a response that didn't parse cleanly, folded to its first lines unless `expanded`
+///
+/// Short terminals get fewer lines, so the actions stay on screen.
+pub fn display_raw_response(raw: &str, reason: &str, expanded: bool, provider: &str, model: &str) {
+    let lines: Vec<&str> = raw.trim().lines().collect();
+    let preview = RAW_PREVIEW_LINES.min(capabilities().height / 3).max(3);
+    let hidden = lines.len().saturating_sub(preview);
+    let body = if expanded || hidden == 0 {
+        lines.join("\n")
+    } else {
+        format!("{}\n{}", lines[..preview].join("\n"), tf(Msg::MoreLines, &[&hidden]))
+    };
+    let subject = tf(Msg::SuspiciousResponse, &[&reason]);
+    display_message_box("🔍 ", t(Msg::RawTitle), None, &subject, Some(&body), provider, model);
+}
+
+/// A message box as it was asked for, kept to draw it again at a new size
+#[derive(Clone)]
+struct Shown {
+    icon: &'static str,
+    title: String,
+    quality: Option<u8>,
+    subject: String,
+    body: Option<String>,
+    provider: String,
+    model: String,
+    /// Warnings printed before the box, about the message in it
+    warnings: Vec<String>,
+}
+
+/// The box shown last, which is the one being reviewed
+static SHOWN: Mutex<Option<Shown>> = Mutex::new(None);
+
+fn display_message_box(
+    icon: &'static str,
+    title: &str,
+    quality: Option<u8>,
+    subject: &str,
+    body: Option<&str>,
+    provider: &str,
+    model: &str,
+) {
+    // Borders and emoji read badly in screen readers, so print labelled lines instead
+    if super::accessible() {
+        print_lines(title, quality, subject, body, provider, model);
+        return;
+    }
+    let shown = Shown {
+        icon,
+        title: title.to_string(),
+        quality,
+        subject: subject.to_string(),
+        body: body.map(str::to_string),
+        provider: provider.to_string(),
+        model: model.to_string(),
+        warnings: super::warnings(false),
+    };
+    draw(&shown);
+    *SHOWN.lock().unwrap_or_else(|e| e.into_inner()) = Some(shown);
+}
+
+/// Clear the screen and draw the last box again at the current width,
+/// returning whether there was one
+///
+/// The old layout wraps differently at the new width, so it can't be
+/// redrawn in place; the warnings printed with the box are repeated
+/// around it instead, so clearing doesn't lose them.
+pub fn redraw_message_box() -> bool {
+    let Some(shown) = SHOWN.lock().unwrap_or_else(|e| e.into_inner()).clone() else {
+        return false;
+    };
+    print!("\x1b[2J\x1b[H");
+    let _ = io::stdout().flush();
+    for warning in &shown.warnings {
+        log::warn!("{}", warning);
+    }
+    draw(&shown);
+    let _ = io::stdout().flush();
+    for warning in super::warnings(true) {
+        log::warn!("{}", warning);
+    }
+    true
+}
+
+/// The message as plain labelled lines, without borders or emoji
+fn print_lines(title: &str, quality: Option<u8>, subject: &str, body: Option<&str>, provider: &str, model: &str) {
+    println!("\n{}, {}", title, tf(Msg::Via, &[&provider, &model]));
+    if let Some(score) = quality {
+        println!("{}", tf(Msg::Quality, &[&score]));
+    }
+    println!("{}", tf(Msg::Subject, &[&remove_emoji_prefix(subject)]));
+    println!("{}", tf(Msg::Body, &[&body.unwrap_or(t(Msg::NoBody))]));
+}
+
+fn draw(shown: &Shown) {
+    let terminal = capabilities();
+    let width = terminal.width.min(MAX_WIDTH);
+    if width < MIN_WIDTH {
+        let body = shown.body.as_deref();
+        print_lines(&shown.title, shown.quality, &shown.subject, body, &shown.provider, &shown.model);
+        return;
+    }
+    // Emoji the terminal can't draw come out as boxes or shift the border
+    let (title, subject) = if terminal.emoji {
+        (format!("{}{}", shown.icon, shown.title), shown.subject.clone())
+    } else {
+        (shown.title.clone(), remove_emoji_prefix(&shown.subject))
+    };
+    let compact = width < NARROW_WIDTH;
+    let margin = if compact { 0 } else { 2 };
+    let canvas = element! {
         MessageBox(
-            subject: subject,
-            body: body,
-            provider: provider,
-            model: model,
+            title: title.as_str(),
+            quality: shown.quality,
+            subject: subject.as_str(),
+            body: shown.body.as_deref(),
+            provider: shown.provider.as_str(),
+            model: shown.model.as_str(),
+            ascii: !terminal.unicode,
+            compact: compact,
+            width: width.saturating_sub(margin) as u32,
         )
-    }.print();
+    }
+    .render(Some(width));
+    // Logs and pipes get the layout without color codes
+    let _ = if terminal.tty { canvas.write_ansi(io::stdout()) } else { canvas.write(io::stdout()) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minimal_layout() {
+        let text = element! {
+            MessageBox(
+                title: "Generated",
+                subject: "feat(parser): accept trailing commas in arrays",
+                body: Some("Adds x, which is used by y and z."),
+                provider: "ollama",
+                model: "llama3.2",
+                ascii: true,
+                compact: true,
+                width: 30u32,
+            )
+        }
+        .render(Some(30))
+        .to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].starts_with("+--") && lines[0].trim_end().ends_with('+'));
+        assert!(lines.iter().all(|line| line.is_ascii() && line.chars().count() <= 30));
+        assert!(!lines.iter().any(|line| line.trim().trim_matches('|').trim().is_empty()));
+        assert!(text.contains("| feat(parser): accept       |\n| trailing commas in arrays  |"));
+        assert!(text.contains("| Adds x, which is used by y |"));
+    }
 }
@@ -1,6 +1,17 @@
+mod logger;
 mod message_box;
+mod terminal;
+mod theme;
 
-pub use message_box::{MessageBox, display_commit_message};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::i18n::{t, Msg};
+
+pub use logger::{init_logging, set_verbosity, status_to_stderr, Verbosity};
+pub use message_box::{display_candidate, display_commit_message, display_raw_response, redraw_message_box};
+pub use terminal::{capabilities, read_line};
+pub use theme::{paint, set_theme, theme};
 
 /// User action choices
 #[derive(Debug, Clone, PartialEq)]
@@ -8,5 +19,119 @@ pub enum UserAction {
     Accept,
     Edit,
     Regenerate,
+    /// Withhold files or hunks from the prompt and regenerate
+    Exclude,
     Quit,
 }
+
+impl UserAction {
+    /// Lowercase name, as written to the audit log
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Accept => "accept",
+            Self::Edit => "edit",
+            Self::Regenerate => "regenerate",
+            Self::Exclude => "exclude",
+            Self::Quit => "quit",
+        }
+    }
+}
+
+static ACCESSIBLE: AtomicBool = AtomicBool::new(false);
+
+/// Switch to linear, label-prefixed output without icons or colors (`--a11y`)
+pub fn set_accessible(on: bool) {
+    ACCESSIBLE.store(on, Ordering::Relaxed);
+}
+
+pub fn accessible() -> bool {
+    ACCESSIBLE.load(Ordering::Relaxed)
+}
+
+/// Word used instead of an icon in accessible mode, if the icon carries meaning
+fn icon_label(icon: &str) -> &'static str {
+    match icon {
+        "⚠️" | "⛔" => t(Msg::Warning),
+        _ => "",
+    }
+}
+
+/// Remove ANSI color sequences
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|c| c.is_ascii_alphabetic());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// `icon` (which may start with newlines) followed by `text`
+///
+/// In accessible mode the icon is replaced by a label such as `Warning:` or
+/// dropped, and colors are removed. Terminals without emoji get the label
+/// too, but keep the indentation and colors.
+pub fn line(icon: &str, text: &str) -> String {
+    if !accessible() && capabilities().emoji {
+        return format!("{}{}", icon, text);
+    }
+    let rest = icon.trim_start_matches('\n');
+    let breaks = &icon[..icon.len() - rest.len()];
+    if accessible() {
+        return format!("{}{}{}", breaks, icon_label(rest.trim()), strip_ansi(text));
+    }
+    let indent = &rest[..rest.len() - rest.trim_start().len()];
+    format!("{}{}{}{}", breaks, indent, icon_label(rest.trim()), text)
+}
+
+/// Print a status line to stdout; hidden with `-q`
+pub fn status(icon: &str, text: impl AsRef<str>) {
+    log::info!("{}", line(icon, text.as_ref()));
+}
+
+/// Warnings printed since the last message box, shown again when it is redrawn
+static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Print a warning to stderr; hidden with `-q`
+pub fn warn(icon: &str, text: impl AsRef<str>) {
+    let line = line(icon, text.as_ref());
+    log::warn!("{}", line);
+    if log::log_enabled!(log::Level::Warn) {
+        WARNINGS.lock().unwrap_or_else(|e| e.into_inner()).push(line);
+    }
+}
+
+/// The warnings printed since this was last called, or with `keep` since it was last taken
+fn warnings(keep: bool) -> Vec<String> {
+    let mut warnings = WARNINGS.lock().unwrap_or_else(|e| e.into_inner());
+    if keep {
+        warnings.clone()
+    } else {
+        std::mem::take(&mut *warnings)
+    }
+}
+
+/// Print an error to stderr
+pub fn error(icon: &str, text: impl AsRef<str>) {
+    log::error!("{}", line(icon, text.as_ref()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi("\x1b[31m⛔ banned\x1b[0m"), "⛔ banned");
+    }
+
+    #[test]
+    fn test_icon_label() {
+        assert_eq!(icon_label("⚠️"), "Warning: ");
+        assert_eq!(icon_label("⏳"), "");
+    }
+}
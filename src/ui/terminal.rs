@@ -0,0 +1,178 @@
+use std::env;
+use std::io::{self, IsTerminal};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::RwLock;
+use std::thread;
+use std::time::Duration;
+
+/// Width assumed when output isn't a terminal and `COLUMNS` isn't set, as in most CI logs
+const DEFAULT_WIDTH: usize = 80;
+const DEFAULT_HEIGHT: usize = 24;
+
+/// How often the size is checked while waiting for input
+const RESIZE_POLL: Duration = Duration::from_millis(200);
+
+/// `TERM`s that draw neither box characters nor emoji reliably
+const MINIMAL_TERMS: &[&str] = &["dumb", "linux", "vt100", "vt102", "vt220", "ansi"];
+
+/// What the terminal `cm` prints to can show
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capabilities {
+    /// Whether stdout is a terminal rather than a pipe or log file
+    pub tty: bool,
+    /// 24-bit colors; otherwise RGB colors are mapped to the 256-color palette
+    pub truecolor: bool,
+    /// UTF-8 output, such as rounded borders and `✓`; otherwise ASCII only
+    pub unicode: bool,
+    /// Emoji glyphs, which also need a font that has them
+    pub emoji: bool,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Capabilities {
+    /// Capabilities from the variables `var` reads and the size of the terminal, if it is one
+    ///
+    /// Terminals can't be asked what they draw without waiting on an answer,
+    /// so this goes by what they announce: the locale, `TERM`, `COLORTERM`
+    /// and the variables some terminals set for themselves.
+    fn detect(var: impl Fn(&str) -> Option<String>, size: Option<(u16, u16)>) -> Self {
+        let var = |key: &str| var(key).filter(|v| !v.is_empty());
+        let term = var("TERM").unwrap_or_default();
+        let minimal = MINIMAL_TERMS.contains(&term.as_str());
+        // Windows Terminal and recent consoles are UTF-8 whatever the locale says
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"].into_iter().find_map(var).unwrap_or_default().to_lowercase();
+        let unicode = !minimal && (cfg!(windows) || locale.contains("utf-8") || locale.contains("utf8"));
+        let truecolor = var("COLORTERM").is_some_and(|v| v == "truecolor" || v == "24bit")
+            || var("WT_SESSION").is_some()
+            || var("TERM_PROGRAM").is_some_and(|v| matches!(v.as_str(), "iTerm.app" | "WezTerm" | "vscode"));
+        let number = |key: &str| var(key).and_then(|v| v.parse().ok()).filter(|n| *n > 0);
+        let (width, height) = match size {
+            Some((width, height)) if width > 0 && height > 0 => (width as usize, height as usize),
+            _ => (
+                number("COLUMNS").unwrap_or(DEFAULT_WIDTH),
+                number("LINES").unwrap_or(DEFAULT_HEIGHT),
+            ),
+        };
+        Self {
+            tty: size.is_some(),
+            truecolor,
+            unicode,
+            // The classic Windows console has no emoji font; Windows Terminal and others do
+            emoji: unicode && (!cfg!(windows) || var("WT_SESSION").is_some() || var("TERM_PROGRAM").is_some()),
+            width,
+            height,
+        }
+    }
+
+    /// Short description for bug reports
+    pub fn summary(&self) -> String {
+        let mut features = vec![format!("{}x{}", self.width, self.height)];
+        for (on, name) in [
+            (self.tty, "tty"),
+            (self.unicode, "unicode"),
+            (self.emoji, "emoji"),
+            (self.truecolor, "truecolor"),
+        ] {
+            if on {
+                features.push(name.to_string());
+            }
+        }
+        features.join(", ")
+    }
+}
+
+static CAPABILITIES: RwLock<Option<Capabilities>> = RwLock::new(None);
+
+fn terminal_size() -> Option<(u16, u16)> {
+    io::stdout()
+        .is_terminal()
+        .then(|| crossterm::terminal::size().ok())
+        .flatten()
+}
+
+/// The capabilities of the terminal, probed on first use
+pub fn capabilities() -> Capabilities {
+    if let Some(capabilities) = *CAPABILITIES.read().unwrap_or_else(|e| e.into_inner()) {
+        return capabilities;
+    }
+    let capabilities = Capabilities::detect(|key| env::var(key).ok(), terminal_size());
+    log::debug!("Terminal: {}", capabilities.summary());
+    *CAPABILITIES.write().unwrap_or_else(|e| e.into_inner()) = Some(capabilities);
+    capabilities
+}
+
+/// Whether the terminal changed size since it was last checked, updating
+/// the size everything is laid out for
+fn resized() -> bool {
+    let current = capabilities();
+    let Some((width, height)) = terminal_size().filter(|(w, h)| *w > 0 && *h > 0) else {
+        return false;
+    };
+    let (width, height) = (width as usize, height as usize);
+    if (width, height) == (current.width, current.height) {
+        return false;
+    }
+    log::debug!("Terminal resized to {}x{}", width, height);
+    *CAPABILITIES.write().unwrap_or_else(|e| e.into_inner()) = Some(Capabilities { width, height, ..current });
+    true
+}
+
+/// Read a line from stdin, calling `redraw` whenever the terminal is resized meanwhile
+///
+/// The line is read on a separate thread, so what was already typed stays
+/// in the terminal's input buffer across redraws.
+pub fn read_line(redraw: impl Fn()) -> io::Result<String> {
+    if !capabilities().tty || !io::stdin().is_terminal() {
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        return Ok(input);
+    }
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut input = String::new();
+        let _ = sender.send(io::stdin().read_line(&mut input).map(|_| input));
+    });
+    loop {
+        match receiver.recv_timeout(RESIZE_POLL) {
+            Ok(result) => return result,
+            Err(RecvTimeoutError::Timeout) => {
+                if resized() {
+                    redraw();
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return Err(io::ErrorKind::UnexpectedEof.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(vars: &[(&str, &str)], size: Option<(u16, u16)>) -> Capabilities {
+        Capabilities::detect(
+            |key| vars.iter().find(|(k, _)| *k == key).map(|(_, v)| v.to_string()),
+            size,
+        )
+    }
+
+    #[test]
+    fn test_detect() {
+        let modern = detect(
+            &[("TERM", "xterm-256color"), ("LANG", "en_US.UTF-8"), ("COLORTERM", "truecolor")],
+            Some((120, 40)),
+        );
+        assert!(modern.tty && modern.unicode && modern.emoji && modern.truecolor);
+        assert_eq!((modern.width, modern.height), (120, 40));
+
+        let console = detect(&[("TERM", "linux"), ("LANG", "en_US.UTF-8")], Some((80, 25)));
+        assert!(!console.unicode && !console.emoji && !console.truecolor);
+
+        // A CI log: no terminal, the size from COLUMNS or the default
+        let ci = detect(&[("COLUMNS", "132"), ("LC_ALL", "C.UTF-8"), ("LANG", "C")], None);
+        assert!(!ci.tty && ci.unicode);
+        assert_eq!((ci.width, ci.height), (132, DEFAULT_HEIGHT));
+        assert_eq!(detect(&[], None).summary(), if cfg!(windows) { "80x24, unicode" } else { "80x24" });
+    }
+}
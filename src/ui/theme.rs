@@ -0,0 +1,171 @@
+use iocraft::prelude::Color;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use super::terminal::capabilities;
+use crate::config::ThemeName;
+
+/// Colors used across the message box, prompts and diff listings
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub border: Color,
+    pub title: Color,
+    /// Provider/model line and other secondary text
+    pub muted: Color,
+    pub subject: Color,
+    pub body: Color,
+    pub accept: Color,
+    pub edit: Color,
+    pub regenerate: Color,
+    pub exclude: Color,
+    pub quit: Color,
+    /// Banned phrases and other problems with a message
+    pub warning: Color,
+    /// Commit hashes
+    pub reference: Color,
+}
+
+const DEFAULT: Theme = Theme {
+    border: Color::Cyan,
+    title: Color::White,
+    muted: Color::DarkGrey,
+    subject: Color::Green,
+    body: Color::White,
+    accept: Color::DarkGreen,
+    edit: Color::DarkYellow,
+    regenerate: Color::DarkCyan,
+    exclude: Color::DarkMagenta,
+    quit: Color::DarkRed,
+    warning: Color::DarkRed,
+    reference: Color::DarkYellow,
+};
+
+/// Okabe-Ito colors, which stay distinct with red-green color blindness
+const DEUTERANOPIA: Theme = Theme {
+    border: Color::Rgb { r: 0x56, g: 0xb4, b: 0xe9 },
+    title: Color::White,
+    muted: Color::Grey,
+    subject: Color::Rgb { r: 0x56, g: 0xb4, b: 0xe9 },
+    body: Color::White,
+    accept: Color::Rgb { r: 0x00, g: 0x72, b: 0xb2 },
+    edit: Color::Rgb { r: 0xe6, g: 0x9f, b: 0x00 },
+    regenerate: Color::Rgb { r: 0x56, g: 0xb4, b: 0xe9 },
+    exclude: Color::Rgb { r: 0xcc, g: 0x79, b: 0xa7 },
+    quit: Color::Rgb { r: 0xd5, g: 0x5e, b: 0x00 },
+    warning: Color::Rgb { r: 0xd5, g: 0x5e, b: 0x00 },
+    reference: Color::Rgb { r: 0xf0, g: 0xe4, b: 0x42 },
+};
+
+/// Bright colors only, with no dark grey text
+const HIGH_CONTRAST: Theme = Theme {
+    border: Color::White,
+    title: Color::White,
+    muted: Color::White,
+    subject: Color::Yellow,
+    body: Color::White,
+    accept: Color::White,
+    edit: Color::White,
+    regenerate: Color::White,
+    exclude: Color::White,
+    quit: Color::White,
+    warning: Color::Yellow,
+    reference: Color::Yellow,
+};
+
+static THEME: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_theme(name: ThemeName) {
+    THEME.store(name as u8, Ordering::Relaxed);
+}
+
+/// The palette selected with `CM_THEME`, in colors the terminal can show
+pub fn theme() -> Theme {
+    let theme = match THEME.load(Ordering::Relaxed) {
+        x if x == ThemeName::Deuteranopia as u8 => DEUTERANOPIA,
+        x if x == ThemeName::HighContrast as u8 => HIGH_CONTRAST,
+        _ => DEFAULT,
+    };
+    if capabilities().truecolor {
+        theme
+    } else {
+        theme.map(to_256)
+    }
+}
+
+impl Theme {
+    fn map(self, f: fn(Color) -> Color) -> Self {
+        Self {
+            border: f(self.border),
+            title: f(self.title),
+            muted: f(self.muted),
+            subject: f(self.subject),
+            body: f(self.body),
+            accept: f(self.accept),
+            edit: f(self.edit),
+            regenerate: f(self.regenerate),
+            exclude: f(self.exclude),
+            quit: f(self.quit),
+            warning: f(self.warning),
+            reference: f(self.reference),
+        }
+    }
+}
+
+/// The nearest color of the 6×6×6 cube of the 256-color palette, for
+/// terminals that print 24-bit colors as garbage or not at all
+fn to_256(color: Color) -> Color {
+    match color {
+        Color::Rgb { r, g, b } => {
+            let level = |c: u8| (c as u16 * 5 + 127) / 255;
+            Color::AnsiValue((16 + 36 * level(r) + 6 * level(g) + level(b)) as u8)
+        }
+        other => other,
+    }
+}
+
+/// ANSI SGR parameters selecting `color` as the foreground
+fn sgr(color: Color) -> String {
+    match color {
+        Color::Black => "30".into(),
+        Color::DarkRed => "31".into(),
+        Color::DarkGreen => "32".into(),
+        Color::DarkYellow => "33".into(),
+        Color::DarkBlue => "34".into(),
+        Color::DarkMagenta => "35".into(),
+        Color::DarkCyan => "36".into(),
+        Color::Grey => "37".into(),
+        Color::DarkGrey => "90".into(),
+        Color::Red => "91".into(),
+        Color::Green => "92".into(),
+        Color::Yellow => "93".into(),
+        Color::Blue => "94".into(),
+        Color::Magenta => "95".into(),
+        Color::Cyan => "96".into(),
+        Color::White => "97".into(),
+        Color::Rgb { r, g, b } => format!("38;2;{};{};{}", r, g, b),
+        Color::AnsiValue(n) => format!("38;5;{}", n),
+        Color::Reset => "39".into(),
+    }
+}
+
+/// `text` colored for printing to the terminal
+pub fn paint(text: &str, color: Color) -> String {
+    format!("\x1b[{}m{}\x1b[0m", sgr(color), text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(paint("ok", Color::Green), "\x1b[92mok\x1b[0m");
+        assert_eq!(paint("ok", Color::Rgb { r: 1, g: 2, b: 3 }), "\x1b[38;2;1;2;3mok\x1b[0m");
+    }
+
+    #[test]
+    fn test_to_256() {
+        assert_eq!(to_256(Color::Rgb { r: 0xd5, g: 0x5e, b: 0x00 }), Color::AnsiValue(172));
+        assert_eq!(to_256(Color::Rgb { r: 0, g: 0, b: 0 }), Color::AnsiValue(16));
+        assert_eq!(to_256(Color::Cyan), Color::Cyan);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::config::{BudgetAction, Config, ConfigError, ScopeMode};
+use crate::deps::{bump_message, dependency_changes};
+use crate::context::GitContext;
+use crate::diff::render_diff;
+use crate::generated;
+use crate::exit::{provider_failure, Exit, Failure};
+use crate::heuristics::{depth_scope, infer_type};
+use crate::message::CommitMessage;
+use crate::postprocess::{Origin, Pipeline};
+use crate::privacy::Privacy;
+use crate::prompt::depth_scope_section;
+use crate::provider::AiProvider;
+use crate::scripts;
+use crate::spend::{self, prompt_tokens};
+use crate::vcs::git::get_commit_history;
+use crate::{fit_prompt, generate_candidates, template_vars, ui};
+
+/// Changes to describe when nobody is there to review the message
+pub struct Changes<'a> {
+    pub diff: &'a str,
+    /// Recent commit subjects, newest first
+    pub history: &'a [String],
+    /// Branch name for templates and ticket detection
+    pub branch: Option<&'a str>,
+}
+
+/// Configuration, or `None` without an API key, for commands that fall back to a local message
+pub fn optional_config() -> Result<Option<Config>, ConfigError> {
+    match Config::from_env() {
+        Ok(config) => Ok(Some(config)),
+        Err(ConfigError::MissingApiKey(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Subjects of the latest non-merge commits, for [`Changes::history`]
+pub fn recent_subjects(count: usize) -> Vec<String> {
+    get_commit_history(count, None, None, true)
+        .unwrap_or_default()
+        .iter()
+        .map(|entry| entry.subject().to_string())
+        .collect()
+}
+
+/// The generation pipeline of an interactive run, minus the prompts and learned preferences
+///
+/// Used by `cm serve`, `cm wip` and `cm watch`; failures carry the exit code an interactive
+/// run would have produced.
+pub async fn generate(config: &Config, provider: &dyn AiProvider, changes: &Changes<'_>) -> Result<CommitMessage, Failure> {
+    if changes.diff.trim().is_empty() {
+        return Err(Failure::new(Exit::NoStagedChanges, "The diff is empty"));
+    }
+
+    let history = if config.context.history {
+        changes.history.iter().take(config.history_count).map(|line| format!("{}\n", line)).collect()
+    } else {
+        String::new()
+    };
+    let context = GitContext {
+        branch: changes.branch.map(String::from),
+        ..GitContext::from_diff(changes.diff.to_string(), history)
+    };
+    let files = &context.files;
+    if config.local_deps {
+        if let Some(mut local) = bump_message(&dependency_changes(files)) {
+            Pipeline::new(config).apply(&mut local, Origin::Local);
+            return Ok(local);
+        }
+    }
+
+    let paths = context.paths();
+    let generated = generated::detect(files);
+    let type_hint = infer_type(files).or_else(|| generated::type_hint(&generated, paths.len()));
+    let prompt_diff = if generated.is_empty() {
+        changes.diff.to_string()
+    } else {
+        let prompt_files: Vec<_> = files.iter().filter(|f| !generated.iter().any(|g| g.path == f.path)).cloned().collect();
+        render_diff(&prompt_files, &Default::default())
+    };
+    let privacy = Privacy::new(config.privacy, config.privacy_hash_paths, &paths);
+    let vars = template_vars(&context, config, &privacy);
+    let generated_context = generated::prompt_section(&generated)
+        .filter(|_| config.context.stat)
+        .map(|s| privacy.apply(&s));
+    let scope = depth_scope(&config.scope_depth, &paths).filter(|_| config.scope_mode != ScopeMode::Omit);
+    let scope_context = scope.as_deref().map(|scope| privacy.apply(&depth_scope_section(scope)));
+    let extra = [scope_context, generated_context].into_iter().flatten().collect::<Vec<_>>().join("\n\n");
+    let scrubbed = context.scrubbed(&privacy);
+    let mut prompt = fit_prompt(
+        config,
+        &vars,
+        Some(extra.as_str()).filter(|extra| !extra.is_empty()),
+        type_hint.as_ref(),
+        &privacy.apply(&prompt_diff),
+        &scrubbed,
+    );
+    scripts::pre_generate(&config.hooks, &mut prompt, &scrubbed)?;
+
+    let estimate = prompt_tokens(&prompt);
+    if let Some(reason) = spend::over_budget(&config.budget, config.provider.id(), estimate) {
+        if config.budget.action == BudgetAction::Refuse {
+            return Err(Failure::new(Exit::QuotaExceeded, reason));
+        }
+        ui::warn("⚠️  ", reason);
+    }
+
+    let pipeline = Pipeline {
+        scope: scope.as_deref(),
+        ..Pipeline::new(config)
+    };
+    let mut last_error = "No valid commit message was generated".to_string();
+    for _ in 0..config.max_retries {
+        match generate_candidates(provider, &prompt, config, type_hint.as_ref(), &pipeline, false).await {
+            Ok(candidates) => match candidates.into_iter().next() {
+                Some(message) => return Ok(message),
+                None => continue,
+            },
+            Err(e) if !e.is_retryable() => return Err(provider_failure(&e, e.to_string())),
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+    Err(Failure::new(Exit::GenerationFailed, last_error))
+}
@@ -0,0 +1,146 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::ui;
+
+/// Keychain entry holding the store key
+const KEYCHAIN_SERVICE: &str = "commit-message";
+const KEYCHAIN_USER: &str = "store-key";
+
+/// Marks an encrypted file; followed by the nonce and the ciphertext
+const MAGIC: &[u8] = b"CMV1";
+const NONCE_LEN: usize = 12;
+
+/// How files written through [`read`] and [`write`] are stored
+#[derive(Clone, Copy)]
+enum Mode {
+    /// Encrypt, fetching the key from the keychain on first use
+    Locked,
+    Encrypted([u8; 32]),
+    /// Encryption was requested but the keychain can't be used: store nothing
+    Unavailable,
+    Plaintext,
+}
+
+static MODE: Mutex<Mode> = Mutex::new(Mode::Locked);
+
+/// Choose between encrypted (default) and plaintext storage
+pub fn configure(encrypt: bool) {
+    *MODE.lock().unwrap() = if encrypt { Mode::Locked } else { Mode::Plaintext };
+}
+
+fn mode() -> Mode {
+    let mut mode = MODE.lock().unwrap();
+    if let Mode::Locked = *mode {
+        *mode = match store_key() {
+            Ok(key) => Mode::Encrypted(key),
+            Err(e) => {
+                ui::warn(
+                    "⚠️  ",
+                    format!(
+                        "OS keychain unavailable ({}); cached responses and learned preferences won't be saved. Set CM_ENCRYPT_STORE=0 to store them unencrypted.",
+                        e
+                    ),
+                );
+                Mode::Unavailable
+            }
+        };
+    }
+    *mode
+}
+
+/// The store key from the OS keychain, created on first use
+fn store_key() -> keyring::Result<[u8; 32]> {
+    let entry = keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_USER)?;
+    match entry.get_password() {
+        Ok(hex) => {
+            if let Some(key) = decode_hex(&hex) {
+                return Ok(key);
+            }
+        }
+        Err(keyring::Error::NoEntry) => {}
+        Err(e) => return Err(e),
+    }
+
+    let key: [u8; 32] = Aes256Gcm::generate_key(OsRng).into();
+    entry.set_password(&key.iter().map(|b| format!("{:02x}", b)).collect::<String>())?;
+    Ok(key)
+}
+
+fn decode_hex(hex: &str) -> Option<[u8; 32]> {
+    let mut key = [0u8; 32];
+    if hex.len() != 64 {
+        return None;
+    }
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(key)
+}
+
+fn seal(key: &[u8; 32], plaintext: &[u8]) -> Option<Vec<u8>> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce, plaintext).ok()?;
+    Some([MAGIC, nonce.as_slice(), &ciphertext].concat())
+}
+
+fn open(key: &[u8; 32], data: &[u8]) -> Option<Vec<u8>> {
+    let rest = data.strip_prefix(MAGIC)?;
+    if rest.len() < NONCE_LEN {
+        return None;
+    }
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    cipher.decrypt(Nonce::from_slice(nonce), ciphertext).ok()
+}
+
+/// Read a file written by [`write`]; files that can't be decrypted read as missing
+pub fn read(path: &Path) -> Option<String> {
+    match mode() {
+        Mode::Plaintext => fs::read_to_string(path).ok(),
+        Mode::Encrypted(key) => String::from_utf8(open(&key, &fs::read(path).ok()?)?).ok(),
+        Mode::Locked | Mode::Unavailable => None,
+    }
+}
+
+/// Write `contents` to `path`, encrypted unless plaintext storage was configured
+pub fn write(path: &Path, contents: &str) {
+    let data = match mode() {
+        Mode::Plaintext => contents.as_bytes().to_vec(),
+        Mode::Encrypted(key) => match seal(&key, contents.as_bytes()) {
+            Some(data) => data,
+            None => return,
+        },
+        Mode::Locked | Mode::Unavailable => return,
+    };
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let _ = fs::write(path, data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let key = [7u8; 32];
+        let sealed = seal(&key, b"feat: add parser").unwrap();
+        assert!(sealed.starts_with(MAGIC));
+        assert_eq!(open(&key, &sealed).unwrap(), b"feat: add parser");
+        assert!(open(&[8u8; 32], &sealed).is_none());
+        assert!(open(&key, b"{\"accepted\": 1}").is_none());
+    }
+
+    #[test]
+    fn test_decode_hex() {
+        let hex = "ab".repeat(32);
+        assert_eq!(decode_hex(&hex), Some([0xab; 32]));
+        assert_eq!(decode_hex("abc"), None);
+    }
+}
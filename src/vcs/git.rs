@@ -0,0 +1,658 @@
+use std::process::Command;
+
+use super::{output, status, stdout_of, Vcs};
+use crate::diff::normalize_newlines;
+
+pub type GitResult<T> = Result<T, GitError>;
+
+#[derive(Debug)]
+pub enum GitError {
+    NotARepository,
+    NoStagedChanges,
+    CommandFailed(String),
+    /// A command of another VCS (`jj`, ...) failed
+    ToolFailed(&'static str, String),
+    /// `git push` was rejected, with its diagnosis and stderr
+    PushFailed(PushProblem, String),
+}
+
+impl std::fmt::Display for GitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotARepository => write!(f, "Not in a git, jj or hg repository. Please run this command from within a repository."),
+            Self::NoStagedChanges => write!(f, "No staged changes to commit"),
+            Self::CommandFailed(msg) => write!(f, "Git command failed: {}", msg),
+            Self::ToolFailed(tool, msg) => write!(f, "{} command failed: {}", tool, msg),
+            Self::PushFailed(_, stderr) => write!(f, "Push failed: {}", stderr),
+        }
+    }
+}
+
+impl std::error::Error for GitError {}
+
+/// Why `git push` was rejected
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PushProblem {
+    /// The remote has commits the local branch doesn't
+    NonFastForward,
+    /// The branch isn't tracking a remote branch yet
+    NoUpstream,
+    /// Credentials were missing or refused
+    Auth,
+    /// The server doesn't accept direct pushes to the branch
+    ProtectedBranch,
+    Other,
+}
+
+impl PushProblem {
+    /// Classify a failed push by its stderr
+    pub fn diagnose(stderr: &str) -> Self {
+        let lower = stderr.to_lowercase();
+        let has = |needles: &[&str]| needles.iter().any(|needle| lower.contains(needle));
+        if has(&["protected branch", "gh006", "not allowed to push", "not allowed to force push"]) {
+            Self::ProtectedBranch
+        } else if has(&["has no upstream branch", "no upstream branch"]) {
+            Self::NoUpstream
+        } else if has(&["non-fast-forward", "fetch first", "tip of your current branch is behind"]) {
+            Self::NonFastForward
+        } else if has(&["authentication failed", "permission denied", "could not read username", "403", "invalid username or password"]) {
+            Self::Auth
+        } else {
+            Self::Other
+        }
+    }
+
+    /// Git arguments that would fix the problem for `branch` on `remote`, if any command can
+    pub fn fix(self, remote: Option<&str>, branch: &str) -> Option<Vec<String>> {
+        let args: Vec<&str> = match self {
+            Self::NoUpstream => vec!["push", "--set-upstream", remote.unwrap_or("origin"), branch],
+            Self::NonFastForward => match remote {
+                Some(remote) => vec!["pull", "--rebase", remote, branch],
+                None => vec!["pull", "--rebase"],
+            },
+            _ => return None,
+        };
+        Some(args.into_iter().map(String::from).collect())
+    }
+}
+
+/// Check if current directory is inside a git repository
+pub fn is_git_repo() -> bool {
+    output(Command::new("git").args(["rev-parse", "--git-dir"]))
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// Name of the current branch (`HEAD` when detached)
+pub fn current_branch() -> Option<String> {
+    git_output(&["rev-parse", "--abbrev-ref", "HEAD"])
+}
+
+/// Value of a git config key, e.g. `user.name`
+pub fn config_value(key: &str) -> Option<String> {
+    git_output(&["config", "--get", key])
+}
+
+/// Path of the repository's top-level directory
+pub fn root() -> Option<String> {
+    git_output(&["rev-parse", "--show-toplevel"])
+}
+
+/// Absolute paths of the checked-out submodules, nested ones before their parents
+pub fn submodules() -> GitResult<Vec<std::path::PathBuf>> {
+    let root = root().ok_or(GitError::NotARepository)?;
+    let mut cmd = Command::new("git");
+    cmd.current_dir(&root).args(["submodule", "status", "--recursive"]);
+    let output = output(&mut cmd).map_err(|e| GitError::CommandFailed(e.to_string()))?;
+    if !output.status.success() {
+        return Err(GitError::CommandFailed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    // Lines look like ` <sha> <path> (<describe>)`; `-` marks submodules that aren't checked out
+    let mut paths: Vec<&str> = Vec::new();
+    let text = String::from_utf8_lossy(&output.stdout);
+    for line in text.lines().filter(|line| !line.starts_with('-')) {
+        if let Some(path) = line[1..].split_whitespace().nth(1) {
+            paths.push(path);
+        }
+    }
+    paths.sort_by_key(|path| std::cmp::Reverse(path.matches('/').count()));
+    Ok(paths.into_iter().map(|path| std::path::Path::new(&root).join(path)).collect())
+}
+
+/// A merge being concluded by the next commit
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeState {
+    /// Git's prepared subject, e.g. `Merge branch 'feature' into main`
+    pub summary: String,
+    /// Files that had conflicts
+    pub conflicts: Vec<String>,
+    /// How the resolved conflicted files differ from the side being merged in
+    pub theirs_diff: String,
+}
+
+/// The merge in progress, if `MERGE_HEAD` exists
+pub fn merge_state() -> Option<MergeState> {
+    git_output(&["rev-parse", "--quiet", "--verify", "MERGE_HEAD"])?;
+    let path = git_output(&["rev-parse", "--git-path", "MERGE_MSG"])?;
+    let (summary, conflicts) = parse_merge_msg(&std::fs::read_to_string(path).ok()?);
+    let theirs_diff = if conflicts.is_empty() {
+        String::new()
+    } else {
+        output(Command::new("git").args(["diff", "--cached", "MERGE_HEAD", "--"]).args(&conflicts))
+            .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+            .unwrap_or_default()
+    };
+    Some(MergeState { summary, conflicts, theirs_diff })
+}
+
+/// Subject and the `# Conflicts:` list from git's `MERGE_MSG`
+fn parse_merge_msg(text: &str) -> (String, Vec<String>) {
+    let summary = text.lines().next().unwrap_or_default().trim().to_string();
+    let conflicts = text
+        .lines()
+        .skip_while(|line| line.trim() != "# Conflicts:")
+        .skip(1)
+        .map_while(|line| line.strip_prefix("#\t"))
+        .map(|path| path.trim().to_string())
+        .collect();
+    (summary, conflicts)
+}
+
+/// Trimmed stdout of a git command, or `None` if it failed or printed nothing
+fn git_output(args: &[&str]) -> Option<String> {
+    stdout_of(Command::new("git").args(args))
+}
+
+/// A commit from `git log`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub hash: String,
+    pub author_name: String,
+    pub author_email: String,
+    /// Full message: subject, then body if any
+    pub message: String,
+}
+
+impl LogEntry {
+    pub fn subject(&self) -> &str {
+        self.message.lines().next().unwrap_or_default()
+    }
+}
+
+/// Get the last N commits reachable from `tip` (default `HEAD`), optionally only those whose author matches `author`
+pub fn get_commit_history(
+    count: usize,
+    tip: Option<&str>,
+    author: Option<&str>,
+    no_merges: bool,
+) -> GitResult<Vec<LogEntry>> {
+    let mut cmd = Command::new("git");
+    cmd.args(["log", &format!("-{}", count), "--format=%h%x00%an%x00%ae%x00%B%x1e"]);
+    if let Some(tip) = tip {
+        cmd.arg(tip);
+    }
+    if no_merges {
+        cmd.arg("--no-merges");
+    }
+    if let Some(author) = author {
+        cmd.arg(format!("--author={}", author));
+    }
+    read_log(&mut cmd)
+}
+
+/// The last `count` non-merge commits that touched any of `paths`, newest first
+pub fn file_history(paths: &[String], count: usize) -> GitResult<Vec<LogEntry>> {
+    let mut cmd = Command::new("git");
+    cmd.args(["log", &format!("-{}", count), "--no-merges", "--format=%h%x00%an%x00%ae%x00%B%x1e", "--"])
+        .args(paths);
+    read_log(&mut cmd)
+}
+
+/// The last `count` commits that changed `path`, or only lines `start..=end` of it, newest first
+///
+/// Without a line range, renames are followed.
+pub fn line_history(path: &str, lines: Option<(usize, usize)>, count: usize) -> GitResult<Vec<LogEntry>> {
+    let mut cmd = Command::new("git");
+    cmd.args(["log", &format!("-{}", count), "--format=%h%x00%an%x00%ae%x00%B%x1e"]);
+    match lines {
+        Some((start, end)) => cmd.args(["-s".to_string(), format!("-L{},{}:{}", start, end, path)]),
+        None => cmd.args(["--follow", "--", path]),
+    };
+    read_log(&mut cmd)
+}
+
+/// Entries printed by a `git log` using the record format of [`get_commit_history`]
+fn read_log(cmd: &mut Command) -> GitResult<Vec<LogEntry>> {
+    let output = output(cmd).map_err(|e| GitError::CommandFailed(e.to_string()))?;
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(4, '\0');
+            Some(LogEntry {
+                hash: fields.next()?.to_string(),
+                author_name: fields.next()?.to_string(),
+                author_email: fields.next()?.to_string(),
+                message: fields.next()?.trim().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Get the actual diff content of staged changes, optionally limited to `pathspec`
+pub fn get_staged_diff(pathspec: &[String]) -> GitResult<String> {
+    let output = output(Command::new("git").args(["diff", "--cached", "--"]).args(pathspec))
+        .map_err(|e| GitError::CommandFailed(e.to_string()))?;
+
+    let diff = normalize_newlines(&String::from_utf8_lossy(&output.stdout));
+
+    if diff.trim().is_empty() {
+        return Err(GitError::NoStagedChanges);
+    }
+
+    Ok(diff)
+}
+
+/// The changes made by commit `rev` (against its first parent for merges)
+pub fn commit_diff(rev: &str) -> GitResult<String> {
+    let output = output(Command::new("git").args(["show", "--format=", "-m", "--first-parent", rev, "--"]))
+        .map_err(|e| GitError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(GitError::CommandFailed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(normalize_newlines(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Get a statistical summary of the diff
+pub fn get_diff_stat(pathspec: &[String]) -> String {
+    output(Command::new("git").args(["diff", "--cached", "--stat", "--"]).args(pathspec))
+        .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+        .unwrap_or_default()
+}
+
+/// List staged file paths matching `pathspec` (all staged files if empty)
+pub fn staged_files(pathspec: &[String]) -> Vec<String> {
+    output(Command::new("git").args(["diff", "--cached", "--name-only", "--"]).args(pathspec))
+        .map(|o| String::from_utf8_lossy(&o.stdout).lines().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// Files among `paths` that also have unstaged modifications in the working tree
+pub fn unstaged_files(paths: &[String]) -> Vec<String> {
+    output(Command::new("git").args(["diff", "--name-only", "--"]).args(paths))
+        .map(|o| String::from_utf8_lossy(&o.stdout).lines().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// Full messages of the commits in `range`, oldest first, as `(hash, message)` pairs
+///
+/// A single revision (no `..`) selects just that commit.
+pub fn commit_messages(range: &str) -> GitResult<Vec<(String, String)>> {
+    let mut cmd = Command::new("git");
+    cmd.args(["log", "--reverse", "--format=%H%x00%B%x1e"]);
+    if range.contains("..") {
+        cmd.arg(range);
+    } else {
+        cmd.args(["-1", range]);
+    }
+    let output = output(&mut cmd).map_err(|e| GitError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(GitError::CommandFailed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\x1e')
+        .filter_map(|entry| entry.trim_start_matches('\n').split_once('\0'))
+        .map(|(hash, message)| (hash.to_string(), message.trim().to_string()))
+        .collect())
+}
+
+/// Diff between the two ends of `range` (`a..b`)
+pub fn range_diff(range: &str) -> GitResult<String> {
+    let output = output(Command::new("git").args(["diff", range]))
+        .map_err(|e| GitError::CommandFailed(e.to_string()))?;
+    if !output.status.success() {
+        return Err(GitError::CommandFailed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(normalize_newlines(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Month (`YYYY-MM`) and subject of every non-merge commit in `range`, oldest first
+pub fn dated_subjects(range: &str) -> GitResult<Vec<(String, String)>> {
+    let mut cmd = Command::new("git");
+    cmd.args(["log", "--reverse", "--no-merges", "--date=format:%Y-%m", "--format=%ad%x00%s", range]);
+    let output = output(&mut cmd).map_err(|e| GitError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(GitError::CommandFailed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once('\0'))
+        .map(|(month, subject)| (month.to_string(), subject.to_string()))
+        .collect())
+}
+
+/// Remote branches that already contain `rev`
+pub fn remote_branches_containing(rev: &str) -> Vec<String> {
+    git_output(&["branch", "-r", "--contains", rev])
+        .map(|text| text.lines().map(|line| line.trim().to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// Whether `range` contains merge commits
+pub fn has_merges(range: &str) -> bool {
+    git_output(&["rev-list", "--merges", range]).is_some()
+}
+
+/// Paths changed by commit `rev`
+pub fn commit_files(rev: &str) -> Vec<String> {
+    git_output(&["diff-tree", "--no-commit-id", "--name-only", "-r", "--root", rev])
+        .map(|text| text.lines().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// Resolve a revision to its full hash
+pub fn rev_parse(rev: &str) -> Option<String> {
+    git_output(&["rev-parse", "--verify", "--quiet", rev])
+}
+
+/// The best common ancestor of `a` and `b`
+pub fn merge_base(a: &str, b: &str) -> Option<String> {
+    git_output(&["merge-base", a, b])
+}
+
+/// Give each `(hash, message)` commit after `base` its new message, rebasing everything up to HEAD
+pub fn reword_commits(base: &str, messages: &[(String, String)]) -> GitResult<()> {
+    let io_error = |e: std::io::Error| GitError::CommandFailed(e.to_string());
+    // One message file per commit, then a rebase todo that amends each pick with its file
+    let dir = std::env::temp_dir().join(format!("cm-reword-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).map_err(io_error)?;
+    let mut todo = String::new();
+    for (hash, text) in messages {
+        let path = dir.join(hash);
+        std::fs::write(&path, text).map_err(io_error)?;
+        todo.push_str(&format!(
+            "pick {}\nexec git commit --amend --allow-empty --no-verify -F '{}'\n",
+            hash,
+            path.display()
+        ));
+    }
+    let todo_path = dir.join("git-rebase-todo");
+    std::fs::write(&todo_path, todo).map_err(io_error)?;
+
+    let result = rebase_with_todo(base, &todo_path);
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+/// Names the prepared todo list while git runs `cm` as its sequence editor
+const REBASE_TODO_ENV: &str = "CM_REBASE_TODO";
+
+/// Run `git rebase -i <base>` with a prepared todo list instead of opening an editor
+///
+/// `cm` itself is the sequence editor, so this doesn't depend on a `cp` being
+/// on the path (plain Windows has none); see `sequence_editor`.
+pub fn rebase_with_todo(base: &str, todo: &std::path::Path) -> GitResult<()> {
+    let exe = std::env::current_exe().map_err(|e| GitError::CommandFailed(e.to_string()))?;
+    let mut cmd = Command::new("git");
+    cmd.args(["rebase", "-i", base])
+        .env("GIT_SEQUENCE_EDITOR", sequence_editor_command(&exe))
+        .env(REBASE_TODO_ENV, todo);
+    let status = status(&mut cmd).map_err(|e| GitError::CommandFailed(e.to_string()))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(GitError::CommandFailed("Rebase failed; run 'git rebase --abort' to undo".into()))
+    }
+}
+
+/// The `GIT_SEQUENCE_EDITOR` that runs `exe`; git hands it to a shell, so it is single-quoted
+fn sequence_editor_command(exe: &std::path::Path) -> String {
+    format!("'{}'", exe.display().to_string().replace('\'', "'\\''"))
+}
+
+/// Act as git's sequence editor when `rebase_with_todo` started this process
+///
+/// Returns `None` for a normal run. Only a `git-rebase-todo` argument is
+/// overwritten, so hooks run by the rebase's own commits can't clobber
+/// anything else even though they inherit the variable.
+pub fn sequence_editor() -> Option<std::io::Result<()>> {
+    let todo = std::env::var_os(REBASE_TODO_ENV)?;
+    let target = std::env::args_os().nth(1)?;
+    copy_todo(std::path::Path::new(&todo), std::path::Path::new(&target))
+}
+
+fn copy_todo(todo: &std::path::Path, target: &std::path::Path) -> Option<std::io::Result<()>> {
+    (target.file_name()? == "git-rebase-todo").then(|| std::fs::copy(todo, target).map(|_| ()))
+}
+
+/// Commit staged changes with the given message
+///
+/// When `paths` is non-empty only those paths are committed (`git commit -- <paths>`);
+/// the rest of the index stays staged.
+pub fn commit(message: &str, paths: &[String]) -> GitResult<()> {
+    let mut cmd = Command::new("git");
+    cmd.args(["commit", "-m", message]);
+    if !paths.is_empty() {
+        cmd.arg("--").args(paths);
+    }
+    let status = status(&mut cmd).map_err(|e| GitError::CommandFailed(e.to_string()))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(GitError::CommandFailed("Commit failed".into()))
+    }
+}
+
+/// Every uncommitted change plus the names of untracked files, empty when the tree is clean
+pub fn working_tree_state() -> String {
+    let tracked = git_output(&["diff", "HEAD"]).unwrap_or_default();
+    let untracked = git_output(&["ls-files", "--others", "--exclude-standard"]).unwrap_or_default();
+    format!("{}{}", tracked, untracked)
+}
+
+/// Stage every change in the working tree, including new and deleted files
+pub fn stage_all() -> GitResult<()> {
+    run_status(&["add", "--all"], "Staging failed")
+}
+
+/// Commit the index with `message`, skipping hooks
+pub fn commit_no_verify(message: &str) -> GitResult<()> {
+    run_status(&["commit", "--no-verify", "--quiet", "-m", message], "Commit failed")
+}
+
+/// Move the branch to `rev`, keeping the undone commits' changes staged
+pub fn reset_soft(rev: &str) -> GitResult<()> {
+    run_status(&["reset", "--soft", rev], "Reset failed")
+}
+
+/// Run a git command attached to the terminal, reporting `failure` if it exits non-zero
+fn run_status(args: &[&str], failure: &str) -> GitResult<()> {
+    let status = status(Command::new("git").args(args)).map_err(|e| GitError::CommandFailed(e.to_string()))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(GitError::CommandFailed(failure.into()))
+    }
+}
+
+/// Push to `remote`, or the default remote
+///
+/// Stderr is captured so a rejection can be diagnosed; on success it is
+/// passed through, since hosts put links such as "create a pull request" there.
+pub fn push(remote: Option<&str>) -> GitResult<()> {
+    let output = output(Command::new("git").arg("push").args(remote))
+        .map_err(|e| GitError::CommandFailed(e.to_string()))?;
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+
+    if output.status.success() {
+        if !stderr.is_empty() {
+            eprintln!("{}", stderr);
+        }
+        Ok(())
+    } else {
+        Err(GitError::PushFailed(PushProblem::diagnose(&stderr), stderr))
+    }
+}
+
+/// Run git with `args` attached to the terminal, e.g. a fix from [`PushProblem::fix`]
+pub fn run_attached(args: &[String]) -> GitResult<()> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    run_status(&args, &format!("git {} failed", args.join(" ")))
+}
+
+/// Git, committing the staged changes
+pub struct Git;
+
+impl Vcs for Git {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn has_index(&self) -> bool {
+        true
+    }
+
+    fn diff(&self, pathspec: &[String]) -> GitResult<String> {
+        get_staged_diff(pathspec)
+    }
+
+    fn diff_stat(&self, pathspec: &[String]) -> String {
+        get_diff_stat(pathspec)
+    }
+
+    fn changed_files(&self, pathspec: &[String]) -> Vec<String> {
+        staged_files(pathspec)
+    }
+
+    fn unstaged_files(&self, paths: &[String]) -> Vec<String> {
+        unstaged_files(paths)
+    }
+
+    fn commit_diff(&self, rev: &str) -> GitResult<String> {
+        commit_diff(rev)
+    }
+
+    fn parent(&self, rev: &str) -> String {
+        format!("{}~1", rev)
+    }
+
+    fn history(&self, count: usize, tip: Option<&str>, author: Option<&str>, no_merges: bool) -> GitResult<Vec<LogEntry>> {
+        get_commit_history(count, tip, author, no_merges)
+    }
+
+    fn file_history(&self, paths: &[String], count: usize) -> Vec<LogEntry> {
+        file_history(paths, count).unwrap_or_default()
+    }
+
+    fn config_value(&self, key: &str) -> Option<String> {
+        config_value(key)
+    }
+
+    fn branch(&self) -> Option<String> {
+        current_branch()
+    }
+
+    fn root(&self) -> Option<String> {
+        root()
+    }
+
+    fn commit(&self, message: &str, paths: &[String]) -> GitResult<()> {
+        commit(message, paths)
+    }
+
+    fn push(&self, remote: Option<&str>) -> GitResult<()> {
+        push(remote)
+    }
+
+    fn create_branch(&self, name: &str) -> GitResult<()> {
+        run_status(&["switch", "--create", name], "Creating the branch failed")
+    }
+
+    fn merge_state(&self) -> Option<MergeState> {
+        merge_state()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence_editor_command() {
+        let exe = std::path::Path::new("/opt/it's here/cm");
+        assert_eq!(sequence_editor_command(exe), "'/opt/it'\\''s here/cm'");
+    }
+
+    #[test]
+    fn test_copy_todo() {
+        let dir = std::env::temp_dir().join(format!("cm-todo-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let todo = dir.join("prepared");
+        std::fs::write(&todo, "pick abc123\n").unwrap();
+
+        let target = dir.join("git-rebase-todo");
+        std::fs::write(&target, "pick abc123 original\n").unwrap();
+        assert!(copy_todo(&todo, &target).unwrap().is_ok());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "pick abc123\n");
+
+        // A hook's message file is left alone
+        let message = dir.join("COMMIT_EDITMSG");
+        std::fs::write(&message, "fix: keep me\n").unwrap();
+        assert!(copy_todo(&todo, &message).is_none());
+        assert_eq!(std::fs::read_to_string(&message).unwrap(), "fix: keep me\n");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_parse_merge_msg() {
+        let text = "Merge branch 'feature/login' into main\n\n# Conflicts:\n#\tsrc/auth.rs\n#\tREADME.md\n#\n# It looks like you may be committing a merge.\n";
+        let (summary, conflicts) = parse_merge_msg(text);
+        assert_eq!(summary, "Merge branch 'feature/login' into main");
+        assert_eq!(conflicts, vec!["src/auth.rs", "README.md"]);
+        assert!(parse_merge_msg("Merge branch 'x'\n").1.is_empty());
+    }
+
+    #[test]
+    fn test_diagnose_push() {
+        let behind = "To github.com:o/r.git\n ! [rejected]        main -> main (fetch first)\nerror: failed to push some refs";
+        assert_eq!(PushProblem::diagnose(behind), PushProblem::NonFastForward);
+        assert_eq!(
+            PushProblem::diagnose("fatal: The current branch feature has no upstream branch."),
+            PushProblem::NoUpstream
+        );
+        assert_eq!(
+            PushProblem::diagnose("remote: error: GH006: Protected branch update failed for refs/heads/main."),
+            PushProblem::ProtectedBranch
+        );
+        assert_eq!(
+            PushProblem::diagnose("fatal: Authentication failed for 'https://github.com/o/r.git/'"),
+            PushProblem::Auth
+        );
+        assert_eq!(PushProblem::diagnose("fatal: unable to access: Could not resolve host"), PushProblem::Other);
+
+        assert_eq!(
+            PushProblem::NoUpstream.fix(None, "feature").unwrap().join(" "),
+            "push --set-upstream origin feature"
+        );
+        assert_eq!(PushProblem::NonFastForward.fix(None, "main").unwrap().join(" "), "pull --rebase");
+        assert_eq!(PushProblem::Auth.fix(Some("origin"), "main"), None);
+    }
+}
@@ -0,0 +1,138 @@
+use std::process::Command;
+
+use super::{checked_output, checked_status, output, parse_log, stdout_of, GitError, GitResult, LogEntry, Vcs};
+use crate::diff::normalize_newlines;
+
+/// Commit hash, author and description, each followed by a NUL
+const LOG_TEMPLATE: &str = r"{node|short}\0{author|person}\0{author|email}\0{desc}\0";
+
+/// Mercurial, committing the working directory's changes
+pub struct Mercurial;
+
+/// Check if the current directory is inside a Mercurial repository
+pub fn is_repo() -> bool {
+    output(Command::new("hg").arg("root"))
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+fn hg() -> Command {
+    let mut cmd = Command::new("hg");
+    // Ignore user aliases and output settings that would change what we parse
+    cmd.env("HGPLAIN", "1").args(["--color", "never", "--pager", "never"]);
+    cmd
+}
+
+/// Stdout of an hg command that must succeed
+fn run(args: &[&str], paths: &[String]) -> GitResult<String> {
+    checked_output("hg", hg().args(args).args(paths))
+}
+
+/// Split `ui.username` (`Name <email>`) into its name and email
+fn split_username(username: &str) -> (String, String) {
+    match username.split_once('<') {
+        Some((name, email)) => (name.trim().to_string(), email.trim_end_matches('>').trim().to_string()),
+        None => (username.trim().to_string(), String::new()),
+    }
+}
+
+impl Vcs for Mercurial {
+    fn name(&self) -> &'static str {
+        "hg"
+    }
+
+    fn has_index(&self) -> bool {
+        false
+    }
+
+    fn diff(&self, pathspec: &[String]) -> GitResult<String> {
+        let diff = normalize_newlines(&run(&["diff", "--git", "--"], pathspec)?);
+        if diff.trim().is_empty() {
+            return Err(GitError::NoStagedChanges);
+        }
+        Ok(diff)
+    }
+
+    fn diff_stat(&self, pathspec: &[String]) -> String {
+        run(&["diff", "--stat", "--"], pathspec).unwrap_or_default()
+    }
+
+    fn changed_files(&self, pathspec: &[String]) -> Vec<String> {
+        // Modified, added and removed files, without the status letter
+        run(&["status", "-mar", "--no-status", "--"], pathspec)
+            .map(|text| text.lines().map(String::from).collect())
+            .unwrap_or_default()
+    }
+
+    fn unstaged_files(&self, _paths: &[String]) -> Vec<String> {
+        // Mercurial has no index; a commit takes every change to the files
+        Vec::new()
+    }
+
+    fn commit_diff(&self, rev: &str) -> GitResult<String> {
+        run(&["diff", "--git", "--change", rev], &[]).map(|diff| normalize_newlines(&diff))
+    }
+
+    fn parent(&self, rev: &str) -> String {
+        format!("p1({})", rev)
+    }
+
+    fn history(&self, count: usize, tip: Option<&str>, author: Option<&str>, no_merges: bool) -> GitResult<Vec<LogEntry>> {
+        let mut revset = format!("reverse(::({}))", tip.unwrap_or("."));
+        if no_merges {
+            revset.push_str(" and not merge()");
+        }
+        if let Some(author) = author {
+            revset.push_str(&format!(" and user({:?})", author));
+        }
+        let limit = count.to_string();
+        let text = run(&["log", "-r", &revset, "--limit", &limit, "--template", LOG_TEMPLATE], &[])?;
+        Ok(parse_log(&text))
+    }
+
+    /// `user.name` and `user.email` are read from `ui.username`
+    fn config_value(&self, key: &str) -> Option<String> {
+        let (name, email) = match key {
+            "user.name" | "user.email" => split_username(&stdout_of(hg().args(["config", "ui.username"]))?),
+            _ => return stdout_of(hg().args(["config", key])),
+        };
+        let value = if key == "user.name" { name } else { email };
+        if value.is_empty() { None } else { Some(value) }
+    }
+
+    /// The active bookmark, or the named branch
+    fn branch(&self) -> Option<String> {
+        stdout_of(hg().args(["log", "-r", ".", "--template", "{activebookmark}"]))
+            .or_else(|| stdout_of(hg().arg("branch")))
+    }
+
+    fn root(&self) -> Option<String> {
+        stdout_of(hg().arg("root"))
+    }
+
+    fn commit(&self, message: &str, paths: &[String]) -> GitResult<()> {
+        checked_status("hg", hg().args(["commit", "-m", message, "--"]).args(paths))
+    }
+
+    fn push(&self, remote: Option<&str>) -> GitResult<()> {
+        checked_status("hg", hg().arg("push").args(remote))
+    }
+
+    fn create_branch(&self, name: &str) -> GitResult<()> {
+        checked_status("hg", hg().args(["branch", name]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_username() {
+        assert_eq!(
+            split_username("Ada Lovelace <ada@example.com>"),
+            ("Ada Lovelace".to_string(), "ada@example.com".to_string())
+        );
+        assert_eq!(split_username("ada"), ("ada".to_string(), String::new()));
+    }
+}
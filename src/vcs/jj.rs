@@ -0,0 +1,150 @@
+use std::process::Command;
+
+use super::{checked_output, checked_status, output, parse_log, stdout_of, GitError, GitResult, LogEntry, Vcs};
+use crate::diff::normalize_newlines;
+
+/// Commit hash, author and description, each followed by a NUL
+const LOG_TEMPLATE: &str =
+    r#"commit_id.short() ++ "\0" ++ author.name() ++ "\0" ++ author.email() ++ "\0" ++ description ++ "\0""#;
+
+/// Jujutsu, describing the working-copy change (`@`)
+pub struct Jujutsu;
+
+/// Check if the current directory is inside a jj repository
+pub fn is_repo() -> bool {
+    output(Command::new("jj").arg("root"))
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+fn jj() -> Command {
+    let mut cmd = Command::new("jj");
+    cmd.args(["--color", "never", "--no-pager"]);
+    cmd
+}
+
+/// Stdout of a jj command that must succeed
+fn run(args: &[&str], paths: &[String]) -> GitResult<String> {
+    checked_output("jj", jj().args(args).args(paths))
+}
+
+/// Run a jj command attached to the terminal
+fn run_attached(args: &[&str], paths: &[String]) -> GitResult<()> {
+    checked_status("jj", jj().args(args).args(paths))
+}
+
+/// Arguments before `paths` for [`Jujutsu::commit`]
+///
+/// The whole working-copy change is described in place with `jj describe`,
+/// so it stays `@` like any change being worked on; only a partial commit
+/// needs `jj commit` to split the paths off into a change of their own.
+fn commit_args<'a>(message: &'a str, paths: &[String]) -> Vec<&'a str> {
+    if paths.is_empty() {
+        vec!["describe", "-m", message]
+    } else {
+        vec!["commit", "-m", message, "--"]
+    }
+}
+
+impl Vcs for Jujutsu {
+    fn name(&self) -> &'static str {
+        "jj"
+    }
+
+    fn has_index(&self) -> bool {
+        false
+    }
+
+    fn diff(&self, pathspec: &[String]) -> GitResult<String> {
+        let diff = normalize_newlines(&run(&["diff", "--git", "--"], pathspec)?);
+        if diff.trim().is_empty() {
+            return Err(GitError::NoStagedChanges);
+        }
+        Ok(diff)
+    }
+
+    fn diff_stat(&self, pathspec: &[String]) -> String {
+        run(&["diff", "--stat", "--"], pathspec).unwrap_or_default()
+    }
+
+    fn changed_files(&self, pathspec: &[String]) -> Vec<String> {
+        run(&["diff", "--name-only", "--"], pathspec)
+            .map(|text| text.lines().map(String::from).collect())
+            .unwrap_or_default()
+    }
+
+    fn unstaged_files(&self, _paths: &[String]) -> Vec<String> {
+        // The working-copy change always holds every modification
+        Vec::new()
+    }
+
+    fn commit_diff(&self, rev: &str) -> GitResult<String> {
+        run(&["diff", "--git", "-r", rev], &[]).map(|diff| normalize_newlines(&diff))
+    }
+
+    fn parent(&self, rev: &str) -> String {
+        format!("{}-", rev)
+    }
+
+    fn history(&self, count: usize, tip: Option<&str>, author: Option<&str>, no_merges: bool) -> GitResult<Vec<LogEntry>> {
+        let mut revset = format!("::({}) & ~root()", tip.unwrap_or("@-"));
+        if no_merges {
+            revset.push_str(" & ~merges()");
+        }
+        if let Some(author) = author {
+            revset.push_str(&format!(" & author({:?})", author));
+        }
+        let limit = count.to_string();
+        let text = run(&["log", "--no-graph", "-r", &revset, "--limit", &limit, "-T", LOG_TEMPLATE], &[])?;
+        Ok(parse_log(&text))
+    }
+
+    fn config_value(&self, key: &str) -> Option<String> {
+        stdout_of(jj().args(["config", "get", key]))
+    }
+
+    fn branch(&self) -> Option<String> {
+        stdout_of(jj().args([
+            "log",
+            "--no-graph",
+            "-r",
+            "latest(::@ & bookmarks())",
+            "-T",
+            r#"local_bookmarks.map(|b| b.name()).join(",")"#,
+        ]))
+    }
+
+    fn root(&self) -> Option<String> {
+        stdout_of(jj().arg("root"))
+    }
+
+    /// Set the working-copy change's description, or split `paths` into a described change
+    fn commit(&self, message: &str, paths: &[String]) -> GitResult<()> {
+        run_attached(&commit_args(message, paths), paths)
+    }
+
+    fn push(&self, remote: Option<&str>) -> GitResult<()> {
+        match remote {
+            Some(remote) => run_attached(&["git", "push", "--remote", remote], &[]),
+            None => run_attached(&["git", "push"], &[]),
+        }
+    }
+
+    fn create_branch(&self, name: &str) -> GitResult<()> {
+        run_attached(&["bookmark", "create", name, "-r", "@"], &[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_args() {
+        assert_eq!(commit_args("feat: add x", &[]), ["describe", "-m", "feat: add x"]);
+        assert_eq!(
+            commit_args("feat: add x", &["src/x.rs".to_string()]),
+            ["commit", "-m", "feat: add x", "--"]
+        );
+    }
+}
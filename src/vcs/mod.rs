@@ -0,0 +1,172 @@
+pub mod git;
+mod hg;
+mod jj;
+
+pub use git::{Git, GitError, GitResult, LogEntry, MergeState, PushProblem};
+pub use hg::Mercurial;
+pub use jj::Jujutsu;
+
+use std::io;
+use std::process::{Command, ExitStatus, Output};
+use std::time::Instant;
+
+/// A version control system holding the changes to describe and commit
+pub trait Vcs {
+    /// Command-line tool, e.g. `git`
+    fn name(&self) -> &'static str;
+    /// Whether changes are staged before committing, like git's index
+    fn has_index(&self) -> bool;
+    /// Diff of the changes the next commit would contain, limited to `pathspec`
+    fn diff(&self, pathspec: &[String]) -> GitResult<String>;
+    /// `--stat` style summary of [`Vcs::diff`]
+    fn diff_stat(&self, pathspec: &[String]) -> String;
+    /// Paths the next commit would change, limited to `pathspec`
+    fn changed_files(&self, pathspec: &[String]) -> Vec<String>;
+    /// Files among `paths` with further changes the commit won't include
+    fn unstaged_files(&self, paths: &[String]) -> Vec<String>;
+    /// Diff introduced by an existing revision
+    fn commit_diff(&self, rev: &str) -> GitResult<String>;
+    /// Revision expression for the parent of `rev`
+    fn parent(&self, rev: &str) -> String;
+    /// The last `count` commits up to `tip` (the latest commit if `None`), newest first
+    fn history(&self, count: usize, tip: Option<&str>, author: Option<&str>, no_merges: bool) -> GitResult<Vec<LogEntry>>;
+    /// The last `count` commits that touched any of `paths`, newest first
+    fn file_history(&self, _paths: &[String], _count: usize) -> Vec<LogEntry> {
+        Vec::new()
+    }
+    /// A user setting such as `user.name` or `user.email`
+    fn config_value(&self, key: &str) -> Option<String>;
+    /// Name of the current branch or bookmark
+    fn branch(&self) -> Option<String>;
+    /// Path of the repository's top-level directory
+    fn root(&self) -> Option<String>;
+    /// Name of the repository's top-level directory
+    fn repo_name(&self) -> Option<String> {
+        dir_name(&self.root()?)
+    }
+    /// Record the changes (only `paths`, if given) with `message`
+    fn commit(&self, message: &str, paths: &[String]) -> GitResult<()>;
+    /// Push to `remote` (a path alias for hg), or the default remote
+    fn push(&self, remote: Option<&str>) -> GitResult<()>;
+    /// Start a branch (a bookmark for jj) named `name` that the next commit goes on
+    fn create_branch(&self, name: &str) -> GitResult<()>;
+    /// The merge the next commit concludes, for tools that stage conflict resolutions
+    fn merge_state(&self) -> Option<MergeState> {
+        None
+    }
+}
+
+/// The repository containing the current directory
+///
+/// jj is checked first because its repositories usually also contain a `.git`
+/// directory.
+pub fn detect() -> Option<Box<dyn Vcs>> {
+    if jj::is_repo() {
+        Some(Box::new(Jujutsu))
+    } else if git::is_git_repo() {
+        Some(Box::new(Git))
+    } else if hg::is_repo() {
+        Some(Box::new(Mercurial))
+    } else {
+        None
+    }
+}
+
+/// `cmd` as typed on the command line, for `-v` logging
+fn display(cmd: &Command) -> String {
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| arg.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Run `cmd` capturing its output, logging the command and its duration
+fn output(cmd: &mut Command) -> io::Result<Output> {
+    let start = Instant::now();
+    let output = cmd.output();
+    if let Ok(output) = &output {
+        log::debug!("$ {} ({} in {:?})", display(cmd), output.status, start.elapsed());
+        log::trace!("  {} bytes of output", output.stdout.len());
+    }
+    output
+}
+
+/// Run `cmd` attached to the terminal, logging the command and its duration
+fn status(cmd: &mut Command) -> io::Result<ExitStatus> {
+    let start = Instant::now();
+    let status = cmd.status();
+    if let Ok(status) = &status {
+        log::debug!("$ {} ({} in {:?})", display(cmd), status, start.elapsed());
+    }
+    status
+}
+
+/// Stdout of `cmd`, which must succeed; failures are reported as `tool` errors
+fn checked_output(tool: &'static str, cmd: &mut Command) -> GitResult<String> {
+    let output = output(cmd).map_err(|e| GitError::ToolFailed(tool, e.to_string()))?;
+    if !output.status.success() {
+        return Err(GitError::ToolFailed(
+            tool,
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Run `cmd` attached to the terminal; failures are reported as `tool` errors
+fn checked_status(tool: &'static str, cmd: &mut Command) -> GitResult<()> {
+    let status = status(cmd).map_err(|e| GitError::ToolFailed(tool, e.to_string()))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(GitError::ToolFailed(tool, format!("{} exited with {}", display(cmd), status)))
+    }
+}
+
+/// Split `log` output of NUL-terminated hash, author name, email and message fields
+fn parse_log(text: &str) -> Vec<LogEntry> {
+    let fields: Vec<&str> = text.split('\0').collect();
+    fields
+        .chunks_exact(4)
+        .map(|entry| LogEntry {
+            hash: entry[0].trim().to_string(),
+            author_name: entry[1].to_string(),
+            author_email: entry[2].to_string(),
+            message: entry[3].trim().to_string(),
+        })
+        .collect()
+}
+
+/// Trimmed stdout of `cmd`, or `None` if it failed or printed nothing
+fn stdout_of(cmd: &mut Command) -> Option<String> {
+    let output = output(cmd).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if text.is_empty() { None } else { Some(text) }
+}
+
+/// Last component of a repository root path
+fn dir_name(root: &str) -> Option<String> {
+    std::path::Path::new(root)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log() {
+        let text = "abc123\0Ada\0ada@example.com\0feat: add parser\n\nWith tests.\n\0def456\0Bob\0bob@example.com\0fix: typo\n\0";
+        let entries = parse_log(text);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].hash, "abc123");
+        assert_eq!(entries[0].message, "feat: add parser\n\nWith tests.");
+        assert_eq!(entries[1].author_email, "bob@example.com");
+        assert_eq!(entries[1].subject(), "fix: typo");
+    }
+}
@@ -0,0 +1,178 @@
+use std::time::{Duration, Instant};
+
+use crate::config::{secret_action_or, Config, SecretAction, WatchOptions};
+use crate::exit::{Exit, Failure};
+use crate::message::CommitMessage;
+use crate::provider::create_provider;
+use crate::scripts;
+use crate::secrets;
+use crate::ui;
+use crate::unattended::{self, Changes};
+use crate::vcs::git::{
+    commit_no_verify, current_branch, get_staged_diff, is_git_repo, stage_all, staged_files, working_tree_state,
+};
+use crate::vcs::GitError;
+
+/// How often the working tree is checked for changes
+const POLL: Duration = Duration::from_secs(2);
+
+/// Trailer marking commits nobody reviewed
+const AUTO_COMMIT_TRAILER: &str = "Auto-committed-by: cm watch";
+
+/// Changes seen since the last commit
+#[derive(Debug, Default)]
+struct Pending {
+    /// Working-tree state at the last check
+    state: String,
+    /// When the state last changed
+    changed_at: Option<Instant>,
+    /// When the first uncommitted change was seen
+    first_seen: Option<Instant>,
+}
+
+impl Pending {
+    /// Record the current state, returning whether it's time to commit
+    fn update(&mut self, state: String, now: Instant, options: &WatchOptions) -> bool {
+        if state.is_empty() {
+            *self = Self::default();
+            return false;
+        }
+        if state != self.state {
+            self.state = state;
+            self.changed_at = Some(now);
+            self.first_seen.get_or_insert(now);
+        }
+        let quiet = self.changed_at.is_some_and(|at| now - at >= options.quiet);
+        let overdue = self.first_seen.is_some_and(|at| now - at >= options.interval);
+        quiet || overdue
+    }
+
+    /// Wait for the tree to change again before the next attempt
+    fn hold(&mut self) {
+        self.changed_at = None;
+        self.first_seen = None;
+    }
+}
+
+/// Commit the working tree whenever it settles down, or at least every interval, until interrupted
+pub async fn run_watch(allow_secrets: bool) -> Result<Exit, Box<dyn std::error::Error>> {
+    if !is_git_repo() {
+        return Err(GitError::NotARepository.into());
+    }
+    let options = WatchOptions::from_env();
+    let config = unattended::optional_config()?;
+    // Nobody reviews these commits, so credentials are refused unless asked otherwise
+    let secret_action = secret_action_or(SecretAction::Refuse)?;
+    ui::status(
+        "👀 ",
+        format!(
+            "Watching for changes; committing after {}s of quiet or every {}s. Ctrl-C stops.",
+            options.quiet.as_secs(),
+            options.interval.as_secs()
+        ),
+    );
+
+    let mut pending = Pending::default();
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => return Ok(Exit::Success),
+            _ = tokio::time::sleep(POLL) => {}
+        }
+        if !pending.update(working_tree_state(), Instant::now(), &options) {
+            continue;
+        }
+        match commit_changes(config.as_ref(), secret_action, allow_secrets).await {
+            Ok(()) => pending = Pending::default(),
+            Err(e) => {
+                ui::error("❌ ", e.to_string());
+                pending.hold();
+            }
+        }
+    }
+}
+
+/// Stage and commit everything with a generated message, or a file count if generation fails
+async fn commit_changes(
+    config: Option<&Config>,
+    secret_action: SecretAction,
+    allow_secrets: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    stage_all()?;
+    let diff = match get_staged_diff(&[]) {
+        Ok(diff) => diff,
+        // Changes that cancelled out before the commit
+        Err(GitError::NoStagedChanges) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    // Refused changes stay staged and are checked again on the next change
+    secrets::check(&diff, secret_action, allow_secrets)?;
+
+    let generated = match config {
+        Some(config) => describe(config, &diff).await.map_err(|e| ui::warn("⚠️  ", e.to_string())).ok(),
+        None => None,
+    };
+    let message = generated.unwrap_or_else(|| CommitMessage {
+        subject: format!("chore: update {} file(s)", staged_files(&[]).len()),
+        body: None,
+    });
+
+    let mut text = with_trailer(&message);
+    if let Some(config) = config {
+        text = scripts::pre_commit_message(&config.hooks, &text)?;
+    }
+    commit_no_verify(&text)?;
+    ui::status("✓ ", format!("Auto-committed {}", message.subject));
+    Ok(())
+}
+
+async fn describe(config: &Config, diff: &str) -> Result<CommitMessage, Failure> {
+    let mut config = config.clone();
+    config.candidates = 1;
+    let history = unattended::recent_subjects(config.history_count);
+    let branch = current_branch();
+    let changes = Changes {
+        diff,
+        history: &history,
+        branch: branch.as_deref(),
+    };
+    unattended::generate(&config, create_provider(&config).as_ref(), &changes).await
+}
+
+/// `message` with the auto-commit trailer, so the history shows nobody reviewed it
+fn with_trailer(message: &CommitMessage) -> String {
+    format!("{}\n\n{}", message.to_git_message(), AUTO_COMMIT_TRAILER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending() {
+        let options = WatchOptions {
+            quiet: Duration::from_secs(60),
+            interval: Duration::from_secs(300),
+        };
+        let start = Instant::now();
+        let mut pending = Pending::default();
+        assert!(!pending.update(String::new(), start, &options));
+        assert!(!pending.update("a".into(), start, &options));
+        assert!(!pending.update("a".into(), start + Duration::from_secs(59), &options));
+        assert!(pending.update("a".into(), start + Duration::from_secs(60), &options));
+        pending.hold();
+        assert!(!pending.update("a".into(), start + Duration::from_secs(200), &options));
+
+        // Constant edits never go quiet, so the interval forces a commit
+        let mut pending = Pending::default();
+        for secs in (0..300).step_by(30) {
+            assert!(!pending.update(format!("{}", secs), start + Duration::from_secs(secs), &options));
+        }
+        assert!(pending.update("end".into(), start + Duration::from_secs(300), &options));
+
+        let message = CommitMessage {
+            subject: "docs: add notes".into(),
+            body: None,
+        };
+        assert_eq!(with_trailer(&message), "docs: add notes\n\nAuto-committed-by: cm watch");
+    }
+}
@@ -0,0 +1,161 @@
+use std::fs;
+
+use crate::budget::estimate_tokens;
+use crate::config::{BudgetAction, Config};
+use crate::exit::{provider_failure, Exit, Failure};
+use crate::prompt::build_why_prompt;
+use crate::provider::create_provider;
+use crate::setup_guidance;
+use crate::spend::{self, prompt_tokens};
+use crate::ui;
+use crate::vault;
+use crate::vcs::git::line_history;
+
+/// Commits shown to the model, newest first
+const WHY_COMMITS: usize = 15;
+
+/// Lines of surrounding code included around a line range
+const CONTEXT_LINES: usize = 5;
+
+/// Most lines of current code included in the prompt
+const MAX_CODE_LINES: usize = 80;
+
+/// What `cm why` was asked about: a file, optionally narrowed to lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub path: String,
+    /// First and last line, 1-based and inclusive
+    pub lines: Option<(usize, usize)>,
+}
+
+impl Target {
+    /// `path`, `path:line` or `path:start-end`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let Some((path, range)) = spec.rsplit_once(':').filter(|(path, range)| {
+            !path.is_empty() && range.chars().next().is_some_and(|c| c.is_ascii_digit())
+        }) else {
+            return Ok(Self {
+                path: spec.to_string(),
+                lines: None,
+            });
+        };
+        let (start, end) = range.split_once('-').unwrap_or((range, range));
+        let number = |n: &str| n.parse::<usize>().ok().filter(|n| *n > 0);
+        match (number(start), number(end)) {
+            (Some(start), Some(end)) if start <= end => Ok(Self {
+                path: path.to_string(),
+                lines: Some((start, end)),
+            }),
+            _ => Err(format!("Invalid line range '{}'. Use <file>:<line> or <file>:<start>-<end>", range)),
+        }
+    }
+
+    /// The current text of the target, with line numbers and some surrounding lines
+    fn code(&self, text: &str) -> String {
+        let lines: Vec<&str> = text.lines().collect();
+        let (first, last) = match self.lines {
+            Some((start, end)) => (start.saturating_sub(CONTEXT_LINES + 1), (end + CONTEXT_LINES).min(lines.len())),
+            None => (0, lines.len()),
+        };
+        let last = last.min(first + MAX_CODE_LINES);
+        (first..last).map(|i| format!("{:>5} | {}", i + 1, lines[i])).collect::<Vec<_>>().join("\n")
+    }
+}
+
+impl std::fmt::Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.lines {
+            Some((start, end)) if start == end => write!(f, "{}:{}", self.path, start),
+            Some((start, end)) => write!(f, "{}:{}-{}", self.path, start, end),
+            None => write!(f, "{}", self.path),
+        }
+    }
+}
+
+/// Explain why `target` looks the way it does from the commits that changed it
+pub async fn run_why(target: &Target, no_cache: bool) -> Result<Exit, Box<dyn std::error::Error>> {
+    let mut config = Config::from_env()?;
+    if no_cache {
+        config.cache_ttl = 0;
+    }
+    vault::configure(config.encrypt_store);
+    ui::set_theme(config.theme);
+
+    let text = fs::read_to_string(&target.path).map_err(|e| format!("{}: {}", target.path, e))?;
+    if let Some((_, end)) = target.lines.filter(|(_, end)| *end > text.lines().count()) {
+        return Err(format!("{} has only {} lines, not {}", target.path, text.lines().count(), end).into());
+    }
+    let commits = line_history(&target.path, target.lines, WHY_COMMITS)?;
+    if commits.is_empty() {
+        return Err(format!("No commits changed {}", target).into());
+    }
+
+    let provider = create_provider(&config);
+    ui::status(
+        "🔎 ",
+        format!("Reading {} commit(s) that shaped {} with {} ({})", commits.len(), target, provider.name(), provider.model()),
+    );
+    let prompt = build_why_prompt(&target.to_string(), &target.code(&text), &commits);
+    let estimate = prompt_tokens(&prompt);
+    if let Some(reason) = spend::over_budget(&config.budget, config.provider.id(), estimate) {
+        if config.budget.action == BudgetAction::Refuse {
+            let message = format!("{}. Set CM_BUDGET_ACTION=warn to continue anyway.", reason);
+            return Err(Failure::new(Exit::QuotaExceeded, message).into());
+        }
+        ui::warn("⚠️  ", format!("Over budget: {}", reason));
+    }
+
+    let mut last_error = None;
+    for _ in 0..config.max_retries {
+        match provider.generate(&prompt).await {
+            Ok(text) if !text.trim().is_empty() => {
+                spend::record(&config.budget, config.provider.id(), estimate + estimate_tokens(&text) as u64);
+                println!("\n{}", text.trim());
+                return Ok(Exit::Success);
+            }
+            Ok(_) => {}
+            Err(e) if !e.is_retryable() => return Err(provider_failure(&e, setup_guidance(&e, &config)).into()),
+            Err(e) => {
+                ui::warn("⚠️  ", format!("API error: {}. Retrying...", e));
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(match last_error {
+        Some(e) => e.into(),
+        None => Failure::new(Exit::GenerationFailed, "Provider returned an empty explanation").into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target() {
+        let target = |path: &str, lines| Target {
+            path: path.to_string(),
+            lines,
+        };
+        assert_eq!(Target::parse("src/main.rs"), Ok(target("src/main.rs", None)));
+        assert_eq!(Target::parse("src/main.rs:42"), Ok(target("src/main.rs", Some((42, 42)))));
+        assert_eq!(Target::parse("src/main.rs:10-20"), Ok(target("src/main.rs", Some((10, 20)))));
+        assert_eq!(Target::parse(r"C:\src\main.rs"), Ok(target(r"C:\src\main.rs", None)));
+        assert!(Target::parse("src/main.rs:20-10").is_err());
+        assert!(Target::parse("src/main.rs:0").is_err());
+        assert_eq!(target("a.rs", Some((3, 3))).to_string(), "a.rs:3");
+    }
+
+    #[test]
+    fn test_code() {
+        let text = (1..=20).map(|n| format!("line {}", n)).collect::<Vec<_>>().join("\n");
+        let code = target_code("a.rs:10", &text);
+        assert!(code.starts_with("    5 | line 5"));
+        assert!(code.ends_with("   15 | line 15"));
+        assert_eq!(target_code("a.rs:19-20", &text).lines().last(), Some("   20 | line 20"));
+    }
+
+    fn target_code(spec: &str, text: &str) -> String {
+        Target::parse(spec).unwrap().code(text)
+    }
+}
@@ -0,0 +1,118 @@
+use crate::config::{secret_action, Config, PromptStyle};
+use crate::emoji::{extract_type, remove_emoji_prefix};
+use crate::exit::{Exit, Failure};
+use crate::message::CommitMessage;
+use crate::postprocess::{Origin, Pipeline};
+use crate::provider::create_provider;
+use crate::scripts;
+use crate::secrets;
+use crate::ui;
+use crate::unattended::{self, Changes};
+use crate::vcs::git::{
+    commit_messages, commit_no_verify, current_branch, get_staged_diff, is_git_repo,
+    reset_soft, stage_all, staged_files,
+};
+use crate::vcs::GitError;
+
+/// Stage everything and commit it as `wip:` without asking anything
+///
+/// When no message can be generated (offline, no API key) the subject falls
+/// back to a file count, so a snapshot never fails for want of a provider.
+pub async fn run_wip(allow_secrets: bool) -> Result<Exit, Box<dyn std::error::Error>> {
+    if !is_git_repo() {
+        return Err(GitError::NotARepository.into());
+    }
+    stage_all()?;
+    let diff = match get_staged_diff(&[]) {
+        Ok(diff) => diff,
+        Err(GitError::NoStagedChanges) => return Err(Failure::new(Exit::NoStagedChanges, "Nothing to snapshot").into()),
+        Err(e) => return Err(e.into()),
+    };
+    secrets::check(&diff, secret_action()?, allow_secrets)?;
+
+    let config = unattended::optional_config()?;
+    let description = match &config {
+        Some(config) => describe(config, &diff).await,
+        None => None,
+    };
+    let description =
+        description.unwrap_or_else(|| format!("snapshot of {} file(s)", staged_files(&[]).len()));
+
+    let mut message = CommitMessage {
+        subject: format!("wip: {}", description),
+        body: None,
+    };
+    if let Some(config) = &config {
+        Pipeline::new(config).apply(&mut message, Origin::Local);
+    }
+    let subject = match &config {
+        Some(config) => scripts::pre_commit_message(&config.hooks, &message.subject)?,
+        None => message.subject,
+    };
+    commit_no_verify(&subject)?;
+    ui::status("✓ ", format!("Committed {}", subject));
+    Ok(Exit::Success)
+}
+
+/// A one-line description of `diff`, or `None` if generation failed
+async fn describe(config: &Config, diff: &str) -> Option<String> {
+    let mut config = config.clone();
+    config.style = PromptStyle::Concise;
+    config.candidates = 1;
+    config.emoji_enabled = false;
+
+    let history = unattended::recent_subjects(config.history_count);
+    let branch = current_branch();
+    let changes = Changes {
+        diff,
+        history: &history,
+        branch: branch.as_deref(),
+    };
+
+    let provider = create_provider(&config);
+    match unattended::generate(&config, provider.as_ref(), &changes).await {
+        Ok(message) => Some(description(&message.subject).to_string()),
+        Err(e) => {
+            ui::warn("⚠️  ", format!("Couldn't generate a description ({}); using a generic one", e));
+            None
+        }
+    }
+}
+
+/// The text after `type(scope):`, or the whole subject if it has no header
+fn description(subject: &str) -> &str {
+    match subject.split_once(':') {
+        Some((_, description)) if extract_type(subject).is_some() => description.trim(),
+        _ => subject.trim(),
+    }
+}
+
+/// Undo the last commit if it is a WIP snapshot, keeping its changes staged
+pub fn pop_wip() -> Result<Exit, Box<dyn std::error::Error>> {
+    let (_, message) = commit_messages("HEAD")?.pop().ok_or(GitError::NotARepository)?;
+    let subject = message.lines().next().unwrap_or_default();
+    if !is_wip(subject) {
+        return Err(Failure::new(Exit::Error, format!("HEAD is not a WIP commit: {}", subject)).into());
+    }
+    reset_soft("HEAD~1")?;
+    ui::status("✓ ", format!("Popped '{}'; its changes are staged", subject));
+    Ok(Exit::Success)
+}
+
+fn is_wip(subject: &str) -> bool {
+    extract_type(&remove_emoji_prefix(subject)).is_some_and(|t| t == "wip")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wip_subjects() {
+        assert_eq!(description("feat(parser): add nested lists"), "add nested lists");
+        assert_eq!(description("half-done parser"), "half-done parser");
+        assert!(is_wip("wip: snapshot of 3 file(s)"));
+        assert!(is_wip("🚧 wip: parser"));
+        assert!(!is_wip("feat: wipe caches"));
+    }
+}